@@ -0,0 +1,1355 @@
+use crate::portfolio::{EquityResolution, Portfolio, Position, Sleeve};
+use crate::report::{BacktestReport, Liquidation, Trade, TradeSide};
+use ephemera_shared::{CandleData, Signal, TimestampMs};
+use ephemera_source::okx::{InstrumentInfo, OkxContractType, OkxInstType};
+use ephemera_strategy::audit::{AuditOutcome, Auditor};
+use ephemera_strategy::risk::{
+    DrawdownKillSwitch, PortfolioConstraints, PositionLimiter, RiskConfig,
+};
+use eyre::Result;
+use futures::{Stream, StreamExt};
+use std::time::{Duration, Instant};
+
+/// 每处理多少根 K 线回调一次回测进度
+pub const PROGRESS_INTERVAL: usize = 100;
+
+/// 执行回测，返回回测报告
+///
+/// 开仓只占用 `名义价值 / 杠杆` 的保证金。每根 K 线开始时先检查该交易对的持仓，
+/// 若以 K 线最低价计算的剩余保证金低于维持保证金，则按强平价强制平仓并记录 [`Liquidation`]。
+///
+/// 配置了 [`RiskConfig`] 时，每根 K 线都会用当前总权益更新回撤熔断，熔断期间买入信号被忽略；
+/// 若要求平仓，则以收盘价卖出该交易对的持仓。
+///
+/// 资金按 [`Allocation`] 划分到子账户，开仓只能使用信号所属交易对的子账户余额。
+///
+/// 配置了审计时，每个信号的成交、拒绝以及强平都会写入审计记录。
+///
+/// 信号的成交价格和时机由 [`FillTiming`] 决定，见 `defer_fills`。
+pub async fn execute_backtest(
+    signal_stream: impl Stream<Item = (Signal, CandleData)> + Send,
+    config: BacktestConfig,
+    mut progress: impl FnMut(BacktestProgress),
+) -> Result<BacktestReport> {
+    use std::collections::HashMap;
+
+    let BacktestConfig {
+        initial_balance,
+        margin,
+        risk,
+        constraints,
+        equity_resolution,
+        allocation,
+        fill_timing,
+        scaled_exit,
+        funding_rates,
+        mut fx,
+        instruments,
+        audit,
+    } = config;
+    let audit = |signal: &Signal, timestamp_ms, outcome| {
+        if let Some(audit) = &audit {
+            audit.signal(timestamp_ms, signal, outcome);
+        }
+    };
+    let rejected = |reason: &str| AuditOutcome::Rejected {
+        reason: reason.to_string(),
+    };
+    let mut kill_switch = risk.map(DrawdownKillSwitch::new);
+    let mut limiter = constraints.map(PositionLimiter::new);
+    let multipliers = contract_multipliers(&instruments)?;
+
+    let mut portfolio = Portfolio::new(initial_balance, &allocation, equity_resolution)?;
+    // 各交易对最新的收盘价，用于计算持仓的浮动盈亏
+    let mut marks: HashMap<String, f64> = HashMap::new();
+    let mut trades = Vec::new();
+    let mut liquidations = Vec::new();
+    // 交易对 -> 按结算时间排序的待结算资金费率
+    let mut funding: HashMap<String, std::collections::VecDeque<FundingRate>> = HashMap::new();
+    for rate in funding_rates {
+        funding
+            .entry(rate.symbol.clone())
+            .or_default()
+            .push_back(rate);
+    }
+    for rates in funding.values_mut() {
+        rates
+            .make_contiguous()
+            .sort_by_key(|rate| rate.funding_time_ms);
+    }
+    let mut funding_paid = 0.0;
+    let mut candles_processed = 0;
+    // 回测覆盖的时间范围: (最早的开盘时间, 最晚的收盘时间)
+    let mut time_range: Option<(TimestampMs, TimestampMs)> = None;
+    let start = Instant::now();
+
+    let signal_stream = defer_fills(signal_stream, fill_timing);
+    futures::pin_mut!(signal_stream);
+
+    while let Some((signal, candle)) = signal_stream.next().await {
+        // 换算为记账货币，之后的撮合和权益计算都以记账货币进行
+        let (signal, candle) = match fx.as_mut() {
+            Some(fx) => {
+                fx.update(&candle);
+                fx.convert(signal, candle)?
+            }
+            None => (signal, candle),
+        };
+        // 合约张数换算为基础货币数量，之后的保证金、盈亏和资金费都不必区分现货与合约
+        let signal = match multipliers.get(&*candle.symbol) {
+            Some(&ct_val) => scale_signal_size(signal, ct_val),
+            None => signal,
+        };
+
+        candles_processed += 1;
+        let close_timestamp_ms = candle.open_timestamp_ms + candle.interval_sc * 1000;
+        time_range = Some(match time_range {
+            Some((start, end)) => (
+                start.min(candle.open_timestamp_ms),
+                end.max(close_timestamp_ms),
+            ),
+            None => (candle.open_timestamp_ms, close_timestamp_ms),
+        });
+
+        let symbol_string = candle.symbol.to_string();
+        // 资金费、强平和分批平仓发生在 K 线内部，按开盘时刻记录权益，此时还不能用收盘价估值
+        marks.insert(symbol_string.clone(), candle.open);
+
+        // 结算在这根 K 线开盘前到期的资金费，以开盘价作为标记价格
+        if let Some(rates) = funding.get_mut(&symbol_string) {
+            while rates
+                .front()
+                .is_some_and(|rate| rate.funding_time_ms <= candle.open_timestamp_ms)
+            {
+                let rate = rates.pop_front().unwrap();
+
+                let Some(index) = portfolio.sleeve_index(&symbol_string) else {
+                    continue;
+                };
+                let Some(position) = portfolio.sleeves[index].positions.get(&symbol_string) else {
+                    continue;
+                };
+
+                // 费率为正时多头支付
+                let payment = position.size * candle.open * rate.rate;
+                portfolio.sleeves[index].available_balance -= payment;
+                funding_paid += payment;
+                portfolio.record(index, candle.open_timestamp_ms, &marks);
+
+                tracing::info!(
+                    "💸 资金费: {} 费率 {:.4}%, 支付 {:.4}",
+                    candle.symbol,
+                    rate.rate * 100.0,
+                    payment
+                );
+            }
+        }
+
+        if let Some(index) = portfolio.sleeve_index(&symbol_string)
+            && let Some(position) = portfolio.sleeves[index].positions.get_mut(&symbol_string)
+        {
+            position.highest = position.highest.max(candle.high);
+            position.lowest = position.lowest.min(candle.low);
+        }
+
+        if let Some(index) = portfolio.sleeve_index(&symbol_string)
+            && let Some(position) = portfolio.sleeves[index].positions.get(&symbol_string)
+            && let Some(liq_price) = position.liquidation_price(margin.maintenance_margin_rate)
+            && candle.low <= liq_price
+        {
+            // 跳空低开时只能以开盘价成交
+            let fill_price = liq_price.min(candle.open);
+            let pnl = (fill_price - position.avg_price) * position.size;
+            let size = position.size;
+            let position_margin = position.margin;
+
+            let sleeve = &mut portfolio.sleeves[index];
+            if let Some(mut position) = sleeve.positions.remove(&symbol_string) {
+                position.realized_pnl += pnl;
+                sleeve
+                    .closed_positions
+                    .push(position.close_record(&symbol_string));
+            }
+            sleeve.available_balance += (position_margin + pnl).max(0.0);
+
+            let equity = portfolio.record(index, candle.open_timestamp_ms, &marks);
+
+            trades.push(Trade {
+                timestamp: candle.open_timestamp_ms,
+                symbol: symbol_string.clone(),
+                side: TradeSide::Sell,
+                price: fill_price,
+                size,
+                balance_after: equity,
+            });
+            liquidations.push(Liquidation {
+                timestamp: candle.open_timestamp_ms,
+                symbol: symbol_string.clone(),
+                price: fill_price,
+                size,
+                loss: -pnl,
+            });
+            audit(
+                &Signal::sell(candle.symbol.clone(), fill_price, size),
+                candle.open_timestamp_ms,
+                AuditOutcome::Liquidated,
+            );
+
+            tracing::warn!(
+                "💥 强平: {} @ {:.2}, 数量: {:.4}, 亏损: {:.2}",
+                candle.symbol,
+                fill_price,
+                size,
+                -pnl
+            );
+        }
+
+        if let Some(scaled_exit) = &scaled_exit
+            && let Some(index) = portfolio.sleeve_index(&symbol_string)
+            && let Some(position) = portfolio.sleeves[index].positions.get_mut(&symbol_string)
+        {
+            for (price, size) in scaled_exit.triggered(position, &candle) {
+                portfolio.sleeves[index].close_position(&symbol_string, price, size);
+                let equity = portfolio.record(index, candle.open_timestamp_ms, &marks);
+
+                trades.push(Trade {
+                    timestamp: candle.open_timestamp_ms,
+                    symbol: symbol_string.clone(),
+                    side: TradeSide::Sell,
+                    price,
+                    size,
+                    balance_after: equity,
+                });
+                audit(
+                    &Signal::sell(candle.symbol.clone(), price, size),
+                    candle.open_timestamp_ms,
+                    AuditOutcome::Filled,
+                );
+
+                tracing::info!(
+                    "🪜 分批平仓: {} @ {:.2}, 数量: {:.4}",
+                    candle.symbol,
+                    price,
+                    size
+                );
+            }
+        }
+
+        // 信号基于收盘后的 K 线产生，之后的处理按收盘价估值
+        marks.insert(symbol_string.clone(), candle.close);
+
+        let signal = match kill_switch.as_mut() {
+            Some(kill_switch) => {
+                kill_switch.on_equity(portfolio.equity(&marks));
+
+                match portfolio.position(&symbol_string) {
+                    Some(position) if kill_switch.should_flatten() => {
+                        Signal::sell(candle.symbol.clone(), candle.close, position.size)
+                    }
+                    _ => {
+                        let filtered = kill_switch.filter(signal.clone());
+                        if filtered.is_hold() && !signal.is_hold() {
+                            audit(&signal, candle.open_timestamp_ms, rejected("kill switch"));
+                        }
+                        filtered
+                    }
+                }
+            }
+            None => signal,
+        };
+
+        let signal = match limiter.as_mut() {
+            Some(limiter) => {
+                limiter.on_bar(&candle.symbol);
+
+                match limiter.check(&signal) {
+                    Some(violation) => {
+                        audit(
+                            &signal,
+                            candle.open_timestamp_ms,
+                            rejected(violation.reason()),
+                        );
+                        Signal::Hold
+                    }
+                    None => signal,
+                }
+            }
+            None => signal,
+        };
+
+        match signal.clone() {
+            Signal::Buy {
+                symbol,
+                price,
+                size,
+            } => {
+                let symbol_string = symbol.to_string();
+                let required_margin = price * size / margin.leverage;
+
+                match portfolio.sleeve_index(&symbol_string) {
+                    Some(index)
+                        if portfolio.sleeves[index].available_balance >= required_margin =>
+                    {
+                        let sleeve = &mut portfolio.sleeves[index];
+                        sleeve.available_balance -= required_margin;
+
+                        let position =
+                            sleeve
+                                .positions
+                                .entry(symbol_string.clone())
+                                .or_insert(Position {
+                                    size: 0.0,
+                                    avg_price: 0.0,
+                                    margin: 0.0,
+                                    exit_base_size: 0.0,
+                                    exits_hit: Vec::new(),
+                                    highest: price,
+                                    lowest: price,
+                                    peak_size: 0.0,
+                                    realized_pnl: 0.0,
+                                    stop_distance: scaled_exit
+                                        .as_ref()
+                                        .and_then(ScaledExit::stop_pct)
+                                        .map(|pct| price * pct / 100.0),
+                                });
+                        position.margin += required_margin;
+
+                        if position.size == 0.0 {
+                            position.avg_price = price;
+                            position.size = size;
+                        } else {
+                            let total_cost = position.avg_price * position.size + price * size;
+                            position.size += size;
+                            position.avg_price = total_cost / position.size;
+                        }
+                        position.peak_size = position.peak_size.max(position.size);
+                        // 加仓后按新的均价和数量重新计算分批平仓档位
+                        position.exit_base_size = position.size;
+                        position.exits_hit.clear();
+
+                        let available_balance = sleeve.available_balance;
+                        let equity = portfolio.record(index, candle.open_timestamp_ms, &marks);
+                        if let Some(limiter) = limiter.as_mut() {
+                            limiter.on_entry(&symbol);
+                        }
+
+                        trades.push(Trade {
+                            timestamp: candle.open_timestamp_ms,
+                            symbol: symbol_string,
+                            side: TradeSide::Buy,
+                            price,
+                            size,
+                            balance_after: equity,
+                        });
+                        audit(&signal, candle.open_timestamp_ms, AuditOutcome::Filled);
+
+                        tracing::info!(
+                            "📈 买入: {} @ {:.2}, 数量: {:.4}, 余额: {:.2}",
+                            symbol,
+                            price,
+                            size,
+                            available_balance
+                        );
+                    }
+                    Some(_) => audit(
+                        &signal,
+                        candle.open_timestamp_ms,
+                        rejected("insufficient margin"),
+                    ),
+                    None => {
+                        tracing::warn!("{} 未分配资金，忽略买入信号", symbol);
+                        audit(&signal, candle.open_timestamp_ms, rejected("no allocation"));
+                    }
+                }
+            }
+            Signal::Sell {
+                symbol,
+                price,
+                size,
+            } => {
+                let symbol_string = symbol.to_string();
+
+                let actual_size = portfolio
+                    .position(&symbol_string)
+                    .map(|p| size.min(p.size))
+                    .unwrap_or(0.0);
+
+                if actual_size > 0.0
+                    && let Some(index) = portfolio.sleeve_index(&symbol_string)
+                {
+                    let sleeve = &mut portfolio.sleeves[index];
+                    sleeve.close_position(&symbol_string, price, actual_size);
+
+                    let available_balance = sleeve.available_balance;
+                    let equity = portfolio.record(index, candle.open_timestamp_ms, &marks);
+
+                    trades.push(Trade {
+                        timestamp: candle.open_timestamp_ms,
+                        symbol: symbol_string,
+                        side: TradeSide::Sell,
+                        price,
+                        size: actual_size,
+                        balance_after: equity,
+                    });
+                    audit(
+                        &Signal::sell(symbol.clone(), price, actual_size),
+                        candle.open_timestamp_ms,
+                        AuditOutcome::Filled,
+                    );
+
+                    tracing::info!(
+                        "📉 卖出: {} @ {:.2}, 数量: {:.4}, 余额: {:.2}",
+                        symbol,
+                        price,
+                        actual_size,
+                        available_balance
+                    );
+                } else {
+                    audit(&signal, candle.open_timestamp_ms, rejected("no position"));
+                }
+            }
+            Signal::Hold => {}
+        }
+
+        // 卖出、分批平仓和强平都可能让持仓归零，统一在 K 线处理完后同步
+        if let Some(limiter) = limiter.as_mut()
+            && portfolio.position(&symbol_string).is_none()
+        {
+            limiter.on_flat(&candle.symbol);
+        }
+
+        if candles_processed % PROGRESS_INTERVAL == 0 {
+            progress(BacktestProgress {
+                candles_processed,
+                equity: portfolio.equity(&marks),
+                elapsed: start.elapsed(),
+            });
+        }
+    }
+
+    // 计算最终余额（持仓按保证金计）
+    let final_balance = portfolio.sleeves.iter().map(Sleeve::balance).sum::<f64>();
+
+    progress(BacktestProgress {
+        candles_processed,
+        equity: final_balance,
+        elapsed: start.elapsed(),
+    });
+
+    Ok(portfolio.into_report(
+        initial_balance,
+        final_balance,
+        trades,
+        liquidations,
+        funding_paid,
+        time_range,
+    ))
+}
+
+/// 按 [`FillTiming`] 推迟成交
+///
+/// `NextOpen`/`NextClose` 时，每根 K 线产生的信号改为在同一交易对的下一根 K 线上以其开盘价/收盘价成交，
+/// 当前 K 线则执行上一根 K 线留下的信号。流结束时最后一个未成交的信号被丢弃。
+fn defer_fills(
+    signal_stream: impl Stream<Item = (Signal, CandleData)> + Send,
+    fill_timing: FillTiming,
+) -> impl Stream<Item = (Signal, CandleData)> + Send {
+    let mut pending: std::collections::HashMap<ephemera_shared::Symbol, Signal> =
+        std::collections::HashMap::new();
+
+    signal_stream.map(move |(signal, candle)| {
+        let fill_price = match fill_timing {
+            FillTiming::SignalPrice => return (signal, candle),
+            FillTiming::NextOpen => candle.open,
+            FillTiming::NextClose => candle.close,
+        };
+
+        let deferred = match pending.insert(candle.symbol.clone(), signal) {
+            Some(Signal::Buy { symbol, size, .. }) => Signal::buy(symbol, fill_price, size),
+            Some(Signal::Sell { symbol, size, .. }) => Signal::sell(symbol, fill_price, size),
+            Some(Signal::Hold) | None => Signal::Hold,
+        };
+
+        (deferred, candle)
+    })
+}
+
+/// 回测配置
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub initial_balance: f64,
+    pub margin: MarginConfig,
+    /// 回撤熔断，`None` 表示不启用
+    pub risk: Option<RiskConfig>,
+    /// 持仓数量约束，`None` 表示不启用
+    pub constraints: Option<PortfolioConstraints>,
+    pub equity_resolution: EquityResolution,
+    pub allocation: Allocation,
+    pub fill_timing: FillTiming,
+    /// 分批止盈止损，`None` 表示不启用
+    pub scaled_exit: Option<ScaledExit>,
+    /// 永续合约的资金费率，为空表示不计资金费
+    pub funding_rates: Vec<FundingRate>,
+    /// 多计价货币组合的汇率，`None` 表示所有交易对都以同一种货币计价
+    pub fx: Option<FxRates>,
+    /// 合约的产品信息，信号数量按张数解释，盈亏按 `张数 * ct_val * 价格变动` 计算。
+    /// 不在列表中的交易对按现货处理
+    pub instruments: Vec<InstrumentInfo>,
+    /// 审计记录，`None` 表示不记录
+    pub audit: Option<Auditor>,
+}
+
+/// 回测中信号的成交时机
+///
+/// 策略通常在 K 线收盘后才产生信号，以同一根 K 线的价格成交相当于使用了未来数据，会高估收益。
+/// `NextOpen` 是最接近实盘的选择，应作为默认值；`SignalPrice` 只适合与旧结果对比。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FillTiming {
+    /// 以信号价格在产生信号的 K 线上立即成交
+    SignalPrice,
+    /// 在下一根 K 线以开盘价成交
+    #[default]
+    NextOpen,
+    /// 在下一根 K 线以收盘价成交
+    NextClose,
+}
+
+/// 分批止盈止损
+///
+/// 每一档为 `(相对开仓均价的偏移百分比, 平仓比例)`。偏移为正时是止盈档，K 线最高价达到目标价时触发；
+/// 为负时是止损档，最低价跌到目标价时触发。平仓比例相对于建仓（或最近一次加仓）后的持仓数量，
+/// 每档只触发一次，加仓后所有档位重新生效。
+#[derive(Debug, Clone, Default)]
+pub struct ScaledExit {
+    pub levels: Vec<(f64, f64)>,
+}
+
+impl ScaledExit {
+    /// 标记本根 K 线触发的档位，返回依次成交的 `(价格, 数量)`
+    ///
+    /// 无法得知 K 线内的价格路径，同一根 K 线同时触及止损和止盈时保守地先止损，止盈档按目标价从低到高成交。
+    /// 跳空越过目标价时以开盘价成交。平仓数量累计不超过剩余持仓。
+    pub(crate) fn triggered(
+        &self,
+        position: &mut Position,
+        candle: &CandleData,
+    ) -> Vec<(f64, f64)> {
+        position.exits_hit.resize(self.levels.len(), false);
+
+        let mut levels: Vec<_> = self.levels.iter().copied().enumerate().collect();
+        levels
+            .sort_by(|(_, (a, _)), (_, (b, _))| (*a >= 0.0).cmp(&(*b >= 0.0)).then(a.total_cmp(b)));
+
+        let mut remaining = position.size;
+        let mut fills = Vec::new();
+
+        for (i, (offset_pct, fraction)) in levels {
+            if position.exits_hit[i] || remaining <= 0.0 {
+                continue;
+            }
+
+            let target = position.avg_price * (1.0 + offset_pct / 100.0);
+            let fill_price = if offset_pct >= 0.0 && candle.high >= target {
+                target.max(candle.open)
+            } else if offset_pct < 0.0 && candle.low <= target {
+                target.min(candle.open)
+            } else {
+                continue;
+            };
+
+            position.exits_hit[i] = true;
+            let mut size = (position.exit_base_size * fraction).min(remaining);
+            // 比例之和为 1 时避免浮点误差留下极小的残余持仓
+            if remaining - size <= remaining * 1e-9 {
+                size = remaining;
+            }
+            if size > 0.0 {
+                remaining -= size;
+                fills.push((fill_price, size));
+            }
+        }
+
+        fills
+    }
+
+    /// 最深一档止损相对开仓均价的跌幅（百分比），没有止损档时为 `None`
+    pub(crate) fn stop_pct(&self) -> Option<f64> {
+        self.levels
+            .iter()
+            .filter(|(offset_pct, _)| *offset_pct < 0.0)
+            .map(|(offset_pct, _)| -offset_pct)
+            .reduce(f64::max)
+    }
+}
+
+/// 一次资金费结算
+///
+/// 在 `funding_time_ms` 持有的仓位按 `数量 × 标记价格 × 费率` 结算，费率为正时多头向空头支付
+#[derive(Debug, Clone)]
+pub struct FundingRate {
+    pub symbol: String,
+    pub funding_time_ms: u64,
+    pub rate: f64,
+}
+
+/// 多计价货币组合的汇率
+///
+/// 回测以 `quote_currency` 记账。交易对的计价货币取最后一个 `-` 之后的部分（如 `ETH-BTC` 的 `BTC`），
+/// 没有 `-` 的交易对视为以记账货币计价。其他货币计价的 K 线和信号价格先按汇率换算为记账货币再撮合，
+/// 因此报告中的成交价格也是记账货币，持仓的盈亏包含汇率变动。
+///
+/// `rates` 为 1 单位货币折合多少记账货币。回测中遇到 `X-{quote_currency}` 的 K 线时以其收盘价更新 `X` 的汇率。
+#[derive(Debug, Clone)]
+pub struct FxRates {
+    quote_currency: String,
+    rates: std::collections::HashMap<String, f64>,
+}
+
+impl FxRates {
+    pub fn new(quote_currency: impl Into<String>) -> Self {
+        Self {
+            quote_currency: quote_currency.into(),
+            rates: Default::default(),
+        }
+    }
+
+    pub fn with_rate(mut self, currency: impl Into<String>, rate: f64) -> Self {
+        self.rates.insert(currency.into(), rate);
+        self
+    }
+
+    /// 交易对的计价货币
+    fn quote_of(symbol: &str) -> Option<&str> {
+        symbol.rsplit_once('-').map(|(_, quote)| quote)
+    }
+
+    /// 1 单位 `currency` 折合多少记账货币
+    fn rate(&self, currency: &str) -> Option<f64> {
+        if currency == self.quote_currency {
+            Some(1.0)
+        } else {
+            self.rates.get(currency).copied()
+        }
+    }
+
+    /// 以记账货币计价的 K 线更新其基础货币的汇率
+    fn update(&mut self, candle: &CandleData) {
+        if let Some((base, quote)) = candle.symbol.rsplit_once('-')
+            && quote == self.quote_currency
+        {
+            self.rates.insert(base.to_string(), candle.close);
+        }
+    }
+
+    /// 将信号和 K 线的价格换算为记账货币
+    fn convert(&self, signal: Signal, mut candle: CandleData) -> Result<(Signal, CandleData)> {
+        let Some(quote) = Self::quote_of(&candle.symbol) else {
+            return Ok((signal, candle));
+        };
+        let rate = self
+            .rate(quote)
+            .ok_or_else(|| eyre::eyre!("No FX rate from {quote} to {}", self.quote_currency))?;
+
+        candle.open *= rate;
+        candle.high *= rate;
+        candle.low *= rate;
+        candle.close *= rate;
+
+        let signal = match signal {
+            Signal::Buy {
+                symbol,
+                price,
+                size,
+            } => Signal::buy(symbol, price * rate, size),
+            Signal::Sell {
+                symbol,
+                price,
+                size,
+            } => Signal::sell(symbol, price * rate, size),
+            Signal::Hold => Signal::Hold,
+        };
+
+        Ok((signal, candle))
+    }
+}
+
+/// 合约交易对 -> 合约面值
+///
+/// 只支持正向合约，反向合约的盈亏以基础货币结算，与回测的记账方式不符
+fn contract_multipliers(
+    instruments: &[InstrumentInfo],
+) -> Result<std::collections::HashMap<String, f64>> {
+    let mut multipliers = std::collections::HashMap::new();
+
+    for info in instruments {
+        if !matches!(info.inst_type, OkxInstType::Swap | OkxInstType::Futures) {
+            continue;
+        }
+        if info.contract_type == Some(OkxContractType::Inverse) {
+            eyre::bail!(
+                "Inverse contract {} is not supported in backtest",
+                info.inst_id
+            );
+        }
+        let ct_val = info
+            .ct_val
+            .ok_or_else(|| eyre::eyre!("Missing ctVal for {}", info.inst_id))?;
+
+        multipliers.insert(info.inst_id.to_string(), ct_val);
+    }
+
+    Ok(multipliers)
+}
+
+fn scale_signal_size(signal: Signal, factor: f64) -> Signal {
+    match signal {
+        Signal::Buy {
+            symbol,
+            price,
+            size,
+        } => Signal::buy(symbol, price, size * factor),
+        Signal::Sell {
+            symbol,
+            price,
+            size,
+        } => Signal::sell(symbol, price, size * factor),
+        Signal::Hold => Signal::Hold,
+    }
+}
+
+/// 资金分配方式
+#[derive(Debug, Clone, Default)]
+pub enum Allocation {
+    /// 所有交易对共用同一份余额，先到先得
+    #[default]
+    Shared,
+    /// 组合模式: 初始资金按权重（会被归一化）分给各交易对的子账户，各自独立开仓和结算。
+    /// 不在列表中的交易对的买入信号会被忽略
+    Weighted(Vec<(String, f64)>),
+}
+
+impl Allocation {
+    /// 等权重分配
+    pub fn equal_weight(symbols: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self::Weighted(symbols.into_iter().map(|s| (s.into(), 1.0)).collect())
+    }
+}
+
+/// 杠杆与保证金配置
+#[derive(Debug, Clone, Copy)]
+pub struct MarginConfig {
+    /// 杠杆倍数，1.0 即现货
+    pub leverage: f64,
+    /// 维持保证金率（占名义价值的比例）
+    pub maintenance_margin_rate: f64,
+}
+
+/// 回测进度
+#[derive(Debug, Clone, Copy)]
+pub struct BacktestProgress {
+    /// 已处理的 K 线数量
+    pub candles_processed: usize,
+    /// 当前总权益
+    pub equity: f64,
+    /// 回测已运行时间
+    pub elapsed: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{candle, spot_config};
+    use ephemera_strategy::audit::SignalKind;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_backtest_liquidation() {
+        let config = BacktestConfig {
+            initial_balance: 1000.0,
+            margin: MarginConfig {
+                leverage: 20.0,
+                maintenance_margin_rate: 0.005,
+            },
+            risk: None,
+            constraints: None,
+            equity_resolution: EquityResolution::Full,
+            allocation: Allocation::Shared,
+            fill_timing: FillTiming::SignalPrice,
+            scaled_exit: None,
+            funding_rates: Vec::new(),
+            fx: None,
+            instruments: Vec::new(),
+            audit: None,
+        };
+
+        // 10 @ 100，占用保证金 50，强平价 = (1000 - 50) / (10 * 0.995) ≈ 95.48
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 10.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (Signal::Hold, candle(60_000, 99.0, 90.0, 92.0)),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        assert_eq!(report.liquidations.len(), 1);
+        assert!(report.positions.is_empty());
+
+        let liquidation = &report.liquidations[0];
+        let liq_price = 950.0 / 9.95;
+        approx::assert_abs_diff_eq!(liquidation.price, liq_price, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(liquidation.loss, (100.0 - liq_price) * 10.0, epsilon = 1e-9);
+        // 剩余的维持保证金退回
+        approx::assert_abs_diff_eq!(
+            report.final_balance,
+            950.0 + 50.0 - liquidation.loss,
+            epsilon = 1e-9
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backtest_fill_timing() {
+        let signals = || {
+            futures::stream::iter(vec![
+                (
+                    Signal::buy("BTC-USDT".into(), 100.0, 1.0),
+                    candle(0, 100.0, 100.0, 100.0),
+                ),
+                (
+                    Signal::sell("BTC-USDT".into(), 110.0, 1.0),
+                    candle(60_000, 105.0, 105.0, 110.0),
+                ),
+                (Signal::Hold, candle(120_000, 108.0, 107.0, 107.0)),
+            ])
+        };
+        let run = |fill_timing| async move {
+            let config = BacktestConfig {
+                fill_timing,
+                ..spot_config(1000.0)
+            };
+            execute_backtest(signals(), config, |_| {}).await.unwrap()
+        };
+        let prices =
+            |report: &BacktestReport| report.trades.iter().map(|t| t.price).collect::<Vec<_>>();
+
+        // 以信号价格成交: 100 买入，110 卖出
+        let report = run(FillTiming::SignalPrice).await;
+        assert_eq!(prices(&report), vec![100.0, 110.0]);
+        approx::assert_abs_diff_eq!(report.final_balance, 1010.0, epsilon = 1e-9);
+
+        // 下一根 K 线开盘成交: 105 买入，108 卖出，收益明显更低
+        let report = run(FillTiming::NextOpen).await;
+        assert_eq!(prices(&report), vec![105.0, 108.0]);
+        assert_eq!(report.trades[0].timestamp, 60_000);
+        approx::assert_abs_diff_eq!(report.final_balance, 1003.0, epsilon = 1e-9);
+
+        // 下一根 K 线收盘成交: 110 买入，107 卖出
+        let report = run(FillTiming::NextClose).await;
+        assert_eq!(prices(&report), vec![110.0, 107.0]);
+        approx::assert_abs_diff_eq!(report.final_balance, 997.0, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_scaled_exit() {
+        let config = BacktestConfig {
+            scaled_exit: Some(ScaledExit {
+                levels: vec![(2.0, 0.5), (4.0, 0.3), (6.0, 0.2), (-3.0, 1.0)],
+            }),
+            ..spot_config(1000.0)
+        };
+
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 1.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (Signal::Hold, candle(60_000, 100.0, 99.0, 101.0)),
+            (Signal::Hold, candle(120_000, 101.0, 100.5, 102.5)),
+            (Signal::Hold, candle(180_000, 102.5, 102.0, 104.2)),
+            // 跳空高开越过 +6%，以开盘价成交
+            (Signal::Hold, candle(240_000, 107.0, 106.0, 107.5)),
+            // 已经全部平仓，不再触发止损
+            (Signal::Hold, candle(300_000, 96.0, 90.0, 91.0)),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        let exits: Vec<_> = report.trades[1..]
+            .iter()
+            .map(|t| (t.timestamp, t.price, t.size))
+            .collect();
+        assert_eq!(
+            exits,
+            vec![
+                (120_000, 102.0, 0.5),
+                (180_000, 104.0, 0.3),
+                (240_000, 107.0, 0.2)
+            ]
+        );
+        assert!(report.positions.is_empty());
+        // 0.5 * 2 + 0.3 * 4 + 0.2 * 7
+        approx::assert_abs_diff_eq!(report.final_balance, 1003.6, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_scaled_exit_stop_loss_first() {
+        let config = BacktestConfig {
+            scaled_exit: Some(ScaledExit {
+                levels: vec![(5.0, 1.0), (-2.0, 1.0)],
+            }),
+            ..spot_config(1000.0)
+        };
+
+        // 同一根 K 线同时触及止盈和止损，按止损处理
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 2.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (
+                Signal::Hold,
+                CandleData {
+                    high: 106.0,
+                    ..candle(60_000, 100.0, 97.0, 100.0)
+                },
+            ),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        assert_eq!(report.trades.len(), 2);
+        assert_eq!((report.trades[1].price, report.trades[1].size), (98.0, 2.0));
+        approx::assert_abs_diff_eq!(report.final_balance, 996.0, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_r_multiple() {
+        // 止损 -5%，100 开仓时每单位风险 5
+        let config = BacktestConfig {
+            scaled_exit: Some(ScaledExit {
+                levels: vec![(-5.0, 1.0)],
+            }),
+            ..spot_config(10_000.0)
+        };
+        let bar = |open_timestamp_ms, open: f64, high: f64, low: f64, close: f64| CandleData {
+            high,
+            ..candle(open_timestamp_ms, open, low, close)
+        };
+        let buy = Signal::buy("BTC-USDT".into(), 100.0, 10.0);
+        let sell = |price| Signal::sell("BTC-USDT".into(), price, 10.0);
+
+        let signals = futures::stream::iter(vec![
+            // 盈利: 最低 97，最高 112，以 110 平仓，+10 / 5 = 2R
+            (buy.clone(), bar(0, 100.0, 100.0, 100.0, 100.0)),
+            (Signal::Hold, bar(60_000, 100.0, 112.0, 97.0, 108.0)),
+            (sell(110.0), bar(120_000, 108.0, 110.0, 108.0, 110.0)),
+            // 亏损: 跌破止损价 95，以 95 止损，-1R
+            (buy, bar(180_000, 100.0, 100.0, 100.0, 100.0)),
+            (Signal::Hold, bar(240_000, 100.0, 101.0, 94.0, 96.0)),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        assert_eq!(report.closed_positions.len(), 2);
+        let (win, loss) = (&report.closed_positions[0], &report.closed_positions[1]);
+        approx::assert_abs_diff_eq!(win.pnl, 100.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(win.r_multiple.unwrap(), 2.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(win.mfe_pct, 12.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(win.mae_pct, 3.0, epsilon = 1e-9);
+
+        approx::assert_abs_diff_eq!(loss.pnl, -50.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(loss.r_multiple.unwrap(), -1.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(loss.mfe_pct, 1.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(loss.mae_pct, 6.0, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_funding() {
+        const HOUR_MS: u64 = 3_600_000;
+        let funding = |symbol: &str, funding_time_ms, rate| FundingRate {
+            symbol: symbol.to_string(),
+            funding_time_ms,
+            rate,
+        };
+        let config = BacktestConfig {
+            funding_rates: vec![
+                funding("BTC-USDT", 16 * HOUR_MS, 0.0005),
+                // 开仓之前的结算不影响
+                funding("BTC-USDT", 0, 0.01),
+                funding("BTC-USDT", 8 * HOUR_MS, 0.001),
+                funding("ETH-USDT", 8 * HOUR_MS, 0.01),
+            ],
+            ..spot_config(1000.0)
+        };
+
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 1.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (Signal::Hold, candle(8 * HOUR_MS, 110.0, 110.0, 110.0)),
+            (Signal::Hold, candle(16 * HOUR_MS, 120.0, 120.0, 120.0)),
+            (
+                Signal::sell("BTC-USDT".into(), 120.0, 1.0),
+                candle(24 * HOUR_MS, 120.0, 120.0, 120.0),
+            ),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        // 1 * 110 * 0.001 + 1 * 120 * 0.0005
+        approx::assert_abs_diff_eq!(report.funding_paid, 0.17, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(report.final_balance, 1000.0 + 20.0 - 0.17, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(
+            *report.equity_curve.last().unwrap(),
+            report.final_balance,
+            epsilon = 1e-9
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backtest_funding_marks_at_open() {
+        let config = BacktestConfig {
+            funding_rates: vec![FundingRate {
+                symbol: "BTC-USDT".to_string(),
+                funding_time_ms: 60_000,
+                rate: 0.001,
+            }],
+            ..spot_config(1000.0)
+        };
+
+        // 第二根 K 线以 110 开盘、150 收盘，开盘时结算资金费
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 1.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (Signal::Hold, candle(60_000, 110.0, 110.0, 150.0)),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        // 资金费记录的权益按开盘价估值，不能提前用到收盘价
+        approx::assert_abs_diff_eq!(
+            *report.equity_curve.last().unwrap(),
+            1000.0 + 10.0 - 0.11,
+            epsilon = 1e-9
+        );
+        approx::assert_abs_diff_eq!(report.max_equity, 1000.0 + 10.0 - 0.11, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_fx_conversion() {
+        let config = BacktestConfig {
+            fx: Some(FxRates::new("USDT").with_rate("BTC", 20000.0)),
+            ..spot_config(100_000.0)
+        };
+        let pair = |symbol: &str, open_timestamp_ms, price| CandleData {
+            symbol: symbol.into(),
+            ..candle(open_timestamp_ms, price, price, price)
+        };
+
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 20000.0, 1.0),
+                pair("BTC-USDT", 0, 20000.0),
+            ),
+            // 10 ETH @ 0.05 BTC，按 20000 换算占用 10000 USDT
+            (
+                Signal::buy("ETH-BTC".into(), 0.05, 10.0),
+                pair("ETH-BTC", 0, 0.05),
+            ),
+            // BTC 上涨 10%，ETH-BTC 不变，但以 USDT 计的 ETH 持仓同样上涨
+            (Signal::Hold, pair("BTC-USDT", 60_000, 22000.0)),
+            (Signal::Hold, pair("ETH-BTC", 60_000, 0.05)),
+            (
+                Signal::sell("ETH-BTC".into(), 0.05, 10.0),
+                pair("ETH-BTC", 120_000, 0.05),
+            ),
+            (
+                Signal::sell("BTC-USDT".into(), 22000.0, 1.0),
+                pair("BTC-USDT", 120_000, 22000.0),
+            ),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        let prices: Vec<_> = report.trades.iter().map(|t| t.price).collect();
+        assert_eq!(prices, vec![20000.0, 1000.0, 1100.0, 22000.0]);
+        approx::assert_abs_diff_eq!(report.max_equity, 103_000.0, epsilon = 1e-6);
+        approx::assert_abs_diff_eq!(report.final_balance, 103_000.0, epsilon = 1e-6);
+
+        // 缺少汇率时报错
+        let config = BacktestConfig {
+            fx: Some(FxRates::new("USDT")),
+            ..spot_config(1000.0)
+        };
+        let signals = futures::stream::iter(vec![(Signal::Hold, pair("ETH-BTC", 0, 0.05))]);
+        assert!(execute_backtest(signals, config, |_| {}).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backtest_contract_multiplier() {
+        let swap = InstrumentInfo {
+            inst_id: "BTC-USDT-SWAP".into(),
+            inst_type: OkxInstType::Swap,
+            tick_sz: 0.1,
+            lot_sz: 1.0,
+            min_sz: 1.0,
+            ct_val: Some(0.01),
+            contract_type: Some(OkxContractType::Linear),
+        };
+        let run = |symbol: &'static str, instruments: Vec<InstrumentInfo>| async move {
+            let config = BacktestConfig {
+                instruments,
+                ..spot_config(100_000.0)
+            };
+            let pair = |open_timestamp_ms, price| CandleData {
+                symbol: symbol.into(),
+                ..candle(open_timestamp_ms, price, price, price)
+            };
+            let signals = futures::stream::iter(vec![
+                (Signal::buy(symbol.into(), 100.0, 100.0), pair(0, 100.0)),
+                (
+                    Signal::sell(symbol.into(), 110.0, 100.0),
+                    pair(60_000, 110.0),
+                ),
+            ]);
+
+            execute_backtest(signals, config, |_| {}).await.unwrap()
+        };
+
+        // 现货: 100 * (110 - 100) = 1000
+        let spot = run("BTC-USDT", vec![swap.clone()]).await;
+        approx::assert_abs_diff_eq!(spot.final_balance, 101_000.0, epsilon = 1e-6);
+
+        // 合约: 100 张 * 0.01 * (110 - 100) = 10
+        let report = run("BTC-USDT-SWAP", vec![swap.clone()]).await;
+        approx::assert_abs_diff_eq!(report.final_balance, 100_010.0, epsilon = 1e-6);
+        approx::assert_abs_diff_eq!(report.trades[0].size, 1.0, epsilon = 1e-9);
+
+        let inverse = InstrumentInfo {
+            contract_type: Some(OkxContractType::Inverse),
+            ..swap
+        };
+        let config = BacktestConfig {
+            instruments: vec![inverse],
+            ..spot_config(1000.0)
+        };
+        let signals = futures::stream::iter(Vec::<(Signal, CandleData)>::new());
+        assert!(execute_backtest(signals, config, |_| {}).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backtest_without_leverage_matches_spot() {
+        let config = spot_config(1000.0);
+
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 5.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (Signal::Hold, candle(60_000, 100.0, 1.0, 50.0)),
+            (
+                Signal::sell("BTC-USDT".into(), 110.0, 5.0),
+                candle(120_000, 110.0, 110.0, 110.0),
+            ),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        assert!(report.liquidations.is_empty());
+        approx::assert_abs_diff_eq!(report.final_balance, 1050.0, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_kill_switch_suppresses_buys() {
+        let config = BacktestConfig {
+            risk: Some(RiskConfig {
+                max_drawdown_pct: 10.0,
+                resume_drawdown_pct: 5.0,
+                flatten_on_breach: false,
+            }),
+            ..spot_config(1000.0)
+        };
+        let buy = |price| Signal::buy("BTC-USDT".into(), price, 5.0);
+
+        let signals = futures::stream::iter(vec![
+            // 全仓买入: 5 @ 200
+            (buy(200.0), candle(0, 200.0, 200.0, 200.0)),
+            // 跌至 150，权益 750，回撤 25% 触发熔断，这次卖出仍然执行
+            (
+                Signal::sell("BTC-USDT".into(), 150.0, 5.0),
+                candle(60_000, 150.0, 150.0, 150.0),
+            ),
+            // 权益仍为 750，买入被忽略
+            (buy(100.0), candle(120_000, 100.0, 100.0, 100.0)),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        assert_eq!(report.trades.len(), 2);
+        assert_eq!(report.trades[1].side, TradeSide::Sell);
+        assert!(report.positions.is_empty());
+        approx::assert_abs_diff_eq!(report.final_balance, 750.0, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_max_concurrent_positions() {
+        let config = BacktestConfig {
+            constraints: Some(PortfolioConstraints {
+                max_concurrent_positions: Some(5),
+                ..Default::default()
+            }),
+            ..spot_config(100_000.0)
+        };
+        let pair = |symbol: &str, open_timestamp_ms| CandleData {
+            symbol: symbol.into(),
+            ..candle(open_timestamp_ms, 100.0, 100.0, 100.0)
+        };
+        let buy = |symbol: &str| Signal::buy(symbol.into(), 100.0, 1.0);
+
+        let mut signals: Vec<_> = (0..6)
+            .map(|i| {
+                let symbol = format!("COIN{i}-USDT");
+                (buy(&symbol), pair(&symbol, 0))
+            })
+            .collect();
+        // 平掉一个持仓后，第 6 个交易对可以开仓
+        signals.push((
+            Signal::sell("COIN0-USDT".into(), 100.0, 1.0),
+            pair("COIN0-USDT", 60_000),
+        ));
+        signals.push((buy("COIN5-USDT"), pair("COIN5-USDT", 60_000)));
+
+        let report = execute_backtest(futures::stream::iter(signals), config, |_| {})
+            .await
+            .unwrap();
+
+        let trades: Vec<_> = report
+            .trades
+            .iter()
+            .map(|t| (t.symbol.as_str(), t.side.clone()))
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                ("COIN0-USDT", TradeSide::Buy),
+                ("COIN1-USDT", TradeSide::Buy),
+                ("COIN2-USDT", TradeSide::Buy),
+                ("COIN3-USDT", TradeSide::Buy),
+                ("COIN4-USDT", TradeSide::Buy),
+                ("COIN0-USDT", TradeSide::Sell),
+                ("COIN5-USDT", TradeSide::Buy),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backtest_portfolio_allocation() {
+        let config = BacktestConfig {
+            allocation: Allocation::equal_weight(["BTC-USDT", "ETH-USDT"]),
+            ..spot_config(2000.0)
+        };
+        let eth_candle = |timestamp, close| CandleData {
+            symbol: "ETH-USDT".into(),
+            ..candle(timestamp, close, close, close)
+        };
+
+        let signals = futures::stream::iter(vec![
+            // 超出 BTC 子账户的 1000，即使总余额足够也不能开仓
+            (
+                Signal::buy("BTC-USDT".into(), 150.0, 10.0),
+                candle(0, 150.0, 150.0, 150.0),
+            ),
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 5.0),
+                candle(60_000, 100.0, 100.0, 100.0),
+            ),
+            (
+                Signal::buy("ETH-USDT".into(), 100.0, 10.0),
+                eth_candle(60_000, 100.0),
+            ),
+            // 未分配资金的交易对被忽略
+            (
+                Signal::buy("SOL-USDT".into(), 10.0, 1.0),
+                CandleData {
+                    symbol: "SOL-USDT".into(),
+                    ..candle(60_000, 10.0, 10.0, 10.0)
+                },
+            ),
+            (
+                Signal::sell("BTC-USDT".into(), 120.0, 5.0),
+                candle(120_000, 120.0, 120.0, 120.0),
+            ),
+            (
+                Signal::sell("ETH-USDT".into(), 50.0, 10.0),
+                eth_candle(120_000, 50.0),
+            ),
+        ]);
+
+        let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        assert_eq!(report.trades.len(), 4);
+        approx::assert_abs_diff_eq!(report.final_balance, 1600.0, epsilon = 1e-9);
+
+        let [btc, eth] = &report.sleeves[..] else {
+            panic!("expected two sleeves");
+        };
+        assert_eq!(btc.symbol, "BTC-USDT");
+        approx::assert_abs_diff_eq!(btc.initial_balance, 1000.0);
+        approx::assert_abs_diff_eq!(btc.final_balance, 1100.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(btc.max_drawdown_pct, 0.0);
+        approx::assert_abs_diff_eq!(eth.final_balance, 500.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(eth.max_drawdown_pct, 50.0, epsilon = 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_audit_records() {
+        use ephemera_strategy::audit::MemoryAuditSink;
+
+        let sink = Arc::new(MemoryAuditSink::new());
+        let config = BacktestConfig {
+            audit: Some(Auditor::new("test", sink.clone())),
+            ..spot_config(1000.0)
+        };
+        let buy = |price, size| Signal::buy("BTC-USDT".into(), price, size);
+
+        let signals = futures::stream::iter(vec![
+            (buy(100.0, 5.0), candle(0, 100.0, 100.0, 100.0)),
+            // 余额只剩 500，买不起 10 @ 100
+            (buy(100.0, 10.0), candle(60_000, 100.0, 100.0, 100.0)),
+            // 只持有 5，按实际平仓数量记录
+            (
+                Signal::sell("BTC-USDT".into(), 110.0, 8.0),
+                candle(120_000, 110.0, 110.0, 110.0),
+            ),
+            (
+                Signal::sell("BTC-USDT".into(), 110.0, 1.0),
+                candle(180_000, 110.0, 110.0, 110.0),
+            ),
+            (Signal::Hold, candle(240_000, 110.0, 110.0, 110.0)),
+        ]);
+        execute_backtest(signals, config, |_| {}).await.unwrap();
+
+        let records = sink.records();
+        let outcomes: Vec<_> = records.iter().map(|r| r.outcome.clone()).collect();
+        let rejected = |reason: &str| AuditOutcome::Rejected {
+            reason: reason.into(),
+        };
+        assert_eq!(
+            outcomes,
+            vec![
+                AuditOutcome::Filled,
+                rejected("insufficient margin"),
+                AuditOutcome::Filled,
+                rejected("no position"),
+            ]
+        );
+        assert!(records.iter().all(|r| r.strategy == "test"));
+        assert_eq!(records[2].signal, SignalKind::Sell);
+        approx::assert_abs_diff_eq!(records[2].size, 5.0);
+        assert_eq!(records[2].timestamp_ms, 120_000);
+    }
+}
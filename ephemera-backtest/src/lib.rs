@@ -0,0 +1,7 @@
+pub mod engine;
+pub mod metrics;
+pub mod pipeline;
+pub mod portfolio;
+pub mod report;
+#[cfg(test)]
+mod test_utils;
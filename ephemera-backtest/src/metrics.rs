@@ -0,0 +1,395 @@
+use crate::report::{BacktestReport, Trade, TradeSide};
+
+/// 每年的交易日。加密货币市场全年无休，传统股票市场通常为 252
+pub const CRYPTO_TRADING_DAYS: f64 = 365.0;
+
+/// 权益曲线每年的采样点数: `(点数 - 1) / elapsed_years`
+///
+/// 权益在每次成交、强平或资金费结算时记录，还可能按 [`EquityResolution`] 降采样，
+/// 相邻两点的间隔并不是一根 K 线，因此不能用 K 线周期推算，只能用回测覆盖的时间平均。
+/// `elapsed_years` 见 [`BacktestReport::elapsed_years`]，不为正时无法年化，返回 0.0。
+///
+/// [`EquityResolution`]: crate::portfolio::EquityResolution
+pub fn periods_per_year(equity_curve: &[f64], elapsed_years: f64) -> f64 {
+    if elapsed_years <= 0.0 {
+        return 0.0;
+    }
+
+    equity_curve.len().saturating_sub(1) as f64 / elapsed_years
+}
+
+/// 回测指标的计算结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricValue {
+    Number(f64),
+    /// 百分比，如 `12.5` 表示 12.5%
+    Percent(f64),
+    /// 数据不足，无法计算（例如没有完成的交易）
+    NotAvailable,
+}
+
+impl std::fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MetricValue::Number(value) => write!(f, "{:.2}", value),
+            MetricValue::Percent(value) => write!(f, "{:.2}%", value),
+            MetricValue::NotAvailable => write!(f, "-"),
+        }
+    }
+}
+
+/// 计算指标所需的回测数据
+pub struct RawBacktestData<'a> {
+    pub report: &'a BacktestReport,
+    /// 年化因子，见 [`periods_per_year`]
+    pub periods_per_year: f64,
+}
+
+impl<'a> RawBacktestData<'a> {
+    /// 按每年 `trading_days_per_year` 个交易日计算年化因子
+    pub fn new(report: &'a BacktestReport, trading_days_per_year: f64) -> Self {
+        let elapsed_years = report.elapsed_years(trading_days_per_year);
+        Self {
+            report,
+            periods_per_year: periods_per_year(&report.equity_curve, elapsed_years),
+        }
+    }
+}
+
+/// 回测报告中的一项指标
+///
+/// 实现该 trait 并注册到 [`MetricRegistry`] 即可在报告中加入自定义指标，无需修改报告生成代码。
+pub trait Metric {
+    /// 报告中显示的名称
+    fn name(&self) -> &str;
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue;
+}
+
+/// 总收益率
+struct TotalReturnMetric;
+
+impl Metric for TotalReturnMetric {
+    fn name(&self) -> &str {
+        "收益率"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        let report = data.report;
+        MetricValue::Percent(
+            (report.final_balance - report.initial_balance) / report.initial_balance * 100.0,
+        )
+    }
+}
+
+/// 全分辨率下的最大回撤
+struct MaxDrawdownMetric;
+
+impl Metric for MaxDrawdownMetric {
+    fn name(&self) -> &str {
+        "最大回撤"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        MetricValue::Percent(data.report.max_drawdown_pct)
+    }
+}
+
+/// 年化夏普比率，见 [`calculate_sharpe_ratio`]
+struct SharpeRatioMetric;
+
+impl Metric for SharpeRatioMetric {
+    fn name(&self) -> &str {
+        "夏普比率"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        MetricValue::Number(calculate_sharpe_ratio(
+            &data.report.equity_curve,
+            data.periods_per_year,
+        ))
+    }
+}
+
+/// 年化索提诺比率，见 [`calculate_sortino_ratio`]
+struct SortinoRatioMetric;
+
+impl Metric for SortinoRatioMetric {
+    fn name(&self) -> &str {
+        "索提诺比率"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        MetricValue::Number(calculate_sortino_ratio(
+            &data.report.equity_curve,
+            data.periods_per_year,
+        ))
+    }
+}
+
+/// 卡玛比率，见 [`calculate_calmar_ratio`]
+struct CalmarRatioMetric;
+
+impl Metric for CalmarRatioMetric {
+    fn name(&self) -> &str {
+        "卡玛比率"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        MetricValue::Number(calculate_calmar_ratio(
+            &data.report.equity_curve,
+            data.periods_per_year,
+        ))
+    }
+}
+
+/// 胜率，见 [`calculate_win_loss`]
+struct WinRateMetric;
+
+impl Metric for WinRateMetric {
+    fn name(&self) -> &str {
+        "胜率"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        match calculate_win_loss(&data.report.trades) {
+            (0, 0) => MetricValue::NotAvailable,
+            (winning, losing) => {
+                MetricValue::Percent(winning as f64 / (winning + losing) as f64 * 100.0)
+            }
+        }
+    }
+}
+
+/// 报告中输出的指标，按注册顺序计算和显示
+///
+/// [`MetricRegistry::default`] 包含内置的收益率、最大回撤、夏普、索提诺、卡玛比率和胜率。
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn Metric>>,
+}
+
+impl Default for MetricRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register(TotalReturnMetric)
+            .register(MaxDrawdownMetric)
+            .register(SharpeRatioMetric)
+            .register(SortinoRatioMetric)
+            .register(CalmarRatioMetric)
+            .register(WinRateMetric);
+        registry
+    }
+}
+
+impl MetricRegistry {
+    /// 不含任何指标
+    pub fn empty() -> Self {
+        Self {
+            metrics: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, metric: impl Metric + 'static) -> &mut Self {
+        self.metrics.push(Box::new(metric));
+        self
+    }
+
+    /// 依次计算所有指标，返回 `(名称, 结果)`
+    pub fn compute(&self, data: &RawBacktestData) -> Vec<(String, MetricValue)> {
+        self.metrics
+            .iter()
+            .map(|metric| (metric.name().to_string(), metric.compute(data)))
+            .collect()
+    }
+}
+
+/// 年化夏普比率，`periods_per_year` 见 [`periods_per_year`]
+pub fn calculate_sharpe_ratio(equity_curve: &[f64], periods_per_year: f64) -> f64 {
+    let returns = calculate_returns(equity_curve);
+    if returns.is_empty() {
+        return 0.0;
+    }
+
+    let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns
+        .iter()
+        .map(|r| (r - mean_return).powi(2))
+        .sum::<f64>()
+        / returns.len() as f64;
+    let std_dev = variance.sqrt();
+
+    if std_dev == 0.0 {
+        0.0
+    } else {
+        mean_return / std_dev * periods_per_year.sqrt()
+    }
+}
+
+/// 年化索提诺比率，只用下行收益率（目标收益率为 0）计算波动
+///
+/// 下行偏差为 `sqrt(Σ min(r, 0)² / n)`，没有亏损的周期时返回 0.0
+pub fn calculate_sortino_ratio(equity_curve: &[f64], periods_per_year: f64) -> f64 {
+    let returns = calculate_returns(equity_curve);
+    if returns.is_empty() {
+        return 0.0;
+    }
+
+    let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
+    let downside_variance =
+        returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64;
+    let downside_dev = downside_variance.sqrt();
+
+    if downside_dev == 0.0 {
+        0.0
+    } else {
+        mean_return / downside_dev * periods_per_year.sqrt()
+    }
+}
+
+/// 卡玛比率: 年化收益率（百分比） / 最大回撤（百分比），回撤见 [`calculate_max_drawdown`]
+///
+/// 年化收益率按曲线首尾权益复利折算，`periods_per_year` 见 [`periods_per_year`]。
+/// 没有回撤或无法年化时返回 0.0；极短的回测年化后可能溢出为无穷大，原样返回
+pub fn calculate_calmar_ratio(equity_curve: &[f64], periods_per_year: f64) -> f64 {
+    let max_drawdown = calculate_max_drawdown(equity_curve);
+    let (Some(&first), Some(&last)) = (equity_curve.first(), equity_curve.last()) else {
+        return 0.0;
+    };
+    if equity_curve.len() < 2
+        || max_drawdown == 0.0
+        || periods_per_year <= 0.0
+        || first <= 0.0
+        || last < 0.0
+    {
+        return 0.0;
+    }
+
+    let years = (equity_curve.len() - 1) as f64 / periods_per_year;
+    let annualized_return = (last / first).powf(1.0 / years) - 1.0;
+    annualized_return * 100.0 / max_drawdown
+}
+
+/// 权益曲线的最大回撤（百分比），跳过非有限的权益
+pub fn calculate_max_drawdown(equity_curve: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_drawdown: f64 = 0.0;
+    for &equity in equity_curve.iter().filter(|e| e.is_finite()) {
+        peak = peak.max(equity);
+        if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - equity) / peak * 100.0);
+        }
+    }
+    max_drawdown
+}
+
+/// 相邻采样点之间的收益率，跳过权益为 0 或非有限值产生的无效收益率
+fn calculate_returns(equity_curve: &[f64]) -> Vec<f64> {
+    equity_curve
+        .windows(2)
+        .map(|w| (w[1] - w[0]) / w[0])
+        .filter(|r| r.is_finite())
+        .collect()
+}
+
+/// 盈利和亏损的交易次数，每笔卖出与同一交易对最近一笔未配对的买入比较价格
+pub fn calculate_win_loss(trades: &[Trade]) -> (usize, usize) {
+    use std::collections::HashMap;
+
+    let mut winning = 0;
+    let mut losing = 0;
+    let mut buy_prices: HashMap<String, Vec<f64>> = HashMap::new();
+
+    for trade in trades {
+        match trade.side {
+            TradeSide::Buy => {
+                buy_prices
+                    .entry(trade.symbol.clone())
+                    .or_default()
+                    .push(trade.price);
+            }
+            TradeSide::Sell => {
+                if let Some(prices) = buy_prices.get_mut(&trade.symbol)
+                    && let Some(buy_price) = prices.pop()
+                {
+                    if trade.price > buy_price {
+                        winning += 1;
+                    } else {
+                        losing += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    (winning, losing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::execute_backtest;
+    use crate::test_utils::{candle, spot_config};
+    use ephemera_shared::Signal;
+
+    #[tokio::test]
+    async fn test_periods_per_year_from_elapsed_time() {
+        // 只有两笔成交，12 根一分钟 K 线只产生 3 个权益采样点
+        let signals = futures::stream::iter((0..12).map(|i| {
+            let signal = match i {
+                0 => Signal::buy("BTC-USDT".into(), 10.0, 1.0),
+                10 => Signal::sell("BTC-USDT".into(), 11.0, 1.0),
+                _ => Signal::Hold,
+            };
+            (signal, candle(i * 60_000, 10.0, 10.0, 10.0))
+        }));
+        let report = execute_backtest(signals, spot_config(1000.0), |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(report.time_range, Some((0, 12 * 60_000)));
+        assert_eq!(report.equity_curve.len(), 3);
+        let years = report.elapsed_years(365.0);
+        approx::assert_abs_diff_eq!(years, 12.0 / (365.0 * 24.0 * 60.0), epsilon = 1e-12);
+        // 平均 6 分钟一个采样点，而不是每根 K 线一个
+        approx::assert_abs_diff_eq!(
+            periods_per_year(&report.equity_curve, years),
+            365.0 * 24.0 * 10.0,
+            epsilon = 1e-6
+        );
+        approx::assert_abs_diff_eq!(periods_per_year(&report.equity_curve, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_sortino_and_calmar_ratio() {
+        let curve = [100.0, 110.0, 99.0, 120.0];
+
+        // 收益率 0.1, -0.1, 120/99 - 1，只有 -0.1 计入下行偏差
+        let returns = [0.1, -0.1, 120.0 / 99.0 - 1.0];
+        let mean = returns.iter().sum::<f64>() / 3.0;
+        let downside_dev = (0.01f64 / 3.0).sqrt();
+        approx::assert_abs_diff_eq!(
+            calculate_sortino_ratio(&curve, 365.0),
+            mean / downside_dev * 365.0f64.sqrt(),
+            epsilon = 1e-9
+        );
+
+        // 3 个周期恰好一年: 年化收益 20%，最大回撤 110 -> 99 即 10%
+        approx::assert_abs_diff_eq!(calculate_max_drawdown(&curve), 10.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(calculate_calmar_ratio(&curve, 3.0), 2.0, epsilon = 1e-9);
+        // 6 个周期一年，曲线覆盖半年: 年化收益 1.2² - 1
+        approx::assert_abs_diff_eq!(
+            calculate_calmar_ratio(&curve, 6.0),
+            (1.2f64.powi(2) - 1.0) * 100.0 / 10.0,
+            epsilon = 1e-9
+        );
+
+        // 没有亏损、回撤或无法年化时返回 0.0
+        let rising = [100.0, 110.0, 120.0];
+        approx::assert_abs_diff_eq!(calculate_sortino_ratio(&rising, 365.0), 0.0);
+        approx::assert_abs_diff_eq!(calculate_calmar_ratio(&rising, 365.0), 0.0);
+        approx::assert_abs_diff_eq!(calculate_sortino_ratio(&[100.0], 365.0), 0.0);
+        approx::assert_abs_diff_eq!(calculate_calmar_ratio(&[100.0, 100.0], 365.0), 0.0);
+        approx::assert_abs_diff_eq!(calculate_calmar_ratio(&curve, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,179 @@
+use ephemera_shared::{CandleData, Signal};
+use ephemera_strategy::audit::{AuditOutcome, Auditor};
+use ephemera_strategy::risk::SymbolControl;
+use ephemera_strategy::strategies::Strategy;
+use eyre::Result;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+
+/// 将策略应用到数据流，生成信号流
+///
+/// 信号与产生它的 K 线一起下发，回测默认按 [`FillTiming::NextOpen`](crate::engine::FillTiming::NextOpen) 推迟到下一根 K 线成交。
+/// 每个信号都会经过 `LookaheadGuard` 检查，疑似使用未来数据时输出警告。
+///
+/// 配置了 `audit` 时，策略产生的每个买卖信号都会记录为 [`AuditOutcome::Generated`]
+pub fn apply_strategy<S>(
+    candle_stream: impl Stream<Item = Result<CandleData>> + Send + 'static,
+    mut strategy: S,
+    control: SymbolControl,
+    audit: Option<Auditor>,
+) -> Pin<Box<dyn Stream<Item = (Signal, CandleData)> + Send>>
+where
+    S: Strategy<Input = CandleData> + Send + 'static,
+    S::Error: std::fmt::Debug + Send,
+{
+    Box::pin(async_stream::stream! {
+        futures::pin_mut!(candle_stream);
+
+        let mut count = 0;
+        let mut guard = LookaheadGuard::default();
+
+        while let Some(result) = candle_stream.next().await {
+            match result {
+                Ok(candle) => {
+                    count += 1;
+
+                    if let Some(warning) = guard.check_candle(&candle) {
+                        tracing::warn!("{}", warning);
+                    }
+
+                    if count % 100 == 0 {
+                        tracing::info!("已处理 {} 根K线...", count);
+                    }
+
+                    match strategy.process(candle.clone()) {
+                        Ok(_) if !strategy.is_ready() => {
+                            // 策略还在预热，信号无效。仍然下发 K 线，以便下游跟踪进度和权益
+                            yield (Signal::Hold, candle);
+                        }
+                        Ok(signal) => {
+                            if let Some(warning) = guard.check_signal(&signal, &candle) {
+                                tracing::warn!("{}", warning);
+                            }
+                            // 策略照常处理暂停的交易对以更新指标，只是不执行其信号
+                            if !signal.is_hold() && !control.is_enabled(&candle.symbol) {
+                                if let Some(audit) = &audit {
+                                    audit.signal(
+                                        candle.open_timestamp_ms,
+                                        &signal,
+                                        AuditOutcome::Rejected {
+                                            reason: "symbol disabled".to_string(),
+                                        },
+                                    );
+                                }
+                                yield (control.filter(signal), candle);
+                                continue;
+                            }
+                            if let Some(audit) = &audit {
+                                audit.signal(
+                                    candle.open_timestamp_ms,
+                                    &signal,
+                                    AuditOutcome::Generated,
+                                );
+                            }
+                            yield (signal, candle);
+                        }
+                        Err(e) => {
+                            tracing::error!("策略处理错误: {:?}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("读取K线数据错误: {}", e);
+                    break;
+                }
+            }
+        }
+
+        tracing::info!("✅ 数据处理完成，共处理 {} 根K线", count);
+    })
+}
+
+/// 检查策略是否可能使用了未来数据
+///
+/// 只能发现明显的迹象，不能证明没有未来数据:
+/// - 同一交易对的 K 线开盘时间没有严格递增，策略在看到较早的 K 线之前已经看到了较晚的 K 线；
+/// - 信号价格不在产生它的 K 线的 `[low, high]` 范围内，这个价格在该 K 线结束时还不可能知道。
+#[derive(Debug, Default)]
+struct LookaheadGuard {
+    last_open: std::collections::HashMap<ephemera_shared::Symbol, u64>,
+}
+
+impl LookaheadGuard {
+    fn check_candle(&mut self, candle: &CandleData) -> Option<String> {
+        let prev = self
+            .last_open
+            .insert(candle.symbol.clone(), candle.open_timestamp_ms)?;
+
+        (candle.open_timestamp_ms <= prev).then(|| {
+            format!(
+                "可能使用了未来数据: {} 的 K 线 {} 出现在 {} 之后",
+                candle.symbol, candle.open_timestamp_ms, prev
+            )
+        })
+    }
+
+    fn check_signal(&self, signal: &Signal, candle: &CandleData) -> Option<String> {
+        let price = match signal {
+            Signal::Buy { price, .. } | Signal::Sell { price, .. } => *price,
+            Signal::Hold => return None,
+        };
+
+        (price < candle.low || price > candle.high).then(|| {
+            format!(
+                "可能使用了未来数据: {} 在 K 线 {} 上的信号价格 {} 超出了该 K 线的范围 [{}, {}]",
+                candle.symbol, candle.open_timestamp_ms, price, candle.low, candle.high
+            )
+        })
+    }
+}
+
+/// 从信号流中只提取 Signal（用于实盘交易）
+pub fn extract_signals(
+    signal_stream: impl Stream<Item = (Signal, CandleData)> + Send + 'static,
+) -> Pin<Box<dyn Stream<Item = Signal> + Send>> {
+    Box::pin(async_stream::stream! {
+        futures::pin_mut!(signal_stream);
+
+        while let Some((signal, _candle)) = signal_stream.next().await {
+            yield signal;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candle;
+
+    #[test]
+    fn test_lookahead_guard() {
+        let mut guard = LookaheadGuard::default();
+
+        assert!(guard.check_candle(&candle(0, 100.0, 95.0, 100.0)).is_none());
+        assert!(
+            guard
+                .check_candle(&candle(60_000, 100.0, 95.0, 100.0))
+                .is_none()
+        );
+        // 重复或倒序的 K 线
+        assert!(
+            guard
+                .check_candle(&candle(60_000, 100.0, 95.0, 100.0))
+                .is_some()
+        );
+        assert!(guard.check_candle(&candle(0, 100.0, 95.0, 100.0)).is_some());
+
+        let bar = candle(120_000, 105.0, 95.0, 100.0);
+        let buy = |price| Signal::buy("BTC-USDT".into(), price, 1.0);
+        assert!(guard.check_signal(&buy(100.0), &bar).is_none());
+        assert!(guard.check_signal(&Signal::Hold, &bar).is_none());
+        // 以该 K 线之后才出现的价格交易
+        assert!(guard.check_signal(&buy(110.0), &bar).is_some());
+        assert!(
+            guard
+                .check_signal(&Signal::sell("BTC-USDT".into(), 90.0, 1.0), &bar)
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use crate::engine::Allocation;
+use crate::report::{BacktestReport, ClosedPosition, Liquidation, SleeveReport, Trade};
+use ephemera_shared::TimestampMs;
+use ephemera_source::metrics;
+use eyre::Result;
+
+/// 子账户: 组合模式下每个交易对一个，共享模式下只有一个
+#[derive(Debug)]
+pub(crate) struct Sleeve {
+    /// 共享模式下为 `None`
+    pub(crate) symbol: Option<String>,
+    pub(crate) initial_balance: f64,
+    pub(crate) available_balance: f64,
+    pub(crate) positions: std::collections::HashMap<String, Position>,
+    pub(crate) closed_positions: Vec<ClosedPosition>,
+    pub(crate) equity_curve: EquityRecorder,
+}
+
+impl Sleeve {
+    /// 按各交易对最新收盘价计算的权益
+    pub(crate) fn equity(&self, marks: &std::collections::HashMap<String, f64>) -> f64 {
+        self.available_balance
+            + self
+                .positions
+                .iter()
+                .map(|(symbol, position)| {
+                    let mark = marks.get(symbol).copied().unwrap_or(position.avg_price);
+                    position.margin + position.size * (mark - position.avg_price)
+                })
+                .sum::<f64>()
+    }
+
+    /// 余额，持仓按保证金计
+    pub(crate) fn balance(&self) -> f64 {
+        self.available_balance + self.positions.values().map(|p| p.margin).sum::<f64>()
+    }
+
+    /// 以 `price` 平掉 `size` 的持仓，`size` 不能超过持仓数量
+    pub(crate) fn close_position(&mut self, symbol: &str, price: f64, size: f64) {
+        let Some(position) = self.positions.get_mut(symbol) else {
+            return;
+        };
+
+        // 按平仓比例释放保证金，并结算盈亏
+        let released_margin = position.margin * size / position.size;
+        let pnl = (price - position.avg_price) * size;
+        position.size -= size;
+        position.margin -= released_margin;
+        position.realized_pnl += pnl;
+
+        if position.size == 0.0 {
+            self.closed_positions.push(position.close_record(symbol));
+            self.positions.remove(symbol);
+        }
+
+        self.available_balance += released_margin + pnl;
+    }
+}
+
+/// 回测中的全部子账户，以及合并后的权益曲线
+#[derive(Debug)]
+pub(crate) struct Portfolio {
+    pub(crate) sleeves: Vec<Sleeve>,
+    /// 交易对 -> 子账户下标，共享模式下为空
+    pub(crate) index: std::collections::HashMap<String, usize>,
+    pub(crate) equity_curve: EquityRecorder,
+}
+
+impl Portfolio {
+    pub(crate) fn new(
+        initial_balance: f64,
+        allocation: &Allocation,
+        resolution: EquityResolution,
+    ) -> Result<Self> {
+        let sleeves = match allocation {
+            Allocation::Shared => vec![Sleeve {
+                symbol: None,
+                initial_balance,
+                available_balance: initial_balance,
+                positions: Default::default(),
+                closed_positions: Vec::new(),
+                equity_curve: EquityRecorder::new(initial_balance, resolution),
+            }],
+            Allocation::Weighted(weights) => {
+                let total_weight = weights.iter().map(|(_, w)| w).sum::<f64>();
+                eyre::ensure!(
+                    !weights.is_empty()
+                        && total_weight > 0.0
+                        && weights.iter().all(|(_, w)| *w >= 0.0),
+                    "Invalid allocation weights: {weights:?}"
+                );
+
+                weights
+                    .iter()
+                    .map(|(symbol, weight)| {
+                        let balance = initial_balance * weight / total_weight;
+                        Sleeve {
+                            symbol: Some(symbol.clone()),
+                            initial_balance: balance,
+                            available_balance: balance,
+                            positions: Default::default(),
+                            closed_positions: Vec::new(),
+                            equity_curve: EquityRecorder::new(balance, resolution),
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        let index = sleeves
+            .iter()
+            .enumerate()
+            .filter_map(|(i, sleeve)| Some((sleeve.symbol.clone()?, i)))
+            .collect();
+
+        Ok(Self {
+            sleeves,
+            index,
+            equity_curve: EquityRecorder::new(initial_balance, resolution),
+        })
+    }
+
+    pub(crate) fn sleeve_index(&self, symbol: &str) -> Option<usize> {
+        if self.index.is_empty() {
+            Some(0)
+        } else {
+            self.index.get(symbol).copied()
+        }
+    }
+
+    pub(crate) fn position(&self, symbol: &str) -> Option<&Position> {
+        self.sleeves[self.sleeve_index(symbol)?]
+            .positions
+            .get(symbol)
+    }
+
+    /// 所有子账户的总权益
+    pub(crate) fn equity(&self, marks: &std::collections::HashMap<String, f64>) -> f64 {
+        self.sleeves.iter().map(|sleeve| sleeve.equity(marks)).sum()
+    }
+
+    /// 记录子账户和合并后的权益，返回总权益
+    pub(crate) fn record(
+        &mut self,
+        index: usize,
+        timestamp_ms: u64,
+        marks: &std::collections::HashMap<String, f64>,
+    ) -> f64 {
+        let sleeve = &mut self.sleeves[index];
+        let sleeve_equity = sleeve.equity(marks);
+        sleeve.equity_curve.record(timestamp_ms, sleeve_equity);
+
+        let equity = self.equity(marks);
+        self.equity_curve.record(timestamp_ms, equity);
+
+        metrics::set_equity(equity);
+        metrics::set_open_positions(self.sleeves.iter().map(|s| s.positions.len()).sum());
+
+        equity
+    }
+
+    pub(crate) fn into_report(
+        self,
+        initial_balance: f64,
+        final_balance: f64,
+        trades: Vec<Trade>,
+        liquidations: Vec<Liquidation>,
+        funding_paid: f64,
+        time_range: Option<(TimestampMs, TimestampMs)>,
+    ) -> BacktestReport {
+        let mut available_balance = 0.0;
+        let mut positions = std::collections::HashMap::new();
+        let mut closed_positions = Vec::new();
+        let mut sleeves = Vec::new();
+
+        for sleeve in self.sleeves {
+            available_balance += sleeve.available_balance;
+
+            if let Some(symbol) = &sleeve.symbol {
+                sleeves.push(SleeveReport {
+                    symbol: symbol.clone(),
+                    initial_balance: sleeve.initial_balance,
+                    final_balance: sleeve.balance(),
+                    max_drawdown_pct: sleeve.equity_curve.max_drawdown_pct,
+                    equity_curve: sleeve.equity_curve.curve,
+                });
+            }
+
+            positions.extend(sleeve.positions);
+            closed_positions.extend(sleeve.closed_positions);
+        }
+
+        BacktestReport {
+            initial_balance,
+            final_balance,
+            available_balance,
+            positions,
+            closed_positions,
+            trades,
+            liquidations,
+            funding_paid,
+            max_equity: self.equity_curve.max_equity,
+            max_drawdown_pct: self.equity_curve.max_drawdown_pct,
+            equity_curve: self.equity_curve.curve,
+            sleeves,
+            time_range,
+        }
+    }
+}
+
+/// 权益曲线的存储分辨率
+///
+/// 逐笔记录在百万级成交的回测中会占用大量内存。降低分辨率只影响存入报告的曲线（以及基于曲线计算的夏普比率），
+/// 峰值权益和最大回撤始终按全分辨率统计。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EquityResolution {
+    /// 每次权益变化都记录，适合小规模回测
+    Full,
+    /// 每 N 次权益变化记录一次
+    EveryN(usize),
+    /// 每个时间桶（毫秒）只保留最后一个点
+    TimeBucket(u64),
+}
+
+/// 按 [`EquityResolution`] 记录权益曲线，同时以全分辨率跟踪峰值和最大回撤
+#[derive(Debug)]
+pub(crate) struct EquityRecorder {
+    pub(crate) resolution: EquityResolution,
+    pub(crate) curve: Vec<f64>,
+    pub(crate) updates: usize,
+    pub(crate) last_bucket: Option<u64>,
+    pub(crate) max_equity: f64,
+    pub(crate) max_drawdown_pct: f64,
+}
+
+impl EquityRecorder {
+    pub(crate) fn new(initial_balance: f64, resolution: EquityResolution) -> Self {
+        Self {
+            resolution,
+            curve: vec![initial_balance],
+            updates: 0,
+            last_bucket: None,
+            max_equity: initial_balance,
+            max_drawdown_pct: 0.0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, timestamp_ms: u64, equity: f64) {
+        // 非有限的权益（如标记价格异常）不记录，避免污染峰值与回撤
+        if !equity.is_finite() {
+            return;
+        }
+
+        self.max_equity = self.max_equity.max(equity);
+        if self.max_equity > 0.0 {
+            let drawdown = (self.max_equity - equity) / self.max_equity * 100.0;
+            self.max_drawdown_pct = self.max_drawdown_pct.max(drawdown);
+        }
+
+        self.updates += 1;
+
+        match self.resolution {
+            EquityResolution::Full => self.curve.push(equity),
+            EquityResolution::EveryN(n) => {
+                if self.updates.is_multiple_of(n.max(1)) {
+                    self.curve.push(equity);
+                }
+            }
+            EquityResolution::TimeBucket(bucket_ms) => {
+                let bucket = timestamp_ms / bucket_ms.max(1);
+                if self.last_bucket == Some(bucket) {
+                    *self.curve.last_mut().unwrap() = equity;
+                } else {
+                    self.last_bucket = Some(bucket);
+                    self.curve.push(equity);
+                }
+            }
+        }
+    }
+}
+
+/// 未平仓的持仓
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub size: f64,
+    pub avg_price: f64,
+    /// 占用的保证金
+    pub margin: f64,
+    /// 建仓或最近一次加仓后的数量，分批平仓的比例以它为基准
+    pub(crate) exit_base_size: f64,
+    /// [`ScaledExit`](crate::engine::ScaledExit) 中各档位是否已触发
+    pub(crate) exits_hit: Vec<bool>,
+    /// 持仓期间 K 线的最高价和最低价，从开仓价开始记录
+    pub(crate) highest: f64,
+    pub(crate) lowest: f64,
+    /// 持仓期间的最大数量，初始风险以它为基准
+    pub(crate) peak_size: f64,
+    /// 分批平仓累计的已实现盈亏
+    pub(crate) realized_pnl: f64,
+    /// 开仓时每单位的止损距离，由 [`ScaledExit::stop_pct`](crate::engine::ScaledExit::stop_pct) 得出，未设置止损时为 `None`
+    pub(crate) stop_distance: Option<f64>,
+}
+
+impl Position {
+    /// 强平价: 剩余保证金 `margin + (p - avg_price) * size` 等于维持保证金 `mmr * size * p` 时的价格
+    ///
+    /// 无杠杆（保证金覆盖全部名义价值）时强平价不为正，返回 `None`。
+    pub(crate) fn liquidation_price(&self, maintenance_margin_rate: f64) -> Option<f64> {
+        if self.size <= 0.0 {
+            return None;
+        }
+
+        let price = (self.avg_price * self.size - self.margin)
+            / (self.size * (1.0 - maintenance_margin_rate));
+        (price > 0.0).then_some(price)
+    }
+
+    /// 全部平仓时的持仓统计，`realized_pnl` 应已包含最后一次平仓
+    pub(crate) fn close_record(&self, symbol: &str) -> ClosedPosition {
+        ClosedPosition {
+            symbol: symbol.to_string(),
+            avg_price: self.avg_price,
+            pnl: self.realized_pnl,
+            mfe_pct: (self.highest - self.avg_price) / self.avg_price * 100.0,
+            mae_pct: (self.avg_price - self.lowest) / self.avg_price * 100.0,
+            r_multiple: self
+                .stop_distance
+                .filter(|distance| *distance > 0.0)
+                .map(|distance| self.realized_pnl / (distance * self.peak_size)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::calculate_sharpe_ratio;
+
+    #[test]
+    fn test_equity_recorder_downsampling_keeps_drawdown() {
+        let mut full = EquityRecorder::new(100.0, EquityResolution::Full);
+        let mut every_2 = EquityRecorder::new(100.0, EquityResolution::EveryN(2));
+        let mut bucketed = EquityRecorder::new(100.0, EquityResolution::TimeBucket(60_000));
+
+        // 谷底 60 出现在第 3 次更新，不会被 EveryN(2) 采样到
+        let updates = [
+            (0, 120.0),
+            (10_000, 110.0),
+            (20_000, 60.0),
+            (70_000, 90.0),
+            (80_000, 130.0),
+        ];
+        for (timestamp, equity) in updates {
+            full.record(timestamp, equity);
+            every_2.record(timestamp, equity);
+            bucketed.record(timestamp, equity);
+        }
+
+        assert_eq!(full.curve, vec![100.0, 120.0, 110.0, 60.0, 90.0, 130.0]);
+        assert_eq!(every_2.curve, vec![100.0, 110.0, 90.0]);
+        // 每分钟保留最后一个点
+        assert_eq!(bucketed.curve, vec![100.0, 60.0, 130.0]);
+
+        for recorder in [&full, &every_2, &bucketed] {
+            approx::assert_abs_diff_eq!(recorder.max_equity, 130.0);
+            approx::assert_abs_diff_eq!(recorder.max_drawdown_pct, 50.0);
+        }
+    }
+
+    #[test]
+    fn test_non_finite_equity_is_ignored() {
+        let mut recorder = EquityRecorder::new(100.0, EquityResolution::Full);
+        recorder.record(0, 110.0);
+        recorder.record(1, f64::NAN);
+        recorder.record(2, f64::INFINITY);
+        recorder.record(3, 99.0);
+
+        assert_eq!(recorder.curve, vec![100.0, 110.0, 99.0]);
+        approx::assert_abs_diff_eq!(recorder.max_equity, 110.0);
+        approx::assert_abs_diff_eq!(recorder.max_drawdown_pct, 10.0, epsilon = 1e-9);
+
+        let clean = calculate_sharpe_ratio(&[100.0, 110.0, 99.0, 120.0], 365.0);
+        let dirty = calculate_sharpe_ratio(&[100.0, 110.0, f64::NAN, 99.0, 120.0], 365.0);
+        assert!(clean.is_finite());
+        assert!(dirty.is_finite());
+
+        // 权益归零后的收益率无意义，应被跳过
+        let with_zero = calculate_sharpe_ratio(&[100.0, 0.0, 50.0, 60.0], 365.0);
+        assert!(with_zero.is_finite());
+        approx::assert_abs_diff_eq!(calculate_sharpe_ratio(&[0.0, 10.0], 365.0), 0.0);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::metrics::{
+    MetricRegistry, RawBacktestData, calculate_sharpe_ratio, calculate_win_loss, periods_per_year,
+};
+use crate::portfolio::Position;
+use ephemera_shared::TimestampMs;
+use eyre::Result;
+use serde::Serialize;
+use std::path::Path;
+
+/// 一笔已全部平掉的持仓
+#[derive(Debug, Clone)]
+pub struct ClosedPosition {
+    pub symbol: String,
+    pub avg_price: f64,
+    pub pnl: f64,
+    /// 最大有利偏移: 持仓期间最高价高出均价的百分比
+    pub mfe_pct: f64,
+    /// 最大不利偏移: 持仓期间最低价低于均价的百分比
+    pub mae_pct: f64,
+    /// 盈亏相对初始风险（止损距离 × 最大持仓数量）的倍数，未设置止损时为 `None`
+    pub r_multiple: Option<f64>,
+}
+
+/// 一笔成交
+#[derive(Debug, Clone, Serialize)]
+pub struct Trade {
+    /// 成交时间（毫秒）
+    pub timestamp: u64,
+    pub symbol: String,
+    pub side: TradeSide,
+    pub price: f64,
+    pub size: f64,
+    pub balance_after: f64,
+}
+
+/// 强平事件
+#[derive(Debug, Clone)]
+pub struct Liquidation {
+    pub timestamp: u64,
+    pub symbol: String,
+    pub price: f64,
+    pub size: f64,
+    /// 已实现亏损
+    pub loss: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// 回测报告
+#[derive(Debug)]
+pub struct BacktestReport {
+    pub initial_balance: f64,
+    pub final_balance: f64,
+    pub available_balance: f64,
+    pub positions: std::collections::HashMap<String, Position>,
+    /// 已全部平掉的持仓，按子账户依次排列
+    pub closed_positions: Vec<ClosedPosition>,
+    pub trades: Vec<Trade>,
+    pub liquidations: Vec<Liquidation>,
+    /// 累计支付的资金费，为负表示净收取
+    pub funding_paid: f64,
+    /// 按 [`EquityResolution`](crate::portfolio::EquityResolution) 采样后的权益曲线
+    pub equity_curve: Vec<f64>,
+    /// 全分辨率下的权益峰值
+    pub max_equity: f64,
+    /// 全分辨率下的最大回撤（百分比）
+    pub max_drawdown_pct: f64,
+    /// 组合模式下各交易对子账户的结果，共享模式下为空
+    pub sleeves: Vec<SleeveReport>,
+    /// 回测覆盖的时间范围 `(第一根 K 线开盘, 最后一根 K 线收盘)`（毫秒），没有 K 线时为 `None`
+    pub time_range: Option<(TimestampMs, TimestampMs)>,
+}
+
+/// 组合模式下单个交易对子账户的回测结果
+#[derive(Debug)]
+pub struct SleeveReport {
+    pub symbol: String,
+    pub initial_balance: f64,
+    pub final_balance: f64,
+    pub equity_curve: Vec<f64>,
+    pub max_drawdown_pct: f64,
+}
+
+/// 权益曲线 CSV 的一行
+#[derive(Debug, Serialize)]
+struct EquityRow {
+    /// 采样序号，0 为初始资金
+    index: usize,
+    equity: f64,
+}
+
+impl BacktestReport {
+    /// 回测覆盖的年数，每年按 `trading_days_per_year` 天计算，没有 K 线时为 0.0
+    pub fn elapsed_years(&self, trading_days_per_year: f64) -> f64 {
+        self.time_range.map_or(0.0, |(start, end)| {
+            (end - start) as f64 / (trading_days_per_year * 86_400_000.0)
+        })
+    }
+
+    /// 将权益曲线写为 CSV，列为 `index,equity`
+    ///
+    /// 权益曲线按 [`EquityResolution`](crate::portfolio::EquityResolution) 采样，不带时间戳，因此用采样序号作为索引
+    pub async fn write_equity_curve_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let rows = self
+            .equity_curve
+            .iter()
+            .enumerate()
+            .map(|(index, &equity)| EquityRow { index, equity });
+        write_csv(path, rows).await
+    }
+
+    /// 将成交记录写为 CSV，列与 [`print_trades`] 一致:
+    /// `timestamp,symbol,side,price,size,balance_after`
+    pub async fn write_trades_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_csv(path, &self.trades).await
+    }
+}
+
+async fn write_csv<T: Serialize>(
+    path: impl AsRef<Path>,
+    rows: impl IntoIterator<Item = T>,
+) -> Result<()> {
+    let file = tokio::fs::File::create(path).await?;
+    let mut writer = csv_async::AsyncSerializer::from_writer(file);
+    for row in rows {
+        writer.serialize(row).await?;
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+/// 在终端打印回测结果摘要，`metrics` 中的指标按注册顺序输出
+pub fn print_backtest_report(
+    report: &BacktestReport,
+    trading_days_per_year: f64,
+    metrics: &MetricRegistry,
+) {
+    let total_return = report.final_balance - report.initial_balance;
+    let total_return_pct = (total_return / report.initial_balance) * 100.0;
+    let max_drawdown = report.max_drawdown_pct;
+    let years = report.elapsed_years(trading_days_per_year);
+    let sharpe_ratio = calculate_sharpe_ratio(
+        &report.equity_curve,
+        periods_per_year(&report.equity_curve, years),
+    );
+    let (winning_trades, losing_trades) = calculate_win_loss(&report.trades);
+
+    println!("\n{:=<80}", "");
+    println!("📊 回测结果摘要");
+    println!("{:=<80}", "");
+    println!("初始资金: ${:.2}", report.initial_balance);
+    println!("最终资金: ${:.2}", report.final_balance);
+    println!("可用余额: ${:.2}", report.available_balance);
+    println!("总收益: ${:.2}", total_return);
+    println!("峰值权益: ${:.2}", report.max_equity);
+    for (name, value) in metrics.compute(&RawBacktestData::new(report, trading_days_per_year)) {
+        println!("{}: {}", name, value);
+    }
+    println!("总交易次数: {}", report.trades.len());
+    println!("盈利交易: {}", winning_trades);
+    println!("亏损交易: {}", losing_trades);
+    println!("强平次数: {}", report.liquidations.len());
+    println!("资金费: ${:.2}", report.funding_paid);
+    for liquidation in &report.liquidations {
+        println!(
+            "  [{}] {}: {:.4} @ ${:.2}, 亏损 ${:.2}",
+            liquidation.timestamp,
+            liquidation.symbol,
+            liquidation.size,
+            liquidation.price,
+            liquidation.loss
+        );
+    }
+
+    if !report.closed_positions.is_empty() {
+        let count = report.closed_positions.len() as f64;
+        let avg_mfe = report
+            .closed_positions
+            .iter()
+            .map(|p| p.mfe_pct)
+            .sum::<f64>()
+            / count;
+        let avg_mae = report
+            .closed_positions
+            .iter()
+            .map(|p| p.mae_pct)
+            .sum::<f64>()
+            / count;
+        println!("平均 MFE: {:.2}%", avg_mfe);
+        println!("平均 MAE: {:.2}%", avg_mae);
+
+        let r_multiples: Vec<f64> = report
+            .closed_positions
+            .iter()
+            .filter_map(|p| p.r_multiple)
+            .collect();
+        if !r_multiples.is_empty() {
+            let avg_r = r_multiples.iter().sum::<f64>() / r_multiples.len() as f64;
+            println!("平均 R: {:.2}", avg_r);
+        }
+    }
+
+    if !report.sleeves.is_empty() {
+        println!("\n分交易对:");
+        println!(
+            "  {:<15} {:>12} {:>12} {:>10} {:>10} {:>10}",
+            "交易对", "初始资金", "最终资金", "收益率", "夏普", "最大回撤"
+        );
+        for sleeve in &report.sleeves {
+            let return_pct =
+                (sleeve.final_balance - sleeve.initial_balance) / sleeve.initial_balance * 100.0;
+            println!(
+                "  {:<15} {:>12.2} {:>12.2} {:>9.2}% {:>10.2} {:>9.2}%",
+                sleeve.symbol,
+                sleeve.initial_balance,
+                sleeve.final_balance,
+                return_pct,
+                calculate_sharpe_ratio(
+                    &sleeve.equity_curve,
+                    periods_per_year(&sleeve.equity_curve, years)
+                ),
+                sleeve.max_drawdown_pct
+            );
+        }
+        println!(
+            "  {:<15} {:>12.2} {:>12.2} {:>9.2}% {:>10.2} {:>9.2}%",
+            "合计",
+            report.initial_balance,
+            report.final_balance,
+            total_return_pct,
+            sharpe_ratio,
+            max_drawdown
+        );
+    }
+
+    if !report.positions.is_empty() {
+        println!("\n持仓情况:");
+        for (symbol, position) in &report.positions {
+            if position.size > 0.0 {
+                println!(
+                    "  {}: {:.4} @ ${:.2}",
+                    symbol, position.size, position.avg_price
+                );
+            }
+        }
+    }
+
+    println!("{:=<80}\n", "");
+}
+
+/// 在终端打印成交记录，`limit` 为最多显示的条数
+pub fn print_trades(trades: &[Trade], limit: Option<usize>) {
+    println!("\n交易记录:");
+    println!("{:-<100}", "");
+    println!(
+        "{:<20} {:<15} {:<8} {:<12} {:<10} {:<15}",
+        "时间", "交易对", "方向", "价格", "数量", "账户余额"
+    );
+    println!("{:-<100}", "");
+
+    let trades_to_show = if let Some(n) = limit {
+        &trades[..n.min(trades.len())]
+    } else {
+        trades
+    };
+
+    for trade in trades_to_show {
+        let datetime = chrono::DateTime::from_timestamp_millis(trade.timestamp as i64)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "Invalid".to_string());
+
+        println!(
+            "{:<20} {:<15} {:<8} ${:<11.2} {:<10.4} ${:<14.2}",
+            datetime,
+            trade.symbol,
+            if trade.side == TradeSide::Buy {
+                "买入"
+            } else {
+                "卖出"
+            },
+            trade.price,
+            trade.size,
+            trade.balance_after
+        );
+    }
+    println!("{:-<100}\n", "");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::execute_backtest;
+    use crate::test_utils::{candle, spot_config};
+    use ephemera_shared::Signal;
+
+    #[tokio::test]
+    async fn test_export_backtest_csv() {
+        let signals = futures::stream::iter(vec![
+            (
+                Signal::buy("BTC-USDT".into(), 100.0, 2.0),
+                candle(0, 100.0, 100.0, 100.0),
+            ),
+            (
+                Signal::sell("BTC-USDT".into(), 110.0, 2.0),
+                candle(60_000, 110.0, 110.0, 110.0),
+            ),
+        ]);
+        let report = execute_backtest(signals, spot_config(1000.0), |_| {})
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let equity_path = dir.path().join("equity_curve.csv");
+        let trades_path = dir.path().join("trades.csv");
+        report.write_equity_curve_csv(&equity_path).await.unwrap();
+        report.write_trades_csv(&trades_path).await.unwrap();
+
+        // 每个采样点一行，外加表头
+        let equity = std::fs::read_to_string(&equity_path).unwrap();
+        let lines: Vec<_> = equity.lines().collect();
+        assert_eq!(lines[0], "index,equity");
+        assert_eq!(lines.len(), report.equity_curve.len() + 1);
+        assert_eq!(lines[1], "0,1000.0");
+
+        let trades = std::fs::read_to_string(&trades_path).unwrap();
+        let lines: Vec<_> = trades.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "timestamp,symbol,side,price,size,balance_after",
+                "0,BTC-USDT,buy,100.0,2.0,1000.0",
+                "60000,BTC-USDT,sell,110.0,2.0,1020.0",
+            ]
+        );
+    }
+}
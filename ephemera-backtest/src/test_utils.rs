@@ -0,0 +1,41 @@
+use crate::engine::{Allocation, BacktestConfig, FillTiming, MarginConfig};
+use crate::portfolio::EquityResolution;
+use ephemera_shared::CandleData;
+
+/// `BTC-USDT` 的一分钟 K 线，最高价取开盘价与收盘价中较高者
+pub(crate) fn candle(open_timestamp_ms: u64, open: f64, low: f64, close: f64) -> CandleData {
+    CandleData {
+        symbol: "BTC-USDT".into(),
+        interval_sc: 60,
+        open_timestamp_ms,
+        open,
+        high: open.max(close),
+        low,
+        close,
+        volume: 1.0,
+        trade_count: 0,
+        quote_volume: close,
+        quote_volume_estimated: false,
+    }
+}
+
+/// 无杠杆、以信号价格成交、不启用任何风控的配置
+pub(crate) fn spot_config(initial_balance: f64) -> BacktestConfig {
+    BacktestConfig {
+        initial_balance,
+        margin: MarginConfig {
+            leverage: 1.0,
+            maintenance_margin_rate: 0.005,
+        },
+        risk: None,
+        constraints: None,
+        equity_resolution: EquityResolution::Full,
+        allocation: Allocation::Shared,
+        fill_timing: FillTiming::SignalPrice,
+        scaled_exit: None,
+        funding_rates: Vec::new(),
+        fx: None,
+        instruments: Vec::new(),
+        audit: None,
+    }
+}
@@ -0,0 +1,91 @@
+use ephemera_backtest::engine::{
+    Allocation, BacktestConfig, FillTiming, MarginConfig, execute_backtest,
+};
+use ephemera_backtest::metrics::{
+    CRYPTO_TRADING_DAYS, Metric, MetricRegistry, MetricValue, RawBacktestData, calculate_win_loss,
+};
+use ephemera_backtest::portfolio::EquityResolution;
+use ephemera_shared::{CandleData, Signal};
+
+/// 平均每笔交易的数量
+struct AvgTradeSize;
+
+impl Metric for AvgTradeSize {
+    fn name(&self) -> &str {
+        "平均交易数量"
+    }
+
+    fn compute(&self, data: &RawBacktestData) -> MetricValue {
+        let trades = &data.report.trades;
+        if trades.is_empty() {
+            return MetricValue::NotAvailable;
+        }
+        MetricValue::Number(trades.iter().map(|t| t.size).sum::<f64>() / trades.len() as f64)
+    }
+}
+
+fn candle(open_timestamp_ms: u64, price: f64) -> CandleData {
+    CandleData {
+        symbol: "BTC-USDT".into(),
+        interval_sc: 60,
+        open_timestamp_ms,
+        open: price,
+        high: price,
+        low: price,
+        close: price,
+        volume: 1.0,
+        trade_count: 0,
+        quote_volume: price,
+        quote_volume_estimated: false,
+    }
+}
+
+/// 自定义指标在库外实现并注册到报告中
+#[tokio::test]
+async fn test_custom_metric() {
+    let signals = futures::stream::iter(vec![
+        (Signal::buy("BTC-USDT".into(), 10.0, 2.0), candle(0, 10.0)),
+        (
+            Signal::sell("BTC-USDT".into(), 11.0, 1.0),
+            candle(60_000, 11.0),
+        ),
+    ]);
+    let config = BacktestConfig {
+        initial_balance: 1000.0,
+        margin: MarginConfig {
+            leverage: 1.0,
+            maintenance_margin_rate: 0.005,
+        },
+        risk: None,
+        constraints: None,
+        equity_resolution: EquityResolution::Full,
+        allocation: Allocation::Shared,
+        fill_timing: FillTiming::SignalPrice,
+        scaled_exit: None,
+        funding_rates: Vec::new(),
+        fx: None,
+        instruments: Vec::new(),
+        audit: None,
+    };
+    let report = execute_backtest(signals, config, |_| {}).await.unwrap();
+    let data = RawBacktestData::new(&report, CRYPTO_TRADING_DAYS);
+
+    let mut registry = MetricRegistry::empty();
+    registry.register(AvgTradeSize);
+    assert_eq!(
+        registry.compute(&data),
+        vec![("平均交易数量".to_string(), MetricValue::Number(1.5))]
+    );
+
+    // 自定义指标追加在内置指标之后
+    let mut registry = MetricRegistry::default();
+    registry.register(AvgTradeSize);
+    let values = registry.compute(&data);
+    assert_eq!(values.len(), 7);
+    assert_eq!(values[1].1, MetricValue::Percent(report.max_drawdown_pct));
+    // 唯一一笔卖出高于买入价
+    assert_eq!(calculate_win_loss(&report.trades), (1, 0));
+    assert_eq!(values[5], ("胜率".to_string(), MetricValue::Percent(100.0)));
+    assert_eq!(values[6].1, MetricValue::Number(1.5));
+    assert_eq!(MetricValue::NotAvailable.to_string(), "-");
+}
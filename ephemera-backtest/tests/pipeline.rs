@@ -0,0 +1,146 @@
+use ephemera_backtest::engine::{
+    Allocation, BacktestConfig, FillTiming, MarginConfig, execute_backtest,
+};
+use ephemera_backtest::pipeline::apply_strategy;
+use ephemera_backtest::portfolio::EquityResolution;
+use ephemera_backtest::report::TradeSide;
+use ephemera_shared::{CandleData, Signal};
+use ephemera_source::csv::csv_candle_data_stream;
+use ephemera_strategy::indicators::{Indicator, MA};
+use ephemera_strategy::risk::SymbolControl;
+use ephemera_strategy::strategies::Strategy;
+use std::io::Write;
+
+/// 双均线交叉: 快线上穿慢线时买入，下穿时卖出，慢线窗口填满之前未就绪
+struct MACross {
+    fast: MA,
+    slow: MA,
+    size: f64,
+    prev_diff: Option<f64>,
+}
+
+impl MACross {
+    fn new(fast: usize, slow: usize, size: f64) -> Self {
+        Self {
+            fast: MA::new(fast),
+            slow: MA::new(slow),
+            size,
+            prev_diff: None,
+        }
+    }
+}
+
+impl Strategy for MACross {
+    type Input = CandleData;
+    type Error = std::convert::Infallible;
+
+    fn process(&mut self, candle: CandleData) -> Result<Signal, Self::Error> {
+        let diff = self
+            .fast
+            .on_data(candle.close)
+            .zip(self.slow.on_data(candle.close))
+            .map(|(fast, slow)| fast - slow);
+
+        let signal = match (self.prev_diff, diff) {
+            (Some(prev), Some(diff)) if prev <= 0.0 && diff > 0.0 => {
+                Signal::buy(candle.symbol, candle.close, self.size)
+            }
+            (Some(prev), Some(diff)) if prev >= 0.0 && diff < 0.0 => {
+                Signal::sell(candle.symbol, candle.close, self.size)
+            }
+            _ => Signal::Hold,
+        };
+        self.prev_diff = diff.or(self.prev_diff);
+
+        Ok(signal)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.prev_diff.is_some()
+    }
+}
+
+/// CSV -> K 线流 -> 策略 -> 信号流 -> 回测报告
+#[tokio::test]
+async fn test_backtest_pipeline_from_csv() {
+    // MA2/MA4 两轮交叉: 11 买入、12 卖出（盈利 1），12 买入、11 卖出（亏损 1）
+    let closes = [
+        10.0, 10.0, 10.0, 10.0, 11.0, 12.0, 13.0, 14.0, 13.0, 12.0, 11.0, 10.0, 11.0, 12.0, 13.0,
+        12.0, 11.0,
+    ];
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "symbol,interval_sc,open_timestamp_ms,open,high,low,close,volume"
+    )
+    .unwrap();
+    for (i, close) in closes.iter().enumerate() {
+        writeln!(
+            file,
+            "BTC-USDT,60,{},{close},{close},{close},{close},1",
+            i * 60_000
+        )
+        .unwrap();
+    }
+
+    let candles = csv_candle_data_stream(file.path().to_path_buf())
+        .await
+        .unwrap();
+    let signals = apply_strategy(
+        candles,
+        MACross::new(2, 4, 1.0),
+        SymbolControl::default(),
+        None,
+    );
+    let config = BacktestConfig {
+        initial_balance: 1000.0,
+        margin: MarginConfig {
+            leverage: 1.0,
+            maintenance_margin_rate: 0.005,
+        },
+        risk: None,
+        constraints: None,
+        equity_resolution: EquityResolution::Full,
+        allocation: Allocation::Shared,
+        fill_timing: FillTiming::SignalPrice,
+        scaled_exit: None,
+        funding_rates: Vec::new(),
+        fx: None,
+        instruments: Vec::new(),
+        audit: None,
+    };
+
+    let mut progress_calls = 0;
+    let report = execute_backtest(signals, config, |_| progress_calls += 1)
+        .await
+        .unwrap();
+
+    let trades: Vec<_> = report
+        .trades
+        .iter()
+        .map(|t| (t.timestamp, t.side.clone(), t.price))
+        .collect();
+    assert_eq!(
+        trades,
+        vec![
+            (240_000, TradeSide::Buy, 11.0),
+            (540_000, TradeSide::Sell, 12.0),
+            (780_000, TradeSide::Buy, 12.0),
+            (960_000, TradeSide::Sell, 11.0),
+        ]
+    );
+    assert!(report.positions.is_empty());
+    assert!(report.liquidations.is_empty());
+    approx::assert_abs_diff_eq!(report.final_balance, 1000.0, epsilon = 1e-9);
+    assert_eq!(report.time_range, Some((0, 17 * 60_000)));
+    assert_eq!(progress_calls, 1);
+
+    // 第一轮盈利期间权益单调不减，第二轮亏损后回落
+    let curve = &report.equity_curve;
+    assert_eq!(curve.len(), 5);
+    assert!(curve[..4].windows(2).all(|w| w[1] >= w[0]));
+    approx::assert_abs_diff_eq!(curve[2], 1001.0, epsilon = 1e-9);
+    approx::assert_abs_diff_eq!(curve[4], 1000.0, epsilon = 1e-9);
+    approx::assert_abs_diff_eq!(report.max_equity, 1001.0, epsilon = 1e-9);
+    approx::assert_abs_diff_eq!(report.max_drawdown_pct, 100.0 / 1001.0, epsilon = 1e-9);
+}
@@ -0,0 +1,75 @@
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use ephemera_shared::{
+    Side, TradeData,
+    stream::{transform_multi_symbol_trades_to_candles, transform_trades_to_candles},
+    testing::SplitMix64,
+};
+use futures::{StreamExt, executor::block_on};
+use std::hint::black_box;
+
+const TRADE_COUNT: usize = 1_000_000;
+const INTERVAL_SC: u64 = 60;
+
+/// 价格随机游走的成交，每 100ms 一笔，轮流分配给 `symbols`
+///
+/// 使用固定种子的 [`SplitMix64`]，保证每次运行的输入一致
+fn trades(count: usize, symbols: &[&'static str]) -> Vec<TradeData> {
+    let mut rng = SplitMix64::new(0x2545_f491_4f6c_dd1d);
+    let mut next = move || rng.next_f64();
+
+    let mut price = 50_000.0;
+    (0..count)
+        .map(|i| {
+            price *= 1.0 + (next() - 0.5) * 1e-4;
+            TradeData {
+                symbol: symbols[i % symbols.len()].into(),
+                timestamp_ms: 1_640_000_000_000 + i as u64 * 100,
+                price,
+                quantity: next(),
+                side: if next() < 0.5 { Side::Buy } else { Side::Sell },
+            }
+        })
+        .collect()
+}
+
+fn bench_trades_to_candles(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trades_to_candles");
+    group.throughput(Throughput::Elements(TRADE_COUNT as u64));
+    group.sample_size(10);
+
+    let single = trades(TRADE_COUNT, &["BTC-USDT"]);
+    group.bench_function("single_symbol_1m", |b| {
+        b.iter_batched(
+            || single.clone(),
+            |trades| {
+                let candles =
+                    transform_trades_to_candles(futures::stream::iter(trades), INTERVAL_SC);
+                black_box(block_on(candles.count()))
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let multi = trades(
+        TRADE_COUNT,
+        &["BTC-USDT", "ETH-USDT", "SOL-USDT", "DOGE-USDT"],
+    );
+    group.bench_function("multi_symbol_1m", |b| {
+        b.iter_batched(
+            || multi.clone(),
+            |trades| {
+                let candles = transform_multi_symbol_trades_to_candles(
+                    futures::stream::iter(trades),
+                    INTERVAL_SC,
+                );
+                black_box(block_on(candles.count()))
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_trades_to_candles);
+criterion_main!(benches);
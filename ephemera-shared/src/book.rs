@@ -0,0 +1,314 @@
+use crate::{BookData, BookSide, OrderSide, Signal, Symbol, TimestampMs};
+use std::collections::BTreeMap;
+
+/// 本地订单簿，由快照和增量更新维护
+///
+/// [`BookData::is_snapshot`] 为 `true` 时丢弃现有档位并以其重建；否则逐档覆盖，数量为 0
+/// 的档位表示删除。价格以 `f64::to_bits` 作为键，正数的位模式与数值同序。
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookMaintainer {
+    symbol: Symbol,
+    timestamp: TimestampMs,
+    bids: BTreeMap<u64, f64>,
+    asks: BTreeMap<u64, f64>,
+    initialized: bool,
+}
+
+impl OrderBookMaintainer {
+    pub fn new(symbol: impl Into<Symbol>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 应用一次快照或增量更新
+    ///
+    /// 收到首个快照之前的增量更新会被忽略并返回 `false`，此时订单簿不完整。
+    pub fn apply(&mut self, book: &BookData) -> bool {
+        if book.is_snapshot {
+            self.bids.clear();
+            self.asks.clear();
+            self.initialized = true;
+        } else if !self.initialized {
+            return false;
+        }
+
+        apply_levels(&mut self.bids, &book.bids);
+        apply_levels(&mut self.asks, &book.asks);
+        self.timestamp = book.timestamp;
+
+        true
+    }
+
+    /// 是否已收到快照
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// 清空订单簿，等待下一个快照
+    pub fn reset(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.initialized = false;
+    }
+
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids
+            .last_key_value()
+            .map(|(price, size)| (f64::from_bits(*price), *size))
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks
+            .first_key_value()
+            .map(|(price, size)| (f64::from_bits(*price), *size))
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_bid()?.0 + self.best_ask()?.0) / 2.0)
+    }
+
+    /// 前 `n` 档，bids 按价格降序，asks 按价格升序
+    pub fn depth(&self, n: usize) -> (BookSide, BookSide) {
+        let level = |(price, size): (&u64, &f64)| (f64::from_bits(*price), *size);
+
+        (
+            self.bids.iter().rev().take(n).map(level).collect(),
+            self.asks.iter().take(n).map(level).collect(),
+        )
+    }
+
+    /// 以前 `n` 档生成完整的订单簿快照
+    pub fn to_book_data(&self, n: usize) -> BookData {
+        let (bids, asks) = self.depth(n);
+
+        BookData {
+            symbol: self.symbol.clone(),
+            timestamp: self.timestamp,
+            bids,
+            asks,
+            is_snapshot: true,
+        }
+    }
+}
+
+/// 按订单簿深度模拟市价单成交
+///
+/// 买单从最优卖价、卖单从最优买价开始逐档吃掉挂单，成交价是各档按成交数量加权的均价，
+/// 因此同时体现了买卖价差和大单的冲击成本，比以单一价格成交更接近实盘。
+/// `max_levels` 限制最多吃掉的档位数，相当于市价单的价格保护，超出的数量不成交。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BookFillModel {
+    pub max_levels: Option<usize>,
+}
+
+/// [`BookFillModel`] 的模拟成交结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookFill {
+    /// 成交均价
+    pub avg_price: f64,
+    /// 成交数量，深度不足时小于下单数量
+    pub filled_size: f64,
+    /// 成交均价相对中间价的不利偏离（百分比），只有一侧报价时相对该侧的最优价
+    pub slippage_pct: f64,
+    /// 吃掉的档位数
+    pub levels: usize,
+}
+
+impl BookFillModel {
+    /// 以 `book` 的当前深度模拟数量为 `size` 的市价单
+    ///
+    /// `book` 应为完整的订单簿（快照或 [`OrderBookMaintainer::to_book_data`]），
+    /// 对侧没有挂单或 `size` 不为正时返回 `None`。
+    pub fn fill(&self, book: &BookData, side: OrderSide, size: f64) -> Option<BookFill> {
+        let levels = match side {
+            OrderSide::Buy => &book.asks,
+            OrderSide::Sell => &book.bids,
+        };
+        let reference = book
+            .mid()
+            .or_else(|| levels.first().map(|(price, _)| *price))?;
+
+        let mut remaining = size;
+        let mut notional = 0.0;
+        let mut consumed = 0;
+        for &(price, available) in levels
+            .iter()
+            .filter(|(_, available)| *available > 0.0)
+            .take(self.max_levels.unwrap_or(usize::MAX))
+        {
+            if remaining <= 0.0 {
+                break;
+            }
+            let size = remaining.min(available);
+            notional += price * size;
+            remaining -= size;
+            consumed += 1;
+        }
+
+        let filled_size = size - remaining;
+        if filled_size <= 0.0 {
+            return None;
+        }
+
+        let avg_price = notional / filled_size;
+        let slippage = match side {
+            OrderSide::Buy => avg_price - reference,
+            OrderSide::Sell => reference - avg_price,
+        };
+
+        Some(BookFill {
+            avg_price,
+            filled_size,
+            slippage_pct: slippage / reference * 100.0,
+            levels: consumed,
+        })
+    }
+
+    /// 以模拟成交的均价和数量替换信号中的价格和数量
+    ///
+    /// 不检查信号与订单簿的交易对是否一致。`Hold` 原样返回，无法成交时返回 `None`。
+    pub fn fill_signal(&self, book: &BookData, signal: &Signal) -> Option<Signal> {
+        let (side, symbol, size) = match signal {
+            Signal::Buy { symbol, size, .. } => (OrderSide::Buy, symbol, *size),
+            Signal::Sell { symbol, size, .. } => (OrderSide::Sell, symbol, *size),
+            Signal::Hold => return Some(Signal::Hold),
+        };
+        let fill = self.fill(book, side, size)?;
+
+        Some(match side {
+            OrderSide::Buy => Signal::buy(symbol.clone(), fill.avg_price, fill.filled_size),
+            OrderSide::Sell => Signal::sell(symbol.clone(), fill.avg_price, fill.filled_size),
+        })
+    }
+}
+
+fn apply_levels(levels: &mut BTreeMap<u64, f64>, updates: &BookSide) {
+    for &(price, size) in updates {
+        if size == 0.0 {
+            levels.remove(&price.to_bits());
+        } else {
+            levels.insert(price.to_bits(), size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smallvec::smallvec;
+
+    fn book(timestamp: TimestampMs, bids: BookSide, asks: BookSide, is_snapshot: bool) -> BookData {
+        BookData {
+            symbol: "BTC-USDT".into(),
+            timestamp,
+            bids,
+            asks,
+            is_snapshot,
+        }
+    }
+
+    #[test]
+    fn test_order_book_maintainer() {
+        let mut maintainer = OrderBookMaintainer::new("BTC-USDT");
+
+        // 快照之前的增量更新被忽略
+        assert!(!maintainer.apply(&book(1, smallvec![(99.0, 1.0)], smallvec![], false)));
+        assert_eq!(maintainer.best_bid(), None);
+
+        assert!(maintainer.apply(&book(
+            2,
+            smallvec![(100.0, 1.0), (99.0, 2.0)],
+            smallvec![(101.0, 1.5), (102.0, 3.0)],
+            true,
+        )));
+        assert_eq!(maintainer.mid_price(), Some(100.5));
+
+        // 删除最优卖价，新增一档买价
+        maintainer.apply(&book(
+            3,
+            smallvec![(100.5, 0.5)],
+            smallvec![(101.0, 0.0)],
+            false,
+        ));
+        assert_eq!(maintainer.best_bid(), Some((100.5, 0.5)));
+        assert_eq!(maintainer.best_ask(), Some((102.0, 3.0)));
+
+        let snapshot = maintainer.to_book_data(2);
+        assert_eq!(snapshot.timestamp, 3);
+        assert_eq!(snapshot.bids.as_slice(), &[(100.5, 0.5), (100.0, 1.0)]);
+        assert_eq!(snapshot.asks.as_slice(), &[(102.0, 3.0)]);
+        assert!(snapshot.is_snapshot);
+
+        // 新快照完全替换旧档位
+        maintainer.apply(&book(
+            4,
+            smallvec![(90.0, 1.0)],
+            smallvec![(91.0, 1.0)],
+            true,
+        ));
+        assert_eq!(maintainer.depth(10).0.as_slice(), &[(90.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_book_fill_model() {
+        let thin = book(
+            1,
+            smallvec![(99.0, 1.0), (98.0, 1.0), (97.0, 1.0)],
+            smallvec![(101.0, 1.0), (102.0, 1.0), (105.0, 1.0)],
+            true,
+        );
+        let model = BookFillModel::default();
+
+        // 小单只吃最优档，仍需支付半个价差
+        let small = model.fill(&thin, OrderSide::Buy, 0.5).unwrap();
+        assert_eq!(small.avg_price, 101.0);
+        assert_eq!(small.filled_size, 0.5);
+        assert_eq!(small.levels, 1);
+        assert_eq!(small.slippage_pct, 1.0);
+
+        // 大单逐档成交，均价更差
+        let large = model.fill(&thin, OrderSide::Buy, 2.5).unwrap();
+        assert_eq!(large.avg_price, (101.0 + 102.0 + 105.0 * 0.5) / 2.5);
+        assert_eq!(large.levels, 3);
+        assert!(large.avg_price > small.avg_price);
+        assert!(large.slippage_pct > small.slippage_pct);
+
+        let sell = model.fill(&thin, OrderSide::Sell, 2.0).unwrap();
+        assert_eq!(sell.avg_price, 98.5);
+
+        // 深度不足或受档位限制时部分成交
+        assert_eq!(
+            model.fill(&thin, OrderSide::Buy, 10.0).unwrap().filled_size,
+            3.0
+        );
+        let limited = BookFillModel {
+            max_levels: Some(1),
+        };
+        assert_eq!(
+            limited
+                .fill(&thin, OrderSide::Buy, 2.5)
+                .unwrap()
+                .filled_size,
+            1.0
+        );
+
+        let one_sided = book(1, smallvec![], smallvec![(101.0, 1.0)], true);
+        assert_eq!(
+            model
+                .fill(&one_sided, OrderSide::Buy, 1.0)
+                .unwrap()
+                .slippage_pct,
+            0.0
+        );
+        assert!(model.fill(&one_sided, OrderSide::Sell, 1.0).is_none());
+        assert!(model.fill(&thin, OrderSide::Buy, 0.0).is_none());
+
+        assert_eq!(
+            model.fill_signal(&thin, &Signal::buy("BTC-USDT".into(), 100.0, 2.0)),
+            Some(Signal::buy("BTC-USDT".into(), 101.5, 2.0))
+        );
+        assert_eq!(model.fill_signal(&thin, &Signal::Hold), Some(Signal::Hold));
+    }
+}
@@ -24,6 +24,11 @@ pub const CANDLE_INTERVAL_WEEK1: IntervalSc = 604800;
 pub const CANDLE_INTERVAL_MON1: IntervalSc = 2592000;
 pub const CANDLE_INTERVAL_MON3: IntervalSc = 7776000;
 
+const DAY_MS: TimestampMs = 86_400_000;
+const WEEK_MS: TimestampMs = 7 * DAY_MS;
+/// 1970-01-01 是周四，距离之前最近的周一 3 天
+const EPOCH_WEEKDAY_OFFSET_MS: TimestampMs = 3 * DAY_MS;
+
 #[derive(Debug, Clone, PartialEq, strum::EnumDiscriminants)]
 #[strum_discriminants(vis(pub), name(MarketDataType))]
 pub enum MarketData {
@@ -69,19 +74,163 @@ pub struct CandleData {
     pub low: f64,
     pub close: f64,
     pub volume: f64,
+    /// 聚合进这根 K 线的成交笔数，旧数据中没有该列时为 0
+    #[serde(default)]
+    pub trade_count: u64,
+    /// 以计价货币计的成交额，旧数据中没有该列时为 0，见 [`CandleData::or_estimated_quote_volume`]
+    #[serde(default)]
+    pub quote_volume: f64,
+    /// `quote_volume` 是否由 `close * volume` 估算，而不是来自交易所或逐笔成交。
+    /// 为 `true` 时 [`CandleData::vwap`] 返回 `None`
+    #[serde(default)]
+    pub quote_volume_estimated: bool,
 }
 
 impl CandleData {
+    /// 由 OHLCV 构造 K 线，`trade_count` 为 0，成交额以 `close * volume` 估算
+    ///
+    /// # Error
+    ///
+    /// 数据不满足 [`validate`](Self::validate) 时返回 [`DataError::InvalidCandle`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        symbol: impl Into<Symbol>,
+        interval_sc: IntervalSc,
+        open_timestamp_ms: TimestampMs,
+        open: f64,
+        high: f64,
+        low: f64,
+        close: f64,
+        volume: f64,
+    ) -> DataResult<Self> {
+        let candle = Self {
+            symbol: symbol.into(),
+            interval_sc,
+            open_timestamp_ms,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            trade_count: 0,
+            quote_volume: close * volume,
+            quote_volume_estimated: true,
+        };
+        candle.validate()?;
+
+        Ok(candle)
+    }
+
+    /// 检查 K 线数据是否自洽
+    ///
+    /// - `interval_sc` 大于 0
+    /// - 价格为正的有限值，且 `low <= min(open, close)`、`max(open, close) <= high`
+    /// - 成交量和成交额为非负的有限值
+    pub fn validate(&self) -> DataResult<()> {
+        let invalid = |reason: String| {
+            Err(DataError::InvalidCandle {
+                symbol: self.symbol.clone(),
+                open_timestamp_ms: self.open_timestamp_ms,
+                reason,
+            })
+        };
+
+        if self.interval_sc == 0 {
+            return invalid("interval must be positive".into());
+        }
+
+        let prices = [self.open, self.high, self.low, self.close];
+        if prices.iter().any(|p| !p.is_finite() || *p <= 0.0) {
+            return invalid(format!("prices must be positive and finite: {prices:?}"));
+        }
+
+        if self.low > self.open.min(self.close) || self.high < self.open.max(self.close) {
+            return invalid(format!(
+                "OHLC out of range: open {}, high {}, low {}, close {}",
+                self.open, self.high, self.low, self.close
+            ));
+        }
+
+        if !self.volume.is_finite() || self.volume < 0.0 {
+            return invalid(format!(
+                "volume must be non-negative and finite: {}",
+                self.volume
+            ));
+        }
+
+        if !self.quote_volume.is_finite() || self.quote_volume < 0.0 {
+            return invalid(format!(
+                "quote volume must be non-negative and finite: {}",
+                self.quote_volume
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 成交额为 0 而成交量不为 0 时，以 `close * volume` 估算成交额，并标记
+    /// [`quote_volume_estimated`](Self::quote_volume_estimated)
+    ///
+    /// 用于读取没有成交额一列的旧数据。
+    pub fn or_estimated_quote_volume(mut self) -> Self {
+        if self.quote_volume == 0.0 && self.volume != 0.0 {
+            self.quote_volume = self.close * self.volume;
+            self.quote_volume_estimated = true;
+        }
+        self
+    }
+
+    /// 成交量加权平均价: `quote_volume / volume`
+    ///
+    /// 由逐笔成交聚合的 K 线为精确值，交易所 K 线取决于其成交额字段（OKX 的 `volCcyQuote`、
+    /// Binance 的 `q`）。成交量或成交额为 0（没有成交，或旧数据缺少成交额），或成交额经
+    /// [`Self::or_estimated_quote_volume`] 估算（只能得到收盘价）时返回 `None`。
+    pub fn vwap(&self) -> Option<f64> {
+        (!self.quote_volume_estimated && self.volume > 0.0 && self.quote_volume > 0.0)
+            .then(|| self.quote_volume / self.volume)
+    }
+
     pub(crate) fn new_with_trade(trade: &TradeData, interval_sc: IntervalSc) -> Self {
         Self {
             symbol: trade.symbol.clone(),
             interval_sc,
-            open_timestamp_ms: trade.timestamp_ms - (trade.timestamp_ms % (interval_sc * 1000)),
+            open_timestamp_ms: Self::align_open_timestamp(trade.timestamp_ms, interval_sc),
             open: trade.price,
             high: trade.price,
             low: trade.price,
             close: trade.price,
             volume: trade.quantity,
+            trade_count: 1,
+            quote_volume: trade.price * trade.quantity,
+            quote_volume_estimated: false,
+        }
+    }
+
+    /// 计算 `timestamp_ms` 所在 K 线的开盘时间，按 UTC 自然日历对齐
+    ///
+    /// - 周线 ([`CANDLE_INTERVAL_WEEK1`]): 周一 00:00 UTC
+    /// - 月线 ([`CANDLE_INTERVAL_MON1`] 的整数倍): 自然月 1 日 00:00 UTC，季线对齐到 1/4/7/10 月
+    /// - 其余周期: Unix 纪元的整数倍，日线即 00:00 UTC
+    ///
+    /// 与 OKX `*utc` 频道及 Binance 的 K 线划分一致。
+    pub fn align_open_timestamp(timestamp_ms: TimestampMs, interval_sc: IntervalSc) -> TimestampMs {
+        if let Some(months) = calendar_months(interval_sc) {
+            let index = month_index(timestamp_ms);
+            return month_start_timestamp(index - index.rem_euclid(months));
+        }
+
+        if interval_sc == CANDLE_INTERVAL_WEEK1 {
+            return timestamp_ms.saturating_sub((timestamp_ms + EPOCH_WEEKDAY_OFFSET_MS) % WEEK_MS);
+        }
+
+        timestamp_ms - timestamp_ms % (interval_sc * 1000)
+    }
+
+    /// 收盘时间（不含），即下一根 K 线的开盘时间。月线按自然月长度计算。
+    pub fn close_timestamp_ms(&self) -> TimestampMs {
+        match calendar_months(self.interval_sc) {
+            Some(months) => month_start_timestamp(month_index(self.open_timestamp_ms) + months),
+            None => self.open_timestamp_ms + self.interval_sc * 1000,
         }
     }
 
@@ -91,6 +240,8 @@ impl CandleData {
         self.low = self.low.min(trade.price);
         self.close = trade.price;
         self.volume += trade.quantity;
+        self.trade_count += 1;
+        self.quote_volume += trade.price * trade.quantity;
     }
 
     /// # Error:
@@ -122,6 +273,9 @@ impl CandleData {
         self.low = self.low.min(candle.low);
         self.close = candle.close;
         self.volume += candle.volume;
+        self.trade_count += candle.trade_count;
+        self.quote_volume += candle.quote_volume;
+        self.quote_volume_estimated |= candle.quote_volume_estimated;
     }
 
     /// # Error
@@ -179,6 +333,108 @@ pub struct BookData {
     pub bids: BookSide,
     /// (价格, 数量)
     pub asks: BookSide,
+    /// 是否为完整快照。为 `true` 时下游应丢弃之前维护的订单簿视图，以此为准重建；
+    /// 为 `false` 时只是增量更新。缺省时视为完整快照
+    #[serde(default = "default_is_snapshot")]
+    pub is_snapshot: bool,
+}
+
+fn default_is_snapshot() -> bool {
+    true
+}
+
+impl BookData {
+    /// 最优买价，`bids` 按价格降序排列。
+    #[inline]
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.first().copied()
+    }
+
+    /// 最优卖价，`asks` 按价格升序排列。
+    #[inline]
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.first().copied()
+    }
+
+    /// 中间价: `(best_bid + best_ask) / 2`，任意一侧为空时返回 `None`。
+    #[inline]
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()?.0 + self.best_ask()?.0) / 2.0)
+    }
+
+    /// 微观价格: `(bid_px * ask_size + ask_px * bid_size) / (bid_size + ask_size)`。
+    ///
+    /// 任意一侧为空（或最优档位总数量为 0）时返回 `None`。
+    pub fn microprice(&self) -> Option<f64> {
+        let (bid_px, bid_size) = self.best_bid()?;
+        let (ask_px, ask_size) = self.best_ask()?;
+
+        weighted_price(bid_px, bid_size, ask_px, ask_size)
+    }
+
+    /// 使用前 `depth` 档计算的加权中间价。
+    ///
+    /// 每一侧先按数量求 VWAP，再以对侧的总数量为权重做与 [`Self::microprice`] 相同的加权。
+    /// `depth == 1` 时与 `microprice` 等价。任意一侧为空或 `depth == 0` 时返回 `None`。
+    pub fn weighted_mid(&self, depth: usize) -> Option<f64> {
+        let (bid_px, bid_size) = side_vwap(&self.bids, depth)?;
+        let (ask_px, ask_size) = side_vwap(&self.asks, depth)?;
+
+        weighted_price(bid_px, bid_size, ask_px, ask_size)
+    }
+}
+
+/// 月线周期对应的自然月数量，非月线返回 `None`
+fn calendar_months(interval_sc: IntervalSc) -> Option<i64> {
+    (interval_sc != 0 && interval_sc.is_multiple_of(CANDLE_INTERVAL_MON1))
+        .then_some((interval_sc / CANDLE_INTERVAL_MON1) as i64)
+}
+
+/// 自公元 0 年 1 月起的月序号（UTC）
+fn month_index(timestamp_ms: TimestampMs) -> i64 {
+    // civil_from_days，见 https://howardhinnant.github.io/date_algorithms.html
+    let z = (timestamp_ms / DAY_MS) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    year * 12 + month - 1
+}
+
+/// 月序号对应月份 1 日 00:00 UTC 的时间戳，早于纪元时取 0
+fn month_start_timestamp(index: i64) -> TimestampMs {
+    // days_from_civil，见 https://howardhinnant.github.io/date_algorithms.html
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    days.max(0) as TimestampMs * DAY_MS
+}
+
+/// 返回 (VWAP, 总数量)
+fn side_vwap(side: &BookSide, depth: usize) -> Option<(f64, f64)> {
+    let (notional, size) = side
+        .iter()
+        .take(depth)
+        .fold((0.0, 0.0), |(notional, size), &(px, sz)| {
+            (notional + px * sz, size + sz)
+        });
+
+    (size > 0.0).then(|| (notional / size, size))
+}
+
+#[inline]
+fn weighted_price(bid_px: f64, bid_size: f64, ask_px: f64, ask_size: f64) -> Option<f64> {
+    let total = bid_size + ask_size;
+    (total > 0.0).then(|| (bid_px * ask_size + ask_px * bid_size) / total)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, Serialize, Deserialize)]
@@ -190,6 +446,46 @@ pub enum Side {
     #[strum(serialize = "sell")]
     #[serde(alias = "Sell", alias = "SELL")]
     Sell,
+    /// 交易所没有标注主动方，可用 [`infer_aggressor`] 或
+    /// [`label_trade_sides`](crate::stream::label_trade_sides) 推断
+    #[strum(serialize = "unknown")]
+    #[serde(alias = "Unknown", alias = "UNKNOWN")]
+    Unknown,
+}
+
+/// 外部数据中时间戳的单位
+///
+/// 内部统一使用毫秒（[`TimestampMs`]），读取以秒或微秒记录时间戳的数据时，在数据源处换算，
+/// 避免得到相差 1000 倍的时间。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TimestampUnit {
+    Seconds,
+    #[default]
+    Millis,
+    Micros,
+}
+
+impl TimestampUnit {
+    /// 换算为毫秒，微秒向下取整
+    pub fn to_millis(self, timestamp: u64) -> TimestampMs {
+        match self {
+            TimestampUnit::Seconds => timestamp * 1000,
+            TimestampUnit::Millis => timestamp,
+            TimestampUnit::Micros => timestamp / 1000,
+        }
+    }
+}
+
+/// 根据成交价与订单簿中间价推断成交的主动方
+///
+/// 高于中间价视为买方主动，低于中间价视为卖方主动。恰好等于中间价或订单簿任意一侧为空时
+/// 无法判断，返回 [`Side::Unknown`]。
+pub fn infer_aggressor(trade: &TradeData, book: &BookData) -> Side {
+    match book.mid().and_then(|mid| trade.price.partial_cmp(&mid)) {
+        Some(Ordering::Greater) => Side::Buy,
+        Some(Ordering::Less) => Side::Sell,
+        _ => Side::Unknown,
+    }
 }
 
 pub type DataResult<T> = std::result::Result<T, DataError>;
@@ -224,6 +520,30 @@ pub enum DataError {
 
     #[error("Unexpect end of stream.")]
     UnexpectedStreamEof,
+
+    // 连接异常，例如长时间没有收到任何帧
+    #[error("Connection error: {0}")]
+    Connection(String),
+
+    // 增量推送的序列号不连续，说明中间有推送丢失，需要重新同步订单簿
+    #[error("Sequence gap on {symbol}: expect previous sequence {expected}, but found {found}.")]
+    SequenceGap {
+        symbol: Symbol,
+        expected: u64,
+        found: u64,
+    },
+
+    // 订阅被交易所拒绝，或者迟迟没有收到订阅确认
+    #[error("Subscription error: {0}")]
+    Subscription(String),
+
+    // K 线数据不自洽，例如最高价低于开盘价
+    #[error("Invalid candle {symbol}@{open_timestamp_ms}: {reason}")]
+    InvalidCandle {
+        symbol: Symbol,
+        open_timestamp_ms: TimestampMs,
+        reason: String,
+    },
 }
 
 impl DataError {
@@ -259,3 +579,222 @@ fn display_ordering(order: &Ordering) -> &'static str {
         Ordering::Greater => "greater than",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smallvec::smallvec;
+
+    fn book(bids: BookSide, asks: BookSide) -> BookData {
+        BookData {
+            symbol: "BTC-USDT".into(),
+            timestamp: 0,
+            bids,
+            asks,
+            is_snapshot: true,
+        }
+    }
+
+    #[test]
+    fn test_daily_candle_aligns_to_utc_midnight() {
+        // 2025-08-26 10:03:25 UTC
+        let open = CandleData::align_open_timestamp(1756202605000, CANDLE_INTERVAL_D1);
+        assert_eq!(open, 1756166400000); // 2025-08-26 00:00 UTC
+
+        // 当天最后一毫秒仍属于同一根日线
+        assert_eq!(
+            CandleData::align_open_timestamp(1756166400000 + DAY_MS - 1, CANDLE_INTERVAL_D1),
+            open
+        );
+    }
+
+    #[test]
+    fn test_weekly_candle_aligns_to_monday() {
+        // 2025-08-26 周二 -> 2025-08-25 周一
+        let open = CandleData::align_open_timestamp(1756202605000, CANDLE_INTERVAL_WEEK1);
+        assert_eq!(open, 1756080000000);
+
+        // 周一 00:00 本身不变
+        assert_eq!(
+            CandleData::align_open_timestamp(1756080000000, CANDLE_INTERVAL_WEEK1),
+            1756080000000
+        );
+    }
+
+    #[test]
+    fn test_monthly_candle_aligns_to_calendar_month() {
+        let candle = |timestamp_ms, interval_sc| {
+            CandleData::new_with_trade(
+                &TradeData {
+                    symbol: "BTC-USDT".into(),
+                    timestamp_ms,
+                    price: 1.0,
+                    quantity: 1.0,
+                    side: Side::Buy,
+                },
+                interval_sc,
+            )
+        };
+
+        // 2024-02-15 13:00 UTC，闰年二月有 29 天
+        let feb = candle(1708002000000, CANDLE_INTERVAL_MON1);
+        assert_eq!(feb.open_timestamp_ms, 1706745600000); // 2024-02-01
+        assert_eq!(feb.close_timestamp_ms(), 1709251200000); // 2024-03-01
+
+        // 2024-05-20 08:00 UTC 属于第二季度
+        let q2 = candle(1716192000000, CANDLE_INTERVAL_MON3);
+        assert_eq!(q2.open_timestamp_ms, 1711929600000); // 2024-04-01
+        assert_eq!(q2.close_timestamp_ms(), 1719792000000); // 2024-07-01
+
+        // 跨年：2024-12-31 23:59:59 UTC
+        let dec = candle(1735689599000, CANDLE_INTERVAL_MON1);
+        assert_eq!(dec.close_timestamp_ms(), 1735689600000); // 2025-01-01
+    }
+
+    #[test]
+    fn test_book_is_snapshot_defaults_to_true() {
+        let mut json =
+            br#"{"symbol":"BTC-USDT","timestamp":0,"bids":[[10.0,1.0]],"asks":[]}"#.to_vec();
+        let book: BookData = simd_json::from_slice(&mut json).unwrap();
+        assert!(book.is_snapshot);
+    }
+
+    #[test]
+    fn test_microprice() {
+        let book = book(
+            smallvec![(100.0, 3.0), (99.0, 5.0)],
+            smallvec![(102.0, 1.0), (103.0, 4.0)],
+        );
+
+        // (100 * 1 + 102 * 3) / (3 + 1) = 101.5
+        assert_eq!(book.microprice(), Some(101.5));
+        assert_eq!(book.weighted_mid(1), book.microprice());
+    }
+
+    #[test]
+    fn test_weighted_mid() {
+        let book = book(
+            smallvec![(100.0, 3.0), (99.0, 5.0)],
+            smallvec![(102.0, 1.0), (103.0, 4.0)],
+        );
+
+        // bid vwap = (300 + 495) / 8 = 99.375, ask vwap = (102 + 412) / 5 = 102.8
+        // (99.375 * 5 + 102.8 * 8) / 13 = 101.482692...
+        let expected = (99.375 * 5.0 + 102.8 * 8.0) / 13.0;
+        assert!((book.weighted_mid(2).unwrap() - expected).abs() < 1e-12);
+        // 深度超过档位数时只使用已有档位
+        assert_eq!(book.weighted_mid(10), book.weighted_mid(2));
+        assert_eq!(book.weighted_mid(0), None);
+    }
+
+    #[test]
+    fn test_microprice_empty_side() {
+        let no_asks = book(smallvec![(100.0, 3.0)], smallvec![]);
+        assert_eq!(no_asks.microprice(), None);
+        assert_eq!(no_asks.weighted_mid(5), None);
+
+        let no_bids = book(smallvec![], smallvec![(102.0, 1.0)]);
+        assert_eq!(no_bids.microprice(), None);
+        assert_eq!(no_bids.weighted_mid(5), None);
+    }
+
+    #[test]
+    fn test_infer_aggressor() {
+        let book = book(smallvec![(100.0, 3.0)], smallvec![(102.0, 1.0)]);
+        let trade = |price| TradeData {
+            symbol: "BTC-USDT".into(),
+            timestamp_ms: 0,
+            price,
+            quantity: 1.0,
+            side: Side::Unknown,
+        };
+
+        assert_eq!(book.mid(), Some(101.0));
+        assert_eq!(infer_aggressor(&trade(101.5), &book), Side::Buy);
+        assert_eq!(infer_aggressor(&trade(100.0), &book), Side::Sell);
+        assert_eq!(infer_aggressor(&trade(101.0), &book), Side::Unknown);
+
+        let no_asks = BookData {
+            asks: smallvec![],
+            ..book
+        };
+        assert_eq!(infer_aggressor(&trade(101.5), &no_asks), Side::Unknown);
+    }
+
+    #[test]
+    fn test_candle_new() {
+        let candle = CandleData::new(
+            "BTC-USDT",
+            60,
+            1640000000000,
+            100.0,
+            110.0,
+            90.0,
+            105.0,
+            10.0,
+        )
+        .unwrap();
+        assert_eq!(
+            candle,
+            CandleData {
+                symbol: "BTC-USDT".into(),
+                interval_sc: 60,
+                open_timestamp_ms: 1640000000000,
+                open: 100.0,
+                high: 110.0,
+                low: 90.0,
+                close: 105.0,
+                volume: 10.0,
+                trade_count: 0,
+                quote_volume: 1050.0,
+                quote_volume_estimated: true,
+            }
+        );
+
+        let invalid = [
+            // 最高价低于收盘价
+            CandleData::new("BTC-USDT", 60, 0, 100.0, 104.0, 90.0, 105.0, 10.0),
+            // 最低价高于开盘价
+            CandleData::new("BTC-USDT", 60, 0, 100.0, 110.0, 101.0, 105.0, 10.0),
+            CandleData::new("BTC-USDT", 60, 0, f64::NAN, 110.0, 90.0, 105.0, 10.0),
+            CandleData::new("BTC-USDT", 60, 0, 100.0, 110.0, 0.0, 105.0, 10.0),
+            CandleData::new("BTC-USDT", 60, 0, 100.0, 110.0, 90.0, 105.0, -1.0),
+            CandleData::new("BTC-USDT", 0, 0, 100.0, 110.0, 90.0, 105.0, 10.0),
+        ];
+        for result in invalid {
+            assert!(matches!(result, Err(DataError::InvalidCandle { .. })));
+        }
+    }
+
+    #[test]
+    fn test_candle_vwap() {
+        let trade = |timestamp_ms, price, quantity| TradeData {
+            symbol: "BTC-USDT".into(),
+            timestamp_ms,
+            price,
+            quantity,
+            side: Side::Buy,
+        };
+        let trades = [
+            trade(1672531200000, 100.0, 1.0),
+            trade(1672531210000, 110.0, 3.0),
+        ];
+
+        let candle = CandleData::from_trades(&trades, 60).unwrap().unwrap();
+        assert_eq!(candle.trade_count, 2);
+        assert_eq!(candle.vwap(), Some(107.5));
+
+        // 没有成交额（旧数据）或没有成交量时没有 VWAP
+        let legacy = CandleData {
+            quote_volume: 0.0,
+            quote_volume_estimated: false,
+            ..candle.clone()
+        };
+        assert_eq!(legacy.vwap(), None);
+        let empty = CandleData {
+            volume: 0.0,
+            ..candle
+        };
+        assert_eq!(empty.vwap(), None);
+    }
+}
@@ -1,9 +1,16 @@
+pub mod book;
 pub mod data;
-pub mod id_registry;
 pub mod execution;
+pub mod id_registry;
+pub mod reconnect;
+pub mod stream;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
+pub use book::*;
 pub use data::*;
 pub use execution::*;
+pub use reconnect::*;
 
 pub type TimestampMs = u64;
 pub type Symbol = bytestring::ByteString;
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+/// 断线重连的退避策略
+///
+/// 第 `attempt` 次重连（从 0 开始）前等待 `initial * multiplier^attempt`，不超过 `max`，
+/// 再乘以 `[1 - jitter, 1 + jitter]` 内的随机系数，避免大量连接在同一时刻重连。
+/// 各交易所数据流的自动重连（`ephemera_source::utils::retry_stream`）和实盘策略驱动共用这套退避策略。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// 第一次重连前的等待时间
+    pub initial: Duration,
+    /// 等待时间的上限（加入抖动前）
+    pub max: Duration,
+    /// 每次重连后等待时间的倍数
+    pub multiplier: f64,
+    /// 抖动比例，取值 `[0, 1]`
+    pub jitter: f64,
+    /// 连续重连的最大次数，超过后结束数据流
+    pub max_retries: usize,
+    /// 连接持续至少这么久才算一次成功的运行，之后断开时重新从第 0 次开始计数
+    pub reset_after: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_retries: 10,
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次重连（从 0 开始）前的等待时间
+    pub fn next_delay(&self, attempt: usize) -> Duration {
+        let exp = attempt.min(i32::MAX as usize) as i32;
+        let base =
+            (self.initial.as_secs_f64() * self.multiplier.powi(exp)).min(self.max.as_secs_f64());
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * rand::random::<f64>() - 1.0);
+
+        Duration::from_secs_f64((base * factor).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_policy_delay() {
+        let policy = ReconnectPolicy {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.0,
+            ..Default::default()
+        };
+
+        let delays: Vec<_> = (0..6)
+            .map(|attempt| policy.next_delay(attempt).as_millis())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.next_delay(usize::MAX), Duration::from_secs(1));
+
+        let policy = ReconnectPolicy {
+            jitter: 0.25,
+            ..policy
+        };
+        for (attempt, base) in delays.into_iter().enumerate().cycle().take(600) {
+            let delay = policy.next_delay(attempt).as_secs_f64() * 1000.0;
+            let base = base as f64;
+            assert!(delay >= base * 0.75 - 1e-6 && delay <= base * 1.25 + 1e-6);
+        }
+    }
+}
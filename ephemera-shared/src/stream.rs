@@ -1,10 +1,12 @@
-use crate::error::DataError;
 use crate::*;
 use futures::stream::Peekable;
 use futures::{Stream, StreamExt};
+use std::collections::HashMap;
 use std::pin::Pin;
 
 /// Transforms a stream of into a stream of candles.
+/// The last candle is yielded as if complete; use [`transform_trades_to_candles_with_flush`] to
+/// tell it apart or drop it.
 ///
 /// # Panics
 ///
@@ -14,31 +16,30 @@ use std::pin::Pin;
 ///
 /// ```rust
 /// # use futures::{stream, StreamExt};
-/// # use rust_decimal_macros::dec;
-/// # use my_crate::{trades_to_candle_stream, TradeData, Side, CandleData};
+/// # use ephemera_shared::{stream::transform_trades_to_candles, TradeData, Side};
 /// #
 /// # #[tokio::main]
 /// # async fn main() {
 /// let trades: Vec<TradeData> = vec![
 ///     // Candle #1 (10:00:00 -> 10:01:00)
-///     TradeData { symbol: "BTC-USDT".into(), timestamp_ms: 1756202405000, price: dec!(20000), quantity: dec!(1.5), side: Side::Buy },
-///     TradeData { symbol: "BTC-USDT".into(), timestamp_ms: 1756202455000, price: dec!(20100), quantity: dec!(2.0), side: Side::Buy },
+///     TradeData { symbol: "BTC-USDT".into(), timestamp_ms: 1756202405000, price: 20000.0, quantity: 1.5, side: Side::Buy },
+///     TradeData { symbol: "BTC-USDT".into(), timestamp_ms: 1756202455000, price: 20100.0, quantity: 2.0, side: Side::Buy },
 ///     // Candle #2 (10:02:00 -> 10:03:00)
-///     TradeData { symbol: "BTC-USDT".into(), timestamp_ms: 1756202525000, price: dec!(20120), quantity: dec!(3.0), side: Side::Buy },
+///     TradeData { symbol: "BTC-USDT".into(), timestamp_ms: 1756202525000, price: 20120.0, quantity: 3.0, side: Side::Buy },
 /// ];
 ///
 /// let trade_stream = stream::iter(trades);
 ///
-/// let mut candle_stream = trades_to_candle_stream(trade_stream, 60);
+/// let mut candle_stream = Box::pin(transform_trades_to_candles(trade_stream, 60));
 ///
-/// let candle1 = candle_stream.next().await.unwrap();
-/// assert_eq!(candle1.open_timestamp, 1756202400000);
-/// assert_eq!(candle1.high, dec!(20100));
-/// assert_eq!(candle1.volume, dec!(3.5));
+/// let candle1 = candle_stream.next().await.unwrap().unwrap();
+/// assert_eq!(candle1.open_timestamp_ms, 1756202400000);
+/// assert_eq!(candle1.high, 20100.0);
+/// assert_eq!(candle1.volume, 3.5);
 ///
-/// let candle2 = candle_stream.next().await.unwrap();
-/// assert_eq!(candle2.open_timestamp, 1756202520000);
-/// assert_eq!(candle2.volume, dec!(3.0));
+/// let candle2 = candle_stream.next().await.unwrap().unwrap();
+/// assert_eq!(candle2.open_timestamp_ms, 1756202520000);
+/// assert_eq!(candle2.volume, 3.0);
 ///
 /// assert!(candle_stream.next().await.is_none());
 /// # }
@@ -56,6 +57,44 @@ pub fn transform_trades_to_candles(
     })
 }
 
+/// Like [`transform_trades_to_candles`], but tells closed candles apart from the trailing partial
+/// one, yielding `(candle, is_closed)`.
+///
+/// A candle is closed once a trade at or after its close timestamp has been seen. When the input
+/// ends, the last candle cannot be known to be complete: with `flush_partial` it is yielded with
+/// `is_closed == false`, otherwise it is dropped. Backtests that need the trailing bar can opt in,
+/// while live consumers should leave it out. All earlier candles are yielded with `true`.
+///
+/// # Error
+///
+/// See [`agg_trades_to_candle`].
+///
+/// # Panics
+///
+/// 1. If `interval_sc` is `0`.
+pub fn transform_trades_to_candles_with_flush(
+    stream: impl Stream<Item = TradeData> + Unpin + Send,
+    interval_sc: u64,
+    flush_partial: bool,
+) -> impl Stream<Item = DataResult<(CandleData, bool)>> + Send {
+    let stream = stream.peekable();
+    futures::stream::unfold(Box::pin(stream), move |mut s| async move {
+        let candle = match agg_trades_to_candle(s.as_mut(), interval_sc).await {
+            Ok(Some(candle)) => candle,
+            Ok(None) => return None,
+            Err(e) => return Some((Err(e), s)),
+        };
+
+        // The next trade, if any, is past this candle's close
+        let is_closed = s.as_mut().peek().await.is_some();
+        if !is_closed && !flush_partial {
+            return None;
+        }
+
+        Some((Ok((candle, is_closed)), s))
+    })
+}
+
 /// A low-level helper to aggregate trades from a stream into a single candle.
 /// **Assume the trade data at the end of the line constitutes a complete candle.**
 ///
@@ -71,14 +110,13 @@ pub async fn agg_trades_to_candle(
     target_interval: IntervalSc,
 ) -> DataResult<Option<CandleData>> {
     assert_ne!(target_interval, 0, "Interval shouldn't be zero.");
-    let interval_ms = target_interval * 1000;
 
     let Some(first_trade) = stream.next().await else {
         return Ok(None);
     };
 
     let mut candle = CandleData::new_with_trade(&first_trade, target_interval);
-    let close_timestamp = candle.open_timestamp_ms + interval_ms;
+    let close_timestamp = candle.close_timestamp_ms();
 
     while let Some(next_trade) = stream
         .as_mut()
@@ -91,8 +129,188 @@ pub async fn agg_trades_to_candle(
     Ok(Some(candle))
 }
 
+/// Transforms a stream of trades which may contain multiple symbols into a stream of candles.
+///
+/// Each symbol keeps its own in-progress candle. The latest trade timestamp acts as a global
+/// clock: once it passes the close of a symbol's candle, that candle is emitted even if the
+/// symbol itself has not traded again. Intervals in which a known symbol did not trade are
+/// emitted as flat candles carrying the previous close forward, with zero volume and trade
+/// count, so every symbol produces a gap-free series once it has traded. Candles completed by
+/// the same trade are ordered by open timestamp and then symbol.
+///
+/// When the input stream ends, all remaining in-progress candles are emitted in the same order.
+///
+/// # Error
+///
+/// Return `DataError::UnexpectedTimestamp` if a trade is earlier than the open timestamp of its
+/// symbol's in-progress candle. The stream ends after the error.
+///
+/// # Panics
+///
+/// 1. If `interval_sc` is `0`.
+pub fn transform_multi_symbol_trades_to_candles(
+    stream: impl Stream<Item = TradeData> + Send,
+    interval_sc: IntervalSc,
+) -> impl Stream<Item = DataResult<CandleData>> + Send {
+    assert_ne!(interval_sc, 0, "Interval shouldn't be zero.");
+
+    async_stream::stream! {
+        // trade_count 为 0 的K线是为静默区间补齐的平盘K线，尚未有真实成交
+        let mut candles: HashMap<Symbol, CandleData> = HashMap::new();
+        // 所有进行中K线里最早的收盘时间，时钟未越过它时无需扫描
+        let mut next_close = TimestampMs::MAX;
+
+        for await trade in stream {
+            if trade.timestamp_ms >= next_close {
+                let mut completed = Vec::new();
+                next_close = TimestampMs::MAX;
+
+                for candle in candles.values_mut() {
+                    while candle.close_timestamp_ms() <= trade.timestamp_ms {
+                        let flat = flat_candle_after(candle);
+                        completed.push(std::mem::replace(candle, flat));
+                    }
+                    next_close = next_close.min(candle.close_timestamp_ms());
+                }
+
+                sort_candles(&mut completed);
+                for candle in completed {
+                    yield Ok(candle);
+                }
+            }
+
+            let Some(candle) = candles.get_mut(&trade.symbol) else {
+                let candle = CandleData::new_with_trade(&trade, interval_sc);
+                next_close = next_close.min(candle.close_timestamp_ms());
+                candles.insert(trade.symbol.clone(), candle);
+                continue;
+            };
+
+            if trade.timestamp_ms < candle.open_timestamp_ms {
+                yield Err(DataError::timestamp_should_be_after(
+                    candle.open_timestamp_ms,
+                    trade.timestamp_ms,
+                ));
+                return;
+            }
+
+            if candle.trade_count == 0 {
+                *candle = CandleData::new_with_trade(&trade, interval_sc);
+            } else {
+                candle.unchecked_agg_with_trade(&trade);
+            }
+        }
+
+        let mut remaining = candles.into_values().collect::<Vec<_>>();
+        sort_candles(&mut remaining);
+
+        for candle in remaining {
+            yield Ok(candle);
+        }
+    }
+}
+
+/// The zero-volume candle following `candle`, with every price at its close.
+fn flat_candle_after(candle: &CandleData) -> CandleData {
+    flat_candle_at(candle, candle.close_timestamp_ms())
+}
+
+/// A zero-volume candle of `candle`'s symbol opening at `open_timestamp_ms`, with every price at
+/// `candle`'s close.
+fn flat_candle_at(candle: &CandleData, open_timestamp_ms: TimestampMs) -> CandleData {
+    CandleData {
+        symbol: candle.symbol.clone(),
+        interval_sc: candle.interval_sc,
+        open_timestamp_ms,
+        open: candle.close,
+        high: candle.close,
+        low: candle.close,
+        close: candle.close,
+        volume: 0.0,
+        trade_count: 0,
+        quote_volume: 0.0,
+        quote_volume_estimated: false,
+    }
+}
+
+fn sort_candles(candles: &mut [CandleData]) {
+    candles.sort_by(|a, b| {
+        a.open_timestamp_ms
+            .cmp(&b.open_timestamp_ms)
+            .then_with(|| a.symbol.cmp(&b.symbol))
+    });
+}
+
+/// Fills in the aggressor side of trades that the exchange left as [`Side::Unknown`].
+///
+/// Both streams are merged, the book stream is polled first so that a trade is always compared
+/// against the latest book already available for its symbol. Each unlabeled trade is classified
+/// with [`infer_aggressor`]; when the trade sits exactly at the mid or no book has been seen yet,
+/// it falls back to the tick rule against the previous trade of the same symbol (uptick = buy,
+/// downtick = sell). Trades that already carry a side are passed through unchanged.
+///
+/// Books are expected to be full views of the top of book (e.g. snapshots or the output of a
+/// local order book), since only the best bid and ask are read.
+///
+/// The returned stream ends when the trade stream ends.
+pub fn label_trade_sides(
+    trade_stream: impl Stream<Item = TradeData> + Send,
+    book_stream: impl Stream<Item = BookData> + Send,
+) -> impl Stream<Item = TradeData> + Send {
+    use futures::future::Either;
+    use futures::stream::{PollNext, select_with_strategy};
+
+    // 交易流结束时整个流结束，订单簿流结束后继续使用最后一份订单簿
+    let trade_stream = trade_stream
+        .map(Either::Right)
+        .chain(futures::stream::once(async { Either::Left(None) }));
+    let merged = select_with_strategy(
+        book_stream.map(|book| Either::Left(Some(book))),
+        trade_stream,
+        |_: &mut ()| PollNext::Left,
+    );
+
+    async_stream::stream! {
+        let mut books: HashMap<Symbol, BookData> = HashMap::new();
+        let mut last_prices: HashMap<Symbol, f64> = HashMap::new();
+
+        for await item in merged {
+            let mut trade = match item {
+                Either::Left(Some(book)) => {
+                    books.insert(book.symbol.clone(), book);
+                    continue;
+                }
+                Either::Left(None) => return,
+                Either::Right(trade) => trade,
+            };
+
+            let last_price = last_prices.insert(trade.symbol.clone(), trade.price);
+
+            if trade.side != Side::Unknown {
+                yield trade;
+                continue;
+            }
+
+            trade.side = books
+                .get(&trade.symbol)
+                .map_or(Side::Unknown, |book| infer_aggressor(&trade, book));
+
+            if trade.side == Side::Unknown {
+                trade.side = match last_price.and_then(|last| trade.price.partial_cmp(&last)) {
+                    Some(std::cmp::Ordering::Greater) => Side::Buy,
+                    Some(std::cmp::Ordering::Less) => Side::Sell,
+                    _ => Side::Unknown,
+                };
+            }
+
+            yield trade;
+        }
+    }
+}
+
 /// Aggregates a stream of smaller-interval candles into a stream of larger-interval candles.
 /// *Incomplete groups at the end of the stream are discarded*.
+/// Use [`transform_candles_to_candles_with_flush`] to keep them.
 ///
 /// # Error
 ///
@@ -101,30 +319,29 @@ pub async fn agg_trades_to_candle(
 /// # Examples
 /// ```rust
 /// # use futures::{stream, StreamExt};
-/// # use rust_decimal_macros::dec;
-/// # use my_crate::{candles_to_candle_stream, CandleData};
+/// # use ephemera_shared::{stream::transform_candles_to_candles, CandleData};
 /// #
 /// # #[tokio::main]
 /// # async fn main() {
 /// let minute_candles: Vec<CandleData> = vec![
 ///     // Group 1
-///     CandleData { symbol: "BTC-USDT".into(), interval_sc: 60, open_timestamp: 1672531200000, open: dec!(20000), high: dec!(20100), low: dec!(19950), close: dec!(20050), volume: dec!(10) },
-///     CandleData { symbol: "BTC-USDT".into(), interval_sc: 60, open_timestamp: 1672531260000, open: dec!(20050), high: dec!(20200), low: dec!(20040), close: dec!(20180), volume: dec!(15) },
+///     CandleData { symbol: "BTC-USDT".into(), interval_sc: 60, open_timestamp_ms: 1672531200000, open: 20000.0, high: 20100.0, low: 19950.0, close: 20050.0, volume: 10.0, trade_count: 10, quote_volume: 200500.0, quote_volume_estimated: false },
+///     CandleData { symbol: "BTC-USDT".into(), interval_sc: 60, open_timestamp_ms: 1672531260000, open: 20050.0, high: 20200.0, low: 20040.0, close: 20180.0, volume: 15.0, trade_count: 15, quote_volume: 302700.0, quote_volume_estimated: false },
 ///     // Incomplete group at the end
-///     CandleData { symbol: "BTC-USDT".into(), interval_sc: 60, open_timestamp: 1672531320000, open: dec!(20180), high: dec!(20190), low: dec!(20150), close: dec!(20160), volume: dec!(12) },
+///     CandleData { symbol: "BTC-USDT".into(), interval_sc: 60, open_timestamp_ms: 1672531320000, open: 20180.0, high: 20190.0, low: 20150.0, close: 20160.0, volume: 12.0, trade_count: 12, quote_volume: 241920.0, quote_volume_estimated: false },
 /// ];
 ///
 /// let candle_stream = stream::iter(minute_candles);
 ///
 /// // Aggregate 2x 1-minute candles into 2-minute candles
-/// let mut two_minute_stream = candles_to_candle_stream(candle_stream, 120);
+/// let mut two_minute_stream = Box::pin(transform_candles_to_candles(candle_stream, 120));
 ///
-/// let candle1 = two_minute_stream.next().await.unwrap();
+/// let candle1 = two_minute_stream.next().await.unwrap().unwrap();
 /// assert_eq!(candle1.interval_sc, 120);
-/// assert_eq!(candle1.open, dec!(20000));
-/// assert_eq!(candle1.high, dec!(20200));
-/// assert_eq!(candle1.close, dec!(20180));
-/// assert_eq!(candle1.volume, dec!(25)); // 10 + 15
+/// assert_eq!(candle1.open, 20000.0);
+/// assert_eq!(candle1.high, 20200.0);
+/// assert_eq!(candle1.close, 20180.0);
+/// assert_eq!(candle1.volume, 25.0); // 10 + 15
 ///
 /// // The stream ends because the last candle forms an incomplete group
 /// assert!(two_minute_stream.next().await.is_none());
@@ -134,12 +351,326 @@ pub fn transform_candles_to_candles(
     candle_stream: impl Stream<Item = CandleData> + Unpin + Send,
     target_interval: IntervalSc,
 ) -> impl Stream<Item = DataResult<CandleData>> + Send {
-    futures::stream::unfold(candle_stream, move |mut stream| async move {
-        agg_candles_to_candle(&mut stream, target_interval)
-            .await
-            .transpose()
-            .map(|candle| (candle, stream))
-    })
+    resample(candle_stream, target_interval, agg_ohlcv)
+}
+
+/// Like [`transform_candles_to_candles`], but can also emit the incomplete group at the end of
+/// the stream, yielding `(candle, is_closed)`.
+///
+/// Every complete group is yielded with `is_closed == true`. With `flush_partial`, a trailing
+/// incomplete group is aggregated as well and yielded with `is_closed == false`; its
+/// `interval_sc` is still `target_interval`, since it is the target bar still being formed.
+/// Without it the tail is discarded, as in [`transform_candles_to_candles`].
+///
+/// # Error
+///
+/// See [`agg_candles_to_candle`].
+///
+/// # Panics
+///
+/// 1. If `target_interval` is `0`.
+pub fn transform_candles_to_candles_with_flush(
+    candle_stream: impl Stream<Item = CandleData> + Unpin + Send,
+    target_interval: IntervalSc,
+    flush_partial: bool,
+) -> impl Stream<Item = DataResult<(CandleData, bool)>> + Send {
+    async_stream::stream! {
+        let mut stream = candle_stream;
+        let mut group = Vec::new();
+
+        loop {
+            match next_candle_group(&mut stream, target_interval, &mut group).await {
+                Ok(true) => yield Ok((agg_ohlcv(&group), true)),
+                Ok(false) => {
+                    if flush_partial && !group.is_empty() {
+                        let mut candle = agg_ohlcv(&group);
+                        candle.interval_sc = target_interval;
+                        yield Ok((candle, false));
+                    }
+                    break;
+                }
+                Err(e) => yield Err(e),
+            }
+        }
+    }
+}
+
+/// How [`transform_candles_to_candles_aligned`] handles missing source candles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapPolicy {
+    /// Fail with [`DataError::UnexpectedTimestamp`] at the first missing candle.
+    #[default]
+    Error,
+    /// Aggregate whatever candles a window has. Windows without any candle are not emitted.
+    Skip,
+    /// Fill every missing slot with a flat, zero-volume candle at the previous close, so every
+    /// window is complete and evenly spaced.
+    FillForward,
+}
+
+/// Aggregates candles into larger-interval candles grouped by wall-clock boundaries.
+///
+/// Unlike [`transform_candles_to_candles`], which takes a fixed count of candles per group, a
+/// candle's window is found by aligning its open timestamp to `target_interval` (see
+/// [`CandleData::align_open_timestamp`]), so a missing source candle cannot shift the boundaries.
+/// Missing candles are handled according to `gap_policy`. Each output candle opens at its window
+/// boundary and has `interval_sc == target_interval`.
+///
+/// The first window is discarded if the stream starts partway into it, and the last one if the
+/// stream ends before it closes.
+///
+/// # Error
+///
+/// 1. If `target_interval` is not a multiple of the first candle's interval.
+/// 2. If symbol or interval_sc mismatched.
+/// 3. If timestamp order is violated, or a candle is missing under [`GapPolicy::Error`].
+///
+/// The stream ends after the error.
+///
+/// # Panics
+///
+/// 1. If `target_interval` is `0`.
+pub fn transform_candles_to_candles_aligned(
+    candle_stream: impl Stream<Item = CandleData> + Send,
+    target_interval: IntervalSc,
+    gap_policy: GapPolicy,
+) -> impl Stream<Item = DataResult<CandleData>> + Send {
+    assert_ne!(target_interval, 0, "Interval shouldn't be zero.");
+
+    async_stream::stream! {
+        let mut windows = WindowAggregator::new(target_interval);
+        let mut last: Option<CandleData> = None;
+
+        for await candle in candle_stream {
+            match &last {
+                None if !target_interval.is_multiple_of(candle.interval_sc) => {
+                    yield Err(DataError::UnDivisibleInterval {
+                        target: target_interval,
+                        base: candle.interval_sc,
+                    });
+                    return;
+                }
+                None => {}
+                Some(prev) => {
+                    if let Err(e) = check_next_candle(prev, &candle, gap_policy) {
+                        yield Err(e);
+                        return;
+                    }
+
+                    if gap_policy == GapPolicy::FillForward {
+                        let mut filler = flat_candle_after(prev);
+                        while filler.open_timestamp_ms < candle.open_timestamp_ms {
+                            let next = flat_candle_after(&filler);
+                            if let Some(window) = windows.push(filler) {
+                                yield Ok(window);
+                            }
+                            filler = next;
+                        }
+                    }
+                }
+            }
+
+            last = Some(candle.clone());
+            if let Some(window) = windows.push(candle) {
+                yield Ok(window);
+            }
+        }
+
+        if let Some(window) = windows.finish() {
+            yield Ok(window);
+        }
+    }
+}
+
+/// Checks that `candle` may follow `prev` in [`transform_candles_to_candles_aligned`].
+fn check_next_candle(
+    prev: &CandleData,
+    candle: &CandleData,
+    gap_policy: GapPolicy,
+) -> DataResult<()> {
+    if candle.symbol != prev.symbol {
+        return Err(DataError::MismatchedSymbol {
+            expected: prev.symbol.clone(),
+            found: candle.symbol.clone(),
+        });
+    }
+
+    if candle.interval_sc != prev.interval_sc {
+        return Err(DataError::MismatchedInterval {
+            expected: prev.interval_sc,
+            found: candle.interval_sc,
+        });
+    }
+
+    if candle.open_timestamp_ms <= prev.open_timestamp_ms {
+        return Err(DataError::timestamp_should_be_after(
+            prev.open_timestamp_ms,
+            candle.open_timestamp_ms,
+        ));
+    }
+
+    let expected = prev.close_timestamp_ms();
+    if candle.open_timestamp_ms < expected
+        || (candle.open_timestamp_ms > expected && gap_policy == GapPolicy::Error)
+    {
+        return Err(DataError::timestamp_should_be_equal(
+            expected,
+            candle.open_timestamp_ms,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Collects candles into the wall-clock window they fall in.
+struct WindowAggregator {
+    target_interval: IntervalSc,
+    /// Open timestamp of the window being collected
+    window: Option<TimestampMs>,
+    group: Vec<CandleData>,
+    /// The current window was entered partway and must not be emitted
+    partial: bool,
+}
+
+impl WindowAggregator {
+    fn new(target_interval: IntervalSc) -> Self {
+        Self {
+            target_interval,
+            window: None,
+            group: Vec::new(),
+            partial: false,
+        }
+    }
+
+    /// Adds `candle`, returning the previous window if `candle` starts a new one.
+    fn push(&mut self, candle: CandleData) -> Option<CandleData> {
+        let window =
+            CandleData::align_open_timestamp(candle.open_timestamp_ms, self.target_interval);
+        if self.window == Some(window) {
+            self.group.push(candle);
+            return None;
+        }
+
+        let completed = self.take();
+        if self.window.is_none() {
+            self.partial = candle.open_timestamp_ms != window;
+        }
+        self.window = Some(window);
+        self.group.push(candle);
+
+        completed
+    }
+
+    /// The last window, if the stream reached its close.
+    fn finish(&mut self) -> Option<CandleData> {
+        let last_close = self.group.last()?.close_timestamp_ms();
+        self.take()
+            .filter(|window| window.close_timestamp_ms() == last_close)
+    }
+
+    fn take(&mut self) -> Option<CandleData> {
+        let window = self.window?;
+        if std::mem::take(&mut self.partial) || self.group.is_empty() {
+            self.group.clear();
+            return None;
+        }
+
+        let mut candle = agg_ohlcv(&self.group);
+        candle.open_timestamp_ms = window;
+        candle.interval_sc = self.target_interval;
+        self.group.clear();
+
+        Some(candle)
+    }
+}
+
+/// Groups a stream of candles into windows of `target_interval` and folds each complete window
+/// into one output with `agg_fn`. [`transform_candles_to_candles`] is this with [`agg_ohlcv`].
+/// *Incomplete groups at the end of the stream are discarded*.
+///
+/// `agg_fn` always receives at least one candle, in timestamp order.
+///
+/// # Error
+///
+/// See [`agg_candles_to_candle`].
+///
+/// # Examples
+/// ```rust
+/// # use futures::{stream, StreamExt};
+/// # use ephemera_shared::{stream::resample, CandleData};
+/// #
+/// #[derive(Debug, PartialEq)]
+/// struct Range {
+///     high: f64,
+///     low: f64,
+///     trade_count: u64,
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let minute_candles: Vec<CandleData> = (0..4)
+///     .map(|i| CandleData {
+///         symbol: "BTC-USDT".into(),
+///         interval_sc: 60,
+///         open_timestamp_ms: 1672531200000 + i * 60_000,
+///         high: 100.0 + i as f64,
+///         low: 90.0 - i as f64,
+///         trade_count: 10,
+///         ..Default::default()
+///     })
+///     .collect();
+///
+/// let mut ranges = Box::pin(resample(stream::iter(minute_candles), 120, |group| Range {
+///     high: group.iter().map(|c| c.high).fold(f64::MIN, f64::max),
+///     low: group.iter().map(|c| c.low).fold(f64::MAX, f64::min),
+///     trade_count: group.iter().map(|c| c.trade_count).sum(),
+/// }));
+///
+/// let range = ranges.next().await.unwrap().unwrap();
+/// assert_eq!(range, Range { high: 101.0, low: 89.0, trade_count: 20 });
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// 1. If `target_interval` is `0`.
+pub fn resample<O, F>(
+    candle_stream: impl Stream<Item = CandleData> + Unpin + Send,
+    target_interval: IntervalSc,
+    agg_fn: F,
+) -> impl Stream<Item = DataResult<O>> + Send
+where
+    O: Send,
+    F: FnMut(&[CandleData]) -> O + Send,
+{
+    futures::stream::unfold(
+        (candle_stream, Vec::new(), agg_fn),
+        move |(mut stream, mut group, mut agg_fn)| async move {
+            let output = match next_candle_group(&mut stream, target_interval, &mut group).await {
+                Ok(true) => Ok(agg_fn(&group)),
+                Ok(false) => return None,
+                Err(e) => Err(e),
+            };
+
+            Some((output, (stream, group, agg_fn)))
+        },
+    )
+}
+
+/// Folds candles of consecutive intervals into one OHLCV candle whose interval is their sum.
+///
+/// # Panics
+///
+/// 1. If `group` is empty.
+pub fn agg_ohlcv(group: &[CandleData]) -> CandleData {
+    let (first, rest) = group.split_first().expect("Group shouldn't be empty.");
+
+    let mut candle = first.clone();
+    for next in rest {
+        candle.unchecked_agg_with_candle(next);
+    }
+
+    candle
 }
 
 /// A low-level helper to aggregate a fixed number of candles, from a stream into a single candle
@@ -158,10 +689,27 @@ pub async fn agg_candles_to_candle(
     stream: &mut (impl Stream<Item = CandleData> + Unpin),
     target_interval: IntervalSc,
 ) -> DataResult<Option<CandleData>> {
+    let mut group = Vec::new();
+
+    Ok(next_candle_group(stream, target_interval, &mut group)
+        .await?
+        .then(|| agg_ohlcv(&group)))
+}
+
+/// Reads the next `target_interval` worth of candles into `group`, replacing its contents.
+/// Returns `false` if the stream ends before the group is complete.
+///
+/// See [`agg_candles_to_candle`] for errors and panics.
+async fn next_candle_group(
+    stream: &mut (impl Stream<Item = CandleData> + Unpin),
+    target_interval: IntervalSc,
+    group: &mut Vec<CandleData>,
+) -> DataResult<bool> {
     assert_ne!(target_interval, 0, "Interval shouldn't be zero.");
+    group.clear();
 
     let Some(first_candle) = stream.next().await else {
-        return Ok(None);
+        return Ok(false);
     };
 
     let first_interval = first_candle.interval_sc;
@@ -174,46 +722,185 @@ pub async fn agg_candles_to_candle(
     }
 
     let n = target_interval / first_interval;
-    let mut agg_candle = first_candle;
+    group.push(first_candle);
 
     for _ in 0..(n - 1) {
         let Some(next_candle) = stream.next().await else {
-            break;
+            return Ok(false);
         };
+        let last_candle = &group[group.len() - 1];
 
-        if next_candle.symbol != agg_candle.symbol {
+        if next_candle.symbol != last_candle.symbol {
             return Err(DataError::MismatchedSymbol {
-                expected: agg_candle.symbol.clone(),
+                expected: last_candle.symbol.clone(),
                 found: next_candle.symbol.clone(),
             });
         }
 
         if next_candle.interval_sc != first_interval {
             return Err(DataError::MismatchedInterval {
-                expected: agg_candle.interval_sc,
+                expected: first_interval,
                 found: next_candle.interval_sc,
             });
         }
 
-        if next_candle.open_timestamp_ms <= agg_candle.open_timestamp_ms {
+        if next_candle.open_timestamp_ms <= last_candle.open_timestamp_ms {
             return Err(DataError::timestamp_should_be_after(
-                agg_candle.open_timestamp_ms,
+                last_candle.open_timestamp_ms,
                 next_candle.open_timestamp_ms,
             ));
         }
 
-        agg_candle.unchecked_agg_with_candle(&next_candle);
+        group.push(next_candle);
+    }
+
+    Ok(true)
+}
+
+/// Forwards only closed candles from a live stream that repeatedly pushes the still-forming bar.
+///
+/// Exchanges such as OKX push the current candle every time it updates, so a strategy fed
+/// directly would act on a bar that may still change. This gate keeps the latest update of each
+/// symbol's forming bar and emits it only once a later bar of the same symbol begins, which is
+/// when the previous one is known to be closed. Updates older than the buffered bar are dropped.
+/// Consumers that display the forming bar should read the original stream instead.
+///
+/// When the input stream ends, the buffered bars are emitted as if closed, ordered by open
+/// timestamp and then symbol. Errors are passed through immediately.
+pub fn on_closed_only<E>(
+    stream: impl Stream<Item = Result<CandleData, E>> + Send,
+) -> impl Stream<Item = Result<CandleData, E>> + Send
+where
+    E: Send,
+{
+    async_stream::stream! {
+        let mut forming: HashMap<Symbol, CandleData> = HashMap::new();
+
+        for await result in stream {
+            let candle = match result {
+                Ok(candle) => candle,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
+            };
+
+            let Some(current) = forming.get_mut(&candle.symbol) else {
+                forming.insert(candle.symbol.clone(), candle);
+                continue;
+            };
+
+            if candle.open_timestamp_ms > current.open_timestamp_ms {
+                yield Ok(std::mem::replace(current, candle));
+            } else if candle.open_timestamp_ms == current.open_timestamp_ms {
+                *current = candle;
+            }
+        }
+
+        let mut remaining = forming.into_values().collect::<Vec<_>>();
+        sort_candles(&mut remaining);
+
+        for candle in remaining {
+            yield Ok(candle);
+        }
     }
+}
+
+/// Pairs the candles of two symbols by open timestamp, for strategies that compare them bar by bar.
+///
+/// Exchanges do not guarantee that two symbols produce a bar for every interval; a quiet symbol
+/// may skip one. Whenever one side has a bar the other lacks, the missing side is forward-filled
+/// with a zero-volume candle at its last close, so every yielded pair shares the same
+/// `open_timestamp_ms`. Bars that arrive before the other side has produced anything cannot be
+/// filled and are dropped, as are bars not later than the previous one of the same side.
+///
+/// Both streams should be ordered by open timestamp and use the same interval; feed live streams
+/// through [`on_closed_only`] first. Errors from either side are passed through immediately. The
+/// output ends as soon as either input ends.
+pub fn align_candle_streams<E>(
+    a: impl Stream<Item = Result<CandleData, E>> + Send,
+    b: impl Stream<Item = Result<CandleData, E>> + Send,
+) -> impl Stream<Item = Result<(CandleData, CandleData), E>> + Send
+where
+    E: Send,
+{
+    async_stream::stream! {
+        let mut a = std::pin::pin!(a);
+        let mut b = std::pin::pin!(b);
+        let mut head_a: Option<CandleData> = None;
+        let mut head_b: Option<CandleData> = None;
+        let mut last_a: Option<CandleData> = None;
+        let mut last_b: Option<CandleData> = None;
 
-    Ok((agg_candle.interval_sc == target_interval).then_some(agg_candle))
+        let is_stale = |candle: &CandleData, last: &Option<CandleData>| {
+            last.as_ref()
+                .is_some_and(|last| candle.open_timestamp_ms <= last.open_timestamp_ms)
+        };
+
+        loop {
+            if head_a.is_none() {
+                match a.next().await {
+                    Some(Ok(candle)) if !is_stale(&candle, &last_a) => head_a = Some(candle),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        yield Err(e);
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            if head_b.is_none() {
+                match b.next().await {
+                    Some(Ok(candle)) if !is_stale(&candle, &last_b) => head_b = Some(candle),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        yield Err(e);
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+
+            let (Some(candle_a), Some(candle_b)) = (head_a.take(), head_b.take()) else {
+                unreachable!("both heads are filled above");
+            };
+
+            match candle_a.open_timestamp_ms.cmp(&candle_b.open_timestamp_ms) {
+                std::cmp::Ordering::Equal => {
+                    last_a = Some(candle_a.clone());
+                    last_b = Some(candle_b.clone());
+                    yield Ok((candle_a, candle_b));
+                }
+                std::cmp::Ordering::Less => {
+                    let fill = last_b
+                        .as_ref()
+                        .map(|last| flat_candle_at(last, candle_a.open_timestamp_ms));
+                    head_b = Some(candle_b);
+                    last_a = Some(candle_a.clone());
+                    if let Some(fill) = fill {
+                        yield Ok((candle_a, fill));
+                    }
+                }
+                std::cmp::Ordering::Greater => {
+                    let fill = last_a
+                        .as_ref()
+                        .map(|last| flat_candle_at(last, candle_b.open_timestamp_ms));
+                    head_a = Some(candle_a);
+                    last_b = Some(candle_b.clone());
+                    if let Some(fill) = fill {
+                        yield Ok((fill, candle_b));
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Side, TradeData};
+    use crate::{Side, TradeData, testing::gen_candles};
     use futures::{StreamExt, TryStreamExt, stream};
-    use rust_decimal::dec;
 
     /// 测试正常聚合：输入流包含足够完成一次聚合的交易，并且还有剩余。
     #[tokio::test]
@@ -223,30 +910,30 @@ mod tests {
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202405000,
-                price: dec!(100),
-                quantity: dec!(1.0),
+                price: 100.0,
+                quantity: 1.0,
                 side: Side::Buy,
             },
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202420000,
-                price: dec!(120),
-                quantity: dec!(2.0),
+                price: 120.0,
+                quantity: 2.0,
                 side: Side::Sell,
             },
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202450000,
-                price: dec!(80),
-                quantity: dec!(1.5),
+                price: 80.0,
+                quantity: 1.5,
                 side: Side::Buy,
             },
             // 这个属于下一个K线，不应该被消耗
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202465000,
-                price: dec!(150),
-                quantity: dec!(3.0),
+                price: 150.0,
+                quantity: 3.0,
                 side: Side::Buy,
             },
         ];
@@ -257,16 +944,19 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        assert_eq!(candle.open, dec!(100));
-        assert_eq!(candle.high, dec!(120));
-        assert_eq!(candle.low, dec!(80));
-        assert_eq!(candle.close, dec!(80));
-        assert_eq!(candle.volume, dec!(4.5));
+        assert_eq!(candle.open, 100.0);
+        assert_eq!(candle.high, 120.0);
+        assert_eq!(candle.low, 80.0);
+        assert_eq!(candle.close, 80.0);
+        assert_eq!(candle.volume, 4.5);
+        assert_eq!(candle.trade_count, 3);
+        // 100 * 1 + 120 * 2 + 80 * 1.5
+        assert_eq!(candle.quote_volume, 460.0);
         assert_eq!(candle.open_timestamp_ms, 1756202400000);
 
         // 断言流中还剩下未被消耗的数据
         let remaining_trade = stream.next().await.unwrap();
-        assert_eq!(remaining_trade.price, dec!(150));
+        assert_eq!(remaining_trade.price, 150.0);
         assert!(stream.next().await.is_none(), "Stream should be empty now");
     }
 
@@ -277,15 +967,15 @@ mod tests {
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202405000,
-                price: dec!(200),
-                quantity: dec!(1.0),
+                price: 200.0,
+                quantity: 1.0,
                 side: Side::Buy,
             },
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202420000,
-                price: dec!(210),
-                quantity: dec!(2.0),
+                price: 210.0,
+                quantity: 2.0,
                 side: Side::Sell,
             },
         ];
@@ -296,9 +986,10 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        assert_eq!(candle.open, dec!(200));
-        assert_eq!(candle.close, dec!(210));
-        assert_eq!(candle.volume, dec!(3.0));
+        assert_eq!(candle.open, 200.0);
+        assert_eq!(candle.close, 210.0);
+        assert_eq!(candle.volume, 3.0);
+        assert_eq!(candle.trade_count, 2);
     }
 
     /// 测试输入流为空的场景，应返回 None。
@@ -313,89 +1004,48 @@ mod tests {
     /// 测试正常聚合：输入流包含足够完成一次聚合的K线，并且还有剩余。
     #[tokio::test]
     async fn test_agg_candles_to_candle_with_remainder() {
-        let all_candles: Vec<CandleData> = vec![
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531200000,
-                open: dec!(200),
-                high: dec!(210),
-                low: dec!(190),
-                close: dec!(205),
-                volume: dec!(10),
-            },
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531260000,
-                open: dec!(205),
-                high: dec!(220),
-                low: dec!(202),
-                close: dec!(218),
-                volume: dec!(15),
-            },
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531320000,
-                open: dec!(218),
-                high: dec!(219),
-                low: dec!(215),
-                close: dec!(216),
-                volume: dec!(12),
-            },
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531380000,
-                open: dec!(216),
-                high: dec!(217),
-                low: dec!(212),
-                close: dec!(213),
-                volume: dec!(8),
-            },
-        ];
+        let all_candles = gen_candles(
+            "BTC-USDT",
+            60,
+            1672531200000,
+            &[
+                (200.0, 210.0, 190.0, 205.0, 10.0),
+                (205.0, 220.0, 202.0, 218.0, 15.0),
+                (218.0, 219.0, 215.0, 216.0, 12.0),
+                (216.0, 217.0, 212.0, 213.0, 8.0),
+            ],
+        );
         let mut stream = stream::iter(all_candles);
         let candle = agg_candles_to_candle(&mut stream, 180)
             .await
             .unwrap()
             .unwrap();
 
-        assert_eq!(candle.open, dec!(200));
-        assert_eq!(candle.high, dec!(220));
-        assert_eq!(candle.low, dec!(190));
-        assert_eq!(candle.close, dec!(216));
-        assert_eq!(candle.volume, dec!(37));
+        assert_eq!(candle.open, 200.0);
+        assert_eq!(candle.high, 220.0);
+        assert_eq!(candle.low, 190.0);
+        assert_eq!(candle.close, 216.0);
+        assert_eq!(candle.volume, 37.0);
+        assert_eq!(candle.trade_count, 37);
+        // 205 * 10 + 218 * 15 + 216 * 12
+        assert_eq!(candle.quote_volume, 7912.0);
         assert_eq!(candle.interval_sc, 180);
-        assert_eq!(stream.next().await.unwrap().open, dec!(216));
+        assert_eq!(stream.next().await.unwrap().open, 216.0);
         assert!(stream.next().await.is_none());
     }
 
     /// 测试流中数据不足以完成一次聚合的场景，应返回 None。
     #[tokio::test]
     async fn test_agg_candles_to_candle_incomplete() {
-        let partial_candles: Vec<CandleData> = vec![
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531200000,
-                open: dec!(200),
-                high: dec!(210),
-                low: dec!(190),
-                close: dec!(205),
-                volume: dec!(10),
-            },
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531260000,
-                open: dec!(205),
-                high: dec!(220),
-                low: dec!(202),
-                close: dec!(218),
-                volume: dec!(15),
-            },
-        ];
+        let partial_candles = gen_candles(
+            "BTC-USDT",
+            60,
+            1672531200000,
+            &[
+                (200.0, 210.0, 190.0, 205.0, 10.0),
+                (205.0, 220.0, 202.0, 218.0, 15.0),
+            ],
+        );
         let mut stream = stream::iter(partial_candles);
         let result = agg_candles_to_candle(&mut stream, 180).await.unwrap();
         assert!(
@@ -420,45 +1070,45 @@ mod tests {
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202405000,
-                price: dec!(20000),
-                quantity: dec!(1.5),
+                price: 20000.0,
+                quantity: 1.5,
                 side: Side::Buy,
             },
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202420000,
-                price: dec!(19950),
-                quantity: dec!(0.5),
+                price: 19950.0,
+                quantity: 0.5,
                 side: Side::Sell,
             },
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202455000,
-                price: dec!(20100),
-                quantity: dec!(2.0),
+                price: 20100.0,
+                quantity: 2.0,
                 side: Side::Buy,
             },
             // Candle #2 (10:02:00 -> 10:03:00)
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202525000,
-                price: dec!(20120),
-                quantity: dec!(3.0),
+                price: 20120.0,
+                quantity: 3.0,
                 side: Side::Buy,
             },
             // Candle #3 (10:03:00 -> 10:04:00)
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202580000,
-                price: dec!(20150),
-                quantity: dec!(1.0),
+                price: 20150.0,
+                quantity: 1.0,
                 side: Side::Sell,
             },
             TradeData {
                 symbol: "BTC-USDT".into(),
                 timestamp_ms: 1756202590000,
-                price: dec!(20130),
-                quantity: dec!(1.0),
+                price: 20130.0,
+                quantity: 1.0,
                 side: Side::Buy,
             },
         ];
@@ -476,112 +1126,49 @@ mod tests {
 
         let candle1 = &candles[0];
         assert_eq!(candle1.open_timestamp_ms, 1756202400000);
-        assert_eq!(candle1.open, dec!(20000));
-        assert_eq!(candle1.high, dec!(20100));
-        assert_eq!(candle1.low, dec!(19950));
-        assert_eq!(candle1.close, dec!(20100));
-        assert_eq!(candle1.volume, dec!(4.0));
+        assert_eq!(candle1.open, 20000.0);
+        assert_eq!(candle1.high, 20100.0);
+        assert_eq!(candle1.low, 19950.0);
+        assert_eq!(candle1.close, 20100.0);
+        assert_eq!(candle1.volume, 4.0);
 
         let candle2 = &candles[1];
         assert_eq!(candle2.open_timestamp_ms, 1756202520000);
-        assert_eq!(candle2.open, dec!(20120));
-        assert_eq!(candle2.high, dec!(20120));
-        assert_eq!(candle2.low, dec!(20120));
-        assert_eq!(candle2.close, dec!(20120));
-        assert_eq!(candle2.volume, dec!(3.0));
+        assert_eq!(candle2.open, 20120.0);
+        assert_eq!(candle2.high, 20120.0);
+        assert_eq!(candle2.low, 20120.0);
+        assert_eq!(candle2.close, 20120.0);
+        assert_eq!(candle2.volume, 3.0);
 
         let candle3 = &candles[2];
         assert_eq!(candle3.open_timestamp_ms, 1756202580000);
-        assert_eq!(candle3.open, dec!(20150));
-        assert_eq!(candle3.high, dec!(20150));
-        assert_eq!(candle3.low, dec!(20130));
-        assert_eq!(candle3.close, dec!(20130));
-        assert_eq!(candle3.volume, dec!(2.0));
+        assert_eq!(candle3.open, 20150.0);
+        assert_eq!(candle3.high, 20150.0);
+        assert_eq!(candle3.low, 20130.0);
+        assert_eq!(candle3.close, 20130.0);
+        assert_eq!(candle3.volume, 2.0);
     }
 
     #[tokio::test]
     async fn test_candle_to_candle_stream() {
-        let minute_candles: Vec<CandleData> = vec![
-            // === 分组 1: 形成第一个3分钟K线 (时间窗口 00:00:00 -> 00:03:00) ===
-            // 1分钟K线 (00:00:00 -> 00:01:00)
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531200000, // 2023-01-01 00:00:00 UTC
-                open: dec!(20000),
-                high: dec!(20100),
-                low: dec!(19950),
-                close: dec!(20050),
-                volume: dec!(10),
-            },
-            // 1分钟K线 (00:01:00 -> 00:02:00)
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531260000,
-                open: dec!(20050),
-                high: dec!(20200),
-                low: dec!(20040),
-                close: dec!(20180),
-                volume: dec!(15),
-            },
-            // 1分钟K线 (00:02:00 -> 00:03:00)
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531320000,
-                open: dec!(20180),
-                high: dec!(20190),
-                low: dec!(20150),
-                close: dec!(20160),
-                volume: dec!(12),
-            },
-            // === 分组 2: 形成第二个3分钟K线 (时间窗口 00:03:00 -> 00:06:00) ===
-            // 1分钟K线 (00:03:00 -> 00:04:00)
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531380000,
-                open: dec!(20160),
-                high: dec!(20170),
-                low: dec!(20155),
-                close: dec!(20165),
-                volume: dec!(8),
-            },
-            // 1分钟K线 (00:04:00 -> 00:05:00)
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531440000,
-                open: dec!(20165),
-                high: dec!(20180),
-                low: dec!(20160),
-                close: dec!(20175),
-                volume: dec!(9),
-            },
-            // 1分钟K线 (00:05:00 -> 00:06:00)
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531500000,
-                open: dec!(20175),
-                high: dec!(20185),
-                low: dec!(20170),
-                close: dec!(20180),
-                volume: dec!(5),
-            },
-            // === 剩余数据: 这个K线不足以形成一个完整的组，将被丢弃 ===
-            CandleData {
-                symbol: "BTC-USDT".into(),
-                interval_sc: 60,
-                open_timestamp_ms: 1672531560000, // 00:06:00 -> 00:07:00
-                open: dec!(20180),
-                high: dec!(20190),
-                low: dec!(20175),
-                close: dec!(20185),
-                volume: dec!(7),
-            },
-        ];
+        // 从 2023-01-01 00:00:00 UTC 开始的 1 分钟 K 线
+        let minute_candles = gen_candles(
+            "BTC-USDT",
+            60,
+            1672531200000,
+            &[
+                // === 分组 1: 形成第一个3分钟K线 (时间窗口 00:00:00 -> 00:03:00) ===
+                (20000.0, 20100.0, 19950.0, 20050.0, 10.0),
+                (20050.0, 20200.0, 20040.0, 20180.0, 15.0),
+                (20180.0, 20190.0, 20150.0, 20160.0, 12.0),
+                // === 分组 2: 形成第二个3分钟K线 (时间窗口 00:03:00 -> 00:06:00) ===
+                (20160.0, 20170.0, 20155.0, 20165.0, 8.0),
+                (20165.0, 20180.0, 20160.0, 20175.0, 9.0),
+                (20175.0, 20185.0, 20170.0, 20180.0, 5.0),
+                // === 剩余数据: 这个K线不足以形成一个完整的组，将被丢弃 ===
+                (20180.0, 20190.0, 20175.0, 20185.0, 7.0),
+            ],
+        );
 
         let candle_iter = stream::iter(minute_candles);
 
@@ -601,20 +1188,578 @@ mod tests {
         let agg1 = &aggregated_candles[0];
         assert_eq!(agg1.interval_sc, 180);
         assert_eq!(agg1.open_timestamp_ms, 1672531200000); // 应为第一组的开盘时间
-        assert_eq!(agg1.open, dec!(20000)); // 第一根K线的开盘价
-        assert_eq!(agg1.high, dec!(20200)); // 前三根K线中的最高价
-        assert_eq!(agg1.low, dec!(19950)); // 前三根K线中的最低价
-        assert_eq!(agg1.close, dec!(20160)); // 第三根K线的收盘价
-        assert_eq!(agg1.volume, dec!(37)); // 10 + 15 + 12
+        assert_eq!(agg1.open, 20000.0); // 第一根K线的开盘价
+        assert_eq!(agg1.high, 20200.0); // 前三根K线中的最高价
+        assert_eq!(agg1.low, 19950.0); // 前三根K线中的最低价
+        assert_eq!(agg1.close, 20160.0); // 第三根K线的收盘价
+        assert_eq!(agg1.volume, 37.0); // 10 + 15 + 12
+        assert_eq!(agg1.trade_count, 37);
 
         // 验证第二个聚合K线
         let agg2 = &aggregated_candles[1];
         assert_eq!(agg2.interval_sc, 180);
         assert_eq!(agg2.open_timestamp_ms, 1672531380000); // 应为第二组的开盘时间
-        assert_eq!(agg2.open, dec!(20160)); // 第四根K线的开盘价
-        assert_eq!(agg2.high, dec!(20185)); // 第4-6根K线中的最高价
-        assert_eq!(agg2.low, dec!(20155)); // 第4-6根K线中的最低价
-        assert_eq!(agg2.close, dec!(20180)); // 第六根K线的收盘价
-        assert_eq!(agg2.volume, dec!(22)); // 8 + 9 + 5
+        assert_eq!(agg2.open, 20160.0); // 第四根K线的开盘价
+        assert_eq!(agg2.high, 20185.0); // 第4-6根K线中的最高价
+        assert_eq!(agg2.low, 20155.0); // 第4-6根K线中的最低价
+        assert_eq!(agg2.close, 20180.0); // 第六根K线的收盘价
+        assert_eq!(agg2.volume, 22.0); // 8 + 9 + 5
+        assert_eq!(agg2.trade_count, 22);
+    }
+
+    #[tokio::test]
+    async fn test_multi_symbol_trades_to_candles() {
+        let trade = |symbol: &str, timestamp_ms, price, quantity| TradeData {
+            symbol: symbol.into(),
+            timestamp_ms,
+            price,
+            quantity,
+            side: Side::Buy,
+        };
+
+        let trades = vec![
+            // 第一个60s K线 (10:00:00 -> 10:01:00)，BTC 与 ETH 交错
+            trade("BTC-USDT", 1756202405000, 20000.0, 1.0),
+            trade("ETH-USDT", 1756202406000, 1500.0, 10.0),
+            trade("BTC-USDT", 1756202420000, 20100.0, 2.0),
+            trade("ETH-USDT", 1756202430000, 1490.0, 5.0),
+            // 第二个60s K线 (10:01:00 -> 10:02:00)
+            trade("BTC-USDT", 1756202465000, 20050.0, 1.0),
+            trade("ETH-USDT", 1756202470000, 1510.0, 2.0),
+            trade("BTC-USDT", 1756202475000, 20080.0, 0.5),
+        ];
+
+        let candles: Vec<_> = transform_multi_symbol_trades_to_candles(stream::iter(trades), 60)
+            .try_collect()
+            .await
+            .unwrap();
+
+        let btc: Vec<_> = candles.iter().filter(|c| c.symbol == "BTC-USDT").collect();
+        let eth: Vec<_> = candles.iter().filter(|c| c.symbol == "ETH-USDT").collect();
+
+        assert_eq!(candles.len(), 4);
+        assert_eq!(btc.len(), 2);
+        assert_eq!(eth.len(), 2);
+
+        assert_eq!(btc[0].open_timestamp_ms, 1756202400000);
+        assert_eq!(btc[0].open, 20000.0);
+        assert_eq!(btc[0].high, 20100.0);
+        assert_eq!(btc[0].close, 20100.0);
+        assert_eq!(btc[0].volume, 3.0);
+        assert_eq!(btc[0].trade_count, 2);
+        assert_eq!(btc[1].open_timestamp_ms, 1756202460000);
+        assert_eq!(btc[1].close, 20080.0);
+        assert_eq!(btc[1].volume, 1.5);
+        assert_eq!(btc[1].trade_count, 2);
+
+        assert_eq!(eth[0].open_timestamp_ms, 1756202400000);
+        assert_eq!(eth[0].open, 1500.0);
+        assert_eq!(eth[0].low, 1490.0);
+        assert_eq!(eth[0].volume, 15.0);
+        assert_eq!(eth[0].trade_count, 2);
+        assert_eq!(eth[1].open_timestamp_ms, 1756202460000);
+        assert_eq!(eth[1].open, 1510.0);
+        assert_eq!(eth[1].volume, 2.0);
+        assert_eq!(eth[1].trade_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_multi_symbol_trades_to_candles_idle_symbol() {
+        let trade = |symbol: &str, timestamp_ms, price| TradeData {
+            symbol: symbol.into(),
+            timestamp_ms,
+            price,
+            quantity: 1.0,
+            side: Side::Buy,
+        };
+
+        // ETH 在第 2、3 个K线内没有成交
+        let trades = vec![
+            trade("BTC-USDT", 1756202405000, 20000.0),
+            trade("ETH-USDT", 1756202410000, 1500.0),
+            trade("BTC-USDT", 1756202465000, 20010.0),
+            trade("BTC-USDT", 1756202525000, 20020.0),
+            trade("BTC-USDT", 1756202585000, 20030.0),
+            trade("ETH-USDT", 1756202590000, 1520.0),
+        ];
+
+        let candles: Vec<_> = transform_multi_symbol_trades_to_candles(stream::iter(trades), 60)
+            .try_collect()
+            .await
+            .unwrap();
+
+        let emitted: Vec<_> = candles
+            .iter()
+            .map(|c| {
+                (
+                    c.symbol.as_ref(),
+                    c.open_timestamp_ms,
+                    c.close,
+                    c.trade_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            emitted,
+            vec![
+                // BTC 的第二笔成交推进时钟，ETH 的K线同时完成
+                ("BTC-USDT", 1756202400000, 20000.0, 1),
+                ("ETH-USDT", 1756202400000, 1500.0, 1),
+                ("BTC-USDT", 1756202460000, 20010.0, 1),
+                ("ETH-USDT", 1756202460000, 1500.0, 0),
+                ("BTC-USDT", 1756202520000, 20020.0, 1),
+                ("ETH-USDT", 1756202520000, 1500.0, 0),
+                ("BTC-USDT", 1756202580000, 20030.0, 1),
+                ("ETH-USDT", 1756202580000, 1520.0, 1),
+            ]
+        );
+
+        let flat = &candles[3];
+        assert_eq!((flat.open, flat.high, flat.low), (1500.0, 1500.0, 1500.0));
+        assert_eq!(flat.volume, 0.0);
+        // 静默后的首笔成交重新开盘，而不是沿用补齐的价格
+        assert_eq!(candles[7].open, 1520.0);
+        assert_eq!(candles[7].volume, 1.0);
+    }
+
+    #[test]
+    fn test_label_trade_sides() {
+        use futures::{FutureExt, channel::mpsc};
+        use smallvec::smallvec;
+
+        let book = |symbol: &str, bid: f64, ask: f64| BookData {
+            symbol: symbol.into(),
+            timestamp: 0,
+            bids: smallvec![(bid, 1.0)],
+            asks: smallvec![(ask, 1.0)],
+            is_snapshot: true,
+        };
+        let trade = |symbol: &str, price, side| TradeData {
+            symbol: symbol.into(),
+            timestamp_ms: 0,
+            price,
+            quantity: 1.0,
+            side,
+        };
+
+        let (trades, trade_rx) = mpsc::unbounded();
+        let (books, book_rx) = mpsc::unbounded();
+        let mut labeled = Box::pin(label_trade_sides(trade_rx, book_rx));
+        let mut next = || labeled.next().now_or_never().flatten().map(|t| t.side);
+
+        // 中间价 101
+        books
+            .unbounded_send(book("BTC-USDT", 100.0, 102.0))
+            .unwrap();
+        trades
+            .unbounded_send(trade("BTC-USDT", 101.5, Side::Unknown))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Buy));
+        trades
+            .unbounded_send(trade("BTC-USDT", 100.5, Side::Unknown))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Sell));
+
+        // 等于中间价时按上一笔成交判断，100.5 -> 101 为上涨
+        trades
+            .unbounded_send(trade("BTC-USDT", 101.0, Side::Unknown))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Buy));
+
+        // 交易所已标注的方向保持不变
+        trades
+            .unbounded_send(trade("BTC-USDT", 101.8, Side::Sell))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Sell));
+
+        // 没有订单簿也没有上一笔成交
+        trades
+            .unbounded_send(trade("ETH-USDT", 4000.0, Side::Unknown))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Unknown));
+
+        // 新的订单簿覆盖旧的，中间价 105
+        books
+            .unbounded_send(book("BTC-USDT", 104.0, 106.0))
+            .unwrap();
+        trades
+            .unbounded_send(trade("BTC-USDT", 104.5, Side::Unknown))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Sell));
+
+        // 订单簿流结束后继续使用最后一份订单簿，交易流结束时整个流结束
+        drop(books);
+        trades
+            .unbounded_send(trade("BTC-USDT", 105.5, Side::Unknown))
+            .unwrap();
+        assert_eq!(next(), Some(Side::Buy));
+        drop(trades);
+        assert!(labeled.next().now_or_never().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_on_closed_only() {
+        let update = |open_timestamp_ms, close: f64| CandleData {
+            symbol: "BTC-USDT".into(),
+            interval_sc: 60,
+            open_timestamp_ms,
+            open: 100.0,
+            high: close.max(100.0),
+            low: close.min(100.0),
+            close,
+            volume: 1.0,
+            trade_count: 1,
+            quote_volume: close,
+            quote_volume_estimated: false,
+        };
+
+        // 同一根K线的多次推送，以及一条迟到的旧K线
+        let updates = vec![
+            Ok::<_, DataError>(update(1672531200000, 101.0)),
+            Ok(update(1672531200000, 99.0)),
+            Ok(update(1672531200000, 102.0)),
+            Ok(update(1672531260000, 103.0)),
+            Ok(update(1672531200000, 98.0)),
+            Ok(update(1672531260000, 104.0)),
+        ];
+
+        let closed: Vec<_> = on_closed_only(stream::iter(updates))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].open_timestamp_ms, 1672531200000);
+        assert_eq!(closed[0].close, 102.0);
+        assert_eq!(closed[1].open_timestamp_ms, 1672531260000);
+        assert_eq!(closed[1].close, 104.0);
+    }
+
+    #[tokio::test]
+    async fn test_resample() {
+        let candles = gen_candles(
+            "BTC-USDT",
+            60,
+            1672531200000,
+            &[
+                (200.0, 210.0, 190.0, 205.0, 10.0),
+                (205.0, 220.0, 202.0, 218.0, 15.0),
+                (218.0, 219.0, 215.0, 216.0, 12.0),
+                (216.0, 217.0, 212.0, 213.0, 8.0),
+                (213.0, 230.0, 210.0, 229.0, 20.0),
+                (229.0, 231.0, 200.0, 201.0, 30.0),
+                // 不完整的一组被丢弃
+                (201.0, 202.0, 200.0, 202.0, 1.0),
+            ],
+        );
+
+        // 每 3 根K线收盘价的中位数
+        let medians: Vec<f64> = resample(stream::iter(candles.clone()), 180, |group| {
+            let mut closes: Vec<f64> = group.iter().map(|c| c.close).collect();
+            closes.sort_by(f64::total_cmp);
+            closes[closes.len() / 2]
+        })
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(medians, vec![216.0, 213.0]);
+
+        // OHLCV 聚合与 transform_candles_to_candles 一致
+        let ohlcv: Vec<_> = resample(stream::iter(candles.clone()), 180, agg_ohlcv)
+            .try_collect()
+            .await
+            .unwrap();
+        let expected: Vec<_> = transform_candles_to_candles(stream::iter(candles), 180)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(ohlcv, expected);
+        assert_eq!(ohlcv[1].open, 216.0);
+        assert_eq!(ohlcv[1].close, 201.0);
+        assert_eq!(ohlcv[1].volume, 58.0);
+    }
+
+    #[tokio::test]
+    async fn test_align_candle_streams() {
+        let start = 1672531200000;
+        let btc = gen_candles(
+            "BTC-USDT",
+            60,
+            start,
+            &[
+                (100.0, 101.0, 99.0, 100.5, 1.0),
+                (100.5, 102.0, 100.0, 101.0, 2.0),
+                (101.0, 103.0, 100.5, 102.0, 3.0),
+                (102.0, 104.0, 101.5, 103.0, 4.0),
+            ],
+        );
+        // ETH 晚一根开始，并且跳过了第三根
+        let mut eth = gen_candles(
+            "ETH-USDT",
+            60,
+            start + 60_000,
+            &[
+                (10.0, 11.0, 9.0, 10.5, 5.0),
+                (10.5, 11.0, 10.0, 10.8, 6.0),
+                (10.8, 11.5, 10.5, 11.0, 7.0),
+            ],
+        );
+        eth.remove(1);
+        eth[1].open_timestamp_ms = start + 180_000;
+
+        let pairs: Vec<_> = align_candle_streams(
+            stream::iter(btc.clone()).map(Ok::<_, DataError>),
+            stream::iter(eth.clone()).map(Ok),
+        )
+        .try_collect()
+        .await
+        .unwrap();
+
+        // 第一根 BTC 没有可以前向填充的 ETH，被丢弃
+        assert_eq!(pairs.len(), 3);
+        assert!(
+            pairs
+                .iter()
+                .all(|(a, b)| a.open_timestamp_ms == b.open_timestamp_ms)
+        );
+        assert_eq!(pairs[0], (btc[1].clone(), eth[0].clone()));
+        assert_eq!(pairs[2], (btc[3].clone(), eth[1].clone()));
+
+        let (btc_bar, filled) = &pairs[1];
+        assert_eq!(btc_bar, &btc[2]);
+        assert_eq!(filled.symbol, "ETH-USDT");
+        assert_eq!(filled.open_timestamp_ms, start + 120_000);
+        assert_eq!(filled.open, 10.5);
+        assert_eq!(filled.close, 10.5);
+        assert_eq!(filled.volume, 0.0);
+        assert_eq!(filled.trade_count, 0);
+
+        // 错误直接透传，较早的重复K线被丢弃
+        let with_error = vec![
+            Ok(btc[0].clone()),
+            Err(DataError::Connection("boom".into())),
+            Ok(btc[0].clone()),
+            Ok(btc[1].clone()),
+        ];
+        let results: Vec<_> = align_candle_streams(
+            stream::iter(with_error),
+            stream::iter(btc[..2].to_vec()).map(Ok),
+        )
+        .collect()
+        .await;
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap().0, btc[1]);
+    }
+
+    #[tokio::test]
+    async fn test_transform_trades_to_candles_with_flush() {
+        let trade = |timestamp_ms, price| TradeData {
+            symbol: "BTC-USDT".into(),
+            timestamp_ms,
+            price,
+            quantity: 1.0,
+            side: Side::Buy,
+        };
+        // 第二根K线在 10:01:30 之后没有交易，无法确定已经收盘
+        let trades = vec![
+            trade(1756202405000, 100.0),
+            trade(1756202455000, 101.0),
+            trade(1756202465000, 102.0),
+            trade(1756202490000, 103.0),
+        ];
+
+        let flushed: Vec<_> =
+            transform_trades_to_candles_with_flush(stream::iter(trades.clone()), 60, true)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(flushed.len(), 2);
+        assert!(flushed[0].1);
+        assert_eq!(flushed[0].0.close, 101.0);
+        assert!(!flushed[1].1);
+        assert_eq!(flushed[1].0.open_timestamp_ms, 1756202460000);
+        assert_eq!(flushed[1].0.close, 103.0);
+
+        let dropped: Vec<_> =
+            transform_trades_to_candles_with_flush(stream::iter(trades.clone()), 60, false)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(dropped, flushed[..1].to_vec());
+
+        // 默认行为不变: 最后一根K线仍然输出
+        let default: Vec<_> = transform_trades_to_candles(stream::iter(trades), 60)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(default.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transform_candles_to_candles_with_flush() {
+        let candles = gen_candles(
+            "BTC-USDT",
+            60,
+            1672531200000,
+            &[
+                (100.0, 102.0, 99.0, 101.0, 1.0),
+                (101.0, 103.0, 100.0, 102.0, 2.0),
+                (102.0, 105.0, 101.0, 104.0, 3.0),
+                (104.0, 104.5, 98.0, 99.0, 4.0),
+                (99.0, 100.0, 97.0, 98.0, 5.0),
+            ],
+        );
+
+        let flushed: Vec<_> =
+            transform_candles_to_candles_with_flush(stream::iter(candles.clone()), 180, true)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(flushed.len(), 2);
+        assert_eq!(flushed[0], (agg_ohlcv(&candles[..3]), true));
+
+        let (partial, is_closed) = &flushed[1];
+        assert!(!is_closed);
+        assert_eq!(partial.interval_sc, 180);
+        assert_eq!(partial.open_timestamp_ms, candles[3].open_timestamp_ms);
+        assert_eq!(partial.open, 104.0);
+        assert_eq!(partial.low, 97.0);
+        assert_eq!(partial.close, 98.0);
+        assert_eq!(partial.volume, 9.0);
+
+        let dropped: Vec<_> =
+            transform_candles_to_candles_with_flush(stream::iter(candles.clone()), 180, false)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(dropped, flushed[..1].to_vec());
+
+        // 恰好整除时没有未完成的分组
+        let exact: Vec<_> =
+            transform_candles_to_candles_with_flush(stream::iter(candles[..3].to_vec()), 180, true)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(exact.len(), 1);
+        assert!(exact[0].1);
+    }
+
+    #[tokio::test]
+    async fn test_transform_candles_to_candles_aligned() {
+        let start = 1672531200000;
+        let candles = gen_candles(
+            "BTC-USDT",
+            60,
+            start,
+            &[
+                (100.0, 102.0, 99.0, 101.0, 1.0),
+                (101.0, 103.0, 100.0, 102.0, 2.0),
+                (102.0, 105.0, 101.0, 104.0, 3.0),
+                (104.0, 104.5, 98.0, 99.0, 4.0),
+                (99.0, 100.0, 97.0, 98.0, 5.0),
+                (98.0, 99.0, 96.0, 97.0, 6.0),
+            ],
+        );
+        let aligned = |candles: Vec<CandleData>, gap_policy| async move {
+            transform_candles_to_candles_aligned(stream::iter(candles), 180, gap_policy)
+                .collect::<Vec<_>>()
+                .await
+        };
+
+        // 没有缺失时与按数量分组的结果一致
+        let complete: Vec<_> = aligned(candles.clone(), GapPolicy::Error)
+            .await
+            .into_iter()
+            .collect::<DataResult<_>>()
+            .unwrap();
+        assert_eq!(
+            complete,
+            vec![agg_ohlcv(&candles[..3]), agg_ohlcv(&candles[3..])]
+        );
+
+        // 第二个窗口缺少中间一根
+        let mut gapped = candles.clone();
+        gapped.remove(4);
+
+        let results = aligned(gapped.clone(), GapPolicy::Error).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap(), &complete[0]);
+        assert!(matches!(
+            results[1],
+            Err(DataError::UnexpectedTimestamp { .. })
+        ));
+
+        let skipped: Vec<_> = aligned(gapped.clone(), GapPolicy::Skip)
+            .await
+            .into_iter()
+            .collect::<DataResult<_>>()
+            .unwrap();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[1].open_timestamp_ms, start + 180_000);
+        assert_eq!(skipped[1].interval_sc, 180);
+        assert_eq!(skipped[1].volume, 10.0);
+        assert_eq!(skipped[1].low, 96.0);
+
+        let filled: Vec<_> = aligned(gapped.clone(), GapPolicy::FillForward)
+            .await
+            .into_iter()
+            .collect::<DataResult<_>>()
+            .unwrap();
+        assert_eq!(filled.len(), 2);
+        assert_eq!(filled[1].volume, 10.0);
+        assert_eq!(filled[1].trade_count, 10);
+        assert_eq!(filled[1].close, 97.0);
+
+        // 整个窗口缺失: Skip 不输出，FillForward 以上一根收盘价补齐
+        let mut hole = candles[..3].to_vec();
+        hole.extend(gen_candles(
+            "BTC-USDT",
+            60,
+            start + 360_000,
+            &[
+                (97.0, 98.0, 96.0, 97.5, 1.0),
+                (97.5, 98.5, 97.0, 98.0, 1.0),
+                (98.0, 99.0, 97.5, 98.5, 1.0),
+            ],
+        ));
+        let skipped: Vec<_> = aligned(hole.clone(), GapPolicy::Skip)
+            .await
+            .into_iter()
+            .collect::<DataResult<_>>()
+            .unwrap();
+        assert_eq!(
+            skipped
+                .iter()
+                .map(|c| c.open_timestamp_ms)
+                .collect::<Vec<_>>(),
+            vec![start, start + 360_000]
+        );
+
+        let filled: Vec<_> = aligned(hole, GapPolicy::FillForward)
+            .await
+            .into_iter()
+            .collect::<DataResult<_>>()
+            .unwrap();
+        assert_eq!(filled.len(), 3);
+        assert_eq!(filled[1].open_timestamp_ms, start + 180_000);
+        assert_eq!(
+            (
+                filled[1].open,
+                filled[1].high,
+                filled[1].low,
+                filled[1].close
+            ),
+            (104.0, 104.0, 104.0, 104.0)
+        );
+        assert_eq!(filled[1].volume, 0.0);
+
+        // 从窗口中间开始、在窗口中间结束时，首尾窗口不完整而被丢弃
+        let partial: Vec<_> = aligned(candles[1..5].to_vec(), GapPolicy::Error)
+            .await
+            .into_iter()
+            .collect::<DataResult<_>>()
+            .unwrap();
+        assert!(partial.is_empty());
     }
 }
@@ -0,0 +1,130 @@
+//! 测试数据生成
+//!
+//! 仅在本 crate 的测试或启用 `testing` feature 时编译，供各 crate 的测试和基准共用。
+
+use crate::{CandleData, IntervalSc, Symbol, TimestampMs};
+
+/// [`random_walk_candles`] 的起始时间: 2023-01-01 00:00:00 UTC
+pub const RANDOM_WALK_START_MS: TimestampMs = 1672531200000;
+
+/// 以 `(open, high, low, close, volume)` 生成连续的 K 线
+///
+/// 第 `i` 根 K 线的开盘时间为 `start_ts + i * interval_sc` 秒，成交笔数取成交量的整数部分，
+/// 便于在聚合测试中同时校验两者。
+pub fn gen_candles(
+    symbol: impl Into<Symbol>,
+    interval_sc: IntervalSc,
+    start_ts: TimestampMs,
+    bars: &[(f64, f64, f64, f64, f64)],
+) -> Vec<CandleData> {
+    let symbol = symbol.into();
+
+    bars.iter()
+        .enumerate()
+        .map(|(i, &(open, high, low, close, volume))| CandleData {
+            symbol: symbol.clone(),
+            interval_sc,
+            open_timestamp_ms: start_ts + i as TimestampMs * interval_sc * 1000,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            trade_count: volume as u64,
+            quote_volume: close * volume,
+            quote_volume_estimated: false,
+        })
+        .collect()
+}
+
+/// 生成 `n` 根随机游走的 K 线，相同的 `seed` 总是生成相同的序列
+///
+/// 从 [`RANDOM_WALK_START_MS`] 开始，价格从 100 起步，每根 K 线的开盘价等于上一根的收盘价，
+/// 收盘价相对开盘价的涨跌幅不超过 1%，最高价和最低价总是包含开盘价和收盘价。
+pub fn random_walk_candles(
+    symbol: impl Into<Symbol>,
+    interval_sc: IntervalSc,
+    n: usize,
+    seed: u64,
+) -> Vec<CandleData> {
+    let mut rng = SplitMix64::new(seed);
+    let mut close = 100.0;
+
+    let bars: Vec<_> = (0..n)
+        .map(|_| {
+            let open = close;
+            close = open * (1.0 + (rng.next_f64() - 0.5) * 0.02);
+            let high = open.max(close) * (1.0 + rng.next_f64() * 0.005);
+            let low = open.min(close) * (1.0 - rng.next_f64() * 0.005);
+            let volume = 1.0 + rng.next_f64() * 99.0;
+
+            (open, high, low, close, volume)
+        })
+        .collect();
+
+    gen_candles(symbol, interval_sc, RANDOM_WALK_START_MS, &bars)
+}
+
+/// SplitMix64 伪随机数生成器，足够测试使用且不引入额外依赖
+///
+/// 相同的种子总是生成相同的序列，测试和基准需要可复现的随机输入时使用。
+#[derive(Debug, Clone)]
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// `[0, 1)` 上的均匀分布
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gen_candles() {
+        let candles = gen_candles(
+            "BTC-USDT",
+            60,
+            1672531200000,
+            &[(1.0, 2.0, 0.5, 1.5, 10.0), (1.5, 1.6, 1.4, 1.45, 2.5)],
+        );
+
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[1].open_timestamp_ms, 1672531260000);
+        assert_eq!(candles[1].close, 1.45);
+        assert_eq!(candles[1].trade_count, 2);
+    }
+
+    #[test]
+    fn test_random_walk_candles_reproducible() {
+        let candles = random_walk_candles("BTC-USDT", 60, 500, 42);
+
+        assert_eq!(candles, random_walk_candles("BTC-USDT", 60, 500, 42));
+        assert_ne!(candles, random_walk_candles("BTC-USDT", 60, 500, 43));
+
+        for pair in candles.windows(2) {
+            assert_eq!(pair[1].open, pair[0].close);
+            assert_eq!(
+                pair[1].open_timestamp_ms - pair[0].open_timestamp_ms,
+                60_000
+            );
+        }
+        assert!(candles.iter().all(|c| {
+            c.low <= c.open.min(c.close) && c.high >= c.open.max(c.close) && c.volume >= 1.0
+        }));
+    }
+}
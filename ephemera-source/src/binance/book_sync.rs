@@ -0,0 +1,629 @@
+use super::{
+    BINANCE_REST_BASE_URL, BinanceBookChannel, METHOD_SUBSCRIBE, binance_raw_data_stream,
+    book_stream_name,
+    model::{RawBookData, RawBookSnapshotData, WsDataResponse, WsRequest},
+};
+use crate::{
+    clock::{Clock, SystemClock},
+    utils::{ReconnectPolicy, WsConfig},
+};
+use async_stream::stream;
+use ephemera_shared::*;
+use eyre::{Context, Result, ensure};
+use futures::{Stream, StreamExt};
+use http::header::USER_AGENT;
+use rand::random;
+use std::pin::Pin;
+
+/// 一次带更新 ID 的增量推送
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthDiff {
+    pub book: BookData,
+    /// 本次推送的第一个更新 ID（`U`）
+    pub first_update_id: u64,
+    /// 本次推送的最后一个更新 ID（`u`）
+    pub final_update_id: u64,
+}
+
+/// 按 Binance 的同步流程，用 REST 快照和增量推送维护完整订单簿
+///
+/// 1. 先订阅增量推送，在拿到快照之前的推送都被缓冲；
+/// 2. 通过 REST 获取快照后调用 [`BinanceBookSync::on_snapshot`]，丢弃 `u <= lastUpdateId` 的推送，
+///    第一条应用的推送需满足 `U <= lastUpdateId + 1`；
+/// 3. 之后每条推送的 `U` 应衔接上一条的 `u`。
+///
+/// 出现缺口时不会 panic，而是返回 [`DataError::SequenceGap`] 并回到未同步状态，引发缺口的推送
+/// 留在缓冲区中，调用方重新获取快照后再次调用 `on_snapshot` 即可继续。
+#[derive(Debug, Clone, Default)]
+pub struct BinanceBookSync {
+    book: OrderBookMaintainer,
+    buffer: Vec<DepthDiff>,
+    last_update_id: Option<u64>,
+}
+
+impl BinanceBookSync {
+    pub fn new(symbol: impl Into<Symbol>) -> Self {
+        Self {
+            book: OrderBookMaintainer::new(symbol),
+            ..Default::default()
+        }
+    }
+
+    /// 处理一条增量推送，返回是否被应用到订单簿
+    ///
+    /// 未同步时推送被缓冲，已经包含在快照中的旧推送被忽略，二者都返回 `Ok(false)`。
+    pub fn on_diff(&mut self, diff: DepthDiff) -> DataResult<bool> {
+        let Some(last_update_id) = self.last_update_id else {
+            self.buffer.push(diff);
+            return Ok(false);
+        };
+
+        if diff.final_update_id <= last_update_id {
+            return Ok(false);
+        }
+
+        if diff.first_update_id > last_update_id + 1 {
+            let symbol = diff.book.symbol.clone();
+            let found = diff.first_update_id - 1;
+            self.desync();
+            self.buffer.push(diff);
+
+            return Err(DataError::SequenceGap {
+                symbol,
+                expected: last_update_id,
+                found,
+            });
+        }
+
+        self.book.apply(&diff.book);
+        self.last_update_id = Some(diff.final_update_id);
+
+        Ok(true)
+    }
+
+    /// 以 REST 快照重建订单簿，并依次应用缓冲的增量推送
+    ///
+    /// 快照比缓冲的推送还旧（第一条需要应用的推送与快照之间有缺口）时返回
+    /// [`DataError::SequenceGap`]，此时应重新获取快照。
+    pub fn on_snapshot(&mut self, snapshot: &BookData, last_update_id: u64) -> DataResult<()> {
+        self.book.apply(&BookData {
+            is_snapshot: true,
+            ..snapshot.clone()
+        });
+        self.last_update_id = Some(last_update_id);
+
+        let mut buffered = std::mem::take(&mut self.buffer).into_iter();
+        while let Some(diff) = buffered.next() {
+            if let Err(e) = self.on_diff(diff) {
+                self.buffer.extend(buffered);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 是否已经同步，未同步时订单簿不完整
+    pub fn is_synced(&self) -> bool {
+        self.last_update_id.is_some()
+    }
+
+    /// 最后应用的更新 ID
+    pub fn last_update_id(&self) -> Option<u64> {
+        self.last_update_id
+    }
+
+    /// 清空订单簿和缓冲区，等待新的快照
+    pub fn reset(&mut self) {
+        self.desync();
+        self.buffer.clear();
+    }
+
+    pub fn book(&self) -> &OrderBookMaintainer {
+        &self.book
+    }
+
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.book.best_bid()
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.book.best_ask()
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        self.book.mid_price()
+    }
+
+    /// 前 `n` 档，bids 按价格降序，asks 按价格升序
+    pub fn depth(&self, n: usize) -> (BookSide, BookSide) {
+        self.book.depth(n)
+    }
+
+    fn desync(&mut self) {
+        self.book.reset();
+        self.last_update_id = None;
+    }
+}
+
+impl TryFrom<WsDataResponse<RawBookData>> for DepthDiff {
+    type Error = eyre::Error;
+
+    fn try_from(value: WsDataResponse<RawBookData>) -> Result<Self, Self::Error> {
+        let first_update_id = value.data.first_update_id;
+        let final_update_id = value.data.final_update_id;
+
+        Ok(Self {
+            book: BookData::try_from(value)?,
+            first_update_id,
+            final_update_id,
+        })
+    }
+}
+
+/// [`binance_book_data_stream_with_reconnect`](super::binance_book_data_stream_with_reconnect)
+/// 同步增量频道时 REST 快照的档位数
+pub const BINANCE_DEPTH_SNAPSHOT_LIMIT: usize = 1000;
+
+/// 通过 REST 获取订单簿快照，返回快照和它的 `lastUpdateId`
+///
+/// `symbol` 与 WebSocket 流名一致（如 `btcusdt`），快照的 symbol 也保持原样。
+pub async fn binance_depth_snapshot(
+    symbol: impl std::fmt::Display,
+    limit: usize,
+) -> Result<(BookData, u64)> {
+    let symbol = symbol.to_string();
+    let url = format!(
+        "{BINANCE_REST_BASE_URL}/api/v3/depth?symbol={}&limit={limit}",
+        symbol.to_uppercase()
+    );
+
+    let resp = reqwest::Client::new()
+        .get(&url)
+        .header(USER_AGENT, "ephemera")
+        .send()
+        .await
+        .wrap_err_with(|| format!("Failed to fetch depth snapshot of {symbol}"))?;
+    ensure!(
+        resp.status().is_success(),
+        "Failed to fetch depth snapshot of {symbol}: {}",
+        resp.status()
+    );
+
+    let mut body = resp.bytes().await?.to_vec();
+    let raw = simd_json::serde::from_slice::<RawBookSnapshotData>(&mut body)?;
+
+    Ok((
+        BookData {
+            symbol: symbol.into(),
+            timestamp: raw.last_update_id,
+            bids: raw.bids,
+            asks: raw.asks,
+            is_snapshot: true,
+        },
+        raw.last_update_id,
+    ))
+}
+
+/// 同步后的 Binance 订单簿数据流
+///
+/// 先订阅增量推送再获取 REST 快照，按 [`BinanceBookSync`] 的流程同步后输出完整快照，
+/// 之后输出每条被应用的增量推送，下游可以直接交给 [`OrderBookMaintainer`] 维护。
+/// 出现缺口时重新获取快照，并再次输出完整快照（`is_snapshot == true`）。
+///
+/// REST 请求失败、快照无法衔接缓冲的推送或推送出现缺口时，按 `policy` 退避后重新获取。
+/// 重试次数只在推送被成功应用后清零，连续失败超过 [`ReconnectPolicy::max_retries`] 次时
+/// 输出最后一个错误并结束。
+///
+/// `channel` 应为增量频道，`snapshot_limit` 是 REST 快照的档位数（Binance 最多 5000）。
+pub async fn binance_synced_book_stream(
+    symbol: impl std::fmt::Display,
+    channel: BinanceBookChannel,
+    snapshot_limit: usize,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> Result<impl Stream<Item = Result<BookData>>> {
+    ensure!(
+        matches!(
+            channel,
+            BinanceBookChannel::Incremental_1000ms
+                | BinanceBookChannel::Incremental_100ms
+                | BinanceBookChannel::OtherIncremental(_)
+        ),
+        "Book synchronization requires an incremental channel, found {channel}"
+    );
+
+    let symbol = symbol.to_string();
+    let request = WsRequest {
+        id: random(),
+        method: METHOD_SUBSCRIBE,
+        params: Some(vec![book_stream_name(&symbol, channel)]),
+    };
+    let diffs = binance_raw_data_stream::<WsDataResponse<RawBookData>>(request, config)
+        .await?
+        .map(|result| result.and_then(DepthDiff::try_from));
+
+    Ok(sync_book_stream(
+        symbol.clone(),
+        diffs,
+        move || binance_depth_snapshot(symbol.clone(), snapshot_limit),
+        policy,
+        SystemClock,
+    ))
+}
+
+/// 按 [`BinanceBookSync`] 的流程把增量推送转换为订单簿数据流，需要同步时通过 `fetch_snapshot`
+/// 获取快照，获取或应用失败以及推送出现缺口时按 `policy` 使用 `clock` 退避
+pub(super) fn sync_book_stream<D, F, Fut>(
+    symbol: String,
+    diffs: D,
+    mut fetch_snapshot: F,
+    policy: ReconnectPolicy,
+    clock: impl Clock,
+) -> Pin<Box<dyn Stream<Item = Result<BookData>> + Send>>
+where
+    D: Stream<Item = Result<DepthDiff>> + Send + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(BookData, u64)>> + Send,
+{
+    let stream = stream! {
+        let mut sync = BinanceBookSync::new(symbol.as_str());
+        let mut attempt = 0;
+        // 推送出现的缺口，与获取快照失败一样先退避再重新同步
+        let mut gap = None;
+        futures::pin_mut!(diffs);
+
+        loop {
+            // 需要同步时，先取快照，尚未读取的推送在连接中排队，之后按更新 ID 筛选
+            if !sync.is_synced() {
+                let result = match gap.take() {
+                    Some(e) => Err(e),
+                    None => match fetch_snapshot().await {
+                        Ok((snapshot, last_update_id)) => sync
+                            .on_snapshot(&snapshot, last_update_id)
+                            .map_err(eyre::Error::from),
+                        Err(e) => Err(e),
+                    },
+                };
+
+                match result {
+                    Ok(()) => yield Ok(sync.book().to_book_data(usize::MAX)),
+                    Err(e) if attempt >= policy.max_retries => {
+                        yield Err(e.wrap_err(format!(
+                            "Failed to synchronize {symbol} order book after {attempt} retries"
+                        )));
+                        break;
+                    }
+                    Err(e) => {
+                        let delay = policy.next_delay(attempt);
+                        attempt += 1;
+                        tracing::warn!(
+                            "Resynchronizing {} order book in {:?} (attempt {}): {}",
+                            symbol,
+                            delay,
+                            attempt,
+                            e
+                        );
+                        clock.sleep(delay).await;
+                        continue;
+                    }
+                }
+            }
+
+            let Some(result) = diffs.next().await else {
+                break;
+            };
+            let diff = match result {
+                Ok(diff) => diff,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
+            };
+
+            let book = diff.book.clone();
+            match sync.on_diff(diff) {
+                Ok(true) => {
+                    attempt = 0;
+                    yield Ok(book);
+                }
+                Ok(false) => {}
+                Err(e) => gap = Some(eyre::Error::from(e)),
+            }
+        }
+    };
+
+    Box::pin(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smallvec::smallvec;
+
+    fn diff(
+        first_update_id: u64,
+        final_update_id: u64,
+        bids: BookSide,
+        asks: BookSide,
+    ) -> DepthDiff {
+        DepthDiff {
+            book: BookData {
+                symbol: "btcusdt".into(),
+                timestamp: final_update_id,
+                bids,
+                asks,
+                is_snapshot: false,
+            },
+            first_update_id,
+            final_update_id,
+        }
+    }
+
+    fn snapshot() -> BookData {
+        BookData {
+            symbol: "btcusdt".into(),
+            timestamp: 0,
+            bids: smallvec![(100.0, 1.0), (99.0, 2.0)],
+            asks: smallvec![(101.0, 1.0), (102.0, 2.0)],
+            is_snapshot: true,
+        }
+    }
+
+    #[test]
+    fn test_binance_book_sync() {
+        let mut sync = BinanceBookSync::new("btcusdt");
+
+        // 快照之前的推送被缓冲
+        assert!(
+            !sync
+                .on_diff(diff(95, 100, smallvec![(98.0, 5.0)], smallvec![]))
+                .unwrap()
+        );
+        assert!(
+            !sync
+                .on_diff(diff(101, 105, smallvec![(100.0, 3.0)], smallvec![]))
+                .unwrap()
+        );
+        assert!(
+            !sync
+                .on_diff(diff(106, 110, smallvec![], smallvec![(101.0, 0.0)]))
+                .unwrap()
+        );
+        assert!(!sync.is_synced());
+        assert_eq!(sync.best_bid(), None);
+
+        // 快照 lastUpdateId = 103: 丢弃 u <= 103 的推送，从 U <= 104 的推送开始应用
+        sync.on_snapshot(&snapshot(), 103).unwrap();
+        assert!(sync.is_synced());
+        assert_eq!(sync.last_update_id(), Some(110));
+        assert_eq!(sync.best_bid(), Some((100.0, 3.0)));
+        assert_eq!(sync.best_ask(), Some((102.0, 2.0)));
+        assert_eq!(sync.mid_price(), Some(101.0));
+        assert_eq!(sync.depth(1).0.as_slice(), &[(100.0, 3.0)]);
+        // 第一条推送早于快照，没有被应用
+        assert!(sync.depth(10).0.iter().all(|(price, _)| *price != 98.0));
+
+        // 旧推送被忽略，衔接的推送被应用
+        assert!(
+            !sync
+                .on_diff(diff(108, 110, smallvec![], smallvec![]))
+                .unwrap()
+        );
+        assert!(
+            sync.on_diff(diff(111, 111, smallvec![(100.5, 1.0)], smallvec![]))
+                .unwrap()
+        );
+        assert_eq!(sync.best_bid(), Some((100.5, 1.0)));
+
+        // 缺口: 要求重新同步，而不是 panic
+        let gap = diff(115, 120, smallvec![(100.7, 1.0)], smallvec![]);
+        assert!(matches!(
+            sync.on_diff(gap),
+            Err(DataError::SequenceGap {
+                expected: 111,
+                found: 114,
+                ..
+            })
+        ));
+        assert!(!sync.is_synced());
+        assert_eq!(sync.best_bid(), None);
+
+        // 新快照衔接上引发缺口的推送
+        sync.on_snapshot(&snapshot(), 116).unwrap();
+        assert_eq!(sync.last_update_id(), Some(120));
+        assert_eq!(sync.best_bid(), Some((100.7, 1.0)));
+    }
+
+    #[test]
+    fn test_binance_book_sync_stale_snapshot() {
+        let mut sync = BinanceBookSync::new("btcusdt");
+        sync.on_diff(diff(110, 115, smallvec![(100.0, 3.0)], smallvec![]))
+            .unwrap();
+
+        // 快照比缓冲的推送还旧，推送保留在缓冲区
+        assert!(sync.on_snapshot(&snapshot(), 100).is_err());
+        assert!(!sync.is_synced());
+
+        sync.on_snapshot(&snapshot(), 112).unwrap();
+        assert_eq!(sync.last_update_id(), Some(115));
+        assert_eq!(sync.best_bid(), Some((100.0, 3.0)));
+
+        sync.reset();
+        assert!(!sync.is_synced());
+        sync.on_snapshot(&snapshot(), 200).unwrap();
+        assert_eq!(sync.best_bid(), Some((100.0, 1.0)));
+    }
+
+    #[tokio::test]
+    async fn test_binance_synced_book_reconnect_resets_book() {
+        use crate::test_utils::MockClock;
+        use crate::utils::retry_stream_with_clock;
+        use std::time::Duration;
+
+        let policy = ReconnectPolicy {
+            initial: Duration::from_secs(1),
+            jitter: 0.0,
+            max_retries: 1,
+            ..Default::default()
+        };
+
+        // 第一次连接同步后应用一条推送再断开，第二次连接拿到的快照已经没有 100.0 这一档
+        let mut connections = 0;
+        let connect = move || {
+            connections += 1;
+            let id = connections;
+            async move {
+                let (diffs, snapshot, last_update_id) = if id == 1 {
+                    (
+                        vec![
+                            Ok(diff(101, 101, smallvec![(100.5, 1.0)], smallvec![])),
+                            Err(eyre::eyre!("disconnected")),
+                        ],
+                        snapshot(),
+                        100,
+                    )
+                } else {
+                    let snapshot = BookData {
+                        bids: smallvec![(99.0, 4.0)],
+                        ..snapshot()
+                    };
+                    (Vec::new(), snapshot, 200)
+                };
+                eyre::Ok(sync_book_stream(
+                    "btcusdt".into(),
+                    futures::stream::iter(diffs),
+                    move || {
+                        let snapshot = snapshot.clone();
+                        async move { Ok((snapshot, last_update_id)) }
+                    },
+                    policy,
+                    MockClock::new(0),
+                ))
+            }
+        };
+
+        let outputs: Vec<_> = retry_stream_with_clock(policy, connect, MockClock::new(0))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let flags: Vec<_> = outputs.iter().map(|book| book.is_snapshot).collect();
+        assert_eq!(flags, vec![true, false, true]);
+
+        // 下游按 `is_snapshot` 重建后，断线前的档位不会残留
+        let mut book = OrderBookMaintainer::new("btcusdt");
+        for data in &outputs {
+            book.apply(data);
+        }
+        let (bids, asks) = book.depth(10);
+        assert_eq!(bids.as_slice(), &[(99.0, 4.0)]);
+        assert_eq!(asks.as_slice(), &[(101.0, 1.0), (102.0, 2.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_binance_synced_book_snapshot_retry() {
+        use crate::test_utils::MockClock;
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        };
+        use std::time::Duration;
+
+        let policy = ReconnectPolicy {
+            initial: Duration::from_secs(1),
+            jitter: 0.0,
+            max_retries: 2,
+            ..Default::default()
+        };
+
+        // 前 `failures` 次获取快照失败
+        let run = |failures: usize| {
+            let clock = MockClock::new(0);
+            let fetches = Arc::new(AtomicUsize::new(0));
+            let stream = sync_book_stream(
+                "btcusdt".into(),
+                futures::stream::empty(),
+                {
+                    let fetches = fetches.clone();
+                    move || {
+                        let n = fetches.fetch_add(1, Ordering::SeqCst);
+                        async move {
+                            eyre::ensure!(n >= failures, "HTTP 503");
+                            Ok((snapshot(), 100))
+                        }
+                    }
+                },
+                policy,
+                clock.clone(),
+            );
+            async move {
+                let outputs = stream.collect::<Vec<_>>().await;
+                (outputs, fetches.load(Ordering::SeqCst), clock.now_ms())
+            }
+        };
+
+        // 失败 2 次后成功，分别等待 1s、2s
+        let (outputs, fetches, elapsed_ms) = run(2).await;
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].as_ref().unwrap().is_snapshot);
+        assert_eq!(fetches, 3);
+        assert_eq!(elapsed_ms, 3_000);
+
+        // 一直失败: 重试 2 次后输出错误并结束，而不是无限重试
+        let (outputs, fetches, _) = run(usize::MAX).await;
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].is_err());
+        assert_eq!(fetches, 3);
+    }
+
+    #[tokio::test]
+    async fn test_binance_synced_book_gap_backoff() {
+        use crate::test_utils::MockClock;
+        use std::time::Duration;
+
+        let policy = ReconnectPolicy {
+            initial: Duration::from_secs(1),
+            jitter: 0.0,
+            max_retries: 2,
+            ..Default::default()
+        };
+
+        // 第 n 次获取的快照 lastUpdateId = 100 * n
+        let run = |diffs: Vec<DepthDiff>| {
+            let clock = MockClock::new(0);
+            let mut fetches = 0;
+            let stream = sync_book_stream(
+                "btcusdt".into(),
+                futures::stream::iter(diffs.into_iter().map(Ok)),
+                move || {
+                    fetches += 1;
+                    let last_update_id = 100 * fetches;
+                    async move { Ok((snapshot(), last_update_id)) }
+                },
+                policy,
+                clock.clone(),
+            );
+            async move {
+                let outputs = stream.collect::<Vec<_>>().await;
+                (outputs, clock.now_ms())
+            }
+        };
+        let gap = |id| diff(id, id, smallvec![], smallvec![]);
+
+        // 每次同步后都出现缺口: 退避 1s、2s 后输出错误并结束，而不是反复获取快照
+        let (outputs, elapsed_ms) = run((1..10).map(|n| gap(100 * n + 50)).collect()).await;
+        assert_eq!(outputs.len(), 4);
+        assert!(outputs[..3].iter().all(|r| r.as_ref().unwrap().is_snapshot));
+        assert!(outputs[3].is_err());
+        assert_eq!(elapsed_ms, 3_000);
+
+        // 推送被成功应用后重试次数清零，下一次缺口仍只等待 1s
+        let (outputs, elapsed_ms) = run(vec![gap(150), gap(201), gap(260)]).await;
+        let flags: Vec<_> = outputs
+            .iter()
+            .map(|r| r.as_ref().unwrap().is_snapshot)
+            .collect();
+        assert_eq!(flags, vec![true, true, false, true]);
+        assert_eq!(elapsed_ms, 2_000);
+    }
+}
@@ -0,0 +1,172 @@
+use super::{
+    BINANCE_REST_BASE_URL, BinanceCandleInterval,
+    model::{RawRestCandleData, parse_interval},
+};
+use crate::utils::FromExchangeCandle;
+use ephemera_shared::*;
+use eyre::{ContextCompat, Result, WrapErr, ensure};
+use http::header::USER_AGENT;
+use std::time::Duration;
+
+/// `/api/v3/klines` 每次请求最多返回的 K 线数量
+const KLINES_LIMIT: usize = 1000;
+
+/// 相邻两次分页请求的间隔。`/api/v3/klines` 每次请求的权重为 2，每分钟的权重上限为 6000
+const KLINES_PAGE_DELAY: Duration = Duration::from_millis(100);
+
+/// 通过 REST 拉取 `[start_ms, end_ms)` 内开盘的历史 K 线，按时间升序返回
+///
+/// 按 [`KLINES_LIMIT`] 分页请求 `/api/v3/klines`，每页之间等待一段时间以遵守频率限制。
+/// 尚未收盘的 K 线不会返回。`symbol` 与 WebSocket 流名一致（如 `btcusdt`），K 线的 symbol
+/// 也保持原样。
+pub async fn binance_historical_candles(
+    symbol: impl std::fmt::Display,
+    interval: BinanceCandleInterval,
+    start_ms: TimestampMs,
+    end_ms: TimestampMs,
+) -> Result<Vec<CandleData>> {
+    let symbol = symbol.to_string();
+    let (bar, interval_sc) = rest_interval(&interval)?;
+    let client = reqwest::Client::new();
+    let mut candles = Vec::new();
+    let mut cursor = start_ms;
+
+    while cursor < end_ms {
+        // endTime 包含在结果内，减一保证区间右开
+        let url = format!(
+            "{BINANCE_REST_BASE_URL}/api/v3/klines?symbol={}&interval={bar}&startTime={cursor}&endTime={}&limit={KLINES_LIMIT}",
+            symbol.to_uppercase(),
+            end_ms - 1,
+        );
+        let resp = client
+            .get(&url)
+            .header(USER_AGENT, "ephemera")
+            .send()
+            .await
+            .wrap_err_with(|| format!("Failed to fetch klines of {symbol}"))?;
+        ensure!(
+            resp.status().is_success(),
+            "Failed to fetch klines of {symbol}: {}",
+            resp.status()
+        );
+
+        let mut body = resp.bytes().await?.to_vec();
+        let page = simd_json::serde::from_slice::<Vec<RawRestCandleData>>(&mut body)?;
+        let Some(last_open_ms) = page.last().map(|raw| raw.0) else {
+            break;
+        };
+        let page_len = page.len();
+
+        let now_ms = chrono::Utc::now().timestamp_millis() as TimestampMs;
+        candles.extend(convert_binance_rest_candles(
+            page,
+            &symbol,
+            interval_sc,
+            now_ms,
+        )?);
+
+        if page_len < KLINES_LIMIT {
+            break;
+        }
+        cursor = last_open_ms + 1;
+        tokio::time::sleep(KLINES_PAGE_DELAY).await;
+    }
+
+    Ok(candles)
+}
+
+/// REST 接口使用的周期参数（去掉 WebSocket 流名的 `kline_` 前缀）及其秒数
+fn rest_interval(interval: &BinanceCandleInterval) -> Result<(String, IntervalSc)> {
+    let name = match interval {
+        BinanceCandleInterval::Other(name) => name.clone(),
+        _ => interval.to_string(),
+    };
+    let bar = name.strip_prefix("kline_").unwrap_or(&name).to_string();
+    let interval_sc = parse_interval(&bar).wrap_err_with(|| format!("Unknown interval: {bar}"))?;
+
+    Ok((bar, interval_sc))
+}
+
+/// 转换一页 REST K 线，丢弃 `now_ms` 时尚未收盘的 K 线
+fn convert_binance_rest_candles(
+    page: Vec<RawRestCandleData>,
+    symbol: &str,
+    interval_sc: IntervalSc,
+    now_ms: TimestampMs,
+) -> Result<Vec<CandleData>> {
+    page.into_iter()
+        .filter(|raw| raw.6 < now_ms)
+        .map(|raw| CandleData::from_exchange_candle(raw, symbol.into(), interval_sc))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_binance_rest_candles() {
+        // 第二根 K 线在 now_ms 时尚未收盘，末尾多出的字段被忽略
+        let mut body = br#"[
+            [1499040000000, "0.01634790", "0.80000000", "0.01575800", "0.01577100", "148976.11427815",
+             1499040059999, "2434.19055334", 308, "1756.87402397", "28.46694368", "0"],
+            [1499040060000, "0.01577100", "0.01600000", "0.01570000", "0.01590000", "10.0",
+             1499040119999, "0.159", 2, "5.0", "0.08", "0"]
+        ]"#
+        .to_vec();
+        let page = simd_json::serde::from_slice::<Vec<RawRestCandleData>>(&mut body).unwrap();
+
+        let candles =
+            convert_binance_rest_candles(page, "btcusdt", CANDLE_INTERVAL_MIN1, 1499040100000)
+                .unwrap();
+        assert_eq!(candles.len(), 1);
+
+        let candle = &candles[0];
+        assert_eq!(candle.symbol, "btcusdt");
+        assert_eq!(candle.interval_sc, CANDLE_INTERVAL_MIN1);
+        assert_eq!(candle.open_timestamp_ms, 1499040000000);
+        assert_eq!(candle.open, 0.0163479);
+        assert_eq!(candle.high, 0.8);
+        assert_eq!(candle.low, 0.015758);
+        assert_eq!(candle.close, 0.015771);
+        assert_eq!(candle.volume, 148976.11427815);
+        assert_eq!(candle.quote_volume, 2434.19055334);
+        assert_eq!(candle.trade_count, 308);
+    }
+
+    #[test]
+    fn test_rest_interval() {
+        assert_eq!(
+            rest_interval(&BinanceCandleInterval::Candle4h).unwrap(),
+            ("4h".to_string(), CANDLE_INTERVAL_H4)
+        );
+        assert_eq!(
+            rest_interval(&BinanceCandleInterval::Other("kline_1M".into())).unwrap(),
+            ("1M".to_string(), CANDLE_INTERVAL_MON1)
+        );
+        assert!(rest_interval(&BinanceCandleInterval::Other("7m".into())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_binance_historical_candles() {
+        // 跨越两页
+        let end_ms = chrono::Utc::now().timestamp_millis() as TimestampMs / 60_000 * 60_000;
+        let start_ms = end_ms - 1500 * 60_000;
+        let candles = binance_historical_candles(
+            "btcusdt",
+            BinanceCandleInterval::Candle1m,
+            start_ms,
+            end_ms,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(candles.len(), 1500);
+        assert!(
+            candles
+                .windows(2)
+                .all(|w| w[0].open_timestamp_ms + 60_000 == w[1].open_timestamp_ms)
+        );
+        assert_eq!(candles[0].open_timestamp_ms, start_ms);
+    }
+}
@@ -1,6 +1,17 @@
+mod book_sync;
+mod history;
 mod model;
 
-use crate::utils::transform_raw_stream;
+pub use book_sync::*;
+pub use history::*;
+
+use crate::{
+    metrics,
+    utils::{
+        JsonScratch, ParseMode, ReconnectPolicy, SequenceTracker, WsConfig, payload_for_log,
+        retry_stream, transform_raw_stream, transform_raw_stream_with,
+    },
+};
 use async_stream::stream;
 use bytestring::ByteString;
 use ephemera_shared::*;
@@ -16,18 +27,20 @@ use tokio_websockets::Message;
 
 pub const BINANCE_WS_BASE_URI: &str = "wss://stream.binance.com:443";
 pub const BINANCE_WS_COMBINED_STREAM_BASE_URI: &str = "wss://stream.binance.com:443/stream";
+pub const BINANCE_REST_BASE_URL: &str = "https://api.binance.com";
 
 const METHOD_SUBSCRIBE: ByteString = ByteString::from_static("SUBSCRIBE");
 
 pub async fn binance_trade_data_stream(
     symbols: Vec<impl std::fmt::Display>,
+    config: WsConfig,
 ) -> eyre::Result<impl Stream<Item = Result<TradeData>>> {
     let request = WsRequest {
         id: random(),
         method: METHOD_SUBSCRIBE,
         params: Some(symbols.into_iter().map(trade_stream_name).collect_vec()),
     };
-    binance_raw_data_stream::<WsDataResponse<RawTradeData>>(request)
+    binance_raw_data_stream::<WsDataResponse<RawTradeData>>(request, config)
         .await
         .map(transform_raw_stream)
 }
@@ -35,6 +48,7 @@ pub async fn binance_trade_data_stream(
 pub async fn binance_candle_data_stream(
     symbols: Vec<impl std::fmt::Display>,
     interval: BinanceCandleInterval,
+    config: WsConfig,
 ) -> eyre::Result<impl Stream<Item = Result<CandleData>>> {
     let request = WsRequest {
         id: random(),
@@ -46,7 +60,7 @@ pub async fn binance_candle_data_stream(
                 .collect_vec(),
         ),
     };
-    binance_raw_data_stream::<WsDataResponse<RawCandleData>>(request)
+    binance_raw_data_stream::<WsDataResponse<RawCandleData>>(request, config)
         .await
         .map(transform_raw_stream)
 }
@@ -54,6 +68,7 @@ pub async fn binance_candle_data_stream(
 pub async fn binance_book_data_stream(
     symbols: Vec<impl std::fmt::Display>,
     channel: BinanceBookChannel,
+    config: WsConfig,
 ) -> eyre::Result<impl Stream<Item = Result<BookData>>> {
     let request = WsRequest {
         id: random(),
@@ -70,11 +85,22 @@ pub async fn binance_book_data_stream(
         BinanceBookChannel::Incremental_1000ms
         | BinanceBookChannel::Incremental_100ms
         | BinanceBookChannel::OtherIncremental(_) => {
-            binance_raw_data_stream::<WsDataResponse<RawBookData>>(request)
+            // 相邻两次增量推送应满足 `U == 上一次的 u + 1`
+            let mut tracker = SequenceTracker::default();
+            binance_raw_data_stream::<WsDataResponse<RawBookData>>(request, config)
                 .await
                 .map(|stream| {
-                    Box::pin(transform_raw_stream(stream))
-                        as Pin<Box<dyn Stream<Item = Result<BookData>> + Send>>
+                    Box::pin(transform_raw_stream_with(
+                        stream,
+                        move |resp: WsDataResponse<RawBookData>| {
+                            tracker.check(
+                                &resp.data.symbol,
+                                resp.data.first_update_id.checked_sub(1),
+                                resp.data.final_update_id,
+                            )?;
+                            BookData::try_from(resp)
+                        },
+                    )) as Pin<Box<dyn Stream<Item = Result<BookData>> + Send>>
                 })
         }
         BinanceBookChannel::Depth5_1000ms
@@ -84,7 +110,7 @@ pub async fn binance_book_data_stream(
         | BinanceBookChannel::Depth20_1000ms
         | BinanceBookChannel::Depth20_100ms
         | BinanceBookChannel::OtherSnapshot(_) => {
-            binance_raw_data_stream::<WsDataResponse<RawBookSnapshotData>>(request)
+            binance_raw_data_stream::<WsDataResponse<RawBookSnapshotData>>(request, config)
                 .await
                 .map(|stream| {
                     Box::pin(transform_raw_stream(stream))
@@ -94,8 +120,79 @@ pub async fn binance_book_data_stream(
     }
 }
 
+/// 同 [`binance_trade_data_stream`]，断线后按 `policy` 重连并重新订阅
+///
+/// 每次重连都会重新发送订阅请求并校验订阅响应，断线期间的数据会丢失。
+pub fn binance_trade_data_stream_with_reconnect(
+    symbols: Vec<impl std::fmt::Display>,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<TradeData>> + Send {
+    let symbols = symbols.iter().map(ToString::to_string).collect_vec();
+    retry_stream(policy, move || {
+        binance_trade_data_stream(symbols.clone(), config)
+    })
+}
+
+/// 同 [`binance_candle_data_stream`]，断线后按 `policy` 重连并重新订阅
+pub fn binance_candle_data_stream_with_reconnect(
+    symbols: Vec<impl std::fmt::Display>,
+    interval: BinanceCandleInterval,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<CandleData>> + Send {
+    let symbols = symbols.iter().map(ToString::to_string).collect_vec();
+    retry_stream(policy, move || {
+        binance_candle_data_stream(symbols.clone(), interval.clone(), config)
+    })
+}
+
+/// 同 [`binance_book_data_stream`]，断线后按 `policy` 重连并重新订阅
+///
+/// 快照频道的每条推送都是完整快照，直接重连即可。增量频道断线期间丢失的推送无法补齐，
+/// 因此每个交易对单独经 [`binance_synced_book_stream`] 同步，每次（重新）连接后先输出一份
+/// `is_snapshot == true` 的完整快照，下游据此丢弃断线前的订单簿视图。
+pub fn binance_book_data_stream_with_reconnect(
+    symbols: Vec<impl std::fmt::Display>,
+    channel: BinanceBookChannel,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<BookData>> + Send {
+    let symbols = symbols.iter().map(ToString::to_string).collect_vec();
+    match channel {
+        BinanceBookChannel::Incremental_1000ms
+        | BinanceBookChannel::Incremental_100ms
+        | BinanceBookChannel::OtherIncremental(_) => {
+            let streams = symbols.into_iter().map(|symbol| {
+                let channel = channel.clone();
+                Box::pin(retry_stream(policy, move || {
+                    binance_synced_book_stream(
+                        symbol.clone(),
+                        channel.clone(),
+                        BINANCE_DEPTH_SNAPSHOT_LIMIT,
+                        config,
+                        policy,
+                    )
+                })) as Pin<Box<dyn Stream<Item = Result<BookData>> + Send>>
+            });
+            Box::pin(futures::stream::select_all(streams))
+                as Pin<Box<dyn Stream<Item = Result<BookData>> + Send>>
+        }
+        BinanceBookChannel::Depth5_1000ms
+        | BinanceBookChannel::Depth5_100ms
+        | BinanceBookChannel::Depth10_1000ms
+        | BinanceBookChannel::Depth10_100ms
+        | BinanceBookChannel::Depth20_1000ms
+        | BinanceBookChannel::Depth20_100ms
+        | BinanceBookChannel::OtherSnapshot(_) => Box::pin(retry_stream(policy, move || {
+            binance_book_data_stream(symbols.clone(), channel.clone(), config)
+        })),
+    }
+}
+
 async fn binance_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
     request: WsRequest,
+    config: WsConfig,
 ) -> Result<Pin<Box<dyn Stream<Item = Result<DR, eyre::Error>> + Send>>, eyre::Error> {
     let params = if let Some(params) = &request.params
         && !params.is_empty()
@@ -105,6 +202,10 @@ async fn binance_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
         panic!("At least one channel must be specified for subscription");
     };
 
+    // 流名形如 `btcusdt@trade`，同一次订阅的频道相同
+    let channel = params[0].split_once('@').map_or("", |(_, channel)| channel);
+    let messages_received = metrics::ws_messages_received("binance", channel);
+
     let stream_names = params.join("/");
     let end_point = format!("{BINANCE_WS_COMBINED_STREAM_BASE_URI}?streams={stream_names}");
 
@@ -143,9 +244,34 @@ async fn binance_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
         "Failed to subscribe with response:\n {resp:?}",
     );
 
+    metrics::ws_connections("binance").inc();
+
+    let WsConfig {
+        idle_timeout,
+        parse_mode,
+        ..
+    } = config;
     let stream = stream! {
-        while let Some(msg) = client.next().await {
-            let msg = msg?;
+        let mut scratch = JsonScratch::default();
+
+        loop {
+            let msg = match tokio::time::timeout(idle_timeout, client.next()).await {
+                Ok(Some(msg)) => msg?,
+                Ok(None) => break,
+                Err(_) => {
+                    let _ = client.close().await;
+                    yield Err(DataError::Connection(format!(
+                        "No frame received within {idle_timeout:?}"
+                    ))
+                    .into());
+                    break;
+                }
+            };
+
+            // 服务端主动关闭连接，结束数据流，由调用方决定是否重连
+            if msg.is_close() {
+                break;
+            }
 
             // Return a pong response for ping messages to keep the connection alive.
             if msg.is_ping() {
@@ -153,10 +279,18 @@ async fn binance_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
                 continue;
             }
 
-            match simd_json::from_slice::<DR>(&mut msg.as_payload().to_vec()) {
-                Ok(resp) => yield Ok(resp),
+            match scratch.parse::<DR>(msg.as_payload()) {
+                Ok(resp) => {
+                    messages_received.inc();
+                    yield Ok(resp)
+                }
+                Err(e) if parse_mode == ParseMode::Tolerant => {
+                    tracing::warn!(
+                        "Skipping unparsable binance message: {e}, payload: {}",
+                        payload_for_log(msg.as_payload())
+                    );
+                }
                 Err(e) => yield Err(e.into()),
-
             }
         }
     };
@@ -267,7 +401,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_binance_trade_data_stream() {
-        binance_trade_data_stream(SYMBOLS.to_vec())
+        binance_trade_data_stream(SYMBOLS.to_vec(), WsConfig::default())
             .await
             .unwrap()
             .take(TEST_DATA_NUM)
@@ -277,9 +411,25 @@ mod tests {
 
     #[tokio::test]
     async fn test_binance_candle_data_stream() {
-        binance_candle_data_stream(SYMBOLS.to_vec(), BinanceCandleInterval::Candle1s)
-            .await
-            .unwrap()
+        binance_candle_data_stream(
+            SYMBOLS.to_vec(),
+            BinanceCandleInterval::Candle1s,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap()
+        .take(TEST_DATA_NUM)
+        .collect::<Vec<_>>()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_binance_trade_data_stream_with_reconnect() {
+        let policy = ReconnectPolicy {
+            max_retries: 1,
+            ..Default::default()
+        };
+        binance_trade_data_stream_with_reconnect(SYMBOLS.to_vec(), WsConfig::default(), policy)
             .take(TEST_DATA_NUM)
             .collect::<Vec<_>>()
             .await;
@@ -287,11 +437,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_binance_book_data_stream() {
-        binance_book_data_stream(SYMBOLS.to_vec(), BinanceBookChannel::Incremental_100ms)
-            .await
-            .unwrap()
-            .take(TEST_DATA_NUM)
-            .collect::<Vec<_>>()
-            .await;
+        binance_book_data_stream(
+            SYMBOLS.to_vec(),
+            BinanceBookChannel::Incremental_100ms,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap()
+        .take(TEST_DATA_NUM)
+        .collect::<Vec<_>>()
+        .await;
     }
 }
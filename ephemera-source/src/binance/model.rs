@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use crate::utils::{FromExchangeCandle, impl_positional_deserialize};
 use bytestring::ByteString;
 use ephemera_shared::*;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -160,15 +161,81 @@ impl TryFrom<WsDataResponse<RawCandleData>> for CandleData {
 
     fn try_from(value: WsDataResponse<RawCandleData>) -> Result<Self, Self::Error> {
         let kline = value.data.kline;
+        let interval_sc = kline.interval;
+        Self::from_exchange_candle(
+            kline,
+            split_symbol_and_channel(value.stream)?.0,
+            interval_sc,
+        )
+    }
+}
+
+impl FromExchangeCandle<RawCandleDataInner> for CandleData {
+    fn from_exchange_candle(
+        raw: RawCandleDataInner,
+        symbol: Symbol,
+        interval_sc: IntervalSc,
+    ) -> eyre::Result<Self> {
         Ok(Self {
-            symbol: split_symbol_and_channel(value.stream)?.0,
-            interval_sc: kline.interval,
-            open_timestamp_ms: kline.start_time,
-            open: kline.open,
-            high: kline.high,
-            low: kline.low,
-            close: kline.close,
-            volume: kline.base_asset_volume,
+            symbol,
+            interval_sc,
+            open_timestamp_ms: raw.start_time,
+            open: raw.open,
+            high: raw.high,
+            low: raw.low,
+            close: raw.close,
+            volume: raw.base_asset_volume,
+            trade_count: raw.number_of_trades,
+            quote_volume: raw.quote_asset_volume,
+            quote_volume_estimated: false,
+        })
+    }
+}
+
+/// REST `/api/v3/klines` 返回的一行 K 线，按位置排列:
+///
+/// 0. 开盘时间,
+/// 1. 开盘价,
+/// 2. 最高价,
+/// 3. 最低价,
+/// 4. 收盘价,
+/// 5. 成交量,
+/// 6. 收盘时间,
+/// 7. 成交额,
+/// 8. 成交笔数
+#[derive(Debug, Clone)]
+pub(super) struct RawRestCandleData(
+    pub(super) TimestampMs,
+    pub(super) ByteString,
+    pub(super) ByteString,
+    pub(super) ByteString,
+    pub(super) ByteString,
+    pub(super) ByteString,
+    pub(super) TimestampMs,
+    pub(super) ByteString,
+    pub(super) u64,
+);
+
+impl_positional_deserialize!(RawRestCandleData, 0, 1, 2, 3, 4, 5, 6, 7, 8);
+
+impl FromExchangeCandle<RawRestCandleData> for CandleData {
+    fn from_exchange_candle(
+        raw: RawRestCandleData,
+        symbol: Symbol,
+        interval_sc: IntervalSc,
+    ) -> eyre::Result<Self> {
+        Ok(Self {
+            symbol,
+            interval_sc,
+            open_timestamp_ms: raw.0,
+            open: raw.1.parse()?,
+            high: raw.2.parse()?,
+            low: raw.3.parse()?,
+            close: raw.4.parse()?,
+            volume: raw.5.parse()?,
+            trade_count: raw.8,
+            quote_volume: raw.7.parse()?,
+            quote_volume_estimated: false,
         })
     }
 }
@@ -250,6 +317,7 @@ impl TryFrom<WsDataResponse<RawBookData>> for BookData {
             timestamp: value.data.event_time,
             bids: value.data.bids,
             asks: value.data.asks,
+            is_snapshot: false,
         })
     }
 }
@@ -273,6 +341,7 @@ impl TryFrom<WsDataResponse<RawBookSnapshotData>> for BookData {
             timestamp: value.data.last_update_id,
             bids: value.data.bids,
             asks: value.data.asks,
+            is_snapshot: true,
         })
     }
 }
@@ -292,7 +361,13 @@ where
     D: Deserializer<'de>,
 {
     let interval_str: &str = Deserialize::deserialize(deserializer)?;
-    let interval = match interval_str {
+    parse_interval(interval_str)
+        .ok_or_else(|| serde::de::Error::custom(format!("Unknown interval: {interval_str}")))
+}
+
+/// 解析 Binance 的 K 线周期（如 `1m`、`4h`），返回秒数
+pub(super) fn parse_interval(interval: &str) -> Option<IntervalSc> {
+    let interval = match interval {
         "1s" => CANDLE_INTERVAL_SEC1,
         "1m" => CANDLE_INTERVAL_MIN1,
         "3m" => CANDLE_INTERVAL_MIN3,
@@ -309,12 +384,8 @@ where
         "3d" => CANDLE_INTERVAL_D3,
         "1w" => CANDLE_INTERVAL_WEEK1,
         "1M" => CANDLE_INTERVAL_MON1,
-        _ => {
-            return Err(serde::de::Error::custom(format!(
-                "Unknown interval: {interval_str}"
-            )));
-        }
+        _ => return None,
     };
 
-    Ok(interval)
+    Some(interval)
 }
@@ -0,0 +1,113 @@
+//! 同步迭代器接口
+//!
+//! 为不想引入 async 的简单脚本提供 `for candle in ...` 式的读取方式。每个迭代器内部持有一个
+//! 单线程 tokio 运行时，在 [`Iterator::next`] 中阻塞地驱动底层的异步数据流。
+//!
+//! 迭代器会阻塞当前线程，不能在 tokio 运行时内部创建或使用，否则会 panic。
+
+use crate::csv::{csv_book_data_stream, csv_candle_data_stream, csv_trade_data_stream};
+use ephemera_shared::{BookData, CandleData, TradeData};
+use eyre::Result;
+use futures::{Stream, StreamExt};
+use std::{path::Path, pin::Pin};
+use tokio::runtime::{Builder, Runtime};
+
+/// 在单线程运行时上逐个阻塞读取异步数据流的迭代器
+pub struct BlockingIter<T> {
+    runtime: Runtime,
+    stream: Pin<Box<dyn Stream<Item = T>>>,
+}
+
+impl<T> Iterator for BlockingIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+fn runtime() -> Result<Runtime> {
+    Ok(Builder::new_current_thread().enable_all().build()?)
+}
+
+/// 将任意数据流包装为同步迭代器
+pub fn block_on_stream<S>(stream: S) -> Result<BlockingIter<S::Item>>
+where
+    S: Stream + 'static,
+{
+    Ok(BlockingIter {
+        runtime: runtime()?,
+        stream: Box::pin(stream),
+    })
+}
+
+/// 在同一个运行时上创建数据流并包装为同步迭代器，数据流可能依赖该运行时（如打开的文件）
+fn block_on_with<F, S>(open: F) -> Result<BlockingIter<S::Item>>
+where
+    F: Future<Output = Result<S>>,
+    S: Stream + 'static,
+{
+    let runtime = runtime()?;
+    let stream = runtime.block_on(open)?;
+
+    Ok(BlockingIter {
+        runtime,
+        stream: Box::pin(stream),
+    })
+}
+
+/// 同步读取 CSV K 线，格式见 [`csv_candle_data_stream`]
+pub fn block_on_candles(path: impl AsRef<Path>) -> Result<BlockingIter<Result<CandleData>>> {
+    // 返回的数据流会捕获路径参数的类型，先转为自有的路径
+    let path = path.as_ref().to_path_buf();
+    block_on_with(csv_candle_data_stream(path))
+}
+
+/// 同步读取 CSV 成交，格式见 [`csv_trade_data_stream`]
+pub fn block_on_trades(path: impl AsRef<Path>) -> Result<BlockingIter<Result<TradeData>>> {
+    let path = path.as_ref().to_path_buf();
+    block_on_with(csv_trade_data_stream(path))
+}
+
+/// 同步读取 CSV 订单簿，格式见 [`csv_book_data_stream`]
+pub fn block_on_books(path: impl AsRef<Path>) -> Result<BlockingIter<Result<BookData>>> {
+    let path = path.as_ref().to_path_buf();
+    block_on_with(csv_book_data_stream(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_block_on_candles() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(
+            [
+                "open_timestamp_ms,symbol,interval_sc,open,high,low,close,volume",
+                "1640000000000,BTC-USDT,60,100.0,110.0,90.0,105.0,10.0",
+                "1640000060000,BTC-USDT,60,105.0,106.0,100.0,101.0,5.0",
+            ]
+            .join("\n")
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut closes = Vec::new();
+        for candle in block_on_candles(file.path()).unwrap() {
+            closes.push(candle.unwrap().close);
+        }
+        assert_eq!(closes, vec![105.0, 101.0]);
+
+        assert!(block_on_trades("/nonexistent/trades.csv").is_err());
+    }
+
+    #[test]
+    fn test_block_on_stream() {
+        let iter = block_on_stream(futures::stream::iter(1..=3)).unwrap();
+
+        assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+}
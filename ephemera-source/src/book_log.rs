@@ -0,0 +1,265 @@
+//! 订单簿事件日志
+//!
+//! 以 JSONL 记录每一次订单簿快照和增量更新，保留序号、纳秒时间戳和快照/增量类型，
+//! 回放时通过 [`OrderBookMaintainer`] 重建完整订单簿，供依赖盘口变化的策略回测使用。
+
+use crate::jsonl::{JsonlWriter, jsonl_data_stream};
+use async_stream::stream;
+use ephemera_shared::*;
+use eyre::{Result, ensure};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs::File, io::AsyncWrite};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BookEventKind {
+    Snapshot,
+    Update,
+}
+
+/// 一条订单簿事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookEvent {
+    /// 日志内从 0 开始连续递增的序号，回放时用于检查事件是否缺失
+    pub seq: u64,
+    /// 记录时的 Unix 时间戳（纳秒）
+    pub timestamp_ns: u64,
+    /// 交易所给出的时间戳（毫秒）
+    pub exchange_timestamp_ms: TimestampMs,
+    pub kind: BookEventKind,
+    pub symbol: Symbol,
+    pub bids: BookSide,
+    pub asks: BookSide,
+}
+
+impl BookEvent {
+    pub fn new(seq: u64, timestamp_ns: u64, book: &BookData) -> Self {
+        Self {
+            seq,
+            timestamp_ns,
+            exchange_timestamp_ms: book.timestamp,
+            kind: if book.is_snapshot {
+                BookEventKind::Snapshot
+            } else {
+                BookEventKind::Update
+            },
+            symbol: book.symbol.clone(),
+            bids: book.bids.clone(),
+            asks: book.asks.clone(),
+        }
+    }
+
+    pub fn to_book_data(&self) -> BookData {
+        BookData {
+            symbol: self.symbol.clone(),
+            timestamp: self.exchange_timestamp_ms,
+            bids: self.bids.clone(),
+            asks: self.asks.clone(),
+            is_snapshot: self.kind == BookEventKind::Snapshot,
+        }
+    }
+}
+
+/// 订单簿事件记录器，为每条事件分配序号和纳秒时间戳
+///
+/// 内部带缓冲，写完后需调用 [`flush`](Self::flush)
+pub struct BookEventRecorder<W> {
+    writer: JsonlWriter<W>,
+    next_seq: u64,
+}
+
+impl BookEventRecorder<File> {
+    /// 创建（或截断）文件并写入
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(JsonlWriter::create(path).await?))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> BookEventRecorder<W> {
+    pub fn new(writer: JsonlWriter<W>) -> Self {
+        Self {
+            writer,
+            next_seq: 0,
+        }
+    }
+
+    /// 以当前系统时间记录一条事件
+    pub async fn record(&mut self, book: &BookData) -> Result<()> {
+        let timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+
+        self.record_at(book, timestamp_ns).await
+    }
+
+    /// 以指定的纳秒时间戳记录一条事件
+    pub async fn record_at(&mut self, book: &BookData, timestamp_ns: u64) -> Result<()> {
+        self.writer
+            .write(&BookEvent::new(self.next_seq, timestamp_ns, book))
+            .await?;
+        self.next_seq += 1;
+
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await
+    }
+}
+
+/// 读取订单簿事件日志，每行一个 [`BookEvent`]
+pub async fn jsonl_book_event_stream(
+    path: impl AsRef<Path>,
+) -> Result<impl Stream<Item = Result<BookEvent>>> {
+    jsonl_data_stream(path).await
+}
+
+/// 回放订单簿事件日志，每条事件之后输出重建的前 `depth` 档完整订单簿
+///
+/// 每个交易对各自维护一个 [`OrderBookMaintainer`]，输出的 [`BookData`] 均为快照。
+/// 收到首个快照之前的增量更新无法重建，会被跳过。序号不连续时返回错误并结束。
+pub async fn replay_book_events(
+    path: impl AsRef<Path>,
+    depth: usize,
+) -> Result<impl Stream<Item = Result<BookData>>> {
+    let events = jsonl_book_event_stream(path).await?;
+
+    Ok(Box::pin(replay_book_event_stream(events, depth)))
+}
+
+/// 同 [`replay_book_events`]，输入为任意事件流
+pub fn replay_book_event_stream(
+    events: impl Stream<Item = Result<BookEvent>>,
+    depth: usize,
+) -> impl Stream<Item = Result<BookData>> {
+    stream! {
+        let mut books: HashMap<Symbol, OrderBookMaintainer> = HashMap::new();
+        let mut expected_seq = None;
+
+        futures::pin_mut!(events);
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            if let Some(expected) = expected_seq
+                && let Err(e) = check_seq(expected, event.seq)
+            {
+                yield Err(e);
+                return;
+            }
+            expected_seq = Some(event.seq + 1);
+
+            let book = books
+                .entry(event.symbol.clone())
+                .or_insert_with(|| OrderBookMaintainer::new(event.symbol.clone()));
+
+            if book.apply(&event.to_book_data()) {
+                yield Ok(book.to_book_data(depth));
+            }
+        }
+    }
+}
+
+fn check_seq(expected: u64, found: u64) -> Result<()> {
+    ensure!(
+        found == expected,
+        "Book event sequence gap: expected {expected}, found {found}"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use smallvec::smallvec;
+    use tempfile::NamedTempFile;
+
+    fn book(timestamp: TimestampMs, bids: BookSide, asks: BookSide, is_snapshot: bool) -> BookData {
+        BookData {
+            symbol: "BTC-USDT".into(),
+            timestamp,
+            bids,
+            asks,
+            is_snapshot,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_book_event_round_trip_and_replay() {
+        let file = NamedTempFile::new().unwrap();
+        let updates = [
+            book(1, smallvec![(99.0, 1.0)], smallvec![], false),
+            book(
+                2,
+                smallvec![(100.0, 1.0), (99.0, 2.0)],
+                smallvec![(101.0, 1.5)],
+                true,
+            ),
+            book(3, smallvec![(100.0, 0.0)], smallvec![(100.5, 0.2)], false),
+        ];
+
+        let mut recorder = BookEventRecorder::create(file.path()).await.unwrap();
+        for (i, update) in updates.iter().enumerate() {
+            recorder
+                .record_at(update, 1_000_000_123 + i as u64)
+                .await
+                .unwrap();
+        }
+        recorder.flush().await.unwrap();
+
+        let events: Vec<_> = jsonl_book_event_stream(file.path())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].seq, 2);
+        assert_eq!(events[2].timestamp_ns, 1_000_000_125);
+        assert_eq!(events[1].kind, BookEventKind::Snapshot);
+        assert_eq!(events[2].to_book_data(), updates[2]);
+
+        // 首个快照之前的增量更新被跳过
+        let books: Vec<_> = replay_book_events(file.path(), 5)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].bids.as_slice(), &[(100.0, 1.0), (99.0, 2.0)]);
+        assert_eq!(books[1].timestamp, 3);
+        assert_eq!(books[1].bids.as_slice(), &[(99.0, 2.0)]);
+        assert_eq!(books[1].asks.as_slice(), &[(100.5, 0.2), (101.0, 1.5)]);
+        assert!(books[1].is_snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_replay_book_events_sequence_gap() {
+        let snapshot = book(1, smallvec![(100.0, 1.0)], smallvec![(101.0, 1.0)], true);
+        let events = futures::stream::iter([
+            Ok(BookEvent::new(0, 0, &snapshot)),
+            Ok(BookEvent::new(2, 0, &snapshot)),
+        ]);
+
+        let mut replay = Box::pin(replay_book_event_stream(events, 5));
+        assert!(replay.next().await.unwrap().is_ok());
+        let err = replay.next().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("expected 1, found 2"));
+        assert!(replay.next().await.is_none());
+    }
+}
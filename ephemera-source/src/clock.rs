@@ -0,0 +1,35 @@
+use ephemera_shared::TimestampMs;
+use std::{
+    future::Future,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// 时间源
+///
+/// 依赖时间的流组合器（按时间戳回放、定时刷新等）通过该 trait 获取当前时间和等待，
+/// 而不是直接调用 `tokio::time::sleep`。生产环境使用 [`SystemClock`]，
+/// 测试中使用 [`MockClock`](crate::test_utils::MockClock) 以虚拟时间立即推进。
+pub trait Clock: Clone + Send + Sync + 'static {
+    /// 当前 Unix 时间戳（毫秒）
+    fn now_ms(&self) -> TimestampMs;
+
+    /// 等待 `duration`
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// 基于系统时间和 tokio 定时器的真实时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> TimestampMs {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as TimestampMs)
+            .unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
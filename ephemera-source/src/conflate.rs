@@ -0,0 +1,179 @@
+use crate::clock::{Clock, SystemClock};
+use async_stream::stream;
+use ephemera_shared::{BookData, OrderBookMaintainer, Symbol, TimestampMs};
+use eyre::Result;
+use futures::{Stream, StreamExt};
+use std::{collections::HashMap, time::Duration};
+
+/// 按固定频率输出合并后的订单簿
+///
+/// 同 [`conflate_books_with_clock`]，使用系统时钟
+pub fn conflate_books(
+    book_stream: impl Stream<Item = Result<BookData>>,
+    interval: Duration,
+) -> impl Stream<Item = Result<BookData>> {
+    conflate_books_with_clock(book_stream, interval, SystemClock)
+}
+
+/// 按固定频率输出合并后的订单簿，使用指定的时钟计时
+///
+/// 每个交易对各自用 [`OrderBookMaintainer`] 合并快照和增量更新。一个周期从收到第一条更新开始，
+/// 到期时每个有变化的交易对只输出一次最新的完整订单簿（快照），周期内的其他中间状态被丢弃，
+/// 适合把 `books-l2-tbt` 这类 10ms 级别的推送降到适合展示的频率。
+///
+/// 与按数量限制的缓冲不同，输出按时间量化: 每个交易对每个 `interval` 最多输出一次。
+/// 错误立即透传；输入结束时输出尚未到期的最新状态。
+pub fn conflate_books_with_clock(
+    book_stream: impl Stream<Item = Result<BookData>>,
+    interval: Duration,
+    clock: impl Clock,
+) -> impl Stream<Item = Result<BookData>> {
+    let interval_ms = interval.as_millis() as TimestampMs;
+
+    stream! {
+        let mut books: HashMap<Symbol, OrderBookMaintainer> = HashMap::new();
+        // 本周期内有变化的交易对，保持首次变化的顺序
+        let mut dirty: Vec<Symbol> = Vec::new();
+        let mut deadline: Option<TimestampMs> = None;
+
+        futures::pin_mut!(book_stream);
+        loop {
+            let wait = deadline.map(|deadline| deadline.saturating_sub(clock.now_ms()));
+
+            // 只在没有就绪的更新时才开始等待，`None` 表示周期到期
+            let tick = async {
+                clock.sleep(Duration::from_millis(wait.unwrap_or_default())).await
+            };
+            let next = tokio::select! {
+                biased;
+                item = book_stream.next() => Some(item),
+                _ = tick, if wait.is_some() => None,
+            };
+
+            match next {
+                Some(None) => break,
+                Some(Some(Err(e))) => yield Err(e),
+                Some(Some(Ok(book))) => {
+                    let maintainer = books
+                        .entry(book.symbol.clone())
+                        .or_insert_with(|| OrderBookMaintainer::new(book.symbol.clone()));
+
+                    if maintainer.apply(&book) {
+                        if !dirty.contains(&book.symbol) {
+                            dirty.push(book.symbol);
+                        }
+                        deadline.get_or_insert(clock.now_ms() + interval_ms);
+                    }
+                }
+                None => {}
+            }
+
+            if deadline.is_some_and(|deadline| clock.now_ms() >= deadline) {
+                deadline = None;
+                for symbol in dirty.drain(..) {
+                    yield Ok(books[&symbol].to_book_data(usize::MAX));
+                }
+            }
+        }
+
+        for symbol in dirty.drain(..) {
+            yield Ok(books[&symbol].to_book_data(usize::MAX));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockClock;
+    use futures::{TryStreamExt, stream};
+    use smallvec::smallvec;
+
+    fn update(symbol: &str, bid: f64, is_snapshot: bool) -> Result<BookData> {
+        Ok(BookData {
+            symbol: symbol.into(),
+            timestamp: 0,
+            bids: smallvec![(bid, 1.0)],
+            asks: smallvec![(200.0, 1.0)],
+            is_snapshot,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_conflate_books_burst() {
+        let clock = MockClock::new(0);
+        let burst = stream::iter([
+            update("BTC-USDT", 100.0, true),
+            update("BTC-USDT", 101.0, false),
+            update("BTC-USDT", 102.0, false),
+            update("BTC-USDT", 100.0, false),
+        ]);
+
+        let books: Vec<_> =
+            conflate_books_with_clock(burst, Duration::from_millis(100), clock.clone())
+                .try_collect()
+                .await
+                .unwrap();
+
+        // 同一周期内的多次更新只输出一次，且为合并后的状态
+        assert_eq!(books.len(), 1);
+        assert_eq!(
+            books[0].bids.as_slice(),
+            &[(102.0, 1.0), (101.0, 1.0), (100.0, 1.0)]
+        );
+        assert!(books[0].is_snapshot);
+
+        // 输入没有结束时，由时钟驱动在周期到期时输出
+        let burst = stream::iter([
+            update("BTC-USDT", 100.0, true),
+            update("BTC-USDT", 101.0, false),
+        ])
+        .chain(stream::pending());
+        let conflated = conflate_books_with_clock(burst, Duration::from_millis(100), clock.clone());
+        futures::pin_mut!(conflated);
+
+        let book = conflated.next().await.unwrap().unwrap();
+        assert_eq!(book.bids.len(), 2);
+        assert_eq!(clock.now_ms(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_conflate_books_per_interval() {
+        let clock = MockClock::new(0);
+        let updates = stream::iter([
+            (0, update("BTC-USDT", 100.0, true)),
+            (30, update("ETH-USDT", 10.0, true)),
+            (60, update("BTC-USDT", 101.0, true)),
+            (120, update("BTC-USDT", 102.0, true)),
+            (130, update("BTC-USDT", 103.0, true)),
+        ])
+        .then({
+            let clock = clock.clone();
+            move |(at, book)| {
+                clock.advance(Duration::from_millis(at - clock.now_ms()));
+                async move { book }
+            }
+        });
+
+        let books: Vec<_> =
+            conflate_books_with_clock(updates, Duration::from_millis(100), clock.clone())
+                .try_collect()
+                .await
+                .unwrap();
+
+        // 第一个周期 [0, 100) 在 120 的更新到来时到期，两个交易对各输出一次；
+        // 130 的更新开启新周期，输入结束时输出
+        let bids: Vec<_> = books
+            .iter()
+            .map(|b| (b.symbol.clone(), b.bids[0].0))
+            .collect();
+        assert_eq!(
+            bids,
+            vec![
+                ("BTC-USDT".into(), 102.0),
+                ("ETH-USDT".into(), 10.0),
+                ("BTC-USDT".into(), 103.0),
+            ]
+        );
+    }
+}
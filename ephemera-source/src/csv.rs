@@ -1,3 +1,5 @@
+use crate::clock::{Clock, SystemClock};
+use async_compression::tokio::bufread::GzipDecoder;
 use async_stream::stream;
 use ephemera_shared::*;
 use eyre::{Context, Result};
@@ -6,19 +8,27 @@ use serde::{Deserialize, Serialize};
 use std::{path::Path, pin::Pin};
 use tokio::{
     fs::File,
-    time::{Duration, sleep},
+    io::{AsyncRead, BufReader},
+    time::Duration,
 };
 
 /// CSV 交易数据流
 ///
 /// CSV 格式：timestamp_ms,symbol,price,quantity,side
+///
+/// 扩展名为 `.gz` 的文件按 gzip 解压后读取，K 线和订单簿数据流同理。
 pub async fn csv_trade_data_stream(
     path: impl AsRef<Path>,
 ) -> Result<impl Stream<Item = Result<TradeData>>> {
-    let path = path.as_ref().to_path_buf();
-    let file = File::open(&path)
-        .await
-        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    csv_trade_data_stream_with_unit(path, TimestampUnit::Millis).await
+}
+
+/// CSV 交易数据流，`timestamp_ms` 列的单位为 `unit`，读取时换算为毫秒
+pub async fn csv_trade_data_stream_with_unit(
+    path: impl AsRef<Path>,
+    unit: TimestampUnit,
+) -> Result<impl Stream<Item = Result<TradeData>>> {
+    let file = open_csv_file(path.as_ref()).await?;
 
     let stream = stream! {
         let mut reader = csv_async::AsyncReaderBuilder::new()
@@ -28,7 +38,12 @@ pub async fn csv_trade_data_stream(
         let mut records = reader.deserialize::<TradeData>();
 
         while let Some(record) = records.next().await {
-            yield record.map_err(Into::into)
+            yield record
+                .map(|mut trade: TradeData| {
+                    trade.timestamp_ms = unit.to_millis(trade.timestamp_ms);
+                    trade
+                })
+                .map_err(Into::into)
         }
     };
 
@@ -37,14 +52,24 @@ pub async fn csv_trade_data_stream(
 
 /// CSV K线数据流
 ///
-/// CSV 格式：open_timestamp_ms,symbol,interval_sc,open,high,low,close,volume
+/// CSV 格式：open_timestamp_ms,symbol,interval_sc,open,high,low,close,volume[,trade_count][,quote_volume]
+///
+/// `trade_count` 列可省略，省略时为 0；`quote_volume` 列可省略，省略时以 `close * volume` 估算，
+/// 此时 [`CandleData::vwap`] 返回 `None`。
 pub async fn csv_candle_data_stream(
     path: impl AsRef<Path>,
 ) -> Result<impl Stream<Item = Result<CandleData>>> {
-    let path = path.as_ref().to_path_buf();
-    let file = File::open(&path)
-        .await
-        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    csv_candle_data_stream_with_unit(path, TimestampUnit::Millis).await
+}
+
+/// CSV K线数据流，`open_timestamp_ms` 列的单位为 `unit`，读取时换算为毫秒
+///
+/// 例如以秒记录开盘时间的 CSV 使用 [`TimestampUnit::Seconds`]，`interval_sc` 列不受影响。
+pub async fn csv_candle_data_stream_with_unit(
+    path: impl AsRef<Path>,
+    unit: TimestampUnit,
+) -> Result<impl Stream<Item = Result<CandleData>>> {
+    let file = open_csv_file(path.as_ref()).await?;
 
     let stream = stream! {
         let mut reader = csv_async::AsyncReaderBuilder::new()
@@ -54,7 +79,86 @@ pub async fn csv_candle_data_stream(
         let mut records = reader.deserialize::<CandleData>();
 
         while let Some(record) = records.next().await {
-            yield record.map_err(Into::into)
+            yield record
+                .map(|mut candle: CandleData| {
+                    candle.open_timestamp_ms = unit.to_millis(candle.open_timestamp_ms);
+                    candle.or_estimated_quote_volume()
+                })
+                .map_err(Into::into)
+        }
+    };
+
+    Ok(Box::pin(stream))
+}
+
+/// 按文件名顺序读取所有匹配 `pattern` 的 CSV 文件，拼接成一条连续的 K 线数据流
+///
+/// 格式同 [`csv_candle_data_stream`]。在文件交界处检查时间戳的连续性：
+/// - 下一个文件的首根 K 线晚于预期：记录警告（数据缺口）
+/// - 下一个文件的首根 K 线早于预期：产生错误（数据重叠）并结束
+///
+/// # Error
+///
+/// - pattern 非法或没有匹配的文件
+pub async fn csv_candle_data_stream_glob(
+    pattern: &str,
+) -> Result<impl Stream<Item = Result<CandleData>>> {
+    let mut paths = glob::glob(pattern)
+        .with_context(|| format!("Invalid glob pattern: {pattern}"))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    if paths.is_empty() {
+        eyre::bail!("No file matches pattern: {pattern}");
+    }
+
+    let stream = stream! {
+        let mut last: Option<CandleData> = None;
+
+        for path in paths {
+            let mut candles = match csv_candle_data_stream(&path).await {
+                Ok(candles) => candles,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut is_first = true;
+
+            while let Some(candle) = candles.next().await {
+                let candle = match candle {
+                    Ok(candle) => candle,
+                    Err(e) => {
+                        yield Err(e);
+                        continue;
+                    }
+                };
+
+                if is_first && let Some(prev) = &last {
+                    let expected = prev.close_timestamp_ms();
+
+                    if candle.open_timestamp_ms < expected {
+                        yield Err(eyre::eyre!(
+                            "Candles overlap at file boundary {}: expected timestamp >= {expected}, found {}",
+                            path.display(),
+                            candle.open_timestamp_ms
+                        ));
+                        return;
+                    }
+
+                    if candle.open_timestamp_ms > expected {
+                        tracing::warn!(
+                            "Candle gap at file boundary {}: expected timestamp {expected}, found {}",
+                            path.display(),
+                            candle.open_timestamp_ms
+                        );
+                    }
+                }
+
+                is_first = false;
+                last = Some(candle.clone());
+                yield Ok(candle);
+            }
         }
     };
 
@@ -68,10 +172,15 @@ pub async fn csv_candle_data_stream(
 pub async fn csv_book_data_stream(
     path: impl AsRef<Path>,
 ) -> Result<impl Stream<Item = Result<BookData>>> {
-    let path = path.as_ref().to_path_buf();
-    let file = File::open(&path)
-        .await
-        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    csv_book_data_stream_with_unit(path, TimestampUnit::Millis).await
+}
+
+/// CSV 订单簿数据流，`timestamp` 列的单位为 `unit`，读取时换算为毫秒
+pub async fn csv_book_data_stream_with_unit(
+    path: impl AsRef<Path>,
+    unit: TimestampUnit,
+) -> Result<impl Stream<Item = Result<BookData>>> {
+    let file = open_csv_file(path.as_ref()).await?;
 
     let stream = stream! {
         let mut reader = csv_async::AsyncReaderBuilder::new()
@@ -81,7 +190,13 @@ pub async fn csv_book_data_stream(
         let mut records = reader.deserialize::<RawBookData>();
 
         while let Some(record) = records.next().await {
-            yield record.map(Into::into).map_err(Into::into)
+            yield record
+                .map(|raw| {
+                    let mut book = BookData::from(raw);
+                    book.timestamp = unit.to_millis(book.timestamp);
+                    book
+                })
+                .map_err(Into::into)
         }
     };
 
@@ -93,10 +208,16 @@ pub async fn csv_trade_data_stream_with_replay(
     path: impl AsRef<Path>,
     speed: f64, // 播放速度倍数，1.0 为实时，2.0 为 2x 速度
 ) -> Result<impl Stream<Item = Result<TradeData>>> {
-    let path = path.as_ref().to_path_buf();
-    let file = File::open(&path)
-        .await
-        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    csv_trade_data_stream_with_replay_clock(path, speed, SystemClock).await
+}
+
+/// 同 [`csv_trade_data_stream_with_replay`]，但使用指定的时钟等待
+pub async fn csv_trade_data_stream_with_replay_clock(
+    path: impl AsRef<Path>,
+    speed: f64,
+    clock: impl Clock,
+) -> Result<impl Stream<Item = Result<TradeData>>> {
+    let file = open_csv_file(path.as_ref()).await?;
 
     let stream = stream! {
         let mut reader = csv_async::AsyncReaderBuilder::new()
@@ -114,7 +235,7 @@ pub async fn csv_trade_data_stream_with_replay(
                         let delay_ms = trade.timestamp_ms.saturating_sub(last_ts);
                         if delay_ms > 0 {
                             let delay = Duration::from_millis((delay_ms as f64 / speed) as u64);
-                            sleep(delay).await;
+                            clock.sleep(delay).await;
                         }
                     }
                     last_timestamp = Some(trade.timestamp_ms);
@@ -128,6 +249,24 @@ pub async fn csv_trade_data_stream_with_replay(
     Ok(Box::pin(stream))
 }
 
+/// 打开 CSV 文件
+///
+/// 扩展名为 `.gz`（如 `candles.csv.gz`）时按 gzip 流式解压，其他文件原样读取。
+async fn open_csv_file(path: &Path) -> Result<Pin<Box<dyn AsyncRead + Send>>> {
+    let file = File::open(path)
+        .await
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+    {
+        Ok(Box::pin(GzipDecoder::new(BufReader::new(file))))
+    } else {
+        Ok(Box::pin(file))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct RawBookData {
     pub symbol: Symbol,
@@ -138,6 +277,13 @@ pub struct RawBookData {
     /// (价格, 数量)
     #[serde(with = "json_string")]
     pub asks: BookSide,
+    /// 缺省时视为完整快照
+    #[serde(default = "default_is_snapshot")]
+    pub is_snapshot: bool,
+}
+
+fn default_is_snapshot() -> bool {
+    true
 }
 
 mod json_string {
@@ -168,6 +314,7 @@ impl From<RawBookData> for BookData {
             timestamp: value.timestamp,
             bids: value.bids,
             asks: value.asks,
+            is_snapshot: value.is_snapshot,
         }
     }
 }
@@ -175,6 +322,7 @@ impl From<RawBookData> for BookData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::MockClock;
     use futures::StreamExt;
     use std::io::Write;
     use tempfile::NamedTempFile;
@@ -235,11 +383,165 @@ mod tests {
         assert_eq!(candle1.low, 49900.0);
         assert_eq!(candle1.close, 50050.0);
         assert_eq!(candle1.volume, 10.5);
+        assert_eq!(candle1.trade_count, 0);
+        // 没有成交额一列时以收盘价估算，估算值不能用来计算 VWAP
+        assert_eq!(candle1.quote_volume, 50050.0 * 10.5);
+        assert!(candle1.quote_volume_estimated);
+        assert_eq!(candle1.vwap(), None);
 
         let candle2 = stream.next().await.unwrap().unwrap();
         assert_eq!(candle2.symbol, "ETH-USDT");
     }
 
+    #[tokio::test]
+    async fn test_csv_candle_data_stream_with_unit() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(
+            [
+                r#"symbol,interval_sc,open_timestamp_ms,open,high,low,close,volume"#,
+                r#"BTC-USDT,60,1640000000,50000.0,50100.0,49900.0,50050.0,12.5"#,
+                r#"BTC-USDT,60,1640000060,50050.0,50200.0,50000.0,50150.0,8.0"#,
+            ]
+            .join("\n")
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let candles: Vec<_> = csv_candle_data_stream_with_unit(file.path(), TimestampUnit::Seconds)
+            .await
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(candles[0].open_timestamp_ms, 1640000000000);
+        assert_eq!(candles[1].open_timestamp_ms, 1640000060000);
+        assert_eq!(candles[0].interval_sc, 60);
+        assert_eq!(
+            candles[1].open_timestamp_ms - candles[0].open_timestamp_ms,
+            candles[0].interval_sc * 1000
+        );
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(
+            [
+                r#"timestamp_ms,symbol,price,quantity,side"#,
+                r#"1640000000123456,BTC-USDT,50000.5,0.1,Buy"#,
+            ]
+            .join("\n")
+            .as_bytes(),
+        )
+        .unwrap();
+        let mut trades = csv_trade_data_stream_with_unit(file.path(), TimestampUnit::Micros)
+            .await
+            .unwrap();
+        assert_eq!(
+            trades.next().await.unwrap().unwrap().timestamp_ms,
+            1640000000123
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_candle_data_stream_with_trade_count() {
+        let mut file = NamedTempFile::new().unwrap();
+
+        file.write_all(
+            [
+                r#"symbol,interval_sc,open_timestamp_ms,open,high,low,close,volume,trade_count,quote_volume"#,
+                r#"BTC-USDT,60,1640000000000,50000.0,50100.0,49900.0,50050.0,10.5,42,525000.0"#,
+            ]
+            .join("\n")
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut stream = csv_candle_data_stream(file.path()).await.unwrap();
+
+        let candle = stream.next().await.unwrap().unwrap();
+        assert_eq!(candle.volume, 10.5);
+        assert_eq!(candle.trade_count, 42);
+        assert_eq!(candle.quote_volume, 525000.0);
+        assert_eq!(candle.vwap(), Some(50000.0));
+    }
+
+    #[tokio::test]
+    async fn test_csv_candle_data_stream_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = r#"symbol,interval_sc,open_timestamp_ms,open,high,low,close,volume"#;
+
+        // 故意倒序创建，读取时应按文件名排序
+        std::fs::write(
+            dir.path().join("btc_2022-01.csv"),
+            [
+                header,
+                r#"BTC-USDT,60,1640000120000,50100.0,50200.0,50000.0,50150.0,3.0"#,
+                r#"BTC-USDT,60,1640000180000,50150.0,50300.0,50100.0,50250.0,4.0"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("btc_2021-12.csv"),
+            [
+                header,
+                r#"BTC-USDT,60,1640000000000,50000.0,50100.0,49900.0,50050.0,1.0"#,
+                r#"BTC-USDT,60,1640000060000,50050.0,50150.0,50000.0,50100.0,2.0"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let pattern = dir.path().join("btc_*.csv");
+        let candles: Vec<_> = csv_candle_data_stream_glob(pattern.to_str().unwrap())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        let timestamps: Vec<_> = candles
+            .into_iter()
+            .map(|c| c.unwrap().open_timestamp_ms)
+            .collect();
+        assert_eq!(
+            timestamps,
+            vec![1640000000000, 1640000060000, 1640000120000, 1640000180000]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_candle_data_stream_glob_overlap() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = r#"symbol,interval_sc,open_timestamp_ms,open,high,low,close,volume"#;
+
+        std::fs::write(
+            dir.path().join("a.csv"),
+            [
+                header,
+                r#"BTC-USDT,60,1640000060000,50000.0,50100.0,49900.0,50050.0,1.0"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("b.csv"),
+            [
+                header,
+                r#"BTC-USDT,60,1640000000000,50050.0,50150.0,50000.0,50100.0,2.0"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let pattern = dir.path().join("*.csv");
+        let mut stream = csv_candle_data_stream_glob(pattern.to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+
     #[tokio::test]
     async fn test_csv_book_data_stream() {
         let mut file = NamedTempFile::new().unwrap();
@@ -267,6 +569,8 @@ mod tests {
         assert_eq!(book1.bids[1], (49999.0, 2.0));
         assert_eq!(book1.asks[0], (50001.0, 1.5));
         assert_eq!(book1.asks[1], (50002.0, 3.0));
+        // 没有 is_snapshot 列时视为完整快照
+        assert!(book1.is_snapshot);
 
         let book2 = stream.next().await.unwrap().unwrap();
         assert_eq!(book2.symbol, "ETH-USDT");
@@ -301,6 +605,36 @@ mod tests {
         assert!(elapsed.as_millis() >= 80 && elapsed.as_millis() <= 200);
     }
 
+    #[tokio::test]
+    async fn test_csv_trade_data_stream_with_replay_mock_clock() {
+        let mut file = NamedTempFile::new().unwrap();
+
+        file.write_all(
+            [
+                r#"timestamp_ms,symbol,price,quantity,side"#,
+                r#"1640000000000,BTC-USDT,50000.0,0.1,Buy"#,
+                r#"1640000001000,BTC-USDT,50001.0,0.2,Sell"#,
+                r#"1640000061000,BTC-USDT,50002.0,0.3,Buy"#,
+            ]
+            .join("\n")
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let clock = MockClock::new(0);
+        let start = std::time::Instant::now();
+        let stream = csv_trade_data_stream_with_replay_clock(file.path(), 10.0, clock.clone())
+            .await
+            .unwrap();
+
+        let trades: Vec<_> = stream.collect().await;
+        assert_eq!(trades.len(), 3);
+
+        // (1000ms + 60000ms) / 10x = 6100ms 虚拟时间，且没有真正等待
+        assert_eq!(clock.now_ms(), 6100);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
     #[tokio::test]
     async fn test_empty_csv() {
         let mut file = NamedTempFile::new().unwrap();
@@ -331,4 +665,67 @@ mod tests {
         let result = stream.next().await.unwrap();
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_gzip_csv() {
+        use async_compression::tokio::write::GzipEncoder;
+        use tokio::io::AsyncWriteExt;
+
+        async fn write_gzip(path: &Path, content: &str) {
+            let mut encoder = GzipEncoder::new(File::create(path).await.unwrap());
+            encoder.write_all(content.as_bytes()).await.unwrap();
+            encoder.shutdown().await.unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("candles.csv.gz");
+        write_gzip(
+            &path,
+            "open_timestamp_ms,symbol,interval_sc,open,high,low,close,volume\n\
+             0,BTC-USDT,60,10,12,9,11,2\n\
+             60000,BTC-USDT,60,11,13,10,12,3\n",
+        )
+        .await;
+        let candles: Vec<_> = csv_candle_data_stream(&path)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[1].open_timestamp_ms, 60_000);
+        assert_eq!(candles[1].close, 12.0);
+
+        let path = dir.path().join("trades.csv.gz");
+        write_gzip(
+            &path,
+            "timestamp_ms,symbol,price,quantity,side\n1,BTC-USDT,100.5,0.1,Buy\n",
+        )
+        .await;
+        let trades: Vec<_> = csv_trade_data_stream(&path)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].price, 100.5);
+
+        let path = dir.path().join("books.csv.gz");
+        write_gzip(
+            &path,
+            "symbol,timestamp,bids,asks\nBTC-USDT,1,\"[[100.0,1.0]]\",\"[[101.0,2.0]]\"\n",
+        )
+        .await;
+        let books: Vec<_> = csv_book_data_stream(&path)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].best_ask(), Some((101.0, 2.0)));
+        assert!(books[0].is_snapshot);
+    }
 }
@@ -0,0 +1,169 @@
+use async_stream::stream;
+use ephemera_shared::{Exchange, Symbol, TimestampMs, TradeData};
+use eyre::Result;
+use futures::{Stream, StreamExt, stream::select_all};
+use std::collections::{HashMap, HashSet};
+
+/// 两个交易所之间同一交易对的价格偏离
+#[derive(Debug, Clone, PartialEq)]
+pub struct DivergenceEvent {
+    /// 规范化后的交易对，见 [`canonical_symbol`]
+    pub symbol: Symbol,
+    /// 触发本次事件（最新成交）的交易所
+    pub exchange_a: Exchange,
+    pub price_a: f64,
+    pub exchange_b: Exchange,
+    pub price_b: f64,
+    /// `(price_a - price_b) / price_b * 10000`，带符号
+    pub bps: f64,
+    pub timestamp_ms: TimestampMs,
+}
+
+/// 规范化交易对名称，使不同交易所的同一交易对可以比较
+///
+/// 去掉分隔符（`-`、`/`、`_`）并转为大写，例如 OKX 的 `BTC-USDT` 和 Binance 的
+/// `btcusdt` 都会变成 `BTCUSDT`。
+pub fn canonical_symbol(symbol: &str) -> Symbol {
+    symbol
+        .chars()
+        .filter(|c| !matches!(c, '-' | '/' | '_'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<String>()
+        .into()
+}
+
+/// 跨交易所价格偏离告警流
+///
+/// 合并多个交易所的成交流，按规范化后的交易对记录每个交易所的最新价格。每笔成交都会与
+/// 其他交易所的最新价格比较，偏离超过 `threshold_bps` 时产生一个 [`DivergenceEvent`]。
+///
+/// 告警是边沿触发的: 同一对交易所在持续偏离期间只告警一次，回到阈值以内后才会再次告警。
+/// 某个交易所的流出错时，错误会原样传出，其他交易所的流不受影响。
+pub fn price_divergence_stream<E, S>(
+    streams: impl IntoIterator<Item = (E, S)>,
+    threshold_bps: f64,
+) -> impl Stream<Item = Result<DivergenceEvent>> + Send
+where
+    E: Into<Exchange>,
+    S: Stream<Item = Result<TradeData>> + Send + 'static,
+{
+    let merged = select_all(streams.into_iter().map(|(exchange, stream)| {
+        let exchange: Exchange = exchange.into();
+        stream
+            .map(move |trade| trade.map(|trade| (exchange.clone(), trade)))
+            .boxed()
+    }));
+
+    stream! {
+        let mut latest: HashMap<Symbol, HashMap<Exchange, f64>> = HashMap::new();
+        // (交易对, 交易所, 交易所)，两个交易所按字典序排列
+        let mut diverged: HashSet<(Symbol, Exchange, Exchange)> = HashSet::new();
+
+        for await item in merged {
+            let (exchange, trade) = match item {
+                Ok(item) => item,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
+            };
+
+            let symbol = canonical_symbol(&trade.symbol);
+            let prices = latest.entry(symbol.clone()).or_default();
+            prices.insert(exchange.clone(), trade.price);
+
+            for (other, &other_price) in prices.iter() {
+                if *other == exchange || other_price <= 0.0 {
+                    continue;
+                }
+
+                let bps = (trade.price - other_price) / other_price * 10_000.0;
+                let key = if exchange < *other {
+                    (symbol.clone(), exchange.clone(), other.clone())
+                } else {
+                    (symbol.clone(), other.clone(), exchange.clone())
+                };
+
+                if bps.abs() <= threshold_bps {
+                    diverged.remove(&key);
+                } else if diverged.insert(key) {
+                    yield Ok(DivergenceEvent {
+                        symbol: symbol.clone(),
+                        exchange_a: exchange.clone(),
+                        price_a: trade.price,
+                        exchange_b: other.clone(),
+                        price_b: other_price,
+                        bps,
+                        timestamp_ms: trade.timestamp_ms,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ephemera_shared::Side;
+    use futures::{FutureExt, channel::mpsc};
+
+    fn trade(symbol: &str, timestamp_ms: TimestampMs, price: f64) -> Result<TradeData> {
+        Ok(TradeData {
+            symbol: symbol.into(),
+            timestamp_ms,
+            price,
+            quantity: 1.0,
+            side: Side::Buy,
+        })
+    }
+
+    #[test]
+    fn test_canonical_symbol() {
+        assert_eq!(canonical_symbol("BTC-USDT"), "BTCUSDT");
+        assert_eq!(canonical_symbol("btcusdt"), "BTCUSDT");
+        assert_eq!(canonical_symbol("ETH/USDT"), "ETHUSDT");
+    }
+
+    #[test]
+    fn test_price_divergence_stream() {
+        let (okx, okx_rx) = mpsc::unbounded();
+        let (binance, binance_rx) = mpsc::unbounded();
+        let mut events = Box::pin(price_divergence_stream(
+            [("okx", okx_rx), ("binance", binance_rx)],
+            10.0,
+        ));
+        let mut next = || events.next().now_or_never().flatten().map(Result::unwrap);
+
+        // 5 bps，未超过阈值
+        okx.unbounded_send(trade("BTC-USDT", 1, 100.0)).unwrap();
+        binance.unbounded_send(trade("btcusdt", 2, 100.05)).unwrap();
+        assert_eq!(next(), None);
+
+        // 20 bps，超过阈值
+        binance.unbounded_send(trade("btcusdt", 3, 100.2)).unwrap();
+        let event = next().unwrap();
+        assert_eq!(event.symbol, "BTCUSDT");
+        assert_eq!(event.exchange_a, "binance");
+        assert_eq!(event.exchange_b, "okx");
+        assert_eq!(event.timestamp_ms, 3);
+        assert!((event.bps - 20.0).abs() < 1e-9);
+
+        // 持续偏离不重复告警，其他交易对互不影响
+        binance.unbounded_send(trade("btcusdt", 4, 100.3)).unwrap();
+        binance.unbounded_send(trade("ethusdt", 5, 4000.0)).unwrap();
+        assert_eq!(next(), None);
+
+        // 回到阈值以内后重新触发
+        okx.unbounded_send(trade("BTC-USDT", 6, 100.25)).unwrap();
+        assert_eq!(next(), None);
+        okx.unbounded_send(trade("BTC-USDT", 7, 99.9)).unwrap();
+        let event = next().unwrap();
+        assert_eq!(event.exchange_a, "okx");
+        assert_eq!((event.price_a, event.price_b), (99.9, 100.3));
+        assert!(event.bps < -10.0);
+
+        drop((okx, binance));
+        assert!(events.next().now_or_never().unwrap().is_none());
+    }
+}
@@ -0,0 +1,376 @@
+use async_stream::stream;
+use ephemera_shared::*;
+use eyre::{Context, Result};
+use futures::{FutureExt, Stream, StreamExt};
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter},
+};
+
+/// JSONL 交易数据流
+///
+/// 每行一个 [`TradeData`] 的 JSON 对象，空行会被跳过
+pub async fn jsonl_trade_data_stream(
+    path: impl AsRef<Path>,
+) -> Result<impl Stream<Item = Result<TradeData>>> {
+    jsonl_data_stream(path).await
+}
+
+/// JSONL K线数据流
+///
+/// 每行一个 [`CandleData`] 的 JSON 对象，空行会被跳过。缺少 `quote_volume` 时以 `close * volume` 估算，
+/// 此时 [`CandleData::vwap`] 返回 `None`
+pub async fn jsonl_candle_data_stream(
+    path: impl AsRef<Path>,
+) -> Result<impl Stream<Item = Result<CandleData>>> {
+    Ok(jsonl_data_stream(path)
+        .await?
+        .map(|candle: Result<CandleData>| candle.map(CandleData::or_estimated_quote_volume)))
+}
+
+/// JSONL 订单簿数据流
+///
+/// 每行一个 [`BookData`] 的 JSON 对象，bids/asks 为 `[[price, size], ...]`
+pub async fn jsonl_book_data_stream(
+    path: impl AsRef<Path>,
+) -> Result<impl Stream<Item = Result<BookData>>> {
+    jsonl_data_stream(path).await
+}
+
+pub(crate) async fn jsonl_data_stream<T>(
+    path: impl AsRef<Path>,
+) -> Result<impl Stream<Item = Result<T>>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let path = path.as_ref().to_path_buf();
+    let file = File::open(&path)
+        .await
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+
+    let stream = stream! {
+        let mut lines = BufReader::new(file).lines();
+        let mut line_no = 0;
+
+        loop {
+            line_no += 1;
+
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    yield Err(e.into());
+                    break;
+                }
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut bytes = line.into_bytes();
+            yield simd_json::from_slice::<T>(&mut bytes)
+                .with_context(|| format!("Invalid JSON at {}:{}", path.display(), line_no));
+        }
+    };
+
+    Ok(Box::pin(stream))
+}
+
+/// JSONL 写入器，每次 [`write`](Self::write) 写入一行
+///
+/// 内部带缓冲，写完后需调用 [`flush`](Self::flush)
+pub struct JsonlWriter<W> {
+    writer: BufWriter<W>,
+}
+
+impl JsonlWriter<File> {
+    /// 创建（或截断）文件并写入
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .await
+            .with_context(|| format!("Failed to create file: {}", path.display()))?;
+
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> JsonlWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+        }
+    }
+
+    pub async fn write<T: Serialize>(&mut self, data: &T) -> Result<()> {
+        let mut line = simd_json::to_vec(data)?;
+        line.push(b'\n');
+        self.writer.write_all(&line).await?;
+
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
+/// [`pipe_stream_json`] 持续有数据时，最长隔多久刷新一次
+const PIPE_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 将数据流逐条以 JSON 行写入 `writer`（文件、Unix socket、TCP 连接等），返回写入的条数
+///
+/// 每条数据写完才拉取下一条，写入端变慢时自然对上游形成背压。写入经过缓冲，
+/// 在上游暂时没有新数据或距上次刷新超过 100ms 时刷新，兼顾吞吐和下游看到数据的延迟。
+/// 上游出错时刷新已写入的数据并返回该错误；上游结束时刷新并返回。
+pub async fn pipe_stream_json<S, T, W>(stream: S, writer: W) -> Result<u64>
+where
+    S: Stream<Item = Result<T>>,
+    T: Serialize,
+    W: AsyncWrite + Unpin,
+{
+    let mut writer = JsonlWriter::new(writer);
+    let mut written = 0;
+    let mut last_flush = Instant::now();
+
+    futures::pin_mut!(stream);
+    loop {
+        // 没有立即可用的数据时先刷新，再等待上游
+        let item = match stream.next().now_or_never() {
+            Some(item) => item,
+            None => {
+                writer.flush().await?;
+                last_flush = Instant::now();
+                stream.next().await
+            }
+        };
+
+        let data = match item {
+            Some(Ok(data)) => data,
+            Some(Err(e)) => {
+                writer.flush().await?;
+                return Err(e);
+            }
+            None => break,
+        };
+
+        writer.write(&data).await?;
+        written += 1;
+
+        if last_flush.elapsed() >= PIPE_FLUSH_INTERVAL {
+            writer.flush().await?;
+            last_flush = Instant::now();
+        }
+    }
+
+    writer.flush().await?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{StreamExt, TryStreamExt};
+    use smallvec::smallvec;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    async fn write_all<T: Serialize>(path: &Path, data: &[T]) {
+        let mut writer = JsonlWriter::create(path).await.unwrap();
+        for item in data {
+            writer.write(item).await.unwrap();
+        }
+        writer.flush().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_trade_round_trip() {
+        let file = NamedTempFile::new().unwrap();
+        let trades = vec![
+            TradeData {
+                symbol: "BTC-USDT".into(),
+                timestamp_ms: 1640000000000,
+                price: 50000.5,
+                quantity: 0.1,
+                side: Side::Buy,
+            },
+            TradeData {
+                symbol: "ETH-USDT".into(),
+                timestamp_ms: 1640000001000,
+                price: 4000.0,
+                quantity: 1.0,
+                side: Side::Sell,
+            },
+        ];
+
+        write_all(file.path(), &trades).await;
+
+        let read: Vec<_> = jsonl_trade_data_stream(file.path())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(read, trades);
+    }
+
+    #[tokio::test]
+    async fn test_pipe_stream_json() {
+        let candles = ephemera_shared::testing::random_walk_candles("BTC-USDT", 60, 50, 7);
+        let mut buf = Vec::new();
+
+        // 中途有一段时间没有数据，期间已写入的数据应当被刷新
+        let stream = futures::stream::iter(candles[..25].to_vec())
+            .chain(futures::stream::once(async {
+                tokio::task::yield_now().await;
+                candles[25].clone()
+            }))
+            .chain(futures::stream::iter(candles[26..].to_vec()))
+            .map(Ok);
+        let written = pipe_stream_json(stream, &mut buf).await.unwrap();
+        assert_eq!(written, 50);
+
+        let read: Vec<CandleData> = buf
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| simd_json::from_slice(&mut line.to_vec()).unwrap())
+            .collect();
+        assert_eq!(read, candles);
+
+        // 上游出错时，之前的数据仍然写出
+        let mut buf = Vec::new();
+        let stream =
+            futures::stream::iter([Ok(candles[0].clone()), Err(eyre::eyre!("disconnected"))]);
+        let err = pipe_stream_json(stream, &mut buf).await.unwrap_err();
+        assert!(err.to_string().contains("disconnected"));
+        assert_eq!(buf.iter().filter(|&&b| b == b'\n').count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_candle_round_trip() {
+        let file = NamedTempFile::new().unwrap();
+        let candles = vec![
+            CandleData {
+                symbol: "BTC-USDT".into(),
+                interval_sc: 60,
+                open_timestamp_ms: 1640000000000,
+                open: 50000.0,
+                high: 50100.0,
+                low: 49900.0,
+                close: 50050.0,
+                volume: 12.5,
+                trade_count: 0,
+                quote_volume: 625625.0,
+                quote_volume_estimated: false,
+            },
+            CandleData {
+                symbol: "BTC-USDT".into(),
+                interval_sc: 60,
+                open_timestamp_ms: 1640000060000,
+                open: 50050.0,
+                high: 50200.0,
+                low: 50000.0,
+                close: 50150.0,
+                volume: 8.0,
+                trade_count: 0,
+                quote_volume: 401200.0,
+                quote_volume_estimated: false,
+            },
+        ];
+
+        write_all(file.path(), &candles).await;
+
+        let read: Vec<_> = jsonl_candle_data_stream(file.path())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(read, candles);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_candle_without_quote_volume() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(
+            br#"{"symbol":"BTC-USDT","interval_sc":60,"open_timestamp_ms":0,"open":10.0,"high":12.0,"low":9.0,"close":11.0,"volume":2.0}"#,
+        )
+        .unwrap();
+
+        let read: Vec<_> = jsonl_candle_data_stream(file.path())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(read[0].quote_volume, 22.0);
+        assert_eq!(read[0].vwap(), None);
+
+        // 写出后再读取，估算标记随之保留
+        write_all(file.path(), &read).await;
+        let reread: Vec<_> = jsonl_candle_data_stream(file.path())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(reread, read);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_book_round_trip() {
+        let file = NamedTempFile::new().unwrap();
+        let books = vec![BookData {
+            symbol: "BTC-USDT".into(),
+            timestamp: 1640000000000,
+            bids: smallvec![(50000.0, 1.5), (49999.5, 2.0)],
+            asks: smallvec![(50000.5, 0.8), (50001.0, 3.2)],
+            is_snapshot: true,
+        }];
+
+        write_all(file.path(), &books).await;
+
+        let read: Vec<_> = jsonl_book_data_stream(file.path())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(read, books);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_invalid_line() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(
+            [
+                r#"{"symbol":"BTC-USDT","timestamp_ms":1640000000000,"price":50000.5,"quantity":0.1,"side":"Buy"}"#,
+                "",
+                r#"{"symbol":"BTC-USDT","timestamp_ms":"#,
+            ]
+            .join("\n")
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut stream = jsonl_trade_data_stream(file.path()).await.unwrap();
+
+        assert!(stream.next().await.unwrap().is_ok());
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains(":3"));
+        assert!(stream.next().await.is_none());
+    }
+}
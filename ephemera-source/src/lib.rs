@@ -1,5 +1,17 @@
 pub mod binance;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod book_log;
+pub mod clock;
+pub mod conflate;
 pub mod csv;
+pub mod divergence;
+pub mod jsonl;
+pub mod merged;
+pub mod metrics;
 pub mod okx;
 pub mod router;
+pub mod synthetic;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod utils;
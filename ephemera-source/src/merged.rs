@@ -0,0 +1,247 @@
+use crate::router::RouterKey;
+use async_stream::stream;
+use dashmap::{DashMap, mapref::entry::Entry};
+use ephemera_shared::{Exchange, Symbol};
+use eyre::Result;
+use futures::{
+    Stream, StreamExt,
+    future::BoxFuture,
+    stream::{AbortHandle, Abortable, BoxStream, SelectAll},
+};
+use std::{future::Future, sync::Arc};
+
+type StreamFactory<T> = Arc<
+    dyn Fn(RouterKey) -> BoxFuture<'static, Result<BoxStream<'static, Result<T>>>> + Send + Sync,
+>;
+
+/// 可在运行期间增删交易对的合并数据流
+///
+/// 每个 `(exchange, symbol)` 由 `factory` 建立一条独立的数据流，所有数据流合并为
+/// [`new`](Self::new) 返回的一条流。[`add_symbol`](Self::add_symbol) 新建订阅并加入合并流，
+/// [`remove_symbol`](Self::remove_symbol) 结束对应的数据流，其他交易对不受影响，
+/// 适合需要全天轮换关注列表的扫描器。
+///
+/// 所有控制器句柄都被丢弃后，合并流在剩余的数据流全部结束时结束。
+pub struct MergedStreamController<T> {
+    factory: StreamFactory<T>,
+    active: Arc<DashMap<RouterKey, AbortHandle>>,
+    tx: flume::Sender<BoxStream<'static, Result<T>>>,
+}
+
+impl<T> Clone for MergedStreamController<T> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            active: self.active.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T: Send + 'static> MergedStreamController<T> {
+    /// 返回控制器和合并后的数据流，初始时不订阅任何交易对
+    pub fn new<F, Fut, S>(factory: F) -> (Self, impl Stream<Item = Result<T>> + Send)
+    where
+        F: Fn(RouterKey) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S>> + Send + 'static,
+        S: Stream<Item = Result<T>> + Send + 'static,
+    {
+        let (tx, rx) = flume::unbounded::<BoxStream<'static, Result<T>>>();
+
+        let controller = Self {
+            factory: Arc::new(move |key| {
+                let fut = factory(key);
+                Box::pin(async move { Ok(fut.await?.boxed()) })
+            }),
+            active: Arc::new(DashMap::new()),
+            tx,
+        };
+
+        let merged = stream! {
+            let mut merged = SelectAll::new();
+
+            loop {
+                let item = tokio::select! {
+                    new = rx.recv_async() => match new {
+                        Ok(stream) => {
+                            merged.push(stream);
+                            continue;
+                        }
+                        // 所有控制器都已丢弃，不会再有新的数据流
+                        Err(_) => break,
+                    },
+                    Some(item) = merged.next(), if !merged.is_empty() => item,
+                };
+
+                yield item;
+            }
+
+            while let Some(item) = merged.next().await {
+                yield item;
+            }
+        };
+
+        (controller, merged)
+    }
+
+    /// 订阅新的交易对并加入合并流
+    ///
+    /// 已经订阅（或正在订阅）时不做任何操作并返回 `false`。建立数据流之前先占用该交易对，
+    /// 并发调用只会建立一条数据流；建立失败时释放占用并返回错误，之后可以重试。
+    pub async fn add_symbol(
+        &self,
+        exchange: impl Into<Exchange>,
+        symbol: impl Into<Symbol>,
+    ) -> Result<bool> {
+        let key = RouterKey::new(exchange, symbol);
+        let (handle, registration) = AbortHandle::new_pair();
+        match self.active.entry(key.clone()) {
+            Entry::Occupied(_) => return Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(handle.clone());
+            }
+        }
+
+        let stream = match (self.factory)(key.clone()).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                // 期间被 `remove_symbol` 移除后，同一个键可能已经被重新订阅，只释放自己的占用
+                handle.abort();
+                self.active.remove_if(&key, |_, handle| handle.is_aborted());
+                return Err(e);
+            }
+        };
+        // 合并流已被丢弃时没有人接收数据，忽略即可。期间被移除时数据流立即结束
+        let _ = self.tx.send(Abortable::new(stream, registration).boxed());
+
+        Ok(true)
+    }
+
+    /// 结束交易对的数据流并将其移出合并流
+    ///
+    /// 未订阅时返回 `false`
+    pub fn remove_symbol(&self, exchange: impl Into<Exchange>, symbol: impl Into<Symbol>) -> bool {
+        match self.active.remove(&RouterKey::new(exchange, symbol)) {
+            Some((_, handle)) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 当前订阅（包括正在建立数据流）的交易对
+    ///
+    /// 自行结束（例如连接断开）的数据流仍会保留在列表中，直到被 [`remove_symbol`](Self::remove_symbol) 移除
+    pub fn symbols(&self) -> Vec<RouterKey> {
+        self.active
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ephemera_shared::{Side, TradeData};
+    use futures::stream;
+
+    fn trade(symbol: Symbol, price: f64) -> TradeData {
+        TradeData {
+            symbol,
+            timestamp_ms: 1,
+            price,
+            quantity: 1.0,
+            side: Side::Buy,
+        }
+    }
+
+    /// 每个交易对推送一笔成交之后保持连接
+    fn controller() -> (
+        MergedStreamController<TradeData>,
+        impl Stream<Item = Result<TradeData>>,
+    ) {
+        MergedStreamController::new(|key: RouterKey| async move {
+            Ok(stream::iter([Ok(trade(key.symbol, 100.0))]).chain(stream::pending()))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_merged_stream_add_symbol_mid_stream() {
+        let (controller, merged) = controller();
+        futures::pin_mut!(merged);
+
+        assert!(controller.add_symbol("okx", "BTC-USDT").await.unwrap());
+        assert_eq!(merged.next().await.unwrap().unwrap().symbol, "BTC-USDT");
+
+        // 运行中加入新的交易对
+        assert!(controller.add_symbol("okx", "ETH-USDT").await.unwrap());
+        assert!(!controller.add_symbol("okx", "ETH-USDT").await.unwrap());
+        assert_eq!(merged.next().await.unwrap().unwrap().symbol, "ETH-USDT");
+        assert_eq!(controller.symbols().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_merged_stream_remove_symbol() {
+        let (controller, merged) = controller();
+        futures::pin_mut!(merged);
+
+        controller.add_symbol("okx", "BTC-USDT").await.unwrap();
+        controller.add_symbol("binance", "btcusdt").await.unwrap();
+        merged.next().await.unwrap().unwrap();
+        merged.next().await.unwrap().unwrap();
+
+        assert!(controller.remove_symbol("okx", "BTC-USDT"));
+        assert!(!controller.remove_symbol("okx", "BTC-USDT"));
+        assert_eq!(
+            controller.symbols(),
+            vec![RouterKey::new("binance", "btcusdt")]
+        );
+
+        // 全部移除并丢弃控制器后合并流结束
+        assert!(controller.remove_symbol("binance", "btcusdt"));
+        drop(controller);
+        assert!(merged.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_merged_stream_concurrent_add_symbol() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // 建立数据流需要让出一次执行权，第一次建立失败
+        let connections = Arc::new(AtomicUsize::new(0));
+        let (controller, merged) = MergedStreamController::new({
+            let connections = connections.clone();
+            move |key: RouterKey| {
+                let n = connections.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    tokio::task::yield_now().await;
+                    eyre::ensure!(n > 0, "connection refused");
+                    Ok(stream::iter([Ok(trade(key.symbol, 100.0))]).chain(stream::pending()))
+                }
+            }
+        });
+        futures::pin_mut!(merged);
+
+        // 失败时释放占用，等待中的重复订阅不会建立第二条数据流
+        let (first, second) = futures::join!(
+            controller.add_symbol("okx", "BTC-USDT"),
+            controller.add_symbol("okx", "BTC-USDT"),
+        );
+        assert!(first.is_err());
+        assert!(!second.unwrap());
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert!(controller.symbols().is_empty());
+
+        let (first, second) = futures::join!(
+            controller.add_symbol("okx", "BTC-USDT"),
+            controller.add_symbol("okx", "BTC-USDT"),
+        );
+        assert!(first.unwrap());
+        assert!(!second.unwrap());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(merged.next().await.unwrap().unwrap().symbol, "BTC-USDT");
+        assert_eq!(controller.symbols().len(), 1);
+    }
+}
@@ -0,0 +1,315 @@
+//! Prometheus 指标
+//!
+//! 启用 `metrics` feature 后，数据流、下单等关键路径会更新这里的指标，并可通过
+//! [`serve_metrics`] 在 `/metrics` 暴露给 Prometheus 抓取。未启用时所有函数都是空操作，
+//! 调用方不需要写条件编译。
+//!
+//! 热路径上应先取得 [`Counter`] 句柄再反复 `inc`，避免每次按标签查找。
+
+#[cfg(feature = "metrics")]
+pub use enabled::*;
+
+#[cfg(not(feature = "metrics"))]
+pub use disabled::*;
+
+#[cfg(not(feature = "metrics"))]
+mod disabled {
+    /// 计数器句柄，未启用 `metrics` 时不做任何事
+    #[derive(Debug, Clone, Copy)]
+    pub struct Counter;
+
+    impl Counter {
+        #[inline(always)]
+        pub fn inc(&self) {}
+    }
+
+    #[inline(always)]
+    pub fn ws_connections(_exchange: &str) -> Counter {
+        Counter
+    }
+
+    #[inline(always)]
+    pub fn ws_messages_received(_exchange: &str, _channel: &str) -> Counter {
+        Counter
+    }
+
+    #[inline(always)]
+    pub fn orders_submitted(_exchange: &str) -> Counter {
+        Counter
+    }
+
+    #[inline(always)]
+    pub fn orders_rejected(_exchange: &str) -> Counter {
+        Counter
+    }
+
+    #[inline(always)]
+    pub fn set_equity(_equity: f64) {}
+
+    #[inline(always)]
+    pub fn set_open_positions(_count: usize) {}
+}
+
+#[cfg(feature = "metrics")]
+mod enabled {
+    use ephemera_xdp::reactor::{ReactorMetricsSnapshot, XdpReactor};
+    use eyre::Result;
+    use prometheus::{Gauge, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+    use std::sync::{LazyLock, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream, ToSocketAddrs},
+    };
+
+    /// 计数器句柄
+    #[derive(Debug, Clone)]
+    pub struct Counter(IntCounter);
+
+    impl Counter {
+        #[inline]
+        pub fn inc(&self) {
+            self.0.inc();
+        }
+    }
+
+    struct Metrics {
+        registry: Registry,
+        ws_connections: IntCounterVec,
+        ws_messages_received: IntCounterVec,
+        orders_submitted: IntCounterVec,
+        orders_rejected: IntCounterVec,
+        equity: Gauge,
+        open_positions: IntGauge,
+        xdp_polls: IntCounter,
+        xdp_flushes: IntCounter,
+        xdp_busy_iterations: IntCounter,
+        xdp_waits: IntCounter,
+        /// 串行化 reactor 统计的同步，否则并发抓取会把同一段差额加两次
+        xdp_sync: Mutex<()>,
+    }
+
+    static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+        let registry = Registry::new_custom(Some("ephemera".into()), None).unwrap();
+
+        let counter_vec = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let counter = |name: &str, help: &str| {
+            let counter = IntCounter::new(name, help).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+
+        let equity = Gauge::new("equity", "Current total equity").unwrap();
+        registry.register(Box::new(equity.clone())).unwrap();
+        let open_positions = IntGauge::new("open_positions", "Open positions").unwrap();
+        registry.register(Box::new(open_positions.clone())).unwrap();
+
+        Metrics {
+            ws_connections: counter_vec(
+                "ws_connections_total",
+                "WebSocket connections established, a burst means a reconnect storm",
+                &["exchange"],
+            ),
+            ws_messages_received: counter_vec(
+                "ws_messages_received_total",
+                "Data messages received per stream",
+                &["exchange", "channel"],
+            ),
+            orders_submitted: counter_vec(
+                "orders_submitted_total",
+                "Orders sent to the exchange",
+                &["exchange"],
+            ),
+            orders_rejected: counter_vec(
+                "orders_rejected_total",
+                "Orders rejected by the exchange",
+                &["exchange"],
+            ),
+            equity,
+            open_positions,
+            xdp_polls: counter("xdp_reactor_polls_total", "XDP reactor interface polls"),
+            xdp_flushes: counter("xdp_reactor_flushes_total", "XDP reactor device flushes"),
+            xdp_busy_iterations: counter(
+                "xdp_reactor_busy_iterations_total",
+                "XDP reactor loop iterations that polled again immediately",
+            ),
+            xdp_waits: counter("xdp_reactor_waits_total", "XDP reactor sleeps in phy::wait"),
+            xdp_sync: Mutex::new(()),
+            registry,
+        }
+    });
+
+    pub fn ws_connections(exchange: &str) -> Counter {
+        Counter(METRICS.ws_connections.with_label_values(&[exchange]))
+    }
+
+    pub fn ws_messages_received(exchange: &str, channel: &str) -> Counter {
+        Counter(
+            METRICS
+                .ws_messages_received
+                .with_label_values(&[exchange, channel]),
+        )
+    }
+
+    pub fn orders_submitted(exchange: &str) -> Counter {
+        Counter(METRICS.orders_submitted.with_label_values(&[exchange]))
+    }
+
+    pub fn orders_rejected(exchange: &str) -> Counter {
+        Counter(METRICS.orders_rejected.with_label_values(&[exchange]))
+    }
+
+    pub fn set_equity(equity: f64) {
+        METRICS.equity.set(equity);
+    }
+
+    pub fn set_open_positions(count: usize) {
+        METRICS.open_positions.set(count as i64);
+    }
+
+    /// 以 Prometheus 文本格式输出所有指标
+    ///
+    /// 全局 XDP reactor 已经创建时，顺带同步它的 poll 统计。
+    pub fn render() -> String {
+        if let Some(reactor) = XdpReactor::try_global() {
+            observe_xdp_reactor(&reactor.metrics());
+        }
+
+        let mut buf = String::new();
+        if let Err(e) = TextEncoder::new().encode_utf8(&METRICS.registry.gather(), &mut buf) {
+            tracing::error!("编码 metrics 失败: {:?}", e);
+        }
+        buf
+    }
+
+    fn observe_xdp_reactor(snapshot: &ReactorMetricsSnapshot) {
+        // reactor 自己维护累计值，这里只补上差额。读取和累加必须在同一把锁内完成
+        let _guard = METRICS
+            .xdp_sync
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let sync = |counter: &IntCounter, total: u64| {
+            counter.inc_by(total.saturating_sub(counter.get()));
+        };
+
+        sync(&METRICS.xdp_polls, snapshot.polls);
+        sync(&METRICS.xdp_flushes, snapshot.flushes);
+        sync(&METRICS.xdp_busy_iterations, snapshot.busy_iterations);
+        sync(&METRICS.xdp_waits, snapshot.waits);
+    }
+
+    /// 监听 `addr`，在 `GET /metrics` 上返回 [`render`] 的结果
+    pub async fn serve_metrics(addr: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+        tracing::info!("metrics 监听于 http://{}/metrics", listener.local_addr()?);
+
+        serve_metrics_on(listener).await
+    }
+
+    /// 同 [`serve_metrics`]，使用已经绑定的 listener
+    pub async fn serve_metrics_on(listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream).await {
+                    tracing::debug!("metrics 请求处理失败: {:?}", e);
+                }
+            });
+        }
+    }
+
+    /// 只处理单个请求，响应后关闭连接。抓取请求很小，读一次就足够拿到请求行
+    async fn handle_connection(mut stream: TcpStream) -> Result<()> {
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await?;
+
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let mut request_line = request
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+
+        let (status, body) = match (request_line.next(), request_line.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", render()),
+            _ => ("404 Not Found", String::new()),
+        };
+
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        async fn get(listener_addr: std::net::SocketAddr, path: &str) -> String {
+            let mut stream = TcpStream::connect(listener_addr).await.unwrap();
+            stream
+                .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        }
+
+        #[tokio::test]
+        async fn test_serve_metrics() {
+            ws_messages_received("okx", "trades").inc();
+            orders_rejected("okx").inc();
+            set_open_positions(2);
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(serve_metrics_on(listener));
+
+            let response = get(addr, "/metrics").await;
+            assert!(response.starts_with("HTTP/1.1 200 OK"));
+            assert!(response.contains(
+                r#"ephemera_ws_messages_received_total{channel="trades",exchange="okx"}"#
+            ));
+            assert!(response.contains(r#"ephemera_orders_rejected_total{exchange="okx"}"#));
+            assert!(response.contains("ephemera_open_positions 2"));
+
+            let response = get(addr, "/").await;
+            assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        }
+
+        #[test]
+        fn test_observe_xdp_reactor_concurrently() {
+            let snapshot = ReactorMetricsSnapshot {
+                uptime: std::time::Duration::from_secs(1),
+                polls: 1_000_000,
+                flushes: 500_000,
+                busy_iterations: 0,
+                waits: 0,
+            };
+
+            // 多个抓取同时同步同一份快照，计数器只能追平而不能超过累计值
+            std::thread::scope(|scope| {
+                for _ in 0..8 {
+                    scope.spawn(|| {
+                        for _ in 0..1000 {
+                            observe_xdp_reactor(&snapshot);
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(METRICS.xdp_polls.get(), snapshot.polls);
+            assert_eq!(METRICS.xdp_flushes.get(), snapshot.flushes);
+        }
+    }
+}
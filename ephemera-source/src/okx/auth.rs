@@ -5,11 +5,11 @@ use chrono::Utc;
 use eyre::{Context, Result};
 use futures::Stream;
 use hmac::{Hmac, Mac};
-use reqwest::{Client, Method};
+use reqwest::{Client, Method, RequestBuilder};
 use sha2::Sha256;
 use std::pin::Pin;
 
-use crate::okx::{OKX_REST_API_BASE, model::HttpResponse};
+use crate::okx::{OKX_REST_API_BASE, OkxEnvironment, model::HttpResponse};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -41,6 +41,15 @@ impl OkxAuth {
         self
     }
 
+    /// 由 `simulated` 决定的服务环境，数据流与下单都应使用它
+    pub fn environment(&self) -> OkxEnvironment {
+        if self.simulated {
+            OkxEnvironment::Demo
+        } else {
+            OkxEnvironment::Live
+        }
+    }
+
     /// 生成签名
     fn sign(&self, timestamp: &str, method: &str, request_path: &str, body: &str) -> String {
         let prehash = format!("{}{}{}{}", timestamp, method, request_path, body);
@@ -66,7 +75,30 @@ pub(super) async fn signed_request<T: serde::de::DeserializeOwned>(
     endpoint: &str,
     body: &str,
 ) -> Result<T> {
-    let client = Client::new();
+    let response = signed_request_builder(&Client::new(), auth, method, endpoint, body)
+        .send()
+        .await
+        .context("Failed to send HTTP request")?;
+
+    response.error_for_status_ref()?;
+
+    let bytes = response
+        .bytes()
+        .await
+        .context("Failed to read response bytes")?;
+    let mut bytesmut = bytes.try_into_mut().expect("Should be unique");
+
+    simd_json::serde::from_slice(&mut bytesmut).context("Failed to parse JSON response")
+}
+
+/// 构造带签名请求头的 HTTP 请求
+fn signed_request_builder(
+    client: &Client,
+    auth: &OkxAuth,
+    method: Method,
+    endpoint: &str,
+    body: &str,
+) -> RequestBuilder {
     let timestamp = OkxAuth::get_timestamp();
     let signature = auth.sign(&timestamp, method.as_str(), endpoint, body);
 
@@ -80,28 +112,13 @@ pub(super) async fn signed_request<T: serde::de::DeserializeOwned>(
         .header::<&str, &str>("OK-ACCESS-PASSPHRASE", auth.passphrase.as_ref())
         .header("Content-Type", "application/json");
 
-    if auth.simulated {
-        request_builder = request_builder.header("x-simulated-trading", "1");
-    }
+    request_builder = auth.environment().apply_header(request_builder);
 
     if !body.is_empty() {
         request_builder = request_builder.body(body.to_string());
     }
 
-    let response = request_builder
-        .send()
-        .await
-        .context("Failed to send HTTP request")?;
-
-    response.error_for_status_ref()?;
-
-    let bytes = response
-        .bytes()
-        .await
-        .context("Failed to read response bytes")?;
-    let mut bytesmut = bytes.try_into_mut().expect("Should be unique");
-
-    simd_json::serde::from_slice(&mut bytesmut).context("Failed to parse JSON response")
+    request_builder
 }
 
 /// 创建一个已验证的认证流
@@ -178,5 +195,25 @@ mod tests {
     fn test_okx_auth_with_simulated() {
         let auth = OkxAuth::new("test_key", "test_secret", "test_pass").with_simulated(true);
         assert!(auth.simulated);
+        assert_eq!(auth.environment(), OkxEnvironment::Demo);
+    }
+
+    #[test]
+    fn test_signed_request_simulated_header() {
+        let client = Client::new();
+        let simulated_header = |auth: &OkxAuth| {
+            signed_request_builder(&client, auth, Method::GET, "/api/v5/account/balance", "")
+                .build()
+                .unwrap()
+                .headers()
+                .get("x-simulated-trading")
+                .cloned()
+        };
+
+        let live = OkxAuth::new("test_key", "test_secret", "test_pass");
+        assert_eq!(simulated_header(&live), None);
+
+        let demo = live.with_simulated(true);
+        assert_eq!(simulated_header(&demo).unwrap(), "1");
     }
 }
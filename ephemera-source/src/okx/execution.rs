@@ -1,15 +1,327 @@
-use crate::okx::{
-    OkxAuth,
-    auth::signed_request,
-    model::{HttpResponse, OrderInfo, PlaceOrderRequest},
+use crate::{
+    metrics,
+    okx::{
+        OKX_REST_API_BASE, OkxAuth, OkxEnvironment,
+        auth::signed_request,
+        model::{
+            HttpResponse, OrderInfo, PlaceOrderRequest, PlaceOrderResponse, RawInstrument,
+            RawPosition, SetLeverageRequest, SetLeverageResponse,
+        },
+    },
 };
 use async_stream::stream;
 use bytestring::ByteString;
-use ephemera_shared::{OrderSide, OrderType, Signal, TradeMode};
-use eyre::Result;
+use dashmap::DashMap;
+use ephemera_shared::{OrderSide, OrderState, OrderType, Signal, TradeMode};
+use eyre::{Context, Result};
 use futures::{Stream, StreamExt};
 use reqwest::Method;
-use std::pin::Pin;
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    sync::LazyLock,
+    time::Duration,
+};
+
+/// 市价单通常立即成交，下单后最多查询这么多次，直到订单不再处于活跃状态
+const ORDER_POLL_ATTEMPTS: usize = 5;
+const ORDER_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 取整时容忍的浮点误差（以步长为单位）
+const STEP_EPSILON: f64 = 1e-9;
+
+/// 下单的交易模式和杠杆
+///
+/// 默认为现货（`cash`）。杠杆和合约交易使用 `cross`/`isolated`，设置了 `leverage` 时，
+/// 每个交易对首次下单前会调用 `/api/v5/account/set-leverage`。`cash` 模式下忽略 `leverage`。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OkxOrderConfig {
+    pub td_mode: TradeMode,
+    pub leverage: Option<f64>,
+}
+
+impl Default for OkxOrderConfig {
+    fn default() -> Self {
+        Self::cash()
+    }
+}
+
+impl OkxOrderConfig {
+    pub fn cash() -> Self {
+        Self {
+            td_mode: TradeMode::Cash,
+            leverage: None,
+        }
+    }
+
+    /// 全仓
+    pub fn cross(leverage: f64) -> Self {
+        Self {
+            td_mode: TradeMode::Cross,
+            leverage: Some(leverage),
+        }
+    }
+
+    /// 逐仓
+    pub fn isolated(leverage: f64) -> Self {
+        Self {
+            td_mode: TradeMode::Isolated,
+            leverage: Some(leverage),
+        }
+    }
+
+    /// 需要设置的杠杆，`cash` 模式下为 `None`
+    fn leverage_to_set(&self) -> Option<f64> {
+        self.leverage.filter(|_| self.td_mode != TradeMode::Cash)
+    }
+}
+
+/// 产品的下单精度，来自 `/api/v5/public/instruments`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstrumentSpec {
+    /// 最小下单数量
+    pub min_sz: f64,
+    /// 下单数量精度
+    pub lot_sz: f64,
+    /// 下单价格精度
+    pub tick_sz: f64,
+}
+
+impl InstrumentSpec {
+    /// 数量向下取整到 `lot_sz` 的整数倍，不足 `min_sz` 时返回 `None`
+    ///
+    /// 向下取整保证卖出不会超过持仓，买入不会超过预算。
+    pub fn round_size(&self, size: f64) -> Option<f64> {
+        let size = floor_to_step(size, self.lot_sz);
+        (size > 0.0 && size >= self.min_sz).then_some(size)
+    }
+
+    /// 价格取整到 `tick_sz` 的整数倍：买单向下、卖单向上，成交价不会比信号价格更差
+    pub fn round_price(&self, price: f64, side: OrderSide) -> f64 {
+        match side {
+            OrderSide::Buy => floor_to_step(price, self.tick_sz),
+            OrderSide::Sell => ceil_to_step(price, self.tick_sz),
+        }
+    }
+}
+
+impl TryFrom<RawInstrument> for InstrumentSpec {
+    type Error = eyre::Error;
+
+    fn try_from(raw: RawInstrument) -> Result<Self> {
+        Ok(Self {
+            min_sz: raw.min_sz.parse()?,
+            lot_sz: raw.lot_sz.parse()?,
+            tick_sz: raw.tick_sz.parse()?,
+        })
+    }
+}
+
+/// 产品类型，即 `/api/v5/public/instruments` 的 `instType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::IntoStaticStr)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum OkxInstType {
+    /// 币币
+    Spot,
+    /// 币币杠杆
+    Margin,
+    /// 永续合约
+    Swap,
+    /// 交割合约
+    Futures,
+    /// 期权
+    Option,
+}
+
+/// 合约类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum OkxContractType {
+    /// 正向合约，以计价货币结算
+    Linear,
+    /// 反向合约，以币结算
+    Inverse,
+}
+
+/// 产品信息，来自 `/api/v5/public/instruments`
+///
+/// 下单取整使用 [`InstrumentInfo::spec`]；合约的持仓价值为 `张数 * ct_val * 价格`（正向合约）。
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentInfo {
+    pub inst_id: ByteString,
+    pub inst_type: OkxInstType,
+    /// 下单价格精度
+    pub tick_sz: f64,
+    /// 下单数量精度
+    pub lot_sz: f64,
+    /// 最小下单数量
+    pub min_sz: f64,
+    /// 合约面值，币币和币币杠杆为 `None`
+    pub ct_val: Option<f64>,
+    /// 合约类型，币币、币币杠杆和期权为 `None`
+    pub contract_type: Option<OkxContractType>,
+}
+
+impl InstrumentInfo {
+    pub fn spec(&self) -> InstrumentSpec {
+        InstrumentSpec {
+            min_sz: self.min_sz,
+            lot_sz: self.lot_sz,
+            tick_sz: self.tick_sz,
+        }
+    }
+}
+
+/// 空字符串表示该字段不适用于此类产品
+fn non_empty(value: &ByteString) -> Option<&ByteString> {
+    (!value.is_empty()).then_some(value)
+}
+
+impl TryFrom<RawInstrument> for InstrumentInfo {
+    type Error = eyre::Error;
+
+    fn try_from(raw: RawInstrument) -> Result<Self> {
+        Ok(Self {
+            inst_type: raw
+                .inst_type
+                .parse()
+                .with_context(|| format!("Unknown instType: {}", raw.inst_type))?,
+            tick_sz: raw.tick_sz.parse()?,
+            lot_sz: raw.lot_sz.parse()?,
+            min_sz: raw.min_sz.parse()?,
+            ct_val: non_empty(&raw.ct_val).map(|v| v.parse()).transpose()?,
+            contract_type: non_empty(&raw.ct_type)
+                .map(|v| v.parse())
+                .transpose()
+                .with_context(|| format!("Unknown ctType: {}", raw.ct_type))?,
+            inst_id: raw.inst_id,
+        })
+    }
+}
+
+/// 按产品类型缓存的产品列表，产品规格很少变化，进程内只拉取一次
+static INSTRUMENTS: LazyLock<DashMap<OkxInstType, Vec<InstrumentInfo>>> =
+    LazyLock::new(DashMap::new);
+
+/// 拉取某一类型的全部产品信息，结果缓存在内存中
+///
+/// 可用于订阅前检查交易对是否存在、按 `ct_val` 计算合约持仓价值，或预先填充 [`InstrumentCache`]。
+pub async fn okx_fetch_instruments(inst_type: OkxInstType) -> Result<Vec<InstrumentInfo>> {
+    if let Some(instruments) = INSTRUMENTS.get(&inst_type) {
+        return Ok(instruments.clone());
+    }
+
+    let instruments = fetch_raw_instruments(OkxEnvironment::Live, inst_type, None)
+        .await?
+        .into_iter()
+        .map(InstrumentInfo::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    INSTRUMENTS.insert(inst_type, instruments.clone());
+
+    Ok(instruments)
+}
+
+fn floor_to_step(value: f64, step: f64) -> f64 {
+    snap_to_step((value / step + STEP_EPSILON).floor() * step, step)
+}
+
+fn ceil_to_step(value: f64, step: f64) -> f64 {
+    snap_to_step((value / step - STEP_EPSILON).ceil() * step, step)
+}
+
+/// 去掉 `n * step` 的浮点误差，保留与 `step` 相同的小数位，如 `3 * 0.1` 得到 `0.3`
+fn snap_to_step(value: f64, step: f64) -> f64 {
+    let decimals = (-step.log10()).ceil().max(0.0) as i32;
+    let scale = 10f64.powi(decimals);
+    (value * scale).round() / scale
+}
+
+/// 按交易对缓存 [`InstrumentSpec`]，首次下单时从交易所拉取
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentCache {
+    specs: HashMap<ByteString, InstrumentSpec>,
+    env: OkxEnvironment,
+}
+
+impl InstrumentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从指定环境拉取精度信息，模拟盘与实盘的产品列表可能不同
+    pub fn with_environment(env: OkxEnvironment) -> Self {
+        Self {
+            env,
+            ..Self::default()
+        }
+    }
+
+    /// 预先写入精度信息，之后不再请求交易所
+    pub fn insert(&mut self, symbol: impl Into<ByteString>, spec: InstrumentSpec) {
+        self.specs.insert(symbol.into(), spec);
+    }
+
+    pub async fn get(&mut self, symbol: &ByteString) -> Result<InstrumentSpec> {
+        if let Some(spec) = self.specs.get(symbol) {
+            return Ok(*spec);
+        }
+
+        let spec = fetch_instrument_spec(self.env, symbol).await?;
+        self.specs.insert(symbol.clone(), spec);
+
+        Ok(spec)
+    }
+}
+
+/// 拉取产品精度
+async fn fetch_instrument_spec(env: OkxEnvironment, symbol: &str) -> Result<InstrumentSpec> {
+    let inst_type = if symbol.ends_with("-SWAP") {
+        OkxInstType::Swap
+    } else {
+        OkxInstType::Spot
+    };
+
+    fetch_raw_instruments(env, inst_type, Some(symbol))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre::eyre!("Empty response data"))?
+        .try_into()
+}
+
+/// 拉取产品信息，公共接口无需签名。`inst_id` 为 `None` 时返回该类型的全部产品
+async fn fetch_raw_instruments(
+    env: OkxEnvironment,
+    inst_type: OkxInstType,
+    inst_id: Option<&str>,
+) -> Result<Vec<RawInstrument>> {
+    let inst_type: &'static str = inst_type.into();
+    let mut url = format!(
+        "{}/api/v5/public/instruments?instType={}",
+        OKX_REST_API_BASE, inst_type
+    );
+    if let Some(inst_id) = inst_id {
+        url.push_str("&instId=");
+        url.push_str(inst_id);
+    }
+
+    let bytes = env
+        .apply_header(reqwest::Client::new().get(url))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context("Failed to fetch instrument")?
+        .bytes()
+        .await?;
+    let response: HttpResponse<RawInstrument> = simd_json::serde::from_slice(&mut bytes.to_vec())
+        .context("Failed to parse instrument response")?;
+
+    if response.code != "0" {
+        eyre::bail!("API Error: code={}, msg={}", response.code, response.msg);
+    }
+
+    Ok(response.data)
+}
 
 /// 处理 API 响应
 fn handle_http_response<T>(response: HttpResponse<T>) -> Result<T> {
@@ -24,59 +336,311 @@ fn handle_http_response<T>(response: HttpResponse<T>) -> Result<T> {
     }
 }
 
-/// 下限价单
-async fn place_limit_order(
+/// 设置交易对的杠杆倍数
+async fn set_leverage(
     auth: &OkxAuth,
-    symbol: impl Into<ByteString>,
+    symbol: &ByteString,
+    leverage: f64,
+    mgn_mode: TradeMode,
+) -> Result<()> {
+    let request = SetLeverageRequest {
+        inst_id: symbol.clone(),
+        lever: leverage.to_string().into(),
+        mgn_mode,
+    };
+
+    let body = simd_json::serde::to_string(&request)?;
+    let response: HttpResponse<SetLeverageResponse> =
+        signed_request(auth, Method::POST, "/api/v5/account/set-leverage", &body).await?;
+    let set = handle_http_response(response)
+        .with_context(|| format!("Failed to set leverage for {}", symbol))?;
+
+    tracing::info!(
+        "Leverage set: symbol={}, lever={}, mgnMode={:?}",
+        symbol,
+        set.lever,
+        set.mgn_mode
+    );
+
+    Ok(())
+}
+
+/// 一笔订单取整之前的下单参数
+#[derive(Debug, Clone, PartialEq)]
+struct OrderRequest {
+    symbol: ByteString,
     side: OrderSide,
+    ord_type: OrderType,
     price: f64,
     size: f64,
-) -> Result<OrderInfo> {
-    let request = PlaceOrderRequest {
-        inst_id: symbol.into(),
-        td_mode: TradeMode::Cash,
+    td_mode: TradeMode,
+}
+
+/// 构造下单请求，数量和价格按 [`InstrumentSpec`] 取整
+fn order_request(spec: &InstrumentSpec, order: OrderRequest) -> Result<PlaceOrderRequest> {
+    let OrderRequest {
+        symbol,
         side,
-        ord_type: OrderType::Limit,
-        sz: size.to_string().into(),
-        px: Some(price.to_string().into()),
+        ord_type,
+        price,
+        size,
+        td_mode,
+    } = order;
+    let rounded_size = spec.round_size(size).ok_or_else(|| {
+        eyre::eyre!(
+            "Order size {} for {} is below the minimum {} (lot size {})",
+            size,
+            symbol,
+            spec.min_sz,
+            spec.lot_sz
+        )
+    })?;
+
+    let (px, tgt_ccy) = match ord_type {
+        // `tgtCcy` 只适用于现货市价单
+        OrderType::Market if td_mode == TradeMode::Cash => (None, Some("base_ccy".into())),
+        OrderType::Market => (None, None),
+        _ => (Some(spec.round_price(price, side).to_string().into()), None),
     };
 
-    let body = simd_json::serde::to_string(&request)?;
-    let response: HttpResponse<OrderInfo> =
+    Ok(PlaceOrderRequest {
+        inst_id: symbol,
+        td_mode,
+        side,
+        ord_type,
+        sz: rounded_size.to_string().into(),
+        px,
+        tgt_ccy,
+        pos_side: None,
+        reduce_only: None,
+    })
+}
+
+/// 下单，成功后查询订单详情以获得实际成交数量和均价
+///
+/// 数量和价格会先按 [`InstrumentSpec`] 取整，数量不足 `minSz` 时不提交，直接返回错误。
+async fn place_order(
+    auth: &OkxAuth,
+    instruments: &mut InstrumentCache,
+    order: OrderRequest,
+) -> Result<OrderInfo> {
+    let spec = instruments.get(&order.symbol).await?;
+    let request = order_request(&spec, order)?;
+
+    submit_order(auth, &request).await
+}
+
+/// 提交下单请求，成功后查询订单详情。市价单会轮询到订单不再处于活跃状态
+async fn submit_order(auth: &OkxAuth, request: &PlaceOrderRequest) -> Result<OrderInfo> {
+    let (symbol, ord_type) = (&request.inst_id, request.ord_type);
+
+    let body = simd_json::serde::to_string(request)?;
+    metrics::orders_submitted("okx").inc();
+    let response: HttpResponse<PlaceOrderResponse> =
         signed_request(auth, Method::POST, "/api/v5/trade/order", &body).await?;
+    let placed = handle_place_order_response(response)
+        .inspect_err(|_| metrics::orders_rejected("okx").inc())?;
+
+    let attempts = match ord_type {
+        OrderType::Market => ORDER_POLL_ATTEMPTS,
+        _ => 1,
+    };
+    let mut order = fetch_order(auth, symbol, &placed.ord_id).await?;
+    for _ in 1..attempts {
+        if !matches!(order.state, OrderState::Live | OrderState::PartiallyFilled) {
+            break;
+        }
+
+        tokio::time::sleep(ORDER_POLL_INTERVAL).await;
+        order = fetch_order(auth, symbol, &placed.ord_id).await?;
+    }
+
+    if order.state == OrderState::PartiallyFilled {
+        tracing::warn!(
+            "Order {} partially filled: {} / {}",
+            order.ord_id,
+            order.acc_fill_sz,
+            order.sz
+        );
+    }
+
+    Ok(order)
+}
+
+/// 下单接口在 `code != 0` 时，具体原因在 `data[].sMsg` 中
+fn handle_place_order_response(
+    response: HttpResponse<PlaceOrderResponse>,
+) -> Result<PlaceOrderResponse> {
+    let placed = response.data.into_iter().next();
+
+    match placed {
+        Some(placed) if response.code == "0" && placed.s_code == "0" => Ok(placed),
+        Some(placed) => eyre::bail!(
+            "Order rejected: code={}, sCode={}, sMsg={}",
+            response.code,
+            placed.s_code,
+            placed.s_msg
+        ),
+        None => eyre::bail!("API Error: code={}, msg={}", response.code, response.msg),
+    }
+}
+
+/// 查询订单详情
+async fn fetch_order(auth: &OkxAuth, symbol: &str, ord_id: &str) -> Result<OrderInfo> {
+    let endpoint = format!("/api/v5/trade/order?instId={}&ordId={}", symbol, ord_id);
+    let response: HttpResponse<OrderInfo> =
+        signed_request(auth, Method::GET, &endpoint, "").await?;
 
     handle_http_response(response)
 }
 
-/// 下市价单
-async fn place_market_order(
-    auth: &OkxAuth,
-    symbol: impl Into<ByteString>,
-    side: OrderSide,
-    size: f64,
-) -> Result<OrderInfo> {
-    let request = PlaceOrderRequest {
-        inst_id: symbol.into(),
-        td_mode: TradeMode::Cash,
+/// 构造平掉整个持仓的市价单，持仓为 0 时返回 `None`
+fn close_position_request(position: &RawPosition) -> Result<Option<PlaceOrderRequest>> {
+    let pos: f64 = position
+        .pos
+        .parse()
+        .with_context(|| format!("Invalid position size for {}", position.inst_id))?;
+    if pos == 0.0 {
+        return Ok(None);
+    }
+
+    let (side, pos_side, reduce_only) = match &*position.pos_side {
+        "long" => (OrderSide::Sell, Some(position.pos_side.clone()), None),
+        "short" => (OrderSide::Buy, Some(position.pos_side.clone()), None),
+        _ if pos > 0.0 => (OrderSide::Sell, None, Some(true)),
+        _ => (OrderSide::Buy, None, Some(true)),
+    };
+
+    Ok(Some(PlaceOrderRequest {
+        inst_id: position.inst_id.clone(),
+        td_mode: position.mgn_mode,
         side,
         ord_type: OrderType::Market,
-        sz: size.to_string().into(),
+        // 持仓数量本身就是合法的下单数量，不需要再取整
+        sz: position.pos.trim_start_matches('-').into(),
         px: None,
-    };
+        tgt_ccy: None,
+        pos_side,
+        reduce_only,
+    }))
+}
 
-    let body = simd_json::serde::to_string(&request)?;
-    let response: HttpResponse<OrderInfo> =
-        signed_request(auth, Method::POST, "/api/v5/trade/order", &body).await?;
+/// 紧急平仓: 以市价单平掉账户的所有持仓，不经过策略
+///
+/// 持仓来自 `/api/v5/account/positions`，只包括杠杆和合约持仓，现货余额不在其中。
+/// 逐个提交平仓单，某个持仓平仓失败时继续处理其余持仓，最后返回错误列出失败的交易对。
+pub async fn flatten_all(auth: &OkxAuth) -> Result<Vec<OrderInfo>> {
+    let response: HttpResponse<RawPosition> =
+        signed_request(auth, Method::GET, "/api/v5/account/positions", "").await?;
+    if response.code != "0" {
+        eyre::bail!("API Error: code={}, msg={}", response.code, response.msg);
+    }
 
-    handle_http_response(response)
+    let mut orders = Vec::new();
+    let mut failed = Vec::new();
+    for position in &response.data {
+        let result = match close_position_request(position) {
+            Ok(Some(request)) => submit_order(auth, &request).await,
+            Ok(None) => continue,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(order) => {
+                tracing::info!(
+                    "Flattened {}: {:?} {}",
+                    position.inst_id,
+                    order.side,
+                    order.acc_fill_sz
+                );
+                orders.push(order);
+            }
+            Err(e) => {
+                tracing::error!("Failed to flatten {}: {:?}", position.inst_id, e);
+                failed.push(position.inst_id.clone());
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        eyre::bail!(
+            "Failed to flatten {} position(s): {}",
+            failed.len(),
+            failed.iter().map(|s| &**s).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    Ok(orders)
+}
+
+/// 将信号流转换为订单执行流，按 `config` 指定交易模式和杠杆，`Hold` 被忽略
+///
+/// 每个交易对首次下单前按 `config` 设置杠杆，设置失败时该信号不下单，返回错误，下次信号会重试。
+pub fn okx_execute_orders(
+    auth: OkxAuth,
+    signal_stream: impl Stream<Item = Signal> + Send + 'static,
+    ord_type: OrderType,
+    config: OkxOrderConfig,
+) -> Pin<Box<dyn Stream<Item = Result<OrderInfo>> + Send>> {
+    let stream = stream! {
+        futures::pin_mut!(signal_stream);
+        let mut instruments = InstrumentCache::with_environment(auth.environment());
+        // 已经设置过杠杆的交易对
+        let mut leveraged = HashSet::new();
+
+        while let Some(signal) = signal_stream.next().await {
+            let (symbol, side, price, size) = match signal {
+                Signal::Buy { symbol, price, size } => (symbol, OrderSide::Buy, price, size),
+                Signal::Sell { symbol, price, size } => (symbol, OrderSide::Sell, price, size),
+                Signal::Hold => continue,
+            };
+
+            tracing::info!(
+                "Executing {:?} {:?} order: symbol={}, price={}, size={}",
+                side, ord_type, symbol, price, size
+            );
+
+            if let Some(leverage) = config.leverage_to_set()
+                && !leveraged.contains(&symbol)
+            {
+                if let Err(e) = set_leverage(&auth, &symbol, leverage, config.td_mode).await {
+                    tracing::error!("{:?}", e);
+                    yield Err(e);
+                    continue;
+                }
+                leveraged.insert(symbol.clone());
+            }
+
+            let order = OrderRequest {
+                symbol,
+                side,
+                ord_type,
+                price,
+                size,
+                td_mode: config.td_mode,
+            };
+            let order = place_order(&auth, &mut instruments, order);
+            match order.await {
+                Ok(order) => yield Ok(order),
+                Err(e) => {
+                    tracing::error!("Failed to place {:?} order: {}", side, e);
+                    yield Err(e);
+                }
+            }
+        }
+    };
+
+    Box::pin(stream)
 }
 
 /// 将信号流转换为订单执行流（限价单）
 ///
+/// 下单前按交易所精度取整数量和价格，返回的 [`OrderInfo`] 为下单后查询到的订单详情。
+///
 /// # 示例
 /// ```no_run
 /// use ephemera_source::okx::{okx_execute_limit_orders, OkxAuth};
-/// use ephemera_shared::SignalWithSymbol;
+/// use ephemera_shared::Signal;
 /// use futures::{stream, StreamExt};
 ///
 /// # async fn example() -> eyre::Result<()> {
@@ -84,7 +648,7 @@ async fn place_market_order(
 ///     .with_simulated(true);
 ///
 /// let signals = stream::iter(vec![
-///     SignalWithSymbol::buy("BTC-USDT".into(), 43000.0, 0.001),
+///     Signal::buy("BTC-USDT".into(), 43000.0, 0.001),
 /// ]);
 ///
 /// let mut order_stream = okx_execute_limit_orders(auth, signals);
@@ -102,55 +666,23 @@ pub fn okx_execute_limit_orders(
     auth: OkxAuth,
     signal_stream: impl Stream<Item = Signal> + Send + 'static,
 ) -> Pin<Box<dyn Stream<Item = Result<OrderInfo>> + Send>> {
-    let stream = stream! {
-        futures::pin_mut!(signal_stream);
-
-        while let Some(signal) = signal_stream.next().await {
-            match signal {
-                ephemera_shared::Signal::Buy { symbol, price, size } => {
-                    tracing::info!(
-                        "Executing BUY limit order: symbol={}, price={}, size={}",
-                        symbol, price, size
-                    );
-
-                    match place_limit_order(&auth, symbol, OrderSide::Buy, price, size).await {
-                        Ok(order) => yield Ok(order),
-                        Err(e) => {
-                            tracing::error!("Failed to place BUY order: {}", e);
-                            yield Err(e);
-                        }
-                    }
-                }
-                ephemera_shared::Signal::Sell { symbol, price, size } => {
-                    tracing::info!(
-                        "Executing SELL limit order: symbol={}, price={}, size={}",
-                        symbol, price, size
-                    );
-
-                    match place_limit_order(&auth, symbol, OrderSide::Sell, price, size).await {
-                        Ok(order) => yield Ok(order),
-                        Err(e) => {
-                            tracing::error!("Failed to place SELL order: {}", e);
-                            yield Err(e);
-                        }
-                    }
-                }
-                ephemera_shared::Signal::Hold => {
-                    // 不执行任何操作
-                }
-            }
-        }
-    };
-
-    Box::pin(stream)
+    okx_execute_orders(
+        auth,
+        signal_stream,
+        OrderType::Limit,
+        OkxOrderConfig::default(),
+    )
 }
 
 /// 将信号流转换为订单执行流（市价单）
 ///
+/// 下单前按交易所精度取整数量，数量以交易货币计。返回的 [`OrderInfo`] 为成交后查询到的订单详情，
+/// 部分成交时 `acc_fill_sz` 小于 `sz`。
+///
 /// # 示例
 /// ```no_run
 /// use ephemera_source::okx::{okx_execute_market_orders, OkxAuth};
-/// use ephemera_shared::SignalWithSymbol;
+/// use ephemera_shared::Signal;
 /// use futures::{stream, StreamExt};
 ///
 /// # async fn example() -> eyre::Result<()> {
@@ -158,7 +690,7 @@ pub fn okx_execute_limit_orders(
 ///     .with_simulated(true);
 ///
 /// let signals = stream::iter(vec![
-///     SignalWithSymbol::buy("BTC-USDT".into(), 43000.0, 0.001),
+///     Signal::buy("BTC-USDT".into(), 43000.0, 0.001),
 /// ]);
 ///
 /// let mut order_stream = okx_execute_market_orders(auth, signals);
@@ -176,45 +708,215 @@ pub fn okx_execute_market_orders(
     auth: OkxAuth,
     signal_stream: impl Stream<Item = Signal> + Send + 'static,
 ) -> Pin<Box<dyn Stream<Item = Result<OrderInfo>> + Send>> {
-    let stream = stream! {
-        futures::pin_mut!(signal_stream);
+    okx_execute_orders(
+        auth,
+        signal_stream,
+        OrderType::Market,
+        OkxOrderConfig::default(),
+    )
+}
 
-        while let Some(signal) = signal_stream.next().await {
-            match signal {
-                ephemera_shared::Signal::Buy { symbol, price: _, size } => {
-                    tracing::info!(
-                        "Executing BUY market order: symbol={}, size={}",
-                        symbol, size
-                    );
-
-                    match place_market_order(&auth, symbol, OrderSide::Buy, size).await {
-                        Ok(order) => yield Ok(order),
-                        Err(e) => {
-                            tracing::error!("Failed to place BUY order: {}", e);
-                            yield Err(e);
-                        }
-                    }
-                }
-                ephemera_shared::Signal::Sell { symbol, price: _, size } => {
-                    tracing::info!(
-                        "Executing SELL market order: symbol={}, size={}",
-                        symbol, size
-                    );
-
-                    match place_market_order(&auth, symbol, OrderSide::Sell, size).await {
-                        Ok(order) => yield Ok(order),
-                        Err(e) => {
-                            tracing::error!("Failed to place SELL order: {}", e);
-                            yield Err(e);
-                        }
-                    }
-                }
-                ephemera_shared::Signal::Hold => {
-                    // 不执行任何操作
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTC_USDT: InstrumentSpec = InstrumentSpec {
+        min_sz: 0.00001,
+        lot_sz: 0.00000001,
+        tick_sz: 0.1,
     };
 
-    Box::pin(stream)
+    #[test]
+    fn test_round_size() {
+        let spec = InstrumentSpec {
+            min_sz: 0.01,
+            lot_sz: 0.01,
+            tick_sz: 0.1,
+        };
+
+        assert_eq!(spec.round_size(1.239), Some(1.23));
+        // 浮点误差不应导致少一个步长
+        assert_eq!(spec.round_size(0.3), Some(0.3));
+        assert_eq!(spec.round_size(0.1 + 0.2), Some(0.3));
+        assert_eq!(spec.round_size(0.01), Some(0.01));
+        assert_eq!(spec.round_size(0.009), None);
+        assert_eq!(spec.round_size(0.0), None);
+
+        assert_eq!(BTC_USDT.round_size(0.123456789), Some(0.12345678));
+        assert_eq!(BTC_USDT.round_size(0.000009), None);
+    }
+
+    #[test]
+    fn test_round_price() {
+        assert_eq!(BTC_USDT.round_price(43000.17, OrderSide::Buy), 43000.1);
+        assert_eq!(BTC_USDT.round_price(43000.11, OrderSide::Sell), 43000.2);
+        // 已经是整数倍时不变
+        assert_eq!(BTC_USDT.round_price(43000.3, OrderSide::Buy), 43000.3);
+        assert_eq!(BTC_USDT.round_price(43000.3, OrderSide::Sell), 43000.3);
+
+        let spec = InstrumentSpec {
+            min_sz: 1.0,
+            lot_sz: 1.0,
+            tick_sz: 5.0,
+        };
+        assert_eq!(spec.round_price(1003.0, OrderSide::Buy), 1000.0);
+        assert_eq!(spec.round_price(1003.0, OrderSide::Sell), 1005.0);
+        assert_eq!(spec.round_size(7.9), Some(7.0));
+    }
+
+    #[test]
+    fn test_parse_instrument() {
+        let mut msg = br#"{"code":"0","msg":"","data":[{"instType":"SPOT","instId":"BTC-USDT","baseCcy":"BTC","quoteCcy":"USDT","minSz":"0.00001","lotSz":"0.00000001","tickSz":"0.1","state":"live"}]}"#.to_vec();
+        let response: HttpResponse<RawInstrument> = simd_json::serde::from_slice(&mut msg).unwrap();
+
+        let spec: InstrumentSpec = handle_http_response(response).unwrap().try_into().unwrap();
+        assert_eq!(spec, BTC_USDT);
+    }
+
+    #[test]
+    fn test_parse_instrument_info() {
+        let mut msg = br#"{"code":"0","msg":"","data":[{"instType":"SPOT","instId":"BTC-USDT","baseCcy":"BTC","quoteCcy":"USDT","ctVal":"","ctType":"","minSz":"0.00001","lotSz":"0.00000001","tickSz":"0.1","state":"live"},{"instType":"SWAP","instId":"BTC-USDT-SWAP","settleCcy":"USDT","ctVal":"0.01","ctValCcy":"BTC","ctType":"linear","minSz":"0.01","lotSz":"0.01","tickSz":"0.1","state":"live"}]}"#.to_vec();
+        let response: HttpResponse<RawInstrument> = simd_json::serde::from_slice(&mut msg).unwrap();
+
+        let instruments: Vec<InstrumentInfo> = response
+            .data
+            .into_iter()
+            .map(|raw| raw.try_into().unwrap())
+            .collect();
+
+        assert_eq!(instruments[0].inst_id, "BTC-USDT");
+        assert_eq!(instruments[0].inst_type, OkxInstType::Spot);
+        assert_eq!(instruments[0].spec(), BTC_USDT);
+        assert_eq!(instruments[0].ct_val, None);
+        assert_eq!(instruments[0].contract_type, None);
+
+        assert_eq!(instruments[1].inst_type, OkxInstType::Swap);
+        assert_eq!(instruments[1].ct_val, Some(0.01));
+        assert_eq!(instruments[1].contract_type, Some(OkxContractType::Linear));
+        assert_eq!(instruments[1].spec().round_size(1.234), Some(1.23));
+    }
+
+    #[test]
+    fn test_close_position_request() {
+        let mut msg = br#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT-SWAP","instType":"SWAP","mgnMode":"cross","posSide":"net","pos":"-3","availPos":""},{"instId":"ETH-USDT-SWAP","instType":"SWAP","mgnMode":"isolated","posSide":"long","pos":"12.5"},{"instId":"BTC-USDT","instType":"MARGIN","mgnMode":"cross","posSide":"net","pos":"0.02"},{"instId":"SOL-USDT-SWAP","instType":"SWAP","mgnMode":"cross","posSide":"net","pos":"0"}]}"#.to_vec();
+        let response: HttpResponse<RawPosition> = simd_json::serde::from_slice(&mut msg).unwrap();
+
+        let requests: Vec<_> = response
+            .data
+            .iter()
+            .map(|position| close_position_request(position).unwrap())
+            .collect();
+
+        // 买卖模式的空头: 只减仓买入
+        let short = requests[0].as_ref().unwrap();
+        assert_eq!(short.side, OrderSide::Buy);
+        assert_eq!(short.sz, "3");
+        assert_eq!(short.td_mode, TradeMode::Cross);
+        assert_eq!(short.reduce_only, Some(true));
+        assert_eq!(
+            simd_json::serde::to_string(short).unwrap(),
+            r#"{"instId":"BTC-USDT-SWAP","tdMode":"cross","side":"buy","ordType":"market","sz":"3","reduceOnly":true}"#
+        );
+
+        // 开平仓模式的多头: 指定 posSide 卖出
+        let long = requests[1].as_ref().unwrap();
+        assert_eq!(long.side, OrderSide::Sell);
+        assert_eq!(long.sz, "12.5");
+        assert_eq!(long.td_mode, TradeMode::Isolated);
+        assert_eq!(long.pos_side.as_deref(), Some("long"));
+        assert_eq!(long.reduce_only, None);
+
+        assert_eq!(requests[2].as_ref().unwrap().side, OrderSide::Sell);
+        // 空仓不下单
+        assert!(requests[3].is_none());
+    }
+
+    #[test]
+    fn test_place_order_rejected() {
+        let mut msg = br#"{"code":"1","msg":"All operations failed","data":[{"clOrdId":"","ordId":"","sCode":"51008","sMsg":"Order failed. Insufficient USDT balance in account."}]}"#.to_vec();
+        let response: HttpResponse<PlaceOrderResponse> =
+            simd_json::serde::from_slice(&mut msg).unwrap();
+
+        let err = handle_place_order_response(response).unwrap_err();
+        assert!(err.to_string().contains("51008"));
+    }
+
+    #[test]
+    fn test_order_info_partial_fill() {
+        let mut msg = br#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT","ordId":"312269865356374016","clOrdId":"","px":"","sz":"0.5","ordType":"market","side":"buy","state":"partially_filled","accFillSz":"0.2","avgPx":"43010.5","fee":"-0.0002","cTime":"1700000000000","uTime":"1700000000100"}]}"#.to_vec();
+        let response: HttpResponse<OrderInfo> = simd_json::serde::from_slice(&mut msg).unwrap();
+        let order = handle_http_response(response).unwrap();
+
+        assert_eq!(order.state, OrderState::PartiallyFilled);
+        assert_eq!(order.filled_size(), 0.2);
+        assert_eq!(order.avg_fill_price(), Some(43010.5));
+
+        let mut msg = br#"{"code":"0","msg":"","data":[{"instId":"BTC-USDT","ordId":"1","px":"43000","sz":"0.5","ordType":"limit","side":"buy","state":"live","accFillSz":"0","avgPx":""}]}"#.to_vec();
+        let response: HttpResponse<OrderInfo> = simd_json::serde::from_slice(&mut msg).unwrap();
+        let order = handle_http_response(response).unwrap();
+
+        assert_eq!(order.filled_size(), 0.0);
+        assert_eq!(order.avg_fill_price(), None);
+    }
+
+    #[test]
+    fn test_order_request_margin_mode() {
+        let symbol = ByteString::from_static("BTC-USDT");
+
+        // 现货市价单按交易货币计
+        let request = order_request(
+            &BTC_USDT,
+            OrderRequest {
+                symbol: symbol.clone(),
+                side: OrderSide::Buy,
+                ord_type: OrderType::Market,
+                price: 43000.0,
+                size: 0.001,
+                td_mode: OkxOrderConfig::default().td_mode,
+            },
+        )
+        .unwrap();
+        let body = simd_json::serde::to_string(&request).unwrap();
+        assert!(body.contains(r#""tdMode":"cash""#));
+        assert!(body.contains(r#""tgtCcy":"base_ccy""#));
+
+        let request = order_request(
+            &BTC_USDT,
+            OrderRequest {
+                symbol,
+                side: OrderSide::Sell,
+                ord_type: OrderType::Limit,
+                price: 43000.11,
+                size: 0.001,
+                td_mode: OkxOrderConfig::isolated(5.0).td_mode,
+            },
+        )
+        .unwrap();
+        let body = simd_json::serde::to_string(&request).unwrap();
+        assert!(body.contains(r#""tdMode":"isolated""#));
+        assert!(body.contains(r#""px":"43000.2""#));
+        assert!(!body.contains("tgtCcy"));
+    }
+
+    #[test]
+    fn test_leverage_config() {
+        assert_eq!(OkxOrderConfig::default().leverage_to_set(), None);
+        assert_eq!(OkxOrderConfig::cross(3.0).leverage_to_set(), Some(3.0));
+
+        let cash_with_leverage = OkxOrderConfig {
+            td_mode: TradeMode::Cash,
+            leverage: Some(3.0),
+        };
+        assert_eq!(cash_with_leverage.leverage_to_set(), None);
+
+        let request = SetLeverageRequest {
+            inst_id: "BTC-USDT-SWAP".into(),
+            lever: 3.0.to_string().into(),
+            mgn_mode: TradeMode::Cross,
+        };
+        assert_eq!(
+            simd_json::serde::to_string(&request).unwrap(),
+            r#"{"instId":"BTC-USDT-SWAP","lever":"3","mgnMode":"cross"}"#
+        );
+    }
 }
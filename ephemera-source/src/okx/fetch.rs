@@ -1,6 +1,11 @@
 use crate::{
-    okx::{OKX_WS_BUSINESS_ENDPOINT, OKX_WS_HOST, OKX_WS_PUBLICE_ENDPOINT, model::*},
-    utils::{transform_raw_vec_stream, transform_raw_vec_stream_with},
+    metrics,
+    okx::{OkxEnvironment, model::*},
+    utils::{
+        FromExchangeCandle, JsonScratch, ParseMode, ReconnectPolicy, SequenceTracker,
+        SubscriptionResult, WsConfig, payload_for_log, retry_stream, transform_raw_vec_stream,
+        transform_raw_vec_stream_with,
+    },
 };
 use async_stream::stream;
 use bytestring::ByteString;
@@ -11,7 +16,7 @@ use futures::{SinkExt, Stream, StreamExt};
 use http::{StatusCode, Uri};
 use itertools::Itertools;
 use serde::de::DeserializeOwned;
-use std::{pin::Pin, str::FromStr};
+use std::{pin::Pin, str::FromStr, time::Duration};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
@@ -19,8 +24,10 @@ use tokio::{
 use tokio_websockets::{Connector, Message};
 
 pub async fn okx_trade_data_stream(
+    env: OkxEnvironment,
     symbols: Vec<impl Into<ByteString>>,
-) -> eyre::Result<impl Stream<Item = Result<TradeData>>> {
+    config: WsConfig,
+) -> eyre::Result<(impl Stream<Item = Result<TradeData>>, SubscriptionResult)> {
     let request = WsRequest {
         op: WsOperation::Subscribe,
         args: symbols
@@ -29,16 +36,24 @@ pub async fn okx_trade_data_stream(
             .collect_vec(),
         id: None,
     };
-    let stream = TcpStream::connect(OKX_WS_HOST).await?;
-    okx_raw_data_stream::<WsDataResponse<RawTradeData>>(OKX_WS_PUBLICE_ENDPOINT, request, stream)
-        .await
-        .map(transform_raw_vec_stream)
+    let stream = TcpStream::connect(env.ws_host()).await?;
+    okx_raw_data_stream::<WsDataResponse<RawTradeData>>(
+        env.ws_public_endpoint(),
+        request,
+        stream,
+        OkxKeepAlive::default(),
+        config,
+    )
+    .await
+    .map(|(stream, result)| (transform_raw_vec_stream(stream), result))
 }
 
 pub async fn okx_candle_data_stream(
+    env: OkxEnvironment,
     symbols: Vec<impl Into<ByteString>>,
     interval: OkxCandleInterval,
-) -> eyre::Result<impl Stream<Item = Result<CandleData>>> {
+    config: WsConfig,
+) -> eyre::Result<(impl Stream<Item = Result<CandleData>>, SubscriptionResult)> {
     let request = WsRequest {
         op: WsOperation::Subscribe,
         args: symbols
@@ -52,20 +67,29 @@ pub async fn okx_candle_data_stream(
             .collect_vec(),
         id: None,
     };
-    let stream = TcpStream::connect(OKX_WS_HOST).await?;
-    okx_raw_data_stream::<WsDataResponse<RawCandleData>>(OKX_WS_BUSINESS_ENDPOINT, request, stream)
-        .await
-        .map(move |stream| {
-            transform_raw_vec_stream_with(stream, move |resp| {
-                convert_okx_candle_datas(resp, interval.clone().into())
-            })
-        })
+    let stream = TcpStream::connect(env.ws_host()).await?;
+    okx_raw_data_stream::<WsDataResponse<RawCandleData>>(
+        env.ws_business_endpoint(),
+        request,
+        stream,
+        OkxKeepAlive::default(),
+        config,
+    )
+    .await
+    .map(move |(stream, result)| {
+        let stream = transform_raw_vec_stream_with(stream, move |resp| {
+            convert_okx_candle_datas(resp, interval.clone().into())
+        });
+        (stream, result)
+    })
 }
 
 pub async fn okx_book_data_stream(
+    env: OkxEnvironment,
     symbols: Vec<impl Into<ByteString>>,
     typ: OkxBookChannel,
-) -> eyre::Result<impl Stream<Item = Result<BookData>>> {
+    config: WsConfig,
+) -> eyre::Result<(impl Stream<Item = Result<BookData>>, SubscriptionResult)> {
     let request = WsRequest {
         op: WsOperation::Subscribe,
         args: symbols
@@ -74,15 +98,28 @@ pub async fn okx_book_data_stream(
             .collect_vec(),
         id: None,
     };
-    let stream = TcpStream::connect(OKX_WS_HOST).await?;
-    okx_raw_data_stream::<WsDataResponse<OkxBookData>>(OKX_WS_PUBLICE_ENDPOINT, request, stream)
-        .await
-        .map(transform_raw_vec_stream)
+    let stream = TcpStream::connect(env.ws_host()).await?;
+    okx_raw_data_stream::<WsDataResponse<OkxBookData>>(
+        env.ws_public_endpoint(),
+        request,
+        stream,
+        OkxKeepAlive::default(),
+        config,
+    )
+    .await
+    .map(|(stream, result)| {
+        (
+            transform_raw_vec_stream_with(stream, convert_okx_book_datas()),
+            result,
+        )
+    })
 }
 
 pub async fn okx_xdp_trade_data_stream(
+    env: OkxEnvironment,
     symbols: Vec<impl Into<ByteString>>,
-) -> eyre::Result<impl Stream<Item = Result<TradeData>>> {
+    config: WsConfig,
+) -> eyre::Result<(impl Stream<Item = Result<TradeData>>, SubscriptionResult)> {
     let request = WsRequest {
         op: WsOperation::Subscribe,
         args: symbols
@@ -91,16 +128,24 @@ pub async fn okx_xdp_trade_data_stream(
             .collect_vec(),
         id: None,
     };
-    let stream = XdpTcpStream::connect(OKX_WS_HOST).await?;
-    okx_raw_data_stream::<WsDataResponse<RawTradeData>>(OKX_WS_PUBLICE_ENDPOINT, request, stream)
-        .await
-        .map(transform_raw_vec_stream)
+    let stream = XdpTcpStream::connect(env.ws_host()).await?;
+    okx_raw_data_stream::<WsDataResponse<RawTradeData>>(
+        env.ws_public_endpoint(),
+        request,
+        stream,
+        OkxKeepAlive::default(),
+        config,
+    )
+    .await
+    .map(|(stream, result)| (transform_raw_vec_stream(stream), result))
 }
 
 pub async fn okx_xdp_candle_data_stream(
+    env: OkxEnvironment,
     symbols: Vec<impl Into<ByteString>>,
     interval: OkxCandleInterval,
-) -> eyre::Result<impl Stream<Item = Result<CandleData>>> {
+    config: WsConfig,
+) -> eyre::Result<(impl Stream<Item = Result<CandleData>>, SubscriptionResult)> {
     let request = WsRequest {
         op: WsOperation::Subscribe,
         args: symbols
@@ -114,20 +159,29 @@ pub async fn okx_xdp_candle_data_stream(
             .collect_vec(),
         id: None,
     };
-    let stream = XdpTcpStream::connect(OKX_WS_HOST).await?;
-    okx_raw_data_stream::<WsDataResponse<RawCandleData>>(OKX_WS_BUSINESS_ENDPOINT, request, stream)
-        .await
-        .map(move |stream| {
-            transform_raw_vec_stream_with(stream, move |resp| {
-                convert_okx_candle_datas(resp, interval.clone().into())
-            })
-        })
+    let stream = XdpTcpStream::connect(env.ws_host()).await?;
+    okx_raw_data_stream::<WsDataResponse<RawCandleData>>(
+        env.ws_business_endpoint(),
+        request,
+        stream,
+        OkxKeepAlive::default(),
+        config,
+    )
+    .await
+    .map(move |(stream, result)| {
+        let stream = transform_raw_vec_stream_with(stream, move |resp| {
+            convert_okx_candle_datas(resp, interval.clone().into())
+        });
+        (stream, result)
+    })
 }
 
 pub async fn okx_xdp_book_data_stream(
+    env: OkxEnvironment,
     symbols: Vec<impl Into<ByteString>>,
     typ: OkxBookChannel,
-) -> eyre::Result<impl Stream<Item = Result<BookData>>> {
+    config: WsConfig,
+) -> eyre::Result<(impl Stream<Item = Result<BookData>>, SubscriptionResult)> {
     let request = WsRequest {
         op: WsOperation::Subscribe,
         args: symbols
@@ -136,19 +190,121 @@ pub async fn okx_xdp_book_data_stream(
             .collect_vec(),
         id: None,
     };
-    let stream = XdpTcpStream::connect(OKX_WS_HOST).await?;
-    okx_raw_data_stream::<WsDataResponse<OkxBookData>>(OKX_WS_PUBLICE_ENDPOINT, request, stream)
-        .await
-        .map(transform_raw_vec_stream)
+    let stream = XdpTcpStream::connect(env.ws_host()).await?;
+    okx_raw_data_stream::<WsDataResponse<OkxBookData>>(
+        env.ws_public_endpoint(),
+        request,
+        stream,
+        OkxKeepAlive::default(),
+        config,
+    )
+    .await
+    .map(|(stream, result)| {
+        (
+            transform_raw_vec_stream_with(stream, convert_okx_book_datas()),
+            result,
+        )
+    })
 }
 
+/// 同 [`okx_trade_data_stream`]，断线后按 `policy` 重连并重新订阅
+///
+/// 每次重连都会重新完成订阅握手，断线期间的数据会丢失。部分交易对订阅失败时只记录日志。
+pub fn okx_trade_data_stream_with_reconnect(
+    env: OkxEnvironment,
+    symbols: Vec<impl Into<ByteString>>,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<TradeData>> + Send {
+    let symbols = symbols.into_iter().map(Into::into).collect_vec();
+    retry_stream(policy, move || {
+        let symbols = symbols.clone();
+        async move {
+            okx_trade_data_stream(env, symbols, config)
+                .await
+                .map(|(stream, _)| stream)
+        }
+    })
+}
+
+/// 同 [`okx_candle_data_stream`]，断线后按 `policy` 重连并重新订阅
+pub fn okx_candle_data_stream_with_reconnect(
+    env: OkxEnvironment,
+    symbols: Vec<impl Into<ByteString>>,
+    interval: OkxCandleInterval,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<CandleData>> + Send {
+    let symbols = symbols.into_iter().map(Into::into).collect_vec();
+    retry_stream(policy, move || {
+        let symbols = symbols.clone();
+        let interval = interval.clone();
+        async move {
+            okx_candle_data_stream(env, symbols, interval, config)
+                .await
+                .map(|(stream, _)| stream)
+        }
+    })
+}
+
+/// 同 [`okx_book_data_stream`]，断线后按 `policy` 重连并重新订阅
+///
+/// 每次连接都会重新收到快照，序号检查随之重新开始。
+pub fn okx_book_data_stream_with_reconnect(
+    env: OkxEnvironment,
+    symbols: Vec<impl Into<ByteString>>,
+    typ: OkxBookChannel,
+    config: WsConfig,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<BookData>> + Send {
+    let symbols = symbols.into_iter().map(Into::into).collect_vec();
+    retry_stream(policy, move || {
+        let symbols = symbols.clone();
+        let typ = typ.clone();
+        async move {
+            okx_book_data_stream(env, symbols, typ, config)
+                .await
+                .map(|(stream, _)| stream)
+        }
+    })
+}
+
+/// OKX 的心跳参数
+///
+/// OKX 在 30 秒内收不到客户端的任何消息就会断开连接。超过 `interval` 没有收到任何帧时，
+/// 发送文本帧 `ping`；之后 `timeout` 内仍未收到 `pong`（或其他帧），就认为连接已经断开。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OkxKeepAlive {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for OkxKeepAlive {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(20),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 订阅数据流，部分交易对订阅失败时只推送订阅成功的交易对，见 [`SubscriptionResult`]
 // TODO: 返回sink和stream
 async fn okx_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
     end_point: &str,
     request: WsRequest,
     stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
-) -> Result<Pin<Box<dyn Stream<Item = Result<DR, eyre::Error>> + Send>>, eyre::Error> {
+    keepalive: OkxKeepAlive,
+    config: WsConfig,
+) -> Result<
+    (
+        Pin<Box<dyn Stream<Item = Result<DR, eyre::Error>> + Send>>,
+        SubscriptionResult,
+    ),
+    eyre::Error,
+> {
     let channel_count = request.args.len();
+    let messages_received = metrics::ws_messages_received("okx", &request.args[0].channel);
 
     assert_ne!(
         channel_count, 0,
@@ -181,9 +337,132 @@ async fn okx_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
         .send(Message::text(simd_json::serde::to_string(&request)?))
         .await?;
 
-    // Each channel subscription will get a response.
-    let mut i = 0;
-    while i < channel_count {
+    let (result, early_frames) = await_subscribe_acks(
+        &mut client,
+        &request.args,
+        SUBSCRIBE_MAX_FRAMES,
+        config.subscribe_timeout,
+    )
+    .await?;
+    for (symbol, reason) in &result.failed {
+        tracing::warn!("Failed to subscribe {}: {}", symbol, reason);
+    }
+
+    metrics::ws_connections("okx").inc();
+
+    let WsConfig {
+        idle_timeout,
+        parse_mode,
+        ..
+    } = config;
+    let stream = stream! {
+        let mut scratch = JsonScratch::default();
+        // 先重放握手期间收到的数据推送，避免订阅刚建立时丢数据
+        let mut early_frames = early_frames.into_iter();
+        let mut last_frame = tokio::time::Instant::now();
+        let mut ping_sent = false;
+
+        loop {
+            let msg = if let Some(msg) = early_frames.next() {
+                msg
+            } else {
+                // 已发出 ping 时等待 pong，否则等到该发 ping 的时候，两者都不超过空闲超时
+                let wait = if ping_sent { keepalive.timeout } else { keepalive.interval };
+                let idle_deadline = last_frame + idle_timeout;
+                let deadline = (tokio::time::Instant::now() + wait).min(idle_deadline);
+
+                match tokio::time::timeout_at(deadline, client.next()).await {
+                    Ok(Some(msg)) => msg?,
+                    Ok(None) => break,
+                    Err(_) if !ping_sent && deadline < idle_deadline => {
+                        client.send(Message::text("ping")).await?;
+                        ping_sent = true;
+                        continue;
+                    }
+                    Err(_) => {
+                        // 关闭握手需要对端回应，对端已无响应时不能无限等待
+                        let _ = tokio::time::timeout(keepalive.timeout, client.close()).await;
+                        let reason = if ping_sent {
+                            format!("No pong received within {:?}", keepalive.timeout)
+                        } else {
+                            format!("No frame received within {idle_timeout:?}")
+                        };
+                        yield Err(DataError::Connection(reason).into());
+                        break;
+                    }
+                }
+            };
+            last_frame = tokio::time::Instant::now();
+            ping_sent = false;
+
+            // 服务端主动关闭连接，结束数据流，由调用方决定是否重连
+            if msg.is_close() {
+                break;
+            }
+            // 回应服务端的心跳，心跳帧不是数据推送
+            if msg.is_ping() {
+                client.send(Message::pong(msg.into_payload())).await?;
+                continue;
+            }
+            match msg.as_text() {
+                Some("ping") => {
+                    client.send(Message::text("pong")).await?;
+                    continue;
+                }
+                Some("pong") => continue,
+                _ => {}
+            }
+
+            match scratch.parse::<DR>(msg.as_payload()) {
+                Ok(resp) => {
+                    messages_received.inc();
+                    yield Ok(resp)
+                }
+                Err(e) if parse_mode == ParseMode::Tolerant => {
+                    tracing::warn!(
+                        "Skipping unparsable okx message: {e}, payload: {}",
+                        payload_for_log(msg.as_payload())
+                    );
+                }
+                Err(e) => yield Err(e.into()),
+            }
+        }
+    };
+
+    Ok((Box::pin(stream), result))
+}
+
+/// 等待订阅确认时最多读取的帧数，包括确认之间夹杂的数据推送
+const SUBSCRIBE_MAX_FRAMES: usize = 1024;
+
+/// 等待每个频道的订阅确认，按交易对记录订阅成功或失败
+///
+/// 确认之间可能夹杂已订阅频道的数据推送，这些帧按收到的顺序返回，由调用方在握手完成后重放。
+/// 某个频道返回 `error` 事件时记为失败，
+/// 其余频道继续等待；读取超过 `max_frames` 帧或超过 `timeout` 仍未确认的频道也记为失败。
+/// 只有所有频道都失败时才返回 [`DataError::Subscription`]。
+async fn await_subscribe_acks<S, E>(
+    client: &mut S,
+    args: &[Arg],
+    max_frames: usize,
+    timeout: Duration,
+) -> Result<(SubscriptionResult, Vec<Message>)>
+where
+    S: Stream<Item = Result<Message, E>> + Unpin,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut pending: Vec<Symbol> = args.iter().map(|arg| arg.inst_id.clone()).collect();
+    let mut result = SubscriptionResult::default();
+    let mut early_frames = Vec::new();
+    // 无法对应到具体交易对的错误，最后归给仍未确认的交易对
+    let mut unattributed = None;
+
+    for _ in 0..max_frames {
+        if pending.is_empty() {
+            break;
+        }
+
         // Expect a response like this:
         // {
         //   "event": "subscribe",
@@ -193,36 +472,97 @@ async fn okx_raw_data_stream<DR: DeserializeOwned + Send + 'static>(
         //   },
         //   "id": "user_sub_01"
         // }
-        let mut resp = client
-            .next()
-            .await
-            .wrap_err("Failed to subscribe")??
-            .as_payload()
-            .to_vec();
+        let frame = match tokio::time::timeout_at(deadline, client.next()).await {
+            Ok(frame) => frame.wrap_err("Failed to subscribe")??,
+            Err(_) => break,
+        };
+        let mut resp = frame.as_payload().to_vec();
 
         // WsResponse 并不总是连续的，有可能成功订阅第一个流之后，马上就在第二个 WsResponse
-        // 之前收到数据，我们需要忽略它。
-        if let Ok(resp) = simd_json::from_slice::<WsResponse>(&mut resp) {
-            i += 1;
-            ensure!(
-                resp.event == WsOperation::Subscribe,
-                "Failed to subscribe with response:\n {resp:?}",
+        // 之前收到数据，我们需要保留它。
+        let Ok(resp) = simd_json::from_slice::<WsResponse>(&mut resp) else {
+            early_frames.push(frame);
+            continue;
+        };
+
+        if resp.event == "error" {
+            let msg = resp.msg.as_deref().unwrap_or_default();
+            let reason = format!(
+                "OKX rejected subscription with code {}: {}",
+                resp.code.as_deref().unwrap_or_default(),
+                msg,
             );
+
+            // error 事件不带 arg，只能从错误消息中找出交易对。取最长的匹配，
+            // 避免 `BTC-USDT` 匹配到 `BTC-USDT-SWAP` 的错误
+            let rejected = pending
+                .iter()
+                .enumerate()
+                .filter(|(_, symbol)| msg.contains(&***symbol))
+                .max_by_key(|(_, symbol)| symbol.len())
+                .map(|(i, _)| i);
+            match rejected {
+                Some(i) => result.failed.push((pending.remove(i), reason)),
+                None => unattributed = Some(reason),
+            }
+            continue;
+        }
+
+        ensure!(
+            resp.event == WsOperation::Subscribe,
+            "Failed to subscribe with response:\n {resp:?}",
+        );
+
+        if let Some(arg) = resp.arg
+            && let Some(i) = pending.iter().position(|symbol| *symbol == arg.inst_id)
+        {
+            result.subscribed.push(pending.remove(i));
         }
     }
 
-    let stream = stream! {
-        while let Some(msg) = client.next().await {
-            let msg = msg?;
-            match simd_json::from_slice::<DR>(&mut msg.as_payload().to_vec()) {
-                Ok(resp) => yield Ok(resp),
-                Err(e) => yield Err(e.into()),
+    let reason = unattributed.unwrap_or_else(|| {
+        format!("No subscription ack within {timeout:?} or {max_frames} frames")
+    });
+    result
+        .failed
+        .extend(pending.into_iter().map(|symbol| (symbol, reason.clone())));
+
+    if result.subscribed.is_empty() {
+        return Err(DataError::Subscription(format!(
+            "All {} subscriptions failed: {}",
+            result.failed.len(),
+            result
+                .failed
+                .iter()
+                .map(|(symbol, reason)| format!("{symbol} ({reason})"))
+                .join(", ")
+        ))
+        .into());
+    }
 
+    Ok((result, early_frames))
+}
+
+/// 转换订单簿推送，并检查 `prevSeqId` 是否衔接上一次推送的 `seqId`
+///
+/// 只有 books，books-l2-tbt，books50-l2-tbt 带有 `prevSeqId`，其他频道不做检查。
+/// 快照推送的 `prevSeqId` 为 -1，会重新开始跟踪。
+fn convert_okx_book_datas()
+-> impl FnMut(WsDataResponse<OkxBookData>) -> Result<Vec<BookData>> + Send + 'static {
+    let mut tracker = SequenceTracker::default();
+
+    move |resp| {
+        let is_snapshot = resp.action.as_deref() == Some("snapshot");
+
+        for book in &resp.data {
+            if let (Some(prev_seq_id), Some(seq_id)) = (book.prev_seq_id, book.seq_id) {
+                let prev = u64::try_from(prev_seq_id).ok().filter(|_| !is_snapshot);
+                tracker.check(&resp.arg.inst_id, prev, u64::try_from(seq_id)?)?;
             }
         }
-    };
 
-    Ok(Box::pin(stream))
+        resp.try_into()
+    }
 }
 
 fn convert_okx_candle_datas(
@@ -233,23 +573,7 @@ fn convert_okx_candle_datas(
         .into_iter()
         .take_while(|candle| matches!(candle.8.as_ref(), "1")) // 只取已完成的K线
         .map(|candle| {
-            let open_timestamp = candle.0.parse()?;
-            let open = candle.1.parse()?;
-            let high = candle.2.parse()?;
-            let low = candle.3.parse()?;
-            let close = candle.4.parse()?;
-            let volume = candle.5.parse()?;
-
-            Ok(CandleData {
-                symbol: resp.arg.inst_id.clone(),
-                interval_sc,
-                open_timestamp_ms: open_timestamp,
-                open,
-                high,
-                low,
-                close,
-                volume,
-            })
+            CandleData::from_exchange_candle(candle, resp.arg.inst_id.clone(), interval_sc)
         })
         .try_collect()
 }
@@ -316,18 +640,40 @@ pub enum OkxCandleInterval {
     Other(u64),
 }
 
+impl OkxCandleInterval {
+    /// K 线周期的边界是否按 UTC 对齐
+    ///
+    /// OKX 的 `6H` 及以上的默认频道按香港时间（UTC+8）开盘，对应的 `*utc` 频道按 UTC 开盘。
+    /// `4H` 及以下的周期整除 8 小时，两种时区的边界相同，视为按 UTC 对齐。
+    pub fn is_utc_aligned(&self) -> bool {
+        !matches!(
+            self,
+            OkxCandleInterval::Mon3
+                | OkxCandleInterval::Mon1
+                | OkxCandleInterval::Week1
+                | OkxCandleInterval::D1
+                | OkxCandleInterval::D2
+                | OkxCandleInterval::D3
+                | OkxCandleInterval::D5
+                | OkxCandleInterval::H12
+                | OkxCandleInterval::H6
+        )
+    }
+}
+
+/// 周期的秒数，`*utc` 频道与对应的默认频道相同，只是开盘时间不同
 impl From<OkxCandleInterval> for u64 {
     fn from(val: OkxCandleInterval) -> Self {
         match val {
-            OkxCandleInterval::Mon3 => CANDLE_INTERVAL_MON3,
-            OkxCandleInterval::Mon1 => CANDLE_INTERVAL_MON1,
-            OkxCandleInterval::Week1 => CANDLE_INTERVAL_WEEK1,
-            OkxCandleInterval::D1 => CANDLE_INTERVAL_D1,
-            OkxCandleInterval::D2 => CANDLE_INTERVAL_D1 * 2,
-            OkxCandleInterval::D3 => CANDLE_INTERVAL_D3,
-            OkxCandleInterval::D5 => CANDLE_INTERVAL_D1 * 5,
-            OkxCandleInterval::H12 => CANDLE_INTERVAL_H12,
-            OkxCandleInterval::H6 => CANDLE_INTERVAL_H6,
+            OkxCandleInterval::Mon3 | OkxCandleInterval::UtcMon3 => CANDLE_INTERVAL_MON3,
+            OkxCandleInterval::Mon1 | OkxCandleInterval::UtcMon1 => CANDLE_INTERVAL_MON1,
+            OkxCandleInterval::Week1 | OkxCandleInterval::UtcWeek1 => CANDLE_INTERVAL_WEEK1,
+            OkxCandleInterval::D1 | OkxCandleInterval::UtcD1 => CANDLE_INTERVAL_D1,
+            OkxCandleInterval::D2 | OkxCandleInterval::UtcD2 => CANDLE_INTERVAL_D1 * 2,
+            OkxCandleInterval::D3 | OkxCandleInterval::UtcD3 => CANDLE_INTERVAL_D3,
+            OkxCandleInterval::D5 | OkxCandleInterval::UtcD5 => CANDLE_INTERVAL_D1 * 5,
+            OkxCandleInterval::H12 | OkxCandleInterval::UtcH12 => CANDLE_INTERVAL_H12,
+            OkxCandleInterval::H6 | OkxCandleInterval::UtcH6 => CANDLE_INTERVAL_H6,
             OkxCandleInterval::H4 => CANDLE_INTERVAL_H4,
             OkxCandleInterval::H2 => CANDLE_INTERVAL_H2,
             OkxCandleInterval::H1 => CANDLE_INTERVAL_H1,
@@ -337,15 +683,6 @@ impl From<OkxCandleInterval> for u64 {
             OkxCandleInterval::Min3 => CANDLE_INTERVAL_MIN3,
             OkxCandleInterval::Min1 => CANDLE_INTERVAL_MIN1,
             OkxCandleInterval::Sec1 => CANDLE_INTERVAL_SEC1,
-            OkxCandleInterval::UtcMon3 => CANDLE_INTERVAL_MIN3,
-            OkxCandleInterval::UtcMon1 => CANDLE_INTERVAL_MON1,
-            OkxCandleInterval::UtcWeek1 => CANDLE_INTERVAL_WEEK1,
-            OkxCandleInterval::UtcD1 => CANDLE_INTERVAL_D1,
-            OkxCandleInterval::UtcD2 => CANDLE_INTERVAL_D1 * 2,
-            OkxCandleInterval::UtcD3 => CANDLE_INTERVAL_D3,
-            OkxCandleInterval::UtcD5 => CANDLE_INTERVAL_D1 * 5,
-            OkxCandleInterval::UtcH12 => CANDLE_INTERVAL_H12,
-            OkxCandleInterval::UtcH6 => CANDLE_INTERVAL_H6,
             OkxCandleInterval::Other(interval) => interval,
         }
     }
@@ -398,24 +735,358 @@ mod tests {
         assert_eq!(OkxCandleInterval::UtcH12.to_string(), "candle12Hutc");
     }
 
+    #[test]
+    fn test_utc_candle_interval() {
+        use OkxCandleInterval::*;
+
+        let pairs = [
+            (Mon3, UtcMon3),
+            (Mon1, UtcMon1),
+            (Week1, UtcWeek1),
+            (D1, UtcD1),
+            (D2, UtcD2),
+            (D3, UtcD3),
+            (D5, UtcD5),
+            (H12, UtcH12),
+            (H6, UtcH6),
+        ];
+        for (local, utc) in pairs {
+            assert!(!local.is_utc_aligned());
+            assert!(utc.is_utc_aligned());
+            assert_eq!(u64::from(local), u64::from(utc));
+        }
+
+        assert_eq!(u64::from(UtcMon3), CANDLE_INTERVAL_MON3);
+        assert!(H4.is_utc_aligned());
+    }
+
+    fn ack_frame(inst_id: &str) -> Result<Message, std::io::Error> {
+        Ok(Message::text(format!(
+            r#"{{"event":"subscribe","arg":{{"channel":"trades","instId":"{inst_id}"}},"connId":"a4d3ae55"}}"#
+        )))
+    }
+
+    fn data_frame() -> Result<Message, std::io::Error> {
+        Ok(Message::text(
+            r#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[]}"#,
+        ))
+    }
+
+    fn args(inst_ids: &[&str]) -> Vec<Arg> {
+        inst_ids
+            .iter()
+            .map(|inst_id| Arg::new("trades", *inst_id))
+            .collect()
+    }
+
+    fn error_frame(inst_id: &str) -> Result<Message, std::io::Error> {
+        Ok(Message::text(format!(
+            r#"{{"event":"error","code":"60018","msg":"Wrong URL or channel:trades,instId:{inst_id} doesn't exist.","connId":"a4d3ae55"}}"#
+        )))
+    }
+
     #[tokio::test]
-    async fn test_okx_trade_data_stream() {
-        okx_trade_data_stream(SYMBOLS.to_vec())
+    async fn test_await_subscribe_acks() {
+        // 确认之间夹杂的数据推送被保留，握手后重放
+        let mut client =
+            futures::stream::iter([ack_frame("BTC-USDT"), data_frame(), ack_frame("ETH-USDT")]);
+        let (result, early_frames) = await_subscribe_acks(
+            &mut client,
+            &args(&["BTC-USDT", "ETH-USDT"]),
+            8,
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        assert!(result.is_complete());
+        assert_eq!(result.subscribed, vec!["BTC-USDT", "ETH-USDT"]);
+        assert_eq!(early_frames.len(), 1);
+        assert_eq!(early_frames[0].as_text(), data_frame().unwrap().as_text());
+
+        // 部分交易对被拒绝，其余交易对照常订阅
+        let mut client = futures::stream::iter([
+            ack_frame("BTC-USDT"),
+            error_frame("FOO-BAR"),
+            data_frame(),
+            error_frame("BTC-USDT-SWAP"),
+            ack_frame("ETH-USDT"),
+        ])
+        .chain(futures::stream::pending());
+        let (result, _) = await_subscribe_acks(
+            &mut client,
+            &args(&["BTC-USDT", "FOO-BAR", "BTC-USDT-SWAP", "ETH-USDT"]),
+            8,
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.subscribed, vec!["BTC-USDT", "ETH-USDT"]);
+        let failed: Vec<_> = result.failed.iter().map(|(s, _)| s.clone()).collect();
+        assert_eq!(failed, vec!["FOO-BAR", "BTC-USDT-SWAP"]);
+        assert!(result.failed[0].1.contains("60018"));
+
+        // 全部被拒绝
+        let mut client = futures::stream::iter([error_frame("FOO-BAR")]);
+        let err = await_subscribe_acks(&mut client, &args(&["FOO-BAR"]), 8, Duration::from_secs(1))
             .await
-            .unwrap()
-            .take(TEST_DATA_NUM)
-            .for_each(|res| {
-                assert!(SYMBOLS.contains(&res.unwrap().symbol));
-                std::future::ready(())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DataError>(),
+            Some(DataError::Subscription(msg)) if msg.contains("FOO-BAR") && msg.contains("60018")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_await_subscribe_acks_missing_ack() {
+        // 第二个频道的确认始终没有到来，只有源源不断的数据推送
+        let mut client = futures::stream::iter([ack_frame("BTC-USDT")])
+            .chain(futures::stream::repeat_with(data_frame));
+        let (result, early_frames) = await_subscribe_acks(
+            &mut client,
+            &args(&["BTC-USDT", "ETH-USDT"]),
+            8,
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.subscribed, vec!["BTC-USDT"]);
+        assert_eq!(result.failed[0].0, "ETH-USDT");
+        // 读满 8 帧，除第一个确认外都是数据推送
+        assert_eq!(early_frames.len(), 7);
+
+        // 连接空闲，没有任何帧
+        let mut client =
+            futures::stream::iter([ack_frame("BTC-USDT")]).chain(futures::stream::pending());
+        let (result, _) = await_subscribe_acks(
+            &mut client,
+            &args(&["BTC-USDT", "ETH-USDT"]),
+            8,
+            Duration::from_millis(20),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.failed.len(), 1);
+
+        // 一个确认都没有
+        let mut client = futures::stream::pending::<Result<Message, std::io::Error>>();
+        let err = await_subscribe_acks(
+            &mut client,
+            &args(&["BTC-USDT"]),
+            8,
+            Duration::from_millis(20),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DataError>(),
+            Some(DataError::Subscription(_))
+        ));
+    }
+
+    async fn next_text<E: std::fmt::Debug>(
+        ws: &mut (impl Stream<Item = Result<Message, E>> + Unpin),
+    ) -> String {
+        let msg = ws.next().await.unwrap().unwrap();
+        msg.as_text().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_okx_raw_data_stream_keepalive() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let keepalive = OkxKeepAlive {
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(50),
+        };
+
+        let server = tokio::spawn(async move {
+            let (_, mut ws) = tokio_websockets::ServerBuilder::new()
+                .accept(server_io)
+                .await
+                .unwrap();
+            assert!(next_text(&mut ws).await.contains("subscribe"));
+            ws.send(ack_frame("BTC-USDT").unwrap()).await.unwrap();
+            ws.send(data_frame().unwrap()).await.unwrap();
+
+            // 服务端发送的 ping 得到 pong 回应
+            ws.send(Message::text("ping")).await.unwrap();
+            assert_eq!(next_text(&mut ws).await, "pong");
+
+            // 空闲时客户端发送 ping，回应 pong 后连接保持
+            assert_eq!(next_text(&mut ws).await, "ping");
+            ws.send(Message::text("pong")).await.unwrap();
+            ws.send(data_frame().unwrap()).await.unwrap();
+
+            // 不再回应 pong，客户端应判定连接已断开
+            assert_eq!(next_text(&mut ws).await, "ping");
+            ws
+        });
+
+        let request = WsRequest {
+            op: WsOperation::Subscribe,
+            args: args(&["BTC-USDT"]),
+            id: None,
+        };
+        let (stream, result) = okx_raw_data_stream::<serde::de::IgnoredAny>(
+            "ws://localhost/ws/v5/public",
+            request,
+            client_io,
+            keepalive,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(result.is_complete());
+
+        let items: Vec<_> = stream.collect().await;
+        assert_eq!(items.len(), 3);
+        // ping 与 pong 被跳过，不会作为数据推送解析
+        assert!(items[0].is_ok() && items[1].is_ok());
+        let err = items[2].as_ref().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DataError>(),
+            Some(DataError::Connection(msg)) if msg.contains("pong")
+        ));
+        server.await.unwrap();
+    }
+
+    /// 建立一条只推送一条无法解析的消息和一条正常消息的数据流
+    async fn raw_stream_with_garbage(
+        config: WsConfig,
+    ) -> Vec<Result<serde::de::IgnoredAny, eyre::Error>> {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+
+        let server = tokio::spawn(async move {
+            let (_, mut ws) = tokio_websockets::ServerBuilder::new()
+                .accept(server_io)
+                .await
+                .unwrap();
+            assert!(next_text(&mut ws).await.contains("subscribe"));
+            ws.send(ack_frame("BTC-USDT").unwrap()).await.unwrap();
+            ws.send(Message::text("not json")).await.unwrap();
+            ws.send(data_frame().unwrap()).await.unwrap();
+            ws.close().await.unwrap();
+        });
+
+        let request = WsRequest {
+            op: WsOperation::Subscribe,
+            args: args(&["BTC-USDT"]),
+            id: None,
+        };
+        let (stream, _) = okx_raw_data_stream::<serde::de::IgnoredAny>(
+            "ws://localhost/ws/v5/public",
+            request,
+            client_io,
+            OkxKeepAlive::default(),
+            config,
+        )
+        .await
+        .unwrap();
+
+        let items = stream.collect().await;
+        server.await.unwrap();
+        items
+    }
+
+    #[tokio::test]
+    async fn test_okx_raw_data_stream_per_stream_parse_mode() {
+        // 同一进程中的两条数据流使用不同的解析模式，互不影响
+        let tolerant = raw_stream_with_garbage(WsConfig {
+            parse_mode: ParseMode::Tolerant,
+            ..Default::default()
+        })
+        .await;
+        let strict = raw_stream_with_garbage(WsConfig::default()).await;
+
+        assert_eq!(tolerant.len(), 1);
+        assert!(tolerant[0].is_ok());
+        assert_eq!(strict.len(), 2);
+        assert!(strict[0].is_err() && strict[1].is_ok());
+    }
+
+    #[test]
+    fn test_convert_okx_candle_datas() {
+        let mut msg = br#"{"arg":{"channel":"candle1m","instId":"BTC-USDT"},"data":[["1640000000000","50000","50100","49900","50050","12.5","625000","625000","1"],["1640000060000","50050","50060","50040","50050","0.5","25000","25000","0"]]}"#.to_vec();
+        let resp = simd_json::from_slice::<WsDataResponse<RawCandleData>>(&mut msg).unwrap();
+
+        // 未完结的 K 线被丢弃
+        let candles = convert_okx_candle_datas(resp, 60).unwrap();
+        assert_eq!(
+            candles,
+            vec![CandleData {
+                symbol: "BTC-USDT".into(),
+                interval_sc: 60,
+                open_timestamp_ms: 1640000000000,
+                open: 50000.0,
+                high: 50100.0,
+                low: 49900.0,
+                close: 50050.0,
+                volume: 12.5,
+                trade_count: 0,
+                quote_volume: 625000.0,
+                quote_volume_estimated: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_convert_okx_candle_datas_extra_fields() {
+        // 交易所在数组末尾、对象中追加了未知字段
+        let mut msg = br#"{"arg":{"channel":"candle1m","instId":"BTC-USDT","extra":1},"data":[["1640000000000","50000","50100","49900","50050","12.5","625000","625000","1","new"]],"unknown":{"a":[1,2]}}"#.to_vec();
+        let resp = simd_json::from_slice::<WsDataResponse<RawCandleData>>(&mut msg).unwrap();
+
+        let candles = convert_okx_candle_datas(resp, 60).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].close, 50050.0);
+        assert_eq!(candles[0].quote_volume, 625000.0);
+        // VWAP 来自 volCcyQuote
+        assert_eq!(candles[0].vwap(), Some(50000.0));
+
+        // 缺少字段仍然报错
+        let mut msg = br#"{"arg":{"channel":"candle1m","instId":"BTC-USDT"},"data":[["1640000000000","50000","50100"]]}"#.to_vec();
+        assert!(simd_json::from_slice::<WsDataResponse<RawCandleData>>(&mut msg).is_err());
+    }
+
+    #[test]
+    fn test_convert_okx_book_datas_sequence_gap() {
+        let book_msg = |action: &str, prev_seq_id: i64, seq_id: i64| {
+            let mut msg = format!(
+                r#"{{"arg":{{"channel":"books","instId":"BTC-USDT"}},"action":"{action}","data":[{{"asks":[["50001","1","0","1"]],"bids":[["50000","2","0","1"]],"ts":"1640000000000","checksum":0,"prevSeqId":{prev_seq_id},"seqId":{seq_id}}}]}}"#
+            )
+            .into_bytes();
+            simd_json::from_slice::<WsDataResponse<OkxBookData>>(&mut msg).unwrap()
+        };
+
+        let mut convert = convert_okx_book_datas();
+
+        let books = convert(book_msg("snapshot", -1, 100)).unwrap();
+        assert_eq!(books[0].bids[0], (50000.0, 2.0));
+        assert!(books[0].is_snapshot);
+        let books = convert(book_msg("update", 100, 101)).unwrap();
+        assert!(!books[0].is_snapshot);
+
+        let err = convert(book_msg("update", 105, 106)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DataError>(),
+            Some(DataError::SequenceGap {
+                expected: 101,
+                found: 105,
+                ..
             })
-            .await;
+        ));
+
+        // 重新订阅后的快照重置跟踪，并标记下游需要重建订单簿
+        let books = convert(book_msg("snapshot", -1, 200)).unwrap();
+        assert!(books[0].is_snapshot);
+        let books = convert(book_msg("update", 200, 201)).unwrap();
+        assert!(!books[0].is_snapshot);
     }
 
     #[tokio::test]
-    async fn test_okx_candle_data_stream() {
-        okx_candle_data_stream(SYMBOLS.to_vec(), OkxCandleInterval::Sec1)
+    async fn test_okx_trade_data_stream() {
+        okx_trade_data_stream(OkxEnvironment::Live, SYMBOLS.to_vec(), WsConfig::default())
             .await
             .unwrap()
+            .0
             .take(TEST_DATA_NUM)
             .for_each(|res| {
                 assert!(SYMBOLS.contains(&res.unwrap().symbol));
@@ -424,17 +1095,42 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_okx_candle_data_stream() {
+        okx_candle_data_stream(
+            OkxEnvironment::Live,
+            SYMBOLS.to_vec(),
+            OkxCandleInterval::Sec1,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap()
+        .0
+        .take(TEST_DATA_NUM)
+        .for_each(|res| {
+            assert!(SYMBOLS.contains(&res.unwrap().symbol));
+            std::future::ready(())
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn test_okx_book_data_stream() {
-        okx_book_data_stream(SYMBOLS.to_vec(), OkxBookChannel::BboTbt)
-            .await
-            .unwrap()
-            .take(TEST_DATA_NUM)
-            .for_each(|res| {
-                assert!(SYMBOLS.contains(&res.unwrap().symbol));
-                std::future::ready(())
-            })
-            .await;
+        okx_book_data_stream(
+            OkxEnvironment::Live,
+            SYMBOLS.to_vec(),
+            OkxBookChannel::BboTbt,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap()
+        .0
+        .take(TEST_DATA_NUM)
+        .for_each(|res| {
+            assert!(SYMBOLS.contains(&res.unwrap().symbol));
+            std::future::ready(())
+        })
+        .await;
     }
 }
 
@@ -466,9 +1162,10 @@ mod tests_xdp {
     #[tokio::test]
     async fn test_okx_xdp_trade_data_stream() {
         setup();
-        okx_xdp_trade_data_stream(SYMBOLS.to_vec())
+        okx_xdp_trade_data_stream(OkxEnvironment::Live, SYMBOLS.to_vec(), WsConfig::default())
             .await
             .unwrap()
+            .0
             .take(TEST_DATA_NUM)
             .for_each(|res| {
                 assert!(SYMBOLS.contains(&res.unwrap().symbol));
@@ -480,28 +1177,40 @@ mod tests_xdp {
     #[tokio::test]
     async fn test_xdp_okx_candle_data_stream() {
         setup();
-        okx_xdp_candle_data_stream(SYMBOLS.to_vec(), OkxCandleInterval::Sec1)
-            .await
-            .unwrap()
-            .take(TEST_DATA_NUM)
-            .for_each(|res| {
-                assert!(SYMBOLS.contains(&res.unwrap().symbol));
-                std::future::ready(())
-            })
-            .await;
+        okx_xdp_candle_data_stream(
+            OkxEnvironment::Live,
+            SYMBOLS.to_vec(),
+            OkxCandleInterval::Sec1,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap()
+        .0
+        .take(TEST_DATA_NUM)
+        .for_each(|res| {
+            assert!(SYMBOLS.contains(&res.unwrap().symbol));
+            std::future::ready(())
+        })
+        .await;
     }
 
     #[tokio::test]
     async fn test_xdp_okx_book_data_stream() {
         setup();
-        okx_xdp_book_data_stream(SYMBOLS.to_vec(), OkxBookChannel::BboTbt)
-            .await
-            .unwrap()
-            .take(TEST_DATA_NUM)
-            .for_each(|res| {
-                assert!(SYMBOLS.contains(&res.unwrap().symbol));
-                std::future::ready(())
-            })
-            .await;
+        okx_xdp_book_data_stream(
+            OkxEnvironment::Live,
+            SYMBOLS.to_vec(),
+            OkxBookChannel::BboTbt,
+            WsConfig::default(),
+        )
+        .await
+        .unwrap()
+        .0
+        .take(TEST_DATA_NUM)
+        .for_each(|res| {
+            assert!(SYMBOLS.contains(&res.unwrap().symbol));
+            std::future::ready(())
+        })
+        .await;
     }
 }
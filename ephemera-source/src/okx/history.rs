@@ -0,0 +1,190 @@
+use super::{
+    OKX_REST_API_BASE, OkxCandleInterval, OkxEnvironment,
+    model::{HttpCandleDataRequest, HttpResponse, RawCandleData},
+};
+use crate::utils::FromExchangeCandle;
+use bytestring::ByteString;
+use ephemera_shared::*;
+use eyre::{Context, Result, ensure};
+use itertools::Itertools;
+use std::time::Duration;
+
+/// `/api/v5/market/history-candles` 每次请求最多返回的 K 线数量
+const HISTORY_CANDLES_LIMIT: usize = 100;
+
+/// 相邻两次分页请求的间隔。`history-candles` 的限速为每 2 秒 20 次
+const HISTORY_CANDLES_PAGE_DELAY: Duration = Duration::from_millis(120);
+
+/// 通过 REST 拉取 `[start_ms, end_ms)` 内开盘的历史 K 线，按时间升序返回
+///
+/// `history-candles` 从新到旧返回数据，因此从 `end_ms` 开始按 [`HISTORY_CANDLES_LIMIT`]
+/// 向前分页，每页之间等待一段时间以遵守频率限制。与 WebSocket 推送一样只返回已完成的 K 线。
+pub async fn okx_historical_candles(
+    env: OkxEnvironment,
+    symbol: impl Into<ByteString>,
+    interval: OkxCandleInterval,
+    start_ms: TimestampMs,
+    end_ms: TimestampMs,
+) -> Result<Vec<CandleData>> {
+    ensure!(
+        !matches!(interval, OkxCandleInterval::Other(_)),
+        "history-candles does not support custom interval {interval:?}"
+    );
+
+    let inst_id: ByteString = symbol.into();
+    let bar = interval.to_string();
+    let bar = ByteString::from(bar.strip_prefix("candle").unwrap_or(&bar));
+    let interval_sc = u64::from(interval);
+    let client = reqwest::Client::new();
+    let url = format!("{OKX_REST_API_BASE}/api/v5/market/history-candles");
+    let mut candles = Vec::new();
+    // `after` 之前（不含）的数据
+    let mut cursor = end_ms;
+
+    while cursor > start_ms {
+        let request = HttpCandleDataRequest {
+            inst_id: inst_id.clone(),
+            bar: Some(bar.clone()),
+            after: Some(cursor),
+            before: None,
+            limit: Some(HISTORY_CANDLES_LIMIT),
+        };
+        let bytes = env
+            .apply_header(client.get(&url).query(&request))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to fetch history candles of {inst_id}"))?
+            .bytes()
+            .await?;
+        let response: HttpResponse<RawCandleData> =
+            simd_json::serde::from_slice(&mut bytes.to_vec())
+                .context("Failed to parse history candles response")?;
+        if response.code != "0" {
+            eyre::bail!("API Error: code={}, msg={}", response.code, response.msg);
+        }
+
+        let Some(oldest) = response.data.last() else {
+            break;
+        };
+        let oldest_ms: TimestampMs = oldest.0.parse()?;
+        let page_len = response.data.len();
+
+        candles.extend(convert_okx_history_candles(
+            response.data,
+            &inst_id,
+            interval_sc,
+            start_ms,
+        )?);
+
+        if page_len < HISTORY_CANDLES_LIMIT {
+            break;
+        }
+        cursor = oldest_ms;
+        tokio::time::sleep(HISTORY_CANDLES_PAGE_DELAY).await;
+    }
+
+    // 各页依次更旧，页内也从新到旧
+    candles.reverse();
+    Ok(candles)
+}
+
+/// 转换一页历史 K 线，只保留已完成且不早于 `start_ms` 开盘的 K 线
+fn convert_okx_history_candles(
+    data: Vec<RawCandleData>,
+    inst_id: &ByteString,
+    interval_sc: IntervalSc,
+    start_ms: TimestampMs,
+) -> Result<Vec<CandleData>> {
+    data.into_iter()
+        .filter(|candle| candle.8 == "1")
+        .map(|candle| CandleData::from_exchange_candle(candle, inst_id.clone(), interval_sc))
+        .filter_ok(|candle| candle.open_timestamp_ms >= start_ms)
+        .try_collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_okx_history_candles() {
+        // 从新到旧: 未完成的 K 线被丢弃，早于 start_ms 的也被丢弃
+        let mut body = br#"{
+            "code": "0",
+            "msg": "",
+            "data": [
+                ["1597026600000", "3.722", "3.722", "3.721", "3.721", "1", "2", "7.4", "0"],
+                ["1597026540000", "3.721", "3.743", "3.72", "3.722", "8", "16", "29.7", "1"],
+                ["1597026480000", "3.73", "3.75", "3.71", "3.721", "5", "10", "18.6", "1"],
+                ["1597026420000", "3.7", "3.73", "3.69", "3.73", "2", "4", "7.4", "1"]
+            ]
+        }"#
+        .to_vec();
+        let response: HttpResponse<RawCandleData> =
+            simd_json::serde::from_slice(&mut body).unwrap();
+
+        let candles = convert_okx_history_candles(
+            response.data,
+            &"BTC-USDT".into(),
+            CANDLE_INTERVAL_MIN1,
+            1597026480000,
+        )
+        .unwrap();
+        let opens: Vec<_> = candles.iter().map(|c| c.open_timestamp_ms).collect();
+        assert_eq!(opens, vec![1597026540000, 1597026480000]);
+
+        let candle = &candles[0];
+        assert_eq!(candle.symbol, "BTC-USDT");
+        assert_eq!(candle.interval_sc, CANDLE_INTERVAL_MIN1);
+        assert_eq!(candle.high, 3.743);
+        assert_eq!(candle.volume, 8.0);
+        assert_eq!(candle.quote_volume, 29.7);
+    }
+
+    #[test]
+    fn test_history_candles_request_query() {
+        let request = HttpCandleDataRequest {
+            inst_id: "BTC-USDT".into(),
+            bar: Some("1H".into()),
+            after: Some(1597026600000),
+            before: None,
+            limit: Some(HISTORY_CANDLES_LIMIT),
+        };
+        let url = reqwest::Client::new()
+            .get("https://www.okx.com/api/v5/market/history-candles")
+            .query(&request)
+            .build()
+            .unwrap()
+            .url()
+            .clone();
+        assert_eq!(
+            url.query(),
+            Some("instId=BTC-USDT&bar=1H&after=1597026600000&limit=100")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_okx_historical_candles() {
+        // 跨越多页
+        let end_ms = chrono::Utc::now().timestamp_millis() as TimestampMs / 60_000 * 60_000;
+        let start_ms = end_ms - 250 * 60_000;
+        let candles = okx_historical_candles(
+            OkxEnvironment::Live,
+            "BTC-USDT",
+            OkxCandleInterval::Min1,
+            start_ms,
+            end_ms,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(candles.len(), 250);
+        assert!(
+            candles
+                .windows(2)
+                .all(|w| w[0].open_timestamp_ms + 60_000 == w[1].open_timestamp_ms)
+        );
+        assert_eq!(candles[0].open_timestamp_ms, start_ms);
+    }
+}
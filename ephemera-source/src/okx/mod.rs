@@ -2,17 +2,74 @@ pub mod auth;
 pub mod execution;
 pub mod fetch;
 
+mod history;
 mod model;
 
 pub use auth::{OkxAuth, okx_verified_auth_stream};
-pub use execution::{okx_execute_limit_orders, okx_execute_market_orders};
+pub use execution::{
+    InstrumentCache, InstrumentInfo, InstrumentSpec, OkxContractType, OkxInstType, OkxOrderConfig,
+    flatten_all, okx_execute_limit_orders, okx_execute_market_orders, okx_execute_orders,
+    okx_fetch_instruments,
+};
 pub use fetch::{
     OkxBookChannel, OkxCandleInterval, okx_xdp_book_data_stream, okx_xdp_candle_data_stream,
     okx_xdp_trade_data_stream,
 };
+pub use history::okx_historical_candles;
 pub use model::{OrderInfo, WsOperation};
 
 pub(super) const OKX_REST_API_BASE: &str = "https://www.okx.com";
 pub(super) const OKX_WS_HOST: &str = "ws.okx.com:8443";
 pub(super) const OKX_WS_PUBLICE_ENDPOINT: &str = "wss://ws.okx.com:8443/ws/v5/public";
 pub(super) const OKX_WS_BUSINESS_ENDPOINT: &str = "wss://ws.okx.com:8443/ws/v5/business";
+pub(super) const OKX_DEMO_WS_HOST: &str = "wspap.okx.com:8443";
+pub(super) const OKX_DEMO_WS_PUBLICE_ENDPOINT: &str = "wss://wspap.okx.com:8443/ws/v5/public";
+pub(super) const OKX_DEMO_WS_BUSINESS_ENDPOINT: &str = "wss://wspap.okx.com:8443/ws/v5/business";
+
+/// OKX 服务环境
+///
+/// 模拟盘的 WebSocket 使用独立的域名，REST 与实盘同域名，但需要带上
+/// `x-simulated-trading: 1` 请求头。通常由 [`OkxAuth::environment`] 得到，保证数据流与下单
+/// 使用同一个环境。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OkxEnvironment {
+    #[default]
+    Live,
+    Demo,
+}
+
+impl OkxEnvironment {
+    pub fn is_simulated(self) -> bool {
+        self == OkxEnvironment::Demo
+    }
+
+    pub(super) fn ws_host(self) -> &'static str {
+        match self {
+            OkxEnvironment::Live => OKX_WS_HOST,
+            OkxEnvironment::Demo => OKX_DEMO_WS_HOST,
+        }
+    }
+
+    pub(super) fn ws_public_endpoint(self) -> &'static str {
+        match self {
+            OkxEnvironment::Live => OKX_WS_PUBLICE_ENDPOINT,
+            OkxEnvironment::Demo => OKX_DEMO_WS_PUBLICE_ENDPOINT,
+        }
+    }
+
+    pub(super) fn ws_business_endpoint(self) -> &'static str {
+        match self {
+            OkxEnvironment::Live => OKX_WS_BUSINESS_ENDPOINT,
+            OkxEnvironment::Demo => OKX_DEMO_WS_BUSINESS_ENDPOINT,
+        }
+    }
+
+    /// 模拟盘时为 REST 请求加上 `x-simulated-trading` 请求头
+    pub(super) fn apply_header(self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.is_simulated() {
+            request.header("x-simulated-trading", "1")
+        } else {
+            request
+        }
+    }
+}
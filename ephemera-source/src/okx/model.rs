@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use crate::utils::{FromExchangeCandle, impl_positional_deserialize};
 use bytestring::ByteString;
 use ephemera_shared::*;
 use eyre::Result;
@@ -9,6 +10,7 @@ use std::{fmt::Debug, str::FromStr};
 use strum::{AsRefStr, Display, EnumString};
 
 #[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub(super) struct HttpCandleDataRequest {
     /// 产品ID，如 BTC-USDT
     pub(super) inst_id: ByteString,
@@ -16,12 +18,16 @@ pub(super) struct HttpCandleDataRequest {
     /// 如 [1m/3m/5m/15m/30m/1H/2H/4H]
     /// 香港时间开盘价k线：[6H/12H/1D/2D/3D/1W/1M/3M]
     /// UTC时间开盘价k线：[/6Hutc/12Hutc/1Dutc/2Dutc/3Dutc/1Wutc/1Mutc/3Mutc]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) bar: Option<ByteString>,
     /// 请求此时间戳之前（更旧的数据）的分页内容，传的值为对应接口的ts
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) after: Option<TimestampMs>,
     /// 请求此时间戳之后（更新的数据）的分页内容，传的值为对应接口的ts, 单独使用时，会返回最新的数据。
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) before: Option<TimestampMs>,
-    /// 分页返回的结果集数量，最大为300，不填默认返回100条
+    /// 分页返回的结果集数量，`candles` 最大为 300，`history-candles` 最大为 100，不填默认返回 100 条
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) limit: Option<usize>,
 }
 
@@ -110,7 +116,7 @@ impl TryFrom<WsDataResponse<OkxBookData>> for Vec<BookData> {
         let parse_levels = |levels: Vec<Level>| -> Result<BookSide> {
             levels
                 .into_iter()
-                .map(|(price_str, size_str, _, _)| {
+                .map(|Level(price_str, size_str, _, _)| {
                     let price = price_str.parse::<f64>()?;
                     let size = size_str.parse::<f64>()?;
                     Ok((price, size))
@@ -119,6 +125,8 @@ impl TryFrom<WsDataResponse<OkxBookData>> for Vec<BookData> {
         };
 
         let symbol = value.arg.inst_id;
+        // 只有增量频道会带 `action`，其余频道每次推送都是完整快照
+        let is_snapshot = value.action.as_deref() != Some("update");
 
         value
             .data
@@ -133,6 +141,7 @@ impl TryFrom<WsDataResponse<OkxBookData>> for Vec<BookData> {
                     timestamp,
                     bids,
                     asks,
+                    is_snapshot,
                 })
             })
             .try_collect()
@@ -198,7 +207,11 @@ pub(super) struct RawTradeData {
 /// 6.交易量（以计价货币为单位）
 /// 7.交易量（以计价货币为单位，适用于合约）
 /// 8.K线状态 (1: a confirmed candle)
-#[derive(Debug, Clone, Deserialize, Serialize)]
+///
+/// 成交额取 7，以计价货币为单位，现货和合约都适用
+///
+/// 按位置解析，末尾多出的元素会被忽略，交易所追加字段时不会解析失败
+#[derive(Debug, Clone, Serialize)]
 pub(super) struct RawCandleData(
     pub(super) ByteString,
     pub(super) ByteString,
@@ -211,11 +224,43 @@ pub(super) struct RawCandleData(
     pub(super) ByteString,
 );
 
+impl FromExchangeCandle<RawCandleData> for CandleData {
+    fn from_exchange_candle(
+        raw: RawCandleData,
+        symbol: Symbol,
+        interval_sc: IntervalSc,
+    ) -> Result<Self> {
+        Ok(Self {
+            symbol,
+            interval_sc,
+            open_timestamp_ms: raw.0.parse()?,
+            open: raw.1.parse()?,
+            high: raw.2.parse()?,
+            low: raw.3.parse()?,
+            close: raw.4.parse()?,
+            volume: raw.5.parse()?,
+            // OKX 的 K 线不提供成交笔数
+            trade_count: 0,
+            quote_volume: raw.7.parse()?,
+            quote_volume_estimated: false,
+        })
+    }
+}
+
 /// 0. 价格,
 /// 1. 数量,
 /// 2. 流动性订单数量,
 /// 3. 订单数量
-pub(super) type Level = (ByteString, ByteString, ByteString, ByteString);
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct Level(
+    pub(super) ByteString,
+    pub(super) ByteString,
+    pub(super) ByteString,
+    pub(super) ByteString,
+);
+
+impl_positional_deserialize!(RawCandleData, 0, 1, 2, 3, 4, 5, 6, 7, 8);
+impl_positional_deserialize!(Level, 0, 1, 2, 3);
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -245,9 +290,85 @@ pub(super) struct PlaceOrderRequest {
     pub sz: ByteString,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub px: Option<ByteString>,
+    /// 市价单 `sz` 的单位，`base_ccy` 表示按交易货币计。现货市价买单默认按计价货币计
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tgt_ccy: Option<ByteString>,
+    /// 开平仓模式下必填，`long`/`short`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos_side: Option<ByteString>,
+    /// 只减仓，买卖模式下平仓时使用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+}
+
+/// 持仓信息，`/api/v5/account/positions`（只保留平仓所需字段）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawPosition {
+    pub inst_id: ByteString,
+    pub mgn_mode: TradeMode,
+    /// `net` 表示买卖模式，此时 `pos` 的正负表示多空；`long`/`short` 表示开平仓模式
+    pub pos_side: ByteString,
+    /// 持仓数量
+    #[serde(default)]
+    pub pos: ByteString,
+}
+
+/// 设置杠杆请求，`/api/v5/account/set-leverage`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SetLeverageRequest {
+    pub inst_id: ByteString,
+    pub lever: ByteString,
+    /// 保证金模式，只能是 `cross` 或 `isolated`
+    pub mgn_mode: TradeMode,
+}
+
+/// 设置杠杆的返回
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SetLeverageResponse {
+    pub lever: ByteString,
+    pub mgn_mode: TradeMode,
+}
+
+/// 下单接口的返回，只包含订单 ID 和处理结果
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PlaceOrderResponse {
+    pub ord_id: ByteString,
+    #[serde(default)]
+    pub cl_ord_id: ByteString,
+    /// 事件执行结果，`0` 表示成功
+    pub s_code: ByteString,
+    pub s_msg: ByteString,
+}
+
+/// `/api/v5/public/instruments` 返回的产品信息（只保留精度和合约相关字段）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawInstrument {
+    pub inst_id: ByteString,
+    #[serde(default)]
+    pub inst_type: ByteString,
+    /// 合约面值，仅适用于交割/永续/期权
+    #[serde(default)]
+    pub ct_val: ByteString,
+    /// 合约类型 `linear`/`inverse`，仅适用于交割/永续
+    #[serde(default)]
+    pub ct_type: ByteString,
+    /// 最小下单数量
+    pub min_sz: ByteString,
+    /// 下单数量精度
+    pub lot_sz: ByteString,
+    /// 下单价格精度
+    pub tick_sz: ByteString,
 }
 
 /// 订单信息
+///
+/// `px`/`sz` 是委托的价格和数量，实际成交以 `acc_fill_sz`/`avg_px` 为准，
+/// 见 [`OrderInfo::filled_size`] 和 [`OrderInfo::avg_fill_price`]。
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderInfo {
@@ -272,3 +393,15 @@ pub struct OrderInfo {
     #[serde(default)]
     pub u_time: ByteString,
 }
+
+impl OrderInfo {
+    /// 累计成交数量，未成交时为 0
+    pub fn filled_size(&self) -> f64 {
+        self.acc_fill_sz.parse().unwrap_or_default()
+    }
+
+    /// 成交均价，未成交时为 `None`
+    pub fn avg_fill_price(&self) -> Option<f64> {
+        self.avg_px.parse().ok().filter(|px| *px > 0.0)
+    }
+}
@@ -0,0 +1,342 @@
+//! 可复现的合成行情
+//!
+//! 用几何布朗运动生成价格路径，不依赖网络，适合演示、测试和压测。
+//! 相同的 [`SyntheticConfig`]（包括 `seed`）总是生成相同的数据序列，时间戳也由配置推算，
+//! 与墙上时钟无关；`rate` 只控制输出的节奏。
+
+use crate::clock::{Clock, SystemClock};
+use async_stream::stream;
+use ephemera_shared::{CandleData, IntervalSc, Side, Symbol, TimestampMs, TradeData};
+use eyre::{Result, ensure};
+use futures::Stream;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::time::Duration;
+
+/// 合成行情的默认起始时间: 2023-01-01 00:00:00 UTC
+pub const SYNTHETIC_START_MS: TimestampMs = 1672531200000;
+
+const MS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0 * 1000.0;
+
+/// 合成行情的参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticConfig {
+    pub symbol: Symbol,
+    /// K 线周期（秒）
+    pub interval_sc: IntervalSc,
+    /// 第一根 K 线的开盘时间
+    pub start_ms: TimestampMs,
+    pub initial_price: f64,
+    /// 年化漂移率
+    pub drift: f64,
+    /// 年化波动率
+    pub volatility: f64,
+    /// 每根 K 线包含的成交笔数，成交均匀分布在 K 线周期内
+    pub ticks_per_candle: usize,
+    /// 每笔成交的平均数量，实际数量在 `(0, 2 * mean_quantity)` 内均匀分布
+    pub mean_quantity: f64,
+    /// 每秒输出的条数，`None` 表示不等待、尽快输出
+    pub rate: Option<f64>,
+    /// 输出的总条数，`None` 表示无限
+    pub limit: Option<usize>,
+    pub seed: u64,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            symbol: Symbol::default(),
+            interval_sc: 60,
+            start_ms: SYNTHETIC_START_MS,
+            initial_price: 100.0,
+            drift: 0.0,
+            volatility: 0.5,
+            ticks_per_candle: 60,
+            mean_quantity: 1.0,
+            rate: None,
+            limit: None,
+            seed: 0,
+        }
+    }
+}
+
+impl SyntheticConfig {
+    pub fn new(symbol: impl Into<Symbol>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(self.interval_sc > 0, "interval_sc must be positive");
+        ensure!(
+            self.initial_price.is_finite() && self.initial_price > 0.0,
+            "initial_price must be positive"
+        );
+        ensure!(
+            self.volatility.is_finite() && self.volatility >= 0.0,
+            "volatility must be non-negative"
+        );
+        ensure!(self.drift.is_finite(), "drift must be finite");
+        ensure!(
+            self.ticks_per_candle > 0,
+            "ticks_per_candle must be positive"
+        );
+        ensure!(
+            self.mean_quantity.is_finite() && self.mean_quantity > 0.0,
+            "mean_quantity must be positive"
+        );
+        if let Some(rate) = self.rate {
+            ensure!(rate.is_finite() && rate > 0.0, "rate must be positive");
+        }
+        Ok(())
+    }
+
+    /// 相邻两次输出之间的等待时间
+    fn pace(&self) -> Option<Duration> {
+        self.rate.map(|rate| Duration::from_secs_f64(1.0 / rate))
+    }
+}
+
+/// 按几何布朗运动逐笔推进的价格路径
+struct PricePath {
+    rng: StdRng,
+    price: f64,
+    /// 每一步的对数收益率均值
+    mu: f64,
+    /// 每一步的对数收益率标准差
+    sigma: f64,
+    mean_quantity: f64,
+    start_ms: TimestampMs,
+    interval_ms: TimestampMs,
+    ticks_per_candle: u64,
+    tick: u64,
+}
+
+impl PricePath {
+    fn new(config: &SyntheticConfig) -> Self {
+        let interval_ms = config.interval_sc * 1000;
+        let dt = interval_ms as f64 / config.ticks_per_candle as f64 / MS_PER_YEAR;
+
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            price: config.initial_price,
+            mu: (config.drift - config.volatility.powi(2) / 2.0) * dt,
+            sigma: config.volatility * dt.sqrt(),
+            mean_quantity: config.mean_quantity,
+            start_ms: config.start_ms,
+            interval_ms,
+            ticks_per_candle: config.ticks_per_candle as u64,
+            tick: 0,
+        }
+    }
+
+    /// 标准正态分布（Box-Muller）
+    fn standard_normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.rng.random::<f64>();
+        let u2 = self.rng.random::<f64>();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+
+    /// 推进一步，返回 `(时间戳, 价格, 数量, 方向)`
+    fn next_tick(&mut self) -> (TimestampMs, f64, f64, Side) {
+        let candle = self.tick / self.ticks_per_candle;
+        let offset = self.tick % self.ticks_per_candle;
+        let timestamp_ms = self.start_ms
+            + candle * self.interval_ms
+            + offset * self.interval_ms / self.ticks_per_candle;
+        self.tick += 1;
+
+        let previous = self.price;
+        self.price *= (self.mu + self.sigma * self.standard_normal()).exp();
+        let quantity = self.mean_quantity * 2.0 * (1.0 - self.rng.random::<f64>());
+        let side = if self.price >= previous {
+            Side::Buy
+        } else {
+            Side::Sell
+        };
+
+        (timestamp_ms, self.price, quantity, side)
+    }
+}
+
+/// 合成的 K 线数据流
+///
+/// 每根 K 线由 `ticks_per_candle` 步价格路径组成，开盘价等于上一根的收盘价。
+///
+/// # Errors
+///
+/// 配置不合法（如价格、周期不为正）时返回错误。
+pub fn synthetic_candle_stream(
+    config: SyntheticConfig,
+) -> Result<impl Stream<Item = Result<CandleData>> + Send + 'static> {
+    synthetic_candle_stream_with_clock(config, SystemClock)
+}
+
+/// 同 [`synthetic_candle_stream`]，但使用指定的时钟控制输出节奏
+pub fn synthetic_candle_stream_with_clock(
+    config: SyntheticConfig,
+    clock: impl Clock,
+) -> Result<impl Stream<Item = Result<CandleData>> + Send + 'static> {
+    config.validate()?;
+
+    let stream = stream! {
+        let mut path = PricePath::new(&config);
+        let pace = config.pace();
+        let mut emitted = 0;
+
+        while config.limit.is_none_or(|limit| emitted < limit) {
+            if emitted > 0 && let Some(pace) = pace {
+                clock.sleep(pace).await;
+            }
+
+            let open = path.price;
+            let mut candle = CandleData {
+                symbol: config.symbol.clone(),
+                interval_sc: config.interval_sc,
+                open_timestamp_ms: config.start_ms + emitted as TimestampMs * path.interval_ms,
+                open,
+                high: open,
+                low: open,
+                close: open,
+                ..Default::default()
+            };
+            for _ in 0..config.ticks_per_candle {
+                let (_, price, quantity, _) = path.next_tick();
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.volume += quantity;
+                candle.quote_volume += price * quantity;
+                candle.trade_count += 1;
+            }
+
+            emitted += 1;
+            yield Ok(candle);
+        }
+    };
+
+    Ok(stream)
+}
+
+/// 合成的逐笔成交数据流
+///
+/// 成交价格沿价格路径变化，价格上涨（或不变）记为买方主动成交，下跌记为卖方主动成交。
+///
+/// # Errors
+///
+/// 配置不合法（如价格、周期不为正）时返回错误。
+pub fn synthetic_trade_stream(
+    config: SyntheticConfig,
+) -> Result<impl Stream<Item = Result<TradeData>> + Send + 'static> {
+    synthetic_trade_stream_with_clock(config, SystemClock)
+}
+
+/// 同 [`synthetic_trade_stream`]，但使用指定的时钟控制输出节奏
+pub fn synthetic_trade_stream_with_clock(
+    config: SyntheticConfig,
+    clock: impl Clock,
+) -> Result<impl Stream<Item = Result<TradeData>> + Send + 'static> {
+    config.validate()?;
+
+    let stream = stream! {
+        let mut path = PricePath::new(&config);
+        let pace = config.pace();
+        let mut emitted = 0;
+
+        while config.limit.is_none_or(|limit| emitted < limit) {
+            if emitted > 0 && let Some(pace) = pace {
+                clock.sleep(pace).await;
+            }
+
+            let (timestamp_ms, price, quantity, side) = path.next_tick();
+            emitted += 1;
+            yield Ok(TradeData {
+                symbol: config.symbol.clone(),
+                timestamp_ms,
+                price,
+                quantity,
+                side,
+            });
+        }
+    };
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+
+    fn config(seed: u64) -> SyntheticConfig {
+        SyntheticConfig {
+            limit: Some(50),
+            seed,
+            ..SyntheticConfig::new("BTC-USDT")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_candle_stream() {
+        let collect = |config| async move {
+            synthetic_candle_stream(config)
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+        };
+
+        let candles = collect(config(7)).await;
+        assert_eq!(candles.len(), 50);
+        assert_eq!(candles, collect(config(7)).await);
+        assert_ne!(candles, collect(config(8)).await);
+
+        for (i, candle) in candles.iter().enumerate() {
+            candle.validate().unwrap();
+            assert_eq!(candle.trade_count, 60);
+            assert_eq!(
+                candle.open_timestamp_ms,
+                SYNTHETIC_START_MS + i as TimestampMs * 60_000
+            );
+        }
+        for pair in candles.windows(2) {
+            assert_eq!(pair[0].close, pair[1].open);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_trade_stream() {
+        let collect = |config| async move {
+            synthetic_trade_stream(config)
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+        };
+
+        let trades = collect(config(7)).await;
+        assert_eq!(trades.len(), 50);
+        assert_eq!(trades, collect(config(7)).await);
+        assert!(
+            trades
+                .windows(2)
+                .all(|w| w[0].timestamp_ms < w[1].timestamp_ms)
+        );
+        assert!(trades.iter().all(|t| t.price > 0.0 && t.quantity > 0.0));
+
+        // 零波动、零漂移时价格保持不变
+        let flat = collect(SyntheticConfig {
+            volatility: 0.0,
+            ..config(7)
+        })
+        .await;
+        assert!(flat.iter().all(|t| (t.price - 100.0).abs() < 1e-9));
+
+        let invalid = SyntheticConfig {
+            initial_price: 0.0,
+            ..config(7)
+        };
+        assert!(synthetic_trade_stream(invalid).is_err());
+    }
+}
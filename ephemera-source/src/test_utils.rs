@@ -0,0 +1,44 @@
+use crate::clock::Clock;
+use ephemera_shared::TimestampMs;
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// 虚拟时钟
+///
+/// `sleep` 不会真正等待，而是立即将虚拟时间向前推进 `duration`。
+/// 克隆出的实例共享同一个时间，测试可以在流运行后通过 [`MockClock::now_ms`] 检查流等待了多久，
+/// 或通过 [`MockClock::advance`] 手动推进时间。
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now_ms: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    pub fn new(start_ms: TimestampMs) -> Self {
+        Self {
+            now_ms: Arc::new(AtomicU64::new(start_ms)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_ms
+            .fetch_add(duration.as_millis() as TimestampMs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> TimestampMs {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        self.advance(duration);
+        std::future::ready(())
+    }
+}
@@ -1,5 +1,348 @@
+use crate::clock::{Clock, SystemClock};
+use async_stream::stream;
+use dashmap::DashMap;
+use ephemera_shared::{
+    BookData, CandleData, DataError, DataResult, IntervalSc, MarketData, Symbol, TimestampMs,
+    TradeData,
+};
 use futures::{Stream, StreamExt};
-use std::iter;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    future::Future,
+    iter,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+
+pub use ephemera_shared::ReconnectPolicy;
+
+/// WebSocket 空闲超时的默认值
+pub const DEFAULT_WS_IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// WebSocket 订阅确认超时的默认值
+pub const DEFAULT_WS_SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 消息解析失败时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// 解析失败时返回错误，由调用方决定是否结束数据流
+    #[default]
+    Strict,
+    /// 解析失败时记录原始消息并跳过，数据流继续。
+    /// 适合交易所未经通知就修改推送格式时保持数据流存活
+    Tolerant,
+}
+
+/// WebSocket 数据流的连接参数
+///
+/// 随数据流的构造函数传入，同一进程中的不同数据流可以使用不同的参数。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsConfig {
+    /// 空闲超时: 超过该时间没有收到任何帧（数据或 pong），就认为连接已经半开，
+    /// 关闭连接并以 [`DataError::Connection`] 结束数据流
+    pub idle_timeout: Duration,
+    /// 订阅确认超时: 发出订阅请求后，超过该时间仍未收到所有频道的确认，
+    /// 就以 [`DataError::Subscription`] 放弃连接
+    pub subscribe_timeout: Duration,
+    pub parse_mode: ParseMode,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: DEFAULT_WS_IDLE_TIMEOUT,
+            subscribe_timeout: DEFAULT_WS_SUBSCRIBE_TIMEOUT,
+            parse_mode: ParseMode::Strict,
+        }
+    }
+}
+
+/// 日志中最多保留的原始消息字节数
+const PAYLOAD_LOG_LIMIT: usize = 512;
+
+/// 截断原始消息用于日志输出
+pub(crate) fn payload_for_log(payload: &[u8]) -> String {
+    let end = payload.len().min(PAYLOAD_LOG_LIMIT);
+    let mut text = String::from_utf8_lossy(&payload[..end]).into_owned();
+    if payload.len() > end {
+        text.push_str("...");
+    }
+    text
+}
+
+/// 按 [`ReconnectPolicy`] 自动重连的数据流
+///
+/// 同 [`retry_stream_with_clock`]，使用系统时钟
+pub fn retry_stream<F, Fut, S, T, E>(
+    policy: ReconnectPolicy,
+    connect: F,
+) -> impl Stream<Item = Result<T, E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<S, E>>,
+    S: Stream<Item = Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    retry_stream_with_clock(policy, connect, SystemClock)
+}
+
+/// 按 [`ReconnectPolicy`] 自动重连的数据流，使用指定的时钟计时
+///
+/// 通过 `connect` 建立数据流，连接失败、数据流出错或结束都视为断线，等待
+/// [`ReconnectPolicy::next_delay`] 后重新连接。断线前的数据原样输出，断线本身只记录日志，
+/// 连续重连超过 [`ReconnectPolicy::max_retries`] 次时输出最后一个错误（如有）并结束。
+///
+/// 一次连接持续了 [`ReconnectPolicy::reset_after`] 以上才断开时，重连次数清零。
+pub fn retry_stream_with_clock<F, Fut, S, T, E>(
+    policy: ReconnectPolicy,
+    mut connect: F,
+    clock: impl Clock,
+) -> impl Stream<Item = Result<T, E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<S, E>>,
+    S: Stream<Item = Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    let reset_after_ms = policy.reset_after.as_millis() as TimestampMs;
+
+    stream! {
+        let mut attempt = 0;
+
+        loop {
+            let last_error = match connect().await {
+                Ok(inner) => {
+                    let connected_at = clock.now_ms();
+                    let mut last_error = None;
+
+                    futures::pin_mut!(inner);
+                    while let Some(item) = inner.next().await {
+                        match item {
+                            Ok(item) => yield Ok(item),
+                            Err(e) => {
+                                last_error = Some(e);
+                                break;
+                            }
+                        }
+                    }
+
+                    if clock.now_ms().saturating_sub(connected_at) >= reset_after_ms {
+                        attempt = 0;
+                    }
+                    last_error
+                }
+                Err(e) => Some(e),
+            };
+
+            if attempt >= policy.max_retries {
+                tracing::warn!("重连 {} 次后放弃: {:?}", attempt, last_error);
+                if let Some(e) = last_error {
+                    yield Err(e);
+                }
+                return;
+            }
+
+            let delay = policy.next_delay(attempt);
+            attempt += 1;
+            tracing::warn!(
+                "数据流断开，{:?} 后第 {} 次重连: {:?}",
+                delay,
+                attempt,
+                last_error
+            );
+            clock.sleep(delay).await;
+        }
+    }
+}
+
+/// 多交易对订阅的结果，与数据流一起返回
+///
+/// 部分交易对订阅失败（已下架、拼写错误等）时不影响其他交易对，数据流只包含订阅成功的交易对。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscriptionResult {
+    pub subscribed: Vec<Symbol>,
+    /// 订阅失败的交易对和原因
+    pub failed: Vec<(Symbol, String)>,
+}
+
+impl SubscriptionResult {
+    /// 是否所有交易对都订阅成功
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// [`tee`] 默认的缓冲区大小
+pub const DEFAULT_TEE_CAPACITY: usize = 1024;
+
+/// 消费者落后超过缓冲区大小时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TeeLagPolicy {
+    /// 丢弃被覆盖的数据，从缓冲区中最旧的数据继续
+    #[default]
+    Skip,
+    /// 结束该消费者的数据流，适合不能容忍丢数据的消费者（如录制）
+    Stop,
+}
+
+/// 由 [`tee`] 返回的数据流，克隆得到的新数据流从克隆之后的数据开始接收
+pub struct TeeStream<T, E> {
+    // 不读取数据，只用于克隆时订阅
+    subscriber: broadcast::Receiver<Result<T, Arc<E>>>,
+    lag: TeeLagPolicy,
+    inner: Pin<Box<dyn Stream<Item = Result<T, Arc<E>>> + Send>>,
+}
+
+impl<T, E> TeeStream<T, E>
+where
+    T: Clone + Send + 'static,
+    E: Send + Sync + 'static,
+{
+    fn new(mut receiver: broadcast::Receiver<Result<T, Arc<E>>>, lag: TeeLagPolicy) -> Self {
+        let subscriber = receiver.resubscribe();
+        let inner = Box::pin(stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(item) => yield item,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Tee consumer lagged behind, {} items dropped", skipped);
+                        if lag == TeeLagPolicy::Stop {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Self {
+            subscriber,
+            lag,
+            inner,
+        }
+    }
+}
+
+impl<T, E> Clone for TeeStream<T, E>
+where
+    T: Clone + Send + 'static,
+    E: Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.subscriber.resubscribe(), self.lag)
+    }
+}
+
+impl<T, E> Stream for TeeStream<T, E> {
+    type Item = Result<T, Arc<E>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// 将一个数据流分发给 `n` 个消费者，例如同时驱动策略和录制，而不必重复订阅交易所
+///
+/// 使用 [`DEFAULT_TEE_CAPACITY`] 和 [`TeeLagPolicy::Skip`]，见 [`tee_with`]。
+pub fn tee<S, T, E>(stream: S, n: usize) -> Vec<TeeStream<T, E>>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: Clone + Send + 'static,
+    E: Send + Sync + 'static,
+{
+    tee_with(stream, n, DEFAULT_TEE_CAPACITY, TeeLagPolicy::Skip)
+}
+
+/// 将一个数据流分发给 `n` 个消费者
+///
+/// 后台任务读取上游并写入容量为 `capacity` 的广播通道，每个消费者看到相同顺序的相同数据，
+/// 错误包装为 `Arc` 后共享。上游不会等待慢消费者：消费者落后超过 `capacity` 条时，
+/// 被覆盖的数据对它丢失，随后按 `lag` 处理。所有消费者都被丢弃后后台任务停止读取上游。
+///
+/// # Panics
+///
+/// 1. 不在 Tokio 运行时中调用。
+/// 2. `capacity` 为 0。
+pub fn tee_with<S, T, E>(
+    stream: S,
+    n: usize,
+    capacity: usize,
+    lag: TeeLagPolicy,
+) -> Vec<TeeStream<T, E>>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: Clone + Send + 'static,
+    E: Send + Sync + 'static,
+{
+    let (sender, receiver) = broadcast::channel(capacity);
+    // 先订阅再启动后台任务，保证每个消费者都能收到第一条数据
+    let streams = iter::repeat_with(|| TeeStream::new(receiver.resubscribe(), lag))
+        .take(n)
+        .collect();
+    drop(receiver);
+
+    tokio::spawn(async move {
+        futures::pin_mut!(stream);
+
+        while let Some(item) = stream.next().await {
+            if sender.send(item.map_err(Arc::new)).is_err() {
+                break;
+            }
+        }
+    });
+
+    streams
+}
+
+/// 从交易所原生格式的 K 线转换为 [`CandleData`](ephemera_shared::CandleData)
+///
+/// 每个交易所为自己的原始 K 线类型实现一次，WebSocket 推送和 REST 拉取共用同一套转换。
+/// `symbol` 和 `interval_sc` 由调用方根据订阅信息传入，原始数据中不一定包含它们。
+pub trait FromExchangeCandle<Raw>: Sized {
+    fn from_exchange_candle(
+        raw: Raw,
+        symbol: Symbol,
+        interval_sc: IntervalSc,
+    ) -> eyre::Result<Self>;
+}
+
+/// 为按位置推送的元组结构体实现反序列化：依次读取各字段，忽略末尾多出的元素
+macro_rules! impl_positional_deserialize {
+    ($name:ident, $($index:literal),+) => {
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct PositionalVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for PositionalVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        write!(f, "an array of at least {} elements", [$($index),+].len())
+                    }
+
+                    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<$name, A::Error> {
+                        let value = $name($(
+                            seq.next_element()?
+                                .ok_or_else(|| serde::de::Error::invalid_length($index, &self))?
+                        ),+);
+                        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+                        Ok(value)
+                    }
+                }
+
+                deserializer.deserialize_seq(PositionalVisitor)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_positional_deserialize;
 
 pub fn transform_raw_stream<Raw, Target, E>(
     stream: impl Stream<Item = Result<Raw, E>> + Send + 'static,
@@ -61,3 +404,410 @@ where
         futures::stream::iter(iterator)
     })
 }
+
+/// 复用缓冲区解析 JSON 消息
+///
+/// simd_json 需要可变的输入缓冲区，解析时还要用到额外的临时缓冲区。
+/// 每条数据流持有一个 `JsonScratch`，避免每条消息都重新分配。
+#[derive(Default)]
+pub(crate) struct JsonScratch {
+    input: Vec<u8>,
+    buffers: simd_json::Buffers,
+}
+
+impl JsonScratch {
+    pub(crate) fn parse<T: DeserializeOwned>(&mut self, payload: &[u8]) -> simd_json::Result<T> {
+        self.input.clear();
+        self.input.extend_from_slice(payload);
+        simd_json::serde::from_slice_with_buffers(&mut self.input, &mut self.buffers)
+    }
+}
+
+/// 按 symbol 检查增量推送的序列号是否连续
+///
+/// 每次推送带有自身的序列号 `current`，以及它所衔接的上一个序列号 `prev`。
+/// 若 `prev` 与该 symbol 上一次记录的序列号不一致，说明中间有推送丢失。
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    last: HashMap<Symbol, u64>,
+}
+
+impl SequenceTracker {
+    /// `prev` 为 `None` 表示快照，直接以 `current` 重新开始跟踪。
+    ///
+    /// 发现缺口时返回 [`DataError::SequenceGap`]，同时以 `current` 继续跟踪，
+    /// 由调用方决定是否重新同步。
+    pub fn check(&mut self, symbol: &Symbol, prev: Option<u64>, current: u64) -> DataResult<()> {
+        let last = self.last.insert(symbol.clone(), current);
+
+        match (last, prev) {
+            (Some(last), Some(prev)) if last != prev => Err(DataError::SequenceGap {
+                symbol: symbol.clone(),
+                expected: last,
+                found: prev,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn reset(&mut self, symbol: &Symbol) {
+        self.last.remove(symbol);
+    }
+}
+
+/// 可被 [`StreamStats`] 统计的数据，提供所属的 symbol 和数据时间戳
+pub trait StatsItem {
+    fn symbol(&self) -> &Symbol;
+    fn timestamp_ms(&self) -> TimestampMs;
+}
+
+impl StatsItem for TradeData {
+    fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    fn timestamp_ms(&self) -> TimestampMs {
+        self.timestamp_ms
+    }
+}
+
+impl StatsItem for CandleData {
+    fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    fn timestamp_ms(&self) -> TimestampMs {
+        self.open_timestamp_ms
+    }
+}
+
+impl StatsItem for BookData {
+    fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    fn timestamp_ms(&self) -> TimestampMs {
+        self.timestamp
+    }
+}
+
+impl StatsItem for MarketData {
+    fn symbol(&self) -> &Symbol {
+        match self {
+            MarketData::Trade(data) => data.symbol(),
+            MarketData::Candle(data) => data.symbol(),
+            MarketData::Book(data) => data.symbol(),
+        }
+    }
+
+    fn timestamp_ms(&self) -> TimestampMs {
+        match self {
+            MarketData::Trade(data) => data.timestamp_ms(),
+            MarketData::Candle(data) => data.timestamp_ms(),
+            MarketData::Book(data) => data.timestamp_ms(),
+        }
+    }
+}
+
+/// 单个 symbol 的统计
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolStats {
+    /// 收到的数据条数
+    pub count: u64,
+    /// 最近一条数据自带的时间戳
+    pub last_timestamp_ms: TimestampMs,
+    /// 最近一条数据到达的本地时间
+    pub last_seen: Instant,
+}
+
+impl SymbolStats {
+    /// 距离最近一条数据到达已经过去的时间，可用于发现停滞的数据流
+    pub fn age(&self) -> Duration {
+        self.last_seen.elapsed()
+    }
+}
+
+/// 数据流统计，按 symbol 记录数据条数和最近一次更新
+///
+/// 通过 [`track`](Self::track) 包装数据流，数据流运行期间可以从任意克隆的句柄查询统计。
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    symbols: Arc<DashMap<Symbol, SymbolStats>>,
+}
+
+impl StreamStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 包装数据流，每条 `Ok` 数据都会计入统计，错误原样透传
+    pub fn track<S, T, E>(&self, stream: S) -> impl Stream<Item = Result<T, E>> + use<S, T, E>
+    where
+        S: Stream<Item = Result<T, E>>,
+        T: StatsItem,
+    {
+        let stats = self.clone();
+
+        stream.inspect(move |item| {
+            if let Ok(data) = item {
+                stats.record(data);
+            }
+        })
+    }
+
+    pub fn record(&self, data: &impl StatsItem) {
+        let now = Instant::now();
+
+        self.symbols
+            .entry(data.symbol().clone())
+            .and_modify(|stats| {
+                stats.count += 1;
+                stats.last_timestamp_ms = data.timestamp_ms();
+                stats.last_seen = now;
+            })
+            .or_insert_with(|| SymbolStats {
+                count: 1,
+                last_timestamp_ms: data.timestamp_ms(),
+                last_seen: now,
+            });
+    }
+
+    pub fn get(&self, symbol: &Symbol) -> Option<SymbolStats> {
+        self.symbols.get(symbol).map(|stats| *stats)
+    }
+
+    /// 距离该 symbol 最近一条数据到达已经过去的时间，尚未收到数据时为 `None`
+    pub fn last_update_age(&self, symbol: &Symbol) -> Option<Duration> {
+        self.get(symbol).map(|stats| stats.age())
+    }
+
+    /// 所有 symbol 的统计，按 symbol 排序
+    pub fn snapshot(&self) -> Vec<(Symbol, SymbolStats)> {
+        let mut snapshot: Vec<_> = self
+            .symbols
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_for_log() {
+        assert_eq!(payload_for_log(b"{}"), "{}");
+        let long = payload_for_log(&[b'a'; PAYLOAD_LOG_LIMIT + 1]);
+        assert_eq!(long.len(), PAYLOAD_LOG_LIMIT + 3);
+        assert!(long.ends_with("..."));
+    }
+
+    #[test]
+    fn test_json_scratch_reuse() {
+        let mut scratch = JsonScratch::default();
+
+        let long: Vec<u64> = scratch.parse(b"[1, 2, 3, 4, 5, 6, 7, 8]").unwrap();
+        assert_eq!(long, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // 较短的消息不会读到上一条残留的数据
+        let short: Vec<u64> = scratch.parse(b"[9]").unwrap();
+        assert_eq!(short, vec![9]);
+
+        assert!(scratch.parse::<Vec<u64>>(b"[1,").is_err());
+        let after_error: (String, bool) = scratch.parse(br#"["ok", true]"#).unwrap();
+        assert_eq!(after_error, ("ok".to_string(), true));
+    }
+
+    #[tokio::test]
+    async fn test_stream_stats() {
+        let trade = |symbol: &'static str, timestamp_ms| TradeData {
+            symbol: Symbol::from_static(symbol),
+            timestamp_ms,
+            price: 100.0,
+            quantity: 1.0,
+            side: ephemera_shared::Side::Buy,
+        };
+        let stats = StreamStats::new();
+        let btc = Symbol::from_static("BTC-USDT");
+
+        let items = vec![
+            Ok(trade("BTC-USDT", 1)),
+            Err("disconnected"),
+            Ok(trade("ETH-USDT", 2)),
+            Ok(trade("BTC-USDT", 3)),
+        ];
+        let tracked = stats.track(futures::stream::iter(items));
+        futures::pin_mut!(tracked);
+
+        // 数据流运行期间可以从克隆的句柄查询
+        let handle = stats.clone();
+        assert!(tracked.next().await.unwrap().is_ok());
+        assert_eq!(handle.get(&btc).unwrap().count, 1);
+        assert_eq!(
+            handle.last_update_age(&Symbol::from_static("ETH-USDT")),
+            None
+        );
+
+        let rest: Vec<_> = tracked.collect().await;
+        assert_eq!(rest.len(), 3);
+
+        let btc_stats = handle.get(&btc).unwrap();
+        assert_eq!(btc_stats.count, 2);
+        assert_eq!(btc_stats.last_timestamp_ms, 3);
+        assert!(handle.last_update_age(&btc).unwrap() < Duration::from_secs(1));
+
+        let snapshot = handle.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[1].0, "ETH-USDT");
+        assert_eq!(snapshot[1].1.count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_stream() {
+        use crate::test_utils::MockClock;
+        use futures::stream;
+
+        let policy = ReconnectPolicy {
+            initial: Duration::from_secs(1),
+            jitter: 0.0,
+            max_retries: 2,
+            reset_after: Duration::from_secs(10),
+            ..Default::default()
+        };
+
+        // 前三次连接各推送一条数据后断开，之后的连接都被拒绝。
+        // `stable` 为 true 时每次连接持续足够久，重连次数清零
+        let run = |stable: bool| {
+            let clock = MockClock::new(0);
+            let mut connections = 0;
+            let connect = {
+                let clock = clock.clone();
+                move || {
+                    connections += 1;
+                    let id = connections;
+                    let clock = clock.clone();
+                    async move {
+                        if id > 3 {
+                            return Err("refused");
+                        }
+                        Ok(
+                            stream::iter([Ok(id), Err("disconnected")]).inspect(move |_| {
+                                if stable {
+                                    clock.advance(Duration::from_secs(5));
+                                }
+                            }),
+                        )
+                    }
+                }
+            };
+
+            let outputs =
+                retry_stream_with_clock(policy, connect, clock.clone()).collect::<Vec<_>>();
+            async move { (outputs.await, clock.now_ms()) }
+        };
+
+        // 不清零: 第一次连接之后最多重连 2 次，分别等待 1s、2s
+        let (outputs, elapsed_ms) = run(false).await;
+        assert_eq!(outputs, vec![Ok(1), Ok(2), Ok(3), Err("disconnected")]);
+        assert_eq!(elapsed_ms, 3_000);
+
+        // 清零: 三次连接的断开都从第 0 次开始重连，之后连接被拒绝 2 次才结束
+        let (outputs, _) = run(true).await;
+        assert_eq!(outputs, vec![Ok(1), Ok(2), Ok(3), Err("refused")]);
+    }
+
+    #[test]
+    fn test_sequence_tracker() {
+        let mut tracker = SequenceTracker::default();
+        let btc = Symbol::from_static("BTC-USDT");
+        let eth = Symbol::from_static("ETH-USDT");
+
+        // 首次推送没有可比较的序列号
+        tracker.check(&btc, Some(100), 105).unwrap();
+        tracker.check(&btc, Some(105), 110).unwrap();
+        // 不同 symbol 独立跟踪
+        tracker.check(&eth, Some(7), 8).unwrap();
+
+        let err = tracker.check(&btc, Some(120), 125).unwrap_err();
+        assert!(matches!(
+            err,
+            DataError::SequenceGap {
+                expected: 110,
+                found: 120,
+                ..
+            }
+        ));
+
+        // 缺口之后从新的序列号继续
+        tracker.check(&btc, Some(125), 130).unwrap();
+
+        // 快照重置跟踪
+        tracker.check(&btc, None, 500).unwrap();
+        tracker.check(&btc, Some(500), 501).unwrap();
+
+        tracker.reset(&eth);
+        tracker.check(&eth, Some(42), 43).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tee() {
+        let items: Vec<Result<u32, String>> = vec![Ok(1), Ok(2), Err("boom".into()), Ok(3)];
+        let mut streams = tee(futures::stream::iter(items), 3);
+        assert_eq!(streams.len(), 3);
+
+        let first = streams.pop().unwrap();
+        let clone = first.clone();
+        let collect = |stream: TeeStream<u32, String>| {
+            stream
+                .map(|item| item.map_err(|e| e.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![Ok(1), Ok(2), Err("boom".to_string()), Ok(3)];
+
+        let (a, b, c, d) = tokio::join!(
+            collect(first),
+            collect(clone),
+            collect(streams.pop().unwrap()),
+            collect(streams.pop().unwrap()),
+        );
+        assert_eq!(a, expected);
+        assert_eq!(c, expected);
+        assert_eq!(d, expected);
+        // 克隆发生在数据发出之前，同样能收到全部数据
+        assert_eq!(b, expected);
+    }
+
+    #[tokio::test]
+    async fn test_tee_lag() {
+        // 上游发完 10 条数据和一个错误后，消费者才开始读取
+        async fn lagged(lag: TeeLagPolicy) -> Vec<Result<u32, String>> {
+            let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+            let source =
+                futures::stream::iter((0..10).map(Ok)).chain(futures::stream::once(async move {
+                    let _ = sender.send(());
+                    Err("end".to_string())
+                }));
+
+            let mut streams = tee_with(source, 1, 4, lag);
+            receiver.await.unwrap();
+
+            streams
+                .pop()
+                .unwrap()
+                .map(|item| item.map_err(|e| e.to_string()))
+                .collect()
+                .await
+        }
+
+        // 只剩缓冲区中最新的 4 条
+        assert_eq!(
+            lagged(TeeLagPolicy::Skip).await,
+            vec![Ok(7), Ok(8), Ok(9), Err("end".to_string())]
+        );
+        assert!(lagged(TeeLagPolicy::Stop).await.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use ephemera_shared::testing::SplitMix64;
+use ephemera_strategy::indicators::{BollingerBands, Indicator, MA, PiCycleTop, RollingStdDev};
+use std::hint::black_box;
+
+const PRICE_COUNT: usize = 1_000_000;
+
+/// 固定种子的价格随机游走
+fn prices(count: usize) -> Vec<f64> {
+    let mut rng = SplitMix64::new(0x9e37_79b9_7f4a_7c15);
+    let mut price = 50_000.0;
+
+    (0..count)
+        .map(|_| {
+            price *= 1.0 + (rng.next_f64() - 0.5) * 1e-3;
+            price
+        })
+        .collect()
+}
+
+fn run<I: Indicator<Input = f64>>(mut indicator: I, prices: &[f64]) {
+    for &price in prices {
+        black_box(indicator.on_data(black_box(price)));
+    }
+}
+
+fn bench_indicators(c: &mut Criterion) {
+    let prices = prices(PRICE_COUNT);
+
+    let mut group = c.benchmark_group("indicators");
+    group.throughput(Throughput::Elements(PRICE_COUNT as u64));
+    group.sample_size(20);
+
+    // 111/350 日均线，350 的窗口是最重的部分
+    group.bench_function("pi_cycle_top", |b| {
+        b.iter(|| run(PiCycleTop::new(), &prices))
+    });
+    group.bench_function("ma_350", |b| b.iter(|| run(MA::new(350), &prices)));
+    group.bench_function("bollinger_20_2", |b| {
+        b.iter(|| run(BollingerBands::new(20, 2.0), &prices))
+    });
+    group.bench_function("bollinger_200_2", |b| {
+        b.iter(|| run(BollingerBands::new(200, 2.0), &prices))
+    });
+    group.bench_function("rolling_std_dev_200", |b| {
+        b.iter(|| run(RollingStdDev::new(200), &prices))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_indicators);
+criterion_main!(benches);
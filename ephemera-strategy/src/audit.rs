@@ -0,0 +1,266 @@
+use ephemera_shared::{Signal, Symbol, TimestampMs};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+type Result<T> = std::result::Result<T, AuditError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("Failed to open audit log: {}", path.display())]
+    Open {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to serialize audit record: {0}")]
+    Serialize(#[from] simd_json::Error),
+    #[error("Failed to write audit record: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// 审计记录中的信号方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalKind {
+    Buy,
+    Sell,
+    Hold,
+}
+
+impl From<&Signal> for SignalKind {
+    fn from(signal: &Signal) -> Self {
+        match signal {
+            Signal::Buy { .. } => Self::Buy,
+            Signal::Sell { .. } => Self::Sell,
+            Signal::Hold => Self::Hold,
+        }
+    }
+}
+
+/// 信号或订单的处理结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// 策略产生了信号，尚未执行
+    Generated,
+    /// 订单已提交到交易所
+    Submitted { order_id: String },
+    /// 已成交
+    Filled,
+    /// 被风控、资金不足或交易所拒绝
+    Rejected { reason: String },
+    /// 被强制平仓
+    Liquidated,
+}
+
+/// 一条审计记录，序列化为 JSONL 的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp_ms: TimestampMs,
+    pub strategy: String,
+    pub symbol: Symbol,
+    pub signal: SignalKind,
+    pub price: f64,
+    pub size: f64,
+    #[serde(flatten)]
+    pub outcome: AuditOutcome,
+}
+
+/// 审计记录的落地方式
+///
+/// 与 `tracing` 的文本日志不同，审计记录是结构化的，便于事后做交易分析。
+pub trait AuditSink: Debug + Send + Sync {
+    fn record(&self, record: &AuditRecord) -> Result<()>;
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Arc<S> {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        (**self).record(record)
+    }
+}
+
+/// 写入 JSONL 文件，每条记录写完立即 flush，进程异常退出也不会丢失已写入的记录
+#[derive(Debug)]
+pub struct FileAuditSink {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl FileAuditSink {
+    /// 以追加方式打开（或创建）文件
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|source| AuditError::Open {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = simd_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&line)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// 保存在内存中，用于测试和回测后分析
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    records: Mutex<Vec<AuditRecord>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        self.records.lock().unwrap().push(record.clone());
+
+        Ok(())
+    }
+}
+
+/// 绑定了策略名的审计入口，可以在信号流和执行流之间共享
+///
+/// 写入失败只记录日志，不会中断交易流程。
+#[derive(Debug, Clone)]
+pub struct Auditor {
+    strategy: String,
+    sink: Arc<dyn AuditSink>,
+}
+
+impl Auditor {
+    pub fn new(strategy: impl Into<String>, sink: Arc<dyn AuditSink>) -> Self {
+        Self {
+            strategy: strategy.into(),
+            sink,
+        }
+    }
+
+    pub fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    /// 记录一个信号及其结果，[`Signal::Hold`] 不记录
+    pub fn signal(&self, timestamp_ms: TimestampMs, signal: &Signal, outcome: AuditOutcome) {
+        let (symbol, price, size) = match signal {
+            Signal::Buy {
+                symbol,
+                price,
+                size,
+            }
+            | Signal::Sell {
+                symbol,
+                price,
+                size,
+            } => (symbol.clone(), *price, *size),
+            Signal::Hold => return,
+        };
+
+        self.record(AuditRecord {
+            timestamp_ms,
+            strategy: self.strategy.clone(),
+            symbol,
+            signal: signal.into(),
+            price,
+            size,
+            outcome,
+        });
+    }
+
+    pub fn record(&self, record: AuditRecord) {
+        if let Err(e) = self.sink.record(&record) {
+            tracing::error!("写入审计记录失败: {:?}, 记录: {:?}", e, record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_auditor_skips_hold() {
+        let sink = Arc::new(MemoryAuditSink::new());
+        let auditor = Auditor::new("ma_cross", sink.clone());
+
+        auditor.signal(1, &Signal::Hold, AuditOutcome::Generated);
+        auditor.signal(
+            2,
+            &Signal::buy("BTC-USDT".into(), 50000.0, 0.1),
+            AuditOutcome::Generated,
+        );
+
+        assert_eq!(
+            sink.records(),
+            vec![AuditRecord {
+                timestamp_ms: 2,
+                strategy: "ma_cross".into(),
+                symbol: "BTC-USDT".into(),
+                signal: SignalKind::Buy,
+                price: 50000.0,
+                size: 0.1,
+                outcome: AuditOutcome::Generated,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_file_audit_sink_jsonl() {
+        let file = NamedTempFile::new().unwrap();
+        let sink = Arc::new(FileAuditSink::open(file.path()).unwrap());
+        let auditor = Auditor::new("ma_cross", sink);
+
+        let sell = Signal::sell("ETH-USDT".into(), 4000.0, 1.0);
+        auditor.signal(1, &sell, AuditOutcome::Filled);
+        auditor.signal(
+            2,
+            &sell,
+            AuditOutcome::Rejected {
+                reason: "no position".into(),
+            },
+        );
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains(r#""status":"rejected""#));
+        assert!(lines[1].contains(r#""reason":"no position""#));
+
+        let records: Vec<AuditRecord> = lines
+            .iter()
+            .map(|line| simd_json::from_slice(&mut line.as_bytes().to_vec()).unwrap())
+            .collect();
+        assert_eq!(records[0].outcome, AuditOutcome::Filled);
+        assert_eq!(records[1].signal, SignalKind::Sell);
+        assert_eq!(records[1].symbol, "ETH-USDT");
+    }
+}
@@ -1,4 +1,4 @@
-use super::Indicator;
+use super::{Indicator, math};
 use crate::indicators::MA;
 
 pub const BTC_GENESIS_TIMESTAMP: u64 = 1_230_912_000; // 2009-01-03 00:00:00 UTC
@@ -43,7 +43,7 @@ impl AHR {
             ma200: MA::new(200),
             genesis_ts,
             slope,
-            intercept_factor: 10_f64.powf(intercept),
+            intercept_factor: math::powf(10.0, intercept),
         }
     }
 
@@ -59,7 +59,7 @@ impl AHR {
     /// Math: age^slope * 10^intercept
     #[inline]
     fn calculate_exponential_growth(&self, coin_age_days: f64) -> f64 {
-        coin_age_days.powf(self.slope) * self.intercept_factor
+        math::powf(coin_age_days, self.slope) * self.intercept_factor
     }
 
     /// 计算预期价格（指数增长估值）
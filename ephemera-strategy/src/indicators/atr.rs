@@ -0,0 +1,149 @@
+use super::Indicator;
+use ephemera_shared::CandleData;
+
+/// ATR - 平均真实波幅 (Average True Range)
+///
+/// # 原理
+/// ATR 由 J. Welles Wilder 在 1978 年提出，衡量价格的波动幅度而不关心方向。
+/// 真实波幅 (TR) 把跳空也计入波动，再用 Wilder 平滑得到 ATR。
+///
+/// # 公式
+/// ```text
+/// TR(t)  = max(High - Low, |High - Close(t-1)|, |Low - Close(t-1)|)
+/// ATR(t) = (ATR(t-1) × (period - 1) + TR(t)) / period
+/// ```
+/// 初始 ATR 为前 `period` 个 TR 的简单平均。
+///
+/// # 用途
+/// - **止损距离**: 以 k × ATR 作为止损距离，随波动率自动调整。
+/// - **仓位管理**: 固定每笔风险金额时，仓位与 ATR 成反比。
+///
+/// TR 依赖上一根 K 线的收盘价，因此首根 K 线只用于初始化，返回 `None`；
+/// 之后还需 `period` 个 TR 才输出第一个值。
+#[derive(Debug, Clone)]
+pub struct Atr {
+    pub(crate) period: usize,
+    pub(crate) prev_close: Option<f64>,
+    pub(crate) tr_sum: f64,
+    pub(crate) tr_count: usize,
+    pub(crate) current_atr: Option<f64>,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            prev_close: None,
+            tr_sum: 0.0,
+            tr_count: 0,
+            current_atr: None,
+        }
+    }
+
+    /// 标准 ATR (14)
+    pub fn standard() -> Self {
+        Self::new(14)
+    }
+
+    /// 当前的 ATR，预热完成前为 `None`
+    pub fn value(&self) -> Option<f64> {
+        self.current_atr
+    }
+
+    /// 清空内部状态，重新开始预热
+    pub fn reset(&mut self) {
+        *self = Self::new(self.period);
+    }
+}
+
+impl Indicator for Atr {
+    type Input = CandleData;
+    type Output = Option<f64>;
+
+    fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        let (high, low, close) = (input.high, input.low, input.close);
+        // 非有限值直接丢弃，避免污染后续输出
+        if !(high.is_finite() && low.is_finite() && close.is_finite()) {
+            return None;
+        }
+
+        let prev_close = self.prev_close.replace(close)?;
+        let true_range = (high - low)
+            .max((high - prev_close).abs())
+            .max((low - prev_close).abs());
+
+        match self.current_atr {
+            None => {
+                // 初始化阶段：使用 TR 的 SMA 作为第一个 ATR
+                self.tr_sum += true_range;
+                self.tr_count += 1;
+
+                if self.tr_count == self.period {
+                    self.current_atr = Some(self.tr_sum / self.period as f64);
+                }
+            }
+            Some(prev_atr) => {
+                let period = self.period as f64;
+                self.current_atr = Some((prev_atr * (period - 1.0) + true_range) / period);
+            }
+        }
+
+        self.current_atr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn candle(high: f64, low: f64, close: f64) -> CandleData {
+        CandleData {
+            symbol: "BTC-USDT".into(),
+            high,
+            low,
+            close,
+            ..Default::default()
+        }
+    }
+
+    fn candles() -> Vec<CandleData> {
+        vec![
+            candle(10.0, 8.0, 9.0),
+            candle(12.0, 9.0, 11.0),  // TR = max(3, 3, 0) = 3
+            candle(11.0, 10.0, 10.0), // TR = max(1, 0, 1) = 1
+            candle(15.0, 12.0, 14.0), // 跳空高开: TR = max(3, 5, 2) = 5
+            candle(14.0, 13.0, 13.0), // TR = max(1, 0, 1) = 1
+            candle(20.0, 18.0, 19.0), // 跳空高开: TR = max(2, 7, 5) = 7
+        ]
+    }
+
+    #[test]
+    fn test_atr_hand_computed() {
+        let mut atr = Atr::new(3);
+        let outputs: Vec<_> = candles().into_iter().map(|c| atr.on_data(c)).collect();
+
+        // 首根 K 线仅初始化，随后需要 3 个 TR
+        assert!(outputs[..3].iter().all(Option::is_none));
+        // 初始 ATR = (3 + 1 + 5) / 3 = 3
+        approx::assert_abs_diff_eq!(outputs[3].unwrap(), 3.0);
+        // (3 × 2 + 1) / 3 = 7/3
+        approx::assert_abs_diff_eq!(outputs[4].unwrap(), 7.0 / 3.0, epsilon = 1e-9);
+        // (7/3 × 2 + 7) / 3 = 35/9
+        approx::assert_abs_diff_eq!(outputs[5].unwrap(), 35.0 / 9.0, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(atr.value().unwrap(), 35.0 / 9.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_atr_reset() {
+        let mut atr = Atr::new(3);
+        candles().into_iter().for_each(|c| {
+            atr.on_data(c);
+        });
+        assert!(atr.value().is_some());
+
+        atr.reset();
+        assert!(atr.value().is_none());
+        assert!(atr.on_data(candle(10.0, 8.0, 9.0)).is_none());
+    }
+}
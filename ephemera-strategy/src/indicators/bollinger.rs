@@ -1,5 +1,5 @@
-use super::{Indicator, MA};
-use std::collections::VecDeque;
+use super::{Indicator, MA, math};
+use alloc::collections::VecDeque;
 
 /// Bollinger Bands - 布林带
 ///
@@ -80,7 +80,7 @@ impl BollingerBands {
             .sum::<f64>()
             / self.values.len() as f64;
 
-        variance.sqrt()
+        math::sqrt(variance)
     }
 }
 
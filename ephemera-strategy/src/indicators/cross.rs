@@ -0,0 +1,184 @@
+use super::Indicator;
+
+/// 均线交叉方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossDirection {
+    /// 金叉: 快线上穿慢线
+    Up,
+    /// 死叉: 快线下穿慢线
+    Down,
+}
+
+/// 带确认延迟的均线交叉
+///
+/// 同一价格分别输入快线和慢线（如 [`MA`](super::MA)、[`EMA`](super::EMA)）。快线穿过慢线后，
+/// 还需在新的一侧再停留 `confirmation_bars` 根 K 线才输出交叉，期间回到原来一侧则视为假突破，
+/// 不输出信号。`confirmation_bars` 为 0 时在交叉当根 K 线立即输出。
+///
+/// 两条线都有输出之前不检测交叉，两线相等时沿用之前的位置关系。
+#[derive(Debug, Clone)]
+pub struct MACross<F, S> {
+    pub(crate) fast: F,
+    pub(crate) slow: S,
+    pub(crate) confirmation_bars: usize,
+    /// 已确认的位置关系，快线在上方为 `true`
+    pub(crate) fast_above: Option<bool>,
+    /// 尚未确认的交叉，以及交叉之后已经经过的 K 线数
+    pub(crate) pending: Option<(CrossDirection, usize)>,
+}
+
+impl<F, S> MACross<F, S>
+where
+    F: Indicator<Input = f64, Output = Option<f64>>,
+    S: Indicator<Input = f64, Output = Option<f64>>,
+{
+    pub fn new(fast: F, slow: S, confirmation_bars: usize) -> Self {
+        Self {
+            fast,
+            slow,
+            confirmation_bars,
+            fast_above: None,
+            pending: None,
+        }
+    }
+}
+
+impl<F, S> Indicator for MACross<F, S>
+where
+    F: Indicator<Input = f64, Output = Option<f64>>,
+    S: Indicator<Input = f64, Output = Option<f64>>,
+{
+    type Input = f64;
+    type Output = Option<CrossDirection>;
+
+    fn on_data(&mut self, price: Self::Input) -> Self::Output {
+        let fast = self.fast.on_data(price);
+        let slow = self.slow.on_data(price);
+        let (Some(fast), Some(slow)) = (fast, slow) else {
+            return None;
+        };
+
+        let Some(confirmed) = self.fast_above else {
+            if fast != slow {
+                self.fast_above = Some(fast > slow);
+            }
+            return None;
+        };
+
+        let above = if fast == slow { confirmed } else { fast > slow };
+        if above == confirmed {
+            // 回到原来一侧，之前的交叉是假突破
+            self.pending = None;
+            return None;
+        }
+
+        let direction = if above {
+            CrossDirection::Up
+        } else {
+            CrossDirection::Down
+        };
+        let bars = match self.pending {
+            Some((pending, bars)) if pending == direction => bars + 1,
+            _ => 0,
+        };
+
+        if bars >= self.confirmation_bars {
+            self.fast_above = Some(above);
+            self.pending = None;
+            Some(direction)
+        } else {
+            self.pending = Some((direction, bars));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// 直接输出输入值，用于精确控制快线
+    struct Identity;
+
+    impl Indicator for Identity {
+        type Input = f64;
+        type Output = Option<f64>;
+
+        fn on_data(&mut self, input: f64) -> Option<f64> {
+            Some(input)
+        }
+    }
+
+    /// 固定值，用作慢线
+    struct Constant(f64);
+
+    impl Indicator for Constant {
+        type Input = f64;
+        type Output = Option<f64>;
+
+        fn on_data(&mut self, _: f64) -> Option<f64> {
+            Some(self.0)
+        }
+    }
+
+    fn run(prices: &[f64], confirmation_bars: usize) -> Vec<Option<CrossDirection>> {
+        let mut cross = MACross::new(Identity, Constant(100.0), confirmation_bars);
+        prices.iter().map(|&price| cross.on_data(price)).collect()
+    }
+
+    #[test]
+    fn test_ma_cross_fake_out() {
+        // 只在 101 停留一根 K 线就跌回慢线下方
+        let prices = [99.0, 101.0, 99.0, 98.0, 97.0];
+
+        assert!(run(&prices, 2).iter().all(Option::is_none));
+        // 不确认时假突破也会触发两次交叉
+        assert_eq!(
+            run(&prices, 0),
+            [
+                None,
+                Some(CrossDirection::Up),
+                Some(CrossDirection::Down),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ma_cross_sustained() {
+        let prices = [99.0, 101.0, 102.0, 103.0, 104.0, 99.0, 98.0, 97.0];
+
+        // 交叉之后再停留 2 根 K 线才确认
+        assert_eq!(
+            run(&prices, 2),
+            [
+                None,
+                None,
+                None,
+                Some(CrossDirection::Up),
+                None,
+                None,
+                None,
+                Some(CrossDirection::Down),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ma_cross_with_moving_averages() {
+        use crate::indicators::{EMA, MA};
+
+        let mut cross = MACross::new(EMA::new(2), MA::new(4), 1);
+        let signals: Vec<_> = [10.0, 10.0, 10.0, 10.0, 8.0, 8.0, 12.0, 14.0, 16.0]
+            .into_iter()
+            .map(|price| cross.on_data(price))
+            .collect();
+
+        assert_eq!(
+            signals.iter().flatten().copied().collect::<Vec<_>>(),
+            [CrossDirection::Up]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use super::Indicator;
+use ephemera_shared::{IntervalSc, Side, TimestampMs, TradeData};
+
+/// CVD - 累计成交量差 (Cumulative Volume Delta)
+///
+/// # 原理
+/// 按主动成交方向累加成交量: 主动买入 `+quantity`，主动卖出 `-quantity`，方向未知的成交不计入。
+/// CVD 反映的是买卖双方谁更主动，而不是价格本身。
+///
+/// # 解释
+/// - **CVD 与价格同向**: 趋势得到订单流确认。
+/// - **CVD 与价格背离**: 价格创新高而 CVD 走低，说明上涨缺乏主动买盘，反之亦然。
+///   可将 `(价格, CVD)` 序列交给 [`detect_divergence`](super::detect_divergence) 检测。
+///
+/// # 重置
+/// [`CumulativeVolumeDelta::new`] 从开始一直累计；[`CumulativeVolumeDelta::with_reset`]
+/// 在成交进入新的 K 线周期时清零，得到每根 K 线内的成交量差。
+#[derive(Debug, Clone, Default)]
+pub struct CumulativeVolumeDelta {
+    pub(crate) reset_interval_sc: Option<IntervalSc>,
+    pub(crate) current_bucket: Option<TimestampMs>,
+    pub(crate) delta: f64,
+}
+
+impl CumulativeVolumeDelta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 每 `interval_sc` 秒（按 K 线开盘时间对齐）重置一次
+    ///
+    /// # Panics
+    ///
+    /// `interval_sc` 为 0 时 panic
+    pub fn with_reset(interval_sc: IntervalSc) -> Self {
+        assert!(interval_sc > 0, "Reset interval must be positive");
+
+        Self {
+            reset_interval_sc: Some(interval_sc),
+            ..Self::default()
+        }
+    }
+}
+
+impl Indicator for CumulativeVolumeDelta {
+    type Input = TradeData;
+    type Output = f64;
+
+    fn on_data(&mut self, trade: Self::Input) -> Self::Output {
+        if let Some(interval_sc) = self.reset_interval_sc {
+            let bucket = trade.timestamp_ms / (interval_sc * 1000);
+            if self.current_bucket != Some(bucket) {
+                self.current_bucket = Some(bucket);
+                self.delta = 0.0;
+            }
+        }
+
+        self.delta += match trade.side {
+            Side::Buy => trade.quantity,
+            Side::Sell => -trade.quantity,
+            Side::Unknown => 0.0,
+        };
+
+        self.delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::{DivergenceKind, detect_divergence};
+    use alloc::vec::Vec;
+
+    fn trade(timestamp_ms: TimestampMs, price: f64, quantity: f64, side: Side) -> TradeData {
+        TradeData {
+            symbol: "BTC-USDT".into(),
+            timestamp_ms,
+            price,
+            quantity,
+            side,
+        }
+    }
+
+    #[test]
+    fn test_cvd() {
+        let mut cvd = CumulativeVolumeDelta::new();
+        let trades = [
+            trade(0, 100.0, 2.0, Side::Buy),
+            trade(1_000, 100.5, 0.5, Side::Sell),
+            trade(2_000, 101.0, 1.0, Side::Unknown),
+            trade(61_000, 101.0, 3.0, Side::Sell),
+            trade(62_000, 100.0, 1.5, Side::Buy),
+        ];
+
+        let outputs: Vec<_> = trades.into_iter().map(|t| cvd.on_data(t)).collect();
+
+        assert_eq!(outputs, [2.0, 1.5, 1.5, -1.5, 0.0]);
+    }
+
+    #[test]
+    fn test_cvd_with_reset() {
+        let mut cvd = CumulativeVolumeDelta::with_reset(60);
+        let trades = [
+            trade(0, 100.0, 2.0, Side::Buy),
+            trade(59_999, 100.5, 0.5, Side::Sell),
+            // 进入下一根 K 线，从 0 开始
+            trade(60_000, 101.0, 3.0, Side::Sell),
+            trade(61_000, 100.0, 1.0, Side::Buy),
+            trade(180_000, 100.0, 1.0, Side::Buy),
+        ];
+
+        let outputs: Vec<_> = trades.into_iter().map(|t| cvd.on_data(t)).collect();
+
+        assert_eq!(outputs, [2.0, 1.5, -3.0, -2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_cvd_price_divergence() {
+        let mut cvd = CumulativeVolumeDelta::new();
+
+        // 价格创新高，但第二波上涨时主动卖出更多
+        let points: Vec<_> = [
+            trade(0, 100.0, 5.0, Side::Buy),
+            trade(1_000, 99.0, 2.0, Side::Sell),
+            trade(2_000, 101.0, 4.0, Side::Sell),
+        ]
+        .into_iter()
+        .map(|t| (t.price, cvd.on_data(t)))
+        .collect();
+
+        let signal = detect_divergence(&points).unwrap();
+        assert_eq!(signal.kind, DivergenceKind::Bearish);
+        assert_eq!((signal.prev_price, signal.prev_indicator), (100.0, 5.0));
+    }
+}
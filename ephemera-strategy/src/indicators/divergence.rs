@@ -0,0 +1,178 @@
+use super::Indicator;
+use alloc::collections::VecDeque;
+
+/// 背离方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// 底背离: 价格创新低，震荡指标却抬高，可能向上反转
+    Bullish,
+    /// 顶背离: 价格创新高，震荡指标却走低，可能向下反转
+    Bearish,
+}
+
+/// 检测到的一次背离
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivergenceSignal {
+    pub kind: DivergenceKind,
+    /// 当前的价格和指标值
+    pub price: f64,
+    pub indicator: f64,
+    /// 回看窗口内被突破的前一个极值点的价格和指标值
+    pub prev_price: f64,
+    pub prev_indicator: f64,
+}
+
+/// 在 `(价格, 指标)` 序列上检测背离
+///
+/// 最后一个点为当前点，之前的点为回看窗口。当前价格低于窗口内最低价、但指标高于该点的指标时
+/// 为底背离；当前价格高于窗口内最高价、但指标低于该点的指标时为顶背离。
+/// 少于两个点时返回 `None`。
+pub fn detect_divergence(points: &[(f64, f64)]) -> Option<DivergenceSignal> {
+    let (&(price, indicator), window) = points.split_last()?;
+
+    let signal = |kind, (prev_price, prev_indicator): (f64, f64)| DivergenceSignal {
+        kind,
+        price,
+        indicator,
+        prev_price,
+        prev_indicator,
+    };
+
+    let low = window.iter().copied().min_by(|a, b| a.0.total_cmp(&b.0))?;
+    if price < low.0 && indicator > low.1 {
+        return Some(signal(DivergenceKind::Bullish, low));
+    }
+
+    let high = window.iter().copied().max_by(|a, b| a.0.total_cmp(&b.0))?;
+    if price > high.0 && indicator < high.1 {
+        return Some(signal(DivergenceKind::Bearish, high));
+    }
+
+    None
+}
+
+/// 背离检测器
+///
+/// 将价格输入震荡指标（如 [`RSI`](super::RSI)），在最近 `lookback` 个有效的 `(价格, 指标)`
+/// 点上用 [`detect_divergence`] 检测当前点是否背离。指标尚未产生输出时不计入窗口。
+#[derive(Debug, Clone)]
+pub struct DivergenceDetector<IND> {
+    pub(crate) oscillator: IND,
+    pub(crate) lookback: usize,
+    pub(crate) points: VecDeque<(f64, f64)>,
+}
+
+impl<IND> DivergenceDetector<IND>
+where
+    IND: Indicator<Input = f64, Output = Option<f64>>,
+{
+    /// # Panics
+    ///
+    /// `lookback` 为 0 时 panic
+    pub fn new(oscillator: IND, lookback: usize) -> Self {
+        assert!(lookback > 0, "Lookback must be positive");
+
+        Self {
+            oscillator,
+            lookback,
+            points: VecDeque::with_capacity(lookback + 1),
+        }
+    }
+}
+
+impl<IND> Indicator for DivergenceDetector<IND>
+where
+    IND: Indicator<Input = f64, Output = Option<f64>>,
+{
+    type Input = f64;
+    type Output = Option<DivergenceSignal>;
+
+    fn on_data(&mut self, price: Self::Input) -> Self::Output {
+        let indicator = self.oscillator.on_data(price)?;
+
+        if self.points.len() > self.lookback {
+            self.points.pop_front();
+        }
+        self.points.push_back((price, indicator));
+
+        detect_divergence(self.points.make_contiguous())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 动量: 当前价格减去上一个价格
+    struct Momentum(Option<f64>);
+
+    impl Indicator for Momentum {
+        type Input = f64;
+        type Output = Option<f64>;
+
+        fn on_data(&mut self, price: f64) -> Option<f64> {
+            self.0.replace(price).map(|prev| price - prev)
+        }
+    }
+
+    #[test]
+    fn test_detect_divergence() {
+        // 价格创新低 (95 < 98)，指标抬高 (25 > 20)
+        let bullish = detect_divergence(&[(98.0, 20.0), (105.0, 60.0), (95.0, 25.0)]).unwrap();
+        assert_eq!(bullish.kind, DivergenceKind::Bullish);
+        assert_eq!((bullish.prev_price, bullish.prev_indicator), (98.0, 20.0));
+
+        // 价格创新高 (110 > 108)，指标走低 (65 < 80)
+        let bearish = detect_divergence(&[(108.0, 80.0), (100.0, 50.0), (110.0, 65.0)]).unwrap();
+        assert_eq!(bearish.kind, DivergenceKind::Bearish);
+        assert_eq!((bearish.price, bearish.indicator), (110.0, 65.0));
+
+        // 价格与指标同步创新低不算背离
+        assert_eq!(detect_divergence(&[(98.0, 20.0), (95.0, 15.0)]), None);
+        assert_eq!(detect_divergence(&[(98.0, 20.0)]), None);
+    }
+
+    #[test]
+    fn test_divergence_detector() {
+        let mut detector = DivergenceDetector::new(Momentum(None), 3);
+
+        // 100 -> 90 动量 -10，反弹后 95 -> 89 动量 -6: 价格更低但跌势减弱
+        let signals = [100.0, 90.0, 95.0, 89.0]
+            .into_iter()
+            .map(|price| detector.on_data(price))
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(signals[..3], [None, None, None]);
+        let signal = signals[3].unwrap();
+        assert_eq!(signal.kind, DivergenceKind::Bullish);
+        assert_eq!((signal.prev_price, signal.prev_indicator), (90.0, -10.0));
+
+        // 110 动量 +21，回落后 104 -> 112 动量 +8: 价格更高但涨势减弱
+        let mut detector = DivergenceDetector::new(Momentum(None), 3);
+        let last = [89.0, 110.0, 104.0, 112.0]
+            .into_iter()
+            .map(|price| detector.on_data(price))
+            .last()
+            .flatten()
+            .unwrap();
+        assert_eq!(last.kind, DivergenceKind::Bearish);
+        assert_eq!(last.prev_price, 110.0);
+    }
+
+    #[test]
+    fn test_divergence_detector_window() {
+        let prices = [100.0, 90.0, 120.0, 110.0, 125.0];
+
+        // 回看 3 个点时，125 突破 120 的高点而动量从 +30 降到 +15
+        let mut detector = DivergenceDetector::new(Momentum(None), 3);
+        let last = prices.map(|price| detector.on_data(price))[4].unwrap();
+        assert_eq!(last.kind, DivergenceKind::Bearish);
+
+        // 只回看 1 个点时，120 已经移出窗口
+        let mut detector = DivergenceDetector::new(Momentum(None), 1);
+        assert!(
+            prices
+                .iter()
+                .all(|&price| detector.on_data(price).is_none())
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use super::Indicator;
+use alloc::vec::Vec;
 
 /// EMA - 指数移动平均线 (Exponential Moving Average)
 ///
@@ -17,20 +18,50 @@ use super::Indicator;
 /// - **趋势跟踪**: 价格在 EMA 上方为上升趋势，下方为下降趋势。
 /// - **支撑阻力**: EMA 可作为动态支撑或阻力位。
 /// - **交叉策略**: 短期 EMA 上穿长期 EMA 为金叉（买入信号），下穿为死叉（卖出信号）。
+///
+/// # 初始值
+/// 初始值的选取会影响前若干个输出，见 [`EmaSeed`]。[`EMA::new`] 默认使用 SMA 作为初始值。
 #[derive(Debug, Clone)]
 pub struct EMA {
     pub(crate) period: usize,
     pub(crate) alpha: f64,
+    pub(crate) seed: EmaSeed,
     pub(crate) current_ema: Option<f64>,
     pub(crate) init_values: Vec<f64>,
 }
 
+/// EMA 初始值的选取方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmaSeed {
+    /// 以前 `period` 个值的 SMA 作为初始值，此前不输出（TradingView 的做法）
+    #[default]
+    Sma,
+    /// 以第一个值作为初始值，从第一个值起就输出（pandas `ewm(adjust=False)` 的做法）
+    FirstValue,
+}
+
 impl EMA {
+    /// 等同于 [`EMA::with_sma_seed`]
     pub fn new(period: usize) -> Self {
+        Self::with_seed(period, EmaSeed::Sma)
+    }
+
+    /// 以前 `period` 个值的 SMA 作为初始值
+    pub fn with_sma_seed(period: usize) -> Self {
+        Self::with_seed(period, EmaSeed::Sma)
+    }
+
+    /// 以第一个值作为初始值
+    pub fn with_first_value_seed(period: usize) -> Self {
+        Self::with_seed(period, EmaSeed::FirstValue)
+    }
+
+    pub fn with_seed(period: usize, seed: EmaSeed) -> Self {
         let alpha = 2.0 / (period as f64 + 1.0);
         Self {
             period,
             alpha,
+            seed,
             current_ema: None,
             init_values: Vec::with_capacity(period),
         }
@@ -67,7 +98,16 @@ impl Indicator for EMA {
     type Output = Option<f64>;
 
     fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        // 非有限值（NaN/inf）直接丢弃，避免污染后续输出
+        if !input.is_finite() {
+            return None;
+        }
+
         match self.current_ema {
+            None if self.seed == EmaSeed::FirstValue => {
+                self.current_ema = Some(input);
+                Some(input)
+            }
             None => {
                 // 初始化阶段：使用 SMA 作为第一个 EMA 值
                 self.init_values.push(input);
@@ -213,4 +253,53 @@ mod tests {
             long
         );
     }
+
+    #[test]
+    fn test_ema_seeding_modes() {
+        let period = 5;
+        let prices: Vec<f64> = (0..period + 5).map(|i| 100.0 + (i * i) as f64).collect();
+
+        let mut sma_seeded = EMA::with_sma_seed(period);
+        let mut first_seeded = EMA::with_first_value_seed(period);
+        let sma_outputs: Vec<_> = prices.iter().map(|&p| sma_seeded.on_data(p)).collect();
+        let first_outputs: Vec<_> = prices.iter().map(|&p| first_seeded.on_data(p)).collect();
+
+        // SMA 初始值: 前 period - 1 个输出为空，第 period 个为 SMA
+        assert!(sma_outputs[..period - 1].iter().all(Option::is_none));
+        let sma = prices[..period].iter().sum::<f64>() / period as f64;
+        approx::assert_abs_diff_eq!(sma_outputs[period - 1].unwrap(), sma);
+
+        // 首值初始值: 从第一个值开始输出
+        approx::assert_abs_diff_eq!(first_outputs[0].unwrap(), prices[0]);
+        let alpha = 2.0 / (period as f64 + 1.0);
+        let mut expected = prices[0];
+        for (&price, output) in prices.iter().zip(&first_outputs).skip(1) {
+            expected = price * alpha + expected * (1.0 - alpha);
+            approx::assert_abs_diff_eq!(output.unwrap(), expected, epsilon = 1e-9);
+        }
+
+        // 两种初始值的输出不同，但差距随时间按 (1 - α) 衰减
+        let gaps: Vec<f64> = (period - 1..prices.len())
+            .map(|i| sma_outputs[i].unwrap() - first_outputs[i].unwrap())
+            .collect();
+        assert!(gaps[0].abs() > 1.0);
+        for pair in gaps.windows(2) {
+            approx::assert_abs_diff_eq!(pair[1], pair[0] * (1.0 - alpha), epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_ema_skips_non_finite() {
+        let mut ema = EMA::new(3);
+
+        // 初始化阶段混入 NaN 不应计入 SMA
+        ema.on_data(10.0);
+        assert!(ema.on_data(f64::NAN).is_none());
+        ema.on_data(20.0);
+        approx::assert_abs_diff_eq!(ema.on_data(30.0).unwrap(), 20.0);
+
+        // 之后的 inf 也不应影响 EMA
+        assert!(ema.on_data(f64::NEG_INFINITY).is_none());
+        approx::assert_abs_diff_eq!(ema.on_data(40.0).unwrap(), 30.0);
+    }
 }
@@ -1,4 +1,5 @@
 use super::Indicator;
+use alloc::collections::VecDeque;
 
 // I: 上游数据源
 // IND: 具体的指标逻辑
@@ -44,3 +45,143 @@ pub trait IndicatorExt: Iterator {
 }
 
 impl<I: Iterator> IndicatorExt for I {}
+
+/// 滑动窗口的最大值和最小值 (Rolling Extremes)
+///
+/// # 原理
+/// 分别维护一个单调递减（最大值）和单调递增（最小值）的双端队列，队列中保存 `(序号, 值)`。
+/// 新值入队前弹出队尾所有不可能再成为极值的元素，队首超出窗口时出队，
+/// 因此 `push` 均摊 O(1)，`current_max`/`current_min` 为 O(1)。
+///
+/// 供 Donchian 通道、SuperTrend、随机指标等需要滚动极值的指标共用，避免每次重新扫描窗口。
+/// 作为 [`Indicator`] 使用时，窗口填满后输出 `(最小值, 最大值)`。
+#[derive(Debug, Clone)]
+pub struct RollingExtremes {
+    pub(crate) period: usize,
+    /// 已经压入的值的数量，也是下一个值的序号
+    pub(crate) count: usize,
+    pub(crate) max_deque: VecDeque<(usize, f64)>,
+    pub(crate) min_deque: VecDeque<(usize, f64)>,
+}
+
+impl RollingExtremes {
+    /// # Panics
+    ///
+    /// `period` 为 0 时 panic
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "Period must be positive");
+
+        Self {
+            period,
+            count: 0,
+            max_deque: VecDeque::new(),
+            min_deque: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        let index = self.count;
+        self.count += 1;
+
+        while self.max_deque.back().is_some_and(|&(_, v)| v <= value) {
+            self.max_deque.pop_back();
+        }
+        self.max_deque.push_back((index, value));
+
+        while self.min_deque.back().is_some_and(|&(_, v)| v >= value) {
+            self.min_deque.pop_back();
+        }
+        self.min_deque.push_back((index, value));
+
+        // 序号小于 `count - period` 的值已经离开窗口
+        let start = self.count.saturating_sub(self.period);
+        while self.max_deque.front().is_some_and(|&(i, _)| i < start) {
+            self.max_deque.pop_front();
+        }
+        while self.min_deque.front().is_some_and(|&(i, _)| i < start) {
+            self.min_deque.pop_front();
+        }
+    }
+
+    /// 窗口内（最近 `period` 个值）的最大值，尚未压入任何值时为 `None`
+    pub fn current_max(&self) -> Option<f64> {
+        self.max_deque.front().map(|&(_, v)| v)
+    }
+
+    /// 窗口内（最近 `period` 个值）的最小值，尚未压入任何值时为 `None`
+    pub fn current_min(&self) -> Option<f64> {
+        self.min_deque.front().map(|&(_, v)| v)
+    }
+
+    /// 窗口是否已经填满
+    pub fn is_full(&self) -> bool {
+        self.count >= self.period
+    }
+}
+
+impl Indicator for RollingExtremes {
+    type Input = f64;
+    type Output = Option<(f64, f64)>;
+
+    fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        self.push(input);
+
+        if self.is_full() {
+            Some((self.current_min()?, self.current_max()?))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use ephemera_shared::testing::SplitMix64;
+
+    #[test]
+    fn test_rolling_extremes() {
+        let mut extremes = RollingExtremes::new(3);
+        assert_eq!(extremes.current_max(), None);
+
+        let outputs: Vec<_> = [1.0, 3.0, 2.0, 0.5, 0.5, 4.0]
+            .into_iter()
+            .map(|v| extremes.on_data(v))
+            .collect();
+
+        assert_eq!(
+            outputs,
+            [
+                None,
+                None,
+                Some((1.0, 3.0)),
+                Some((0.5, 3.0)),
+                Some((0.5, 2.0)),
+                Some((0.5, 4.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rolling_extremes_matches_brute_force() {
+        // 固定种子，保证测试可复现。取少量离散值，覆盖相等元素的情况
+        let mut rng = SplitMix64::new(0x2545F4914F6CDD1D);
+        let values: Vec<f64> = (0..2000).map(|_| (rng.next_u64() % 50) as f64).collect();
+
+        for period in [1, 2, 7, 64] {
+            let mut extremes = RollingExtremes::new(period);
+
+            for (i, &value) in values.iter().enumerate() {
+                extremes.push(value);
+
+                let window = &values[(i + 1).saturating_sub(period)..=i];
+                let max = window.iter().copied().fold(f64::MIN, f64::max);
+                let min = window.iter().copied().fold(f64::MAX, f64::min);
+                assert_eq!(extremes.current_max(), Some(max));
+                assert_eq!(extremes.current_min(), Some(min));
+                assert!(extremes.max_deque.len() <= period);
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use super::Indicator;
-use std::collections::VecDeque;
+use alloc::collections::VecDeque;
 
 /// 简单移动平均线 (Simple Moving Average, SMA)
 ///
@@ -16,11 +16,17 @@ use std::collections::VecDeque;
 /// - **MA20**: 短期趋势，布林带的中轨通常使用此参数。
 /// - **MA50**: 中期趋势，常用于判断中期调整。
 /// - **MA200**: 长期趋势，著名的 "牛熊分界线"。
+///
+/// # 实现
+/// 用环形缓冲区维护窗口内的累加和，每次更新只加新值、减淘汰值，`on_data` 为 O(1)。
+/// 增减累加会积累浮点误差，因此每淘汰 `period` 个值后对窗口重新求和一次，均摊仍为 O(1)。
 #[derive(Debug, Clone)]
 pub struct MA {
     pub(crate) period: usize,
     pub(crate) values: VecDeque<f64>,
     pub(crate) sum: f64,
+    /// 距上次重新求和后淘汰的值数量
+    pub(crate) evictions: usize,
 }
 
 impl MA {
@@ -29,6 +35,7 @@ impl MA {
             period,
             values: VecDeque::with_capacity(period),
             sum: 0.0,
+            evictions: 0,
         }
     }
 
@@ -63,6 +70,11 @@ impl Indicator for MA {
     type Output = Option<f64>;
 
     fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        // 非有限值（NaN/inf）直接丢弃，避免污染后续输出
+        if !input.is_finite() {
+            return None;
+        }
+
         self.values.push_back(input);
         self.sum += input;
 
@@ -70,6 +82,12 @@ impl Indicator for MA {
             && let Some(old_value) = self.values.pop_front()
         {
             self.sum -= old_value;
+            self.evictions += 1;
+
+            if self.evictions >= self.period {
+                self.sum = self.values.iter().sum();
+                self.evictions = 0;
+            }
         }
 
         if self.values.len() == self.period {
@@ -89,3 +107,57 @@ fn test_ma() {
     approx::assert_abs_diff_eq!(ma.on_data(30.0).unwrap(), 20.0);
     approx::assert_abs_diff_eq!(ma.on_data(40.0).unwrap(), 30.0);
 }
+
+#[test]
+fn test_ma_matches_naive_mean() {
+    let period = 350;
+    let mut ma = MA::new(period);
+
+    // 固定种子的噪声序列，量级在 1e-3 与 1e6 之间跳动以放大累加误差
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let prices: Vec<f64> = (0..50_000)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let noise = (state >> 11) as f64 / (1u64 << 53) as f64;
+            let scale = if (i / 1000) % 2 == 0 { 1e6 } else { 1e-3 };
+            noise * scale
+        })
+        .collect();
+
+    for (i, &price) in prices.iter().enumerate() {
+        let output = ma.on_data(price);
+
+        if i + 1 < period {
+            assert!(output.is_none());
+            continue;
+        }
+
+        let window = &prices[i + 1 - period..=i];
+        let naive = window.iter().sum::<f64>() / period as f64;
+
+        // 误差只来自上次重新求和以来的增减，最多跨越 2 * period 个值
+        let recent = &prices[(i + 1).saturating_sub(2 * period)..=i];
+        let magnitude = recent.iter().fold(0.0_f64, |acc, v| acc.max(v.abs()));
+        let tolerance = 2.0 * period as f64 * f64::EPSILON * magnitude;
+        approx::assert_abs_diff_eq!(output.unwrap(), naive, epsilon = tolerance);
+
+        // 大数值完全移出后，重新求和应消除残留误差，而不是一直带着它
+        if magnitude < 1.0 {
+            approx::assert_relative_eq!(output.unwrap(), naive, max_relative = 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_ma_skips_non_finite() {
+    let mut ma = MA::new(3);
+
+    assert!(ma.on_data(10.0).is_none());
+    assert!(ma.on_data(f64::NAN).is_none());
+    assert!(ma.on_data(20.0).is_none());
+    assert!(ma.on_data(f64::INFINITY).is_none());
+    approx::assert_abs_diff_eq!(ma.on_data(30.0).unwrap(), 20.0);
+    approx::assert_abs_diff_eq!(ma.on_data(40.0).unwrap(), 30.0);
+}
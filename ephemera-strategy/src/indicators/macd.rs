@@ -0,0 +1,140 @@
+use super::{EMA, Indicator};
+
+/// MACD - 指数平滑异同移动平均线 (Moving Average Convergence Divergence)
+///
+/// # 原理
+/// MACD 由 Gerald Appel 在 1970 年代提出，通过快慢两条 EMA 的差值衡量趋势的强弱与方向，
+/// 再对差值做一次 EMA 平滑得到信号线。
+///
+/// # 公式
+/// ```text
+/// MACD 线 = EMA(fast) - EMA(slow)
+/// 信号线   = EMA(MACD 线, signal)
+/// 柱状图   = MACD 线 - 信号线
+/// ```
+///
+/// # 解释
+/// - **零轴**: MACD 线在零轴上方表示快线在慢线之上，多头占优；反之空头占优。
+/// - **金叉/死叉**: MACD 线上穿信号线为买入信号，下穿为卖出信号。
+/// - **柱状图**: 柱体放大表示动能增强，缩小表示动能减弱。
+/// - **背离**: 价格创新高而 MACD 未创新高，可能预示趋势反转。
+///
+/// # 预热
+/// 慢线 EMA 需要 `slow` 个值，信号线 EMA 还需要 `signal` 个 MACD 值，
+/// 因此前 `slow + signal - 2` 个输入返回 `None`。
+#[derive(Debug, Clone)]
+pub struct Macd {
+    pub(crate) fast_period: usize,
+    pub(crate) slow_period: usize,
+    pub(crate) signal_period: usize,
+    pub(crate) fast: EMA,
+    pub(crate) slow: EMA,
+    pub(crate) signal: EMA,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdOutput {
+    /// MACD 线: EMA(fast) - EMA(slow)
+    pub macd_line: f64,
+    /// 信号线: MACD 线的 EMA
+    pub signal_line: f64,
+    /// 柱状图: MACD 线 - 信号线
+    pub histogram: f64,
+}
+
+impl Macd {
+    pub fn new(fast_period: usize, slow_period: usize, signal_period: usize) -> Self {
+        Self {
+            fast_period,
+            slow_period,
+            signal_period,
+            fast: EMA::new(fast_period),
+            slow: EMA::new(slow_period),
+            signal: EMA::new(signal_period),
+        }
+    }
+
+    /// 标准 MACD (12, 26, 9)
+    pub fn standard() -> Self {
+        Self::new(12, 26, 9)
+    }
+
+    /// 清空内部状态，重新开始预热
+    pub fn reset(&mut self) {
+        *self = Self::new(self.fast_period, self.slow_period, self.signal_period);
+    }
+}
+
+impl Indicator for Macd {
+    type Input = f64;
+    type Output = Option<MacdOutput>;
+
+    fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        // 快线与慢线需要同时喂入，保证两者看到的是同一段序列
+        let fast = self.fast.on_data(input);
+        let slow = self.slow.on_data(input)?;
+        let macd_line = fast? - slow;
+
+        let signal_line = self.signal.on_data(macd_line)?;
+        Some(MacdOutput {
+            macd_line,
+            signal_line,
+            histogram: macd_line - signal_line,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const PRICES: [f64; 6] = [10.0, 12.0, 11.0, 14.0, 13.0, 16.0];
+
+    fn assert_output(output: Option<MacdOutput>, macd_line: f64, signal_line: f64, histogram: f64) {
+        let output = output.unwrap();
+        approx::assert_abs_diff_eq!(output.macd_line, macd_line, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(output.signal_line, signal_line, epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(output.histogram, histogram, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_macd_hand_computed() {
+        // 快线 EMA(2), α = 2/3:  -, 11, 11, 13, 13, 15
+        // 慢线 EMA(3), α = 1/2:  -, -, 11, 12.5, 12.75, 14.375
+        // MACD 线:               -, -, 0, 0.5, 0.25, 0.625
+        // 信号线 EMA(2), α = 2/3: 以 (0 + 0.5) / 2 = 0.25 为初始值
+        //   0.25 * 2/3 + 0.25 / 3 = 0.25
+        //   0.625 * 2/3 + 0.25 / 3 = 0.5
+        let mut macd = Macd::new(2, 3, 2);
+        let outputs: Vec<_> = PRICES.iter().map(|&p| macd.on_data(p)).collect();
+
+        // 预热: slow + signal - 2 = 3 个输入返回 None
+        assert!(outputs[..3].iter().all(Option::is_none));
+        assert_output(outputs[3], 0.5, 0.25, 0.25);
+        assert_output(outputs[4], 0.25, 0.25, 0.0);
+        assert_output(outputs[5], 0.625, 0.5, 0.125);
+    }
+
+    #[test]
+    fn test_macd_constant_prices() {
+        let mut macd = Macd::standard();
+
+        let outputs: Vec<_> = (0..40).map(|_| macd.on_data(100.0)).collect();
+        assert!(outputs[..33].iter().all(Option::is_none));
+        for output in &outputs[33..] {
+            assert_output(*output, 0.0, 0.0, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_macd_reset() {
+        let mut macd = Macd::new(2, 3, 2);
+        let first: Vec<_> = PRICES.iter().map(|&p| macd.on_data(p)).collect();
+
+        // 重置后需要重新预热，并得到与首次相同的结果
+        macd.reset();
+        let second: Vec<_> = PRICES.iter().map(|&p| macd.on_data(p)).collect();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,27 @@
+//! 指标用到的浮点函数
+//!
+//! `core` 没有 `sqrt`/`powf`，`no_std` 下改用 `libm`。
+
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    x.sqrt()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    libm::sqrt(x)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn powf(x: f64, y: f64) -> f64 {
+    x.powf(y)
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub(crate) fn powf(x: f64, y: f64) -> f64 {
+    libm::pow(x, y)
+}
@@ -1,22 +1,48 @@
 pub mod ahr;
+#[cfg(feature = "std")]
+pub mod atr;
 pub mod bollinger;
+pub mod cross;
+#[cfg(feature = "std")]
+pub mod cvd;
+pub mod divergence;
 pub mod ema;
 pub mod iter;
 pub mod ma;
+pub mod macd;
 pub mod mvrv;
+pub mod pi_cycle;
 pub mod rsi;
+pub mod sharpe;
+pub mod std_dev;
+#[cfg(feature = "std")]
 pub mod stream;
-pub mod pi_cycle;
+#[cfg(feature = "std")]
+pub mod volatility;
+
+mod math;
 
 pub use ahr::*;
+#[cfg(feature = "std")]
+pub use atr::*;
 pub use bollinger::*;
+pub use cross::*;
+#[cfg(feature = "std")]
+pub use cvd::*;
+pub use divergence::*;
 pub use ema::*;
 pub use iter::*;
 pub use ma::*;
+pub use macd::*;
 pub use mvrv::*;
+pub use pi_cycle::*;
 pub use rsi::*;
+pub use sharpe::*;
+pub use std_dev::*;
+#[cfg(feature = "std")]
 pub use stream::*;
-pub use pi_cycle::*;
+#[cfg(feature = "std")]
+pub use volatility::*;
 
 pub trait Indicator {
     type Input;
@@ -24,4 +50,3 @@ pub trait Indicator {
 
     fn on_data(&mut self, input: Self::Input) -> Self::Output;
 }
-
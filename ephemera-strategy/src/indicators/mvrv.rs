@@ -1,5 +1,5 @@
-use super::Indicator;
-use std::collections::VecDeque;
+use super::{Indicator, math};
+use alloc::collections::VecDeque;
 
 /// MVRV Z-Score (滚动窗口版)
 ///
@@ -43,7 +43,7 @@ impl MVRVZScore {
 
     fn calculate_std_dev(&self, mean: f64) -> f64 {
         let variance = (self.sum_squared / self.mvrv_values.len() as f64) - (mean * mean);
-        variance.sqrt()
+        math::sqrt(variance)
     }
 }
 
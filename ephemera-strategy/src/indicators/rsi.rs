@@ -1,5 +1,5 @@
 use super::Indicator;
-use std::collections::VecDeque;
+use alloc::collections::VecDeque;
 
 /// RSI - 相对强弱指标 (Relative Strength Index)
 ///
@@ -93,6 +93,11 @@ impl Indicator for RSI {
     type Output = Option<f64>;
 
     fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        // 非有限值（NaN/inf）直接丢弃，避免污染后续输出
+        if !input.is_finite() {
+            return None;
+        }
+
         let current_price = input;
 
         // 计算价格变化
@@ -190,4 +195,29 @@ mod tests {
             rsi_value
         );
     }
+
+    #[test]
+    fn test_rsi_skips_non_finite() {
+        let prices = [
+            44.0, 44.25, 44.37, 44.10, 44.75, 45.00, 44.80, 45.50, 45.75, 45.20, 46.25, 46.50,
+            46.10, 47.00, 47.25, 46.90, 47.40,
+        ];
+
+        let mut clean = RSI::new(14);
+        let mut dirty = RSI::new(14);
+
+        for (i, &price) in prices.iter().enumerate() {
+            if i % 4 == 1 {
+                assert!(dirty.on_data(f64::NAN).is_none());
+                assert!(dirty.on_data(f64::INFINITY).is_none());
+            }
+
+            let expected = clean.on_data(price);
+            let actual = dirty.on_data(price);
+            assert_eq!(expected.is_some(), actual.is_some());
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                approx::assert_abs_diff_eq!(expected, actual);
+            }
+        }
+    }
 }
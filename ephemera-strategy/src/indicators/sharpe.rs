@@ -0,0 +1,116 @@
+use super::{Indicator, RollingStdDev, math};
+
+/// 一年的秒数，加密货币市场全年无休
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0;
+
+/// 滚动夏普比率 (Rolling Sharpe Ratio)
+///
+/// # 原理
+/// 输入为权益序列，每次更新计算相对上一次权益的简单收益率，在最近 `window` 个收益率上计算:
+/// ```text
+/// sharpe = mean(r) / std(r) * sqrt(periods_per_year)
+/// ```
+/// 标准差为总体标准差，无风险利率按 0 计，与回测报告的夏普比率一致，只是限定在滑动窗口内，
+/// 用于观察实盘中策略表现是否在近期变差。收益率的均值和方差由 [`RollingStdDev`] 以 O(1) 维护。
+///
+/// 权益为 0 或非有限值产生的无效收益率被跳过。窗口填满前输出 `None`，窗口内收益率没有波动时输出 0。
+#[derive(Debug, Clone)]
+pub struct RollingSharpe {
+    pub(crate) periods_per_year: f64,
+    pub(crate) returns: RollingStdDev,
+    pub(crate) prev_equity: Option<f64>,
+}
+
+impl RollingSharpe {
+    /// `periods_per_year` 为一年内的观测次数，用于年化，如日频数据为 365
+    pub fn new(window: usize, periods_per_year: f64) -> Self {
+        Self {
+            periods_per_year,
+            returns: RollingStdDev::new(window),
+            prev_equity: None,
+        }
+    }
+
+    /// 每 `interval_sc` 秒观测一次权益，按此年化
+    pub fn with_interval(window: usize, interval_sc: u64) -> Self {
+        Self::new(window, SECONDS_PER_YEAR / interval_sc as f64)
+    }
+}
+
+impl Indicator for RollingSharpe {
+    type Input = f64;
+    type Output = Option<f64>;
+
+    fn on_data(&mut self, equity: Self::Input) -> Self::Output {
+        let prev_equity = self.prev_equity.replace(equity)?;
+
+        let r = (equity - prev_equity) / prev_equity;
+        if !r.is_finite() {
+            return None;
+        }
+
+        let std_dev = self.returns.on_data(r)?;
+        let mean = self.returns.mean()?;
+
+        if std_dev == 0.0 {
+            Some(0.0)
+        } else {
+            Some(mean / std_dev * math::sqrt(self.periods_per_year))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// 直接按定义计算一组收益率的夏普比率
+    fn sharpe(returns: &[f64], periods_per_year: f64) -> f64 {
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean) * (r - mean)).sum::<f64>() / n;
+        mean / math::sqrt(variance) * math::sqrt(periods_per_year)
+    }
+
+    #[test]
+    fn test_rolling_sharpe() {
+        let returns = [0.01, -0.02, 0.03, 0.005, -0.01, 0.02];
+        let mut equity = 1000.0;
+        let mut curve = Vec::from([equity]);
+        for r in returns {
+            equity *= 1.0 + r;
+            curve.push(equity);
+        }
+
+        let mut rolling = RollingSharpe::new(3, 365.0);
+        let outputs: Vec<_> = curve.into_iter().map(|e| rolling.on_data(e)).collect();
+
+        // 第一个权益没有收益率，之后需要 3 个收益率才填满窗口
+        assert_eq!(&outputs[..3], &[None, None, None]);
+        for (i, output) in outputs[3..].iter().enumerate() {
+            approx::assert_abs_diff_eq!(
+                output.unwrap(),
+                sharpe(&returns[i..i + 3], 365.0),
+                epsilon = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn test_rolling_sharpe_flat_and_annualization() {
+        let mut rolling = RollingSharpe::with_interval(2, 60);
+        assert_eq!(rolling.periods_per_year, 525_600.0);
+
+        // 权益不变，收益率没有波动
+        let outputs: Vec<_> = [100.0, 100.0, 100.0]
+            .into_iter()
+            .map(|e| rolling.on_data(e))
+            .collect();
+        assert_eq!(outputs, [None, None, Some(0.0)]);
+
+        assert!(rolling.on_data(0.0).unwrap() < 0.0);
+        // 权益为 0 之后的收益率无效，被跳过
+        assert_eq!(rolling.on_data(50.0), None);
+    }
+}
@@ -0,0 +1,83 @@
+use super::{Indicator, math};
+use alloc::collections::VecDeque;
+
+/// 滚动标准差 (Rolling Standard Deviation)
+///
+/// # 原理
+/// 在长度为 `period` 的滑动窗口内维护 `Σx` 与 `Σx²`，每次更新为 O(1)：
+/// ```text
+/// mean     = Σx / n
+/// variance = Σx² / n - mean²
+/// ```
+/// 输出为总体标准差。浮点误差可能使方差略小于 0，此时按 0 处理。
+#[derive(Debug, Clone)]
+pub struct RollingStdDev {
+    pub(crate) period: usize,
+    pub(crate) values: VecDeque<f64>,
+    pub(crate) sum: f64,
+    pub(crate) sum_squared: f64,
+}
+
+impl RollingStdDev {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            values: VecDeque::with_capacity(period),
+            sum: 0.0,
+            sum_squared: 0.0,
+        }
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (!self.values.is_empty()).then(|| self.sum / self.values.len() as f64)
+    }
+
+    pub fn variance(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let variance = self.sum_squared / self.values.len() as f64 - mean * mean;
+        Some(variance.max(0.0))
+    }
+}
+
+impl Indicator for RollingStdDev {
+    type Input = f64;
+    type Output = Option<f64>;
+
+    fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        self.values.push_back(input);
+        self.sum += input;
+        self.sum_squared += input * input;
+
+        if self.values.len() > self.period
+            && let Some(old_value) = self.values.pop_front()
+        {
+            self.sum -= old_value;
+            self.sum_squared -= old_value * old_value;
+        }
+
+        if self.values.len() == self.period {
+            self.variance().map(math::sqrt)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_rolling_std_dev() {
+    let mut std_dev = RollingStdDev::new(3);
+
+    assert!(std_dev.on_data(10.0).is_none());
+    assert!(std_dev.on_data(20.0).is_none());
+    approx::assert_abs_diff_eq!(
+        std_dev.on_data(30.0).unwrap(),
+        f64::sqrt(200.0 / 3.0),
+        epsilon = 1e-9
+    );
+    // 窗口滚动为 [20, 30, 40]
+    approx::assert_abs_diff_eq!(
+        std_dev.on_data(40.0).unwrap(),
+        f64::sqrt(200.0 / 3.0),
+        epsilon = 1e-9
+    );
+}
@@ -50,4 +50,3 @@ pub trait IndicatorStreamExt: Stream {
 }
 
 impl<S: Stream> IndicatorStreamExt for S {}
-
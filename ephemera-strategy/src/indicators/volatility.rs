@@ -0,0 +1,98 @@
+use super::{Indicator, RollingStdDev};
+use ephemera_shared::{CandleData, IntervalSc};
+
+/// 一年的秒数，加密货币市场全年无休，按 365 天计算。
+pub const SECONDS_PER_YEAR: f64 = 365.0 * 86400.0;
+
+/// 已实现波动率 (Realized Volatility)
+///
+/// # 原理
+/// 对相邻两根 K 线的收盘价取对数收益率 `ln(close_t / close_{t-1})`，
+/// 在 `period` 个收益率的滑动窗口内计算标准差，再乘以 `sqrt(每年周期数)` 进行年化。
+/// 每年周期数由 K 线的 `interval_sc` 推导: `SECONDS_PER_YEAR / interval_sc`。
+///
+/// # 用途
+/// - **波动率目标仓位**: 仓位与波动率成反比。
+/// - **熔断**: 波动率超过阈值时暂停交易。
+///
+/// 收盘价非正或 `interval_sc == 0` 时无法计算收益率，返回 `None`。
+#[derive(Debug, Clone)]
+pub struct RealizedVolatility {
+    pub(crate) std_dev: RollingStdDev,
+    pub(crate) last_close: Option<f64>,
+}
+
+impl RealizedVolatility {
+    pub fn new(period: usize) -> Self {
+        Self {
+            std_dev: RollingStdDev::new(period),
+            last_close: None,
+        }
+    }
+
+    /// 给定 K 线周期下的年化因子 `sqrt(SECONDS_PER_YEAR / interval_sc)`
+    pub fn annualization_factor(interval_sc: IntervalSc) -> f64 {
+        (SECONDS_PER_YEAR / interval_sc as f64).sqrt()
+    }
+}
+
+impl Indicator for RealizedVolatility {
+    type Input = CandleData;
+    type Output = Option<f64>;
+
+    fn on_data(&mut self, input: Self::Input) -> Self::Output {
+        let close = input.close;
+        let last_close = self.last_close.replace(close)?;
+
+        if close <= 0.0 || last_close <= 0.0 || input.interval_sc == 0 {
+            return None;
+        }
+
+        let log_return = (close / last_close).ln();
+        let std_dev = self.std_dev.on_data(log_return)?;
+
+        Some(std_dev * Self::annualization_factor(input.interval_sc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ephemera_shared::CANDLE_INTERVAL_D1;
+
+    fn candle(close: f64) -> CandleData {
+        CandleData {
+            symbol: "BTC-USDT".into(),
+            interval_sc: CANDLE_INTERVAL_D1,
+            close,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_realized_volatility_constant_price() {
+        let mut vol = RealizedVolatility::new(3);
+
+        // 首根 K 线没有收益率，随后需要 3 个收益率填满窗口
+        assert!(vol.on_data(candle(100.0)).is_none());
+        assert!(vol.on_data(candle(100.0)).is_none());
+        assert!(vol.on_data(candle(100.0)).is_none());
+
+        let result = vol.on_data(candle(100.0)).unwrap();
+        approx::assert_abs_diff_eq!(result, 0.0);
+    }
+
+    #[test]
+    fn test_realized_volatility_annualized() {
+        let mut vol = RealizedVolatility::new(2);
+
+        // 收益率交替为 +r 与 -r，总体标准差为 r
+        let up = 110.0;
+        vol.on_data(candle(100.0));
+        vol.on_data(candle(up));
+        let result = vol.on_data(candle(100.0)).unwrap();
+
+        let r = (up / 100.0f64).ln();
+        approx::assert_abs_diff_eq!(result, r * 365.0f64.sqrt(), epsilon = 1e-9);
+    }
+}
@@ -1,2 +1,15 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "indicators")))]
+compile_error!("ephemera-strategy 至少需要启用 `std` 或 `indicators` 其中一个 feature");
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "indicators")]
 pub mod indicators;
+#[cfg(feature = "std")]
+pub mod risk;
+#[cfg(feature = "std")]
 pub mod strategies;
@@ -0,0 +1,454 @@
+use ephemera_shared::{Signal, Symbol};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+/// 组合层面的风控配置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskConfig {
+    /// 权益相对峰值的最大回撤（百分比），超过后停止开新仓
+    pub max_drawdown_pct: f64,
+    /// 回撤恢复到该值（百分比）以内后重新允许开仓，应不大于 `max_drawdown_pct`
+    pub resume_drawdown_pct: f64,
+    /// 触发时是否平掉所有持仓
+    pub flatten_on_breach: bool,
+}
+
+impl RiskConfig {
+    /// 回撤恢复到阈值一半以内时解除，不主动平仓
+    pub fn new(max_drawdown_pct: f64) -> Self {
+        Self {
+            max_drawdown_pct,
+            resume_drawdown_pct: max_drawdown_pct / 2.0,
+            flatten_on_breach: false,
+        }
+    }
+}
+
+/// 基于回撤的熔断开关
+///
+/// 调用方每次得到新的权益时调用 [`DrawdownKillSwitch::on_equity`]，再用
+/// [`DrawdownKillSwitch::filter`] 过滤策略信号。熔断期间买入信号被替换为 `Signal::Hold`，
+/// 卖出信号照常放行（只减少风险）。回测和实盘只需在各自的权益更新点接入即可。
+#[derive(Debug, Clone)]
+pub struct DrawdownKillSwitch {
+    pub(crate) config: RiskConfig,
+    pub(crate) peak_equity: f64,
+    pub(crate) drawdown_pct: f64,
+    pub(crate) halted: bool,
+}
+
+impl DrawdownKillSwitch {
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            peak_equity: 0.0,
+            drawdown_pct: 0.0,
+            halted: false,
+        }
+    }
+
+    /// 更新权益，返回本次是否**新触发**熔断
+    pub fn on_equity(&mut self, equity: f64) -> bool {
+        if !equity.is_finite() {
+            return false;
+        }
+
+        self.peak_equity = self.peak_equity.max(equity);
+        self.drawdown_pct = if self.peak_equity > 0.0 {
+            (self.peak_equity - equity) / self.peak_equity * 100.0
+        } else {
+            0.0
+        };
+
+        if self.halted {
+            if self.drawdown_pct <= self.config.resume_drawdown_pct {
+                self.halted = false;
+                tracing::info!("回撤恢复至 {:.2}%，解除熔断", self.drawdown_pct);
+            }
+            false
+        } else if self.drawdown_pct > self.config.max_drawdown_pct {
+            self.halted = true;
+            tracing::warn!(
+                "回撤 {:.2}% 超过阈值 {:.2}%，停止开仓",
+                self.drawdown_pct,
+                self.config.max_drawdown_pct
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 熔断期间将买入信号替换为 `Signal::Hold`
+    pub fn filter(&self, signal: Signal) -> Signal {
+        if self.halted && signal.is_buy() {
+            Signal::Hold
+        } else {
+            signal
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// 熔断期间是否需要平掉持仓
+    pub fn should_flatten(&self) -> bool {
+        self.halted && self.config.flatten_on_breach
+    }
+
+    pub fn drawdown_pct(&self) -> f64 {
+        self.drawdown_pct
+    }
+
+    pub fn config(&self) -> &RiskConfig {
+        &self.config
+    }
+}
+
+/// 组合层面基于持仓数量的约束，与基于资金的 [`RiskConfig`] 互补
+///
+/// 默认不做任何限制。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PortfolioConstraints {
+    /// 同时持仓的交易对数量上限
+    pub max_concurrent_positions: Option<usize>,
+    /// 同一交易对在平仓前最多开仓（含加仓）的次数
+    pub max_positions_per_symbol: Option<usize>,
+    /// 同一交易对两次开仓之间至少间隔的 K 线数
+    pub min_bars_between_entries: usize,
+}
+
+/// 违反的 [`PortfolioConstraints`] 约束
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    MaxConcurrentPositions,
+    MaxPositionsPerSymbol,
+    MinBarsBetweenEntries,
+}
+
+impl ConstraintViolation {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::MaxConcurrentPositions => "max concurrent positions",
+            Self::MaxPositionsPerSymbol => "max positions per symbol",
+            Self::MinBarsBetweenEntries => "min bars between entries",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SymbolEntries {
+    /// 平仓以来的开仓次数，为 0 表示没有持仓
+    entries: usize,
+    /// 最近一次开仓以来经过的 K 线数，从未开仓时为 `None`
+    bars_since_entry: Option<usize>,
+}
+
+/// 按 [`PortfolioConstraints`] 过滤开仓信号
+///
+/// 调用方在每根 K 线调用 [`PositionLimiter::on_bar`]，用 [`PositionLimiter::filter`] 过滤信号，
+/// 并在开仓成交后调用 [`PositionLimiter::on_entry`]、持仓归零后调用 [`PositionLimiter::on_flat`]。
+/// 与 [`DrawdownKillSwitch`] 一样只拦截买入信号，卖出信号照常放行。
+#[derive(Debug, Clone, Default)]
+pub struct PositionLimiter {
+    pub(crate) constraints: PortfolioConstraints,
+    symbols: HashMap<Symbol, SymbolEntries>,
+}
+
+impl PositionLimiter {
+    pub fn new(constraints: PortfolioConstraints) -> Self {
+        Self {
+            constraints,
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// `symbol` 产生了一根新的 K 线
+    pub fn on_bar(&mut self, symbol: &Symbol) {
+        if let Some(bars) = self
+            .symbols
+            .get_mut(symbol)
+            .and_then(|entries| entries.bars_since_entry.as_mut())
+        {
+            *bars += 1;
+        }
+    }
+
+    /// `symbol` 开仓或加仓成交
+    pub fn on_entry(&mut self, symbol: &Symbol) {
+        let entries = self.symbols.entry(symbol.clone()).or_default();
+        entries.entries += 1;
+        entries.bars_since_entry = Some(0);
+    }
+
+    /// `symbol` 的持仓已全部平掉
+    pub fn on_flat(&mut self, symbol: &Symbol) {
+        if let Some(entries) = self.symbols.get_mut(symbol) {
+            entries.entries = 0;
+        }
+    }
+
+    /// 当前持仓的交易对数量
+    pub fn open_positions(&self) -> usize {
+        self.symbols.values().filter(|e| e.entries > 0).count()
+    }
+
+    /// 信号违反的约束，卖出和观望信号总是返回 `None`
+    pub fn check(&self, signal: &Signal) -> Option<ConstraintViolation> {
+        let Signal::Buy { symbol, .. } = signal else {
+            return None;
+        };
+        let entries = self.symbols.get(symbol).cloned().unwrap_or_default();
+
+        if entries.entries == 0
+            && self
+                .constraints
+                .max_concurrent_positions
+                .is_some_and(|max| self.open_positions() >= max)
+        {
+            return Some(ConstraintViolation::MaxConcurrentPositions);
+        }
+
+        if self
+            .constraints
+            .max_positions_per_symbol
+            .is_some_and(|max| entries.entries >= max)
+        {
+            return Some(ConstraintViolation::MaxPositionsPerSymbol);
+        }
+
+        if entries
+            .bars_since_entry
+            .is_some_and(|bars| bars < self.constraints.min_bars_between_entries)
+        {
+            return Some(ConstraintViolation::MinBarsBetweenEntries);
+        }
+
+        None
+    }
+
+    /// 违反约束的买入信号替换为 `Signal::Hold`
+    pub fn filter(&self, signal: Signal) -> Signal {
+        match self.check(&signal) {
+            Some(_) => Signal::Hold,
+            None => signal,
+        }
+    }
+
+    pub fn constraints(&self) -> &PortfolioConstraints {
+        &self.constraints
+    }
+}
+
+/// 运行时按交易对暂停交易的开关
+///
+/// 克隆得到的句柄共享同一份状态: 运维侧持有一个句柄调用 [`SymbolControl::disable`]/
+/// [`SymbolControl::enable`]，策略驱动在处理信号前用 [`SymbolControl::filter`] 过滤。
+/// 被暂停的交易对的所有信号（包括卖出）都被替换为 `Signal::Hold`，但策略仍然接收行情、
+/// 更新指标，恢复后可以立即产生有效信号。
+#[derive(Debug, Clone, Default)]
+pub struct SymbolControl {
+    disabled: Arc<RwLock<HashSet<Symbol>>>,
+}
+
+impl SymbolControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 暂停 `symbol`，返回它之前是否处于启用状态
+    pub fn disable(&self, symbol: impl Into<Symbol>) -> bool {
+        self.disabled
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(symbol.into())
+    }
+
+    /// 恢复 `symbol`，返回它之前是否处于暂停状态
+    pub fn enable(&self, symbol: &str) -> bool {
+        self.disabled
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(symbol)
+    }
+
+    /// 切换 `symbol` 的状态，返回切换后是否启用
+    pub fn toggle(&self, symbol: impl Into<Symbol>) -> bool {
+        let symbol = symbol.into();
+        let mut disabled = self.disabled.write().unwrap_or_else(|e| e.into_inner());
+
+        if disabled.remove(&symbol) {
+            true
+        } else {
+            disabled.insert(symbol);
+            false
+        }
+    }
+
+    pub fn is_enabled(&self, symbol: &str) -> bool {
+        !self
+            .disabled
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(symbol)
+    }
+
+    /// 当前被暂停的交易对
+    pub fn disabled(&self) -> Vec<Symbol> {
+        self.disabled
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// 被暂停的交易对的信号替换为 `Signal::Hold`
+    pub fn filter(&self, signal: Signal) -> Signal {
+        match &signal {
+            Signal::Buy { symbol, .. } | Signal::Sell { symbol, .. }
+                if !self.is_enabled(symbol) =>
+            {
+                Signal::Hold
+            }
+            _ => signal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buy() -> Signal {
+        Signal::buy("BTC-USDT".into(), 100.0, 1.0)
+    }
+
+    #[test]
+    fn test_kill_switch_suppresses_buys_until_recovery() {
+        let mut kill_switch = DrawdownKillSwitch::new(RiskConfig::new(10.0));
+
+        assert!(!kill_switch.on_equity(1000.0));
+        assert_eq!(kill_switch.filter(buy()), buy());
+
+        // 回撤 5%，未触发
+        assert!(!kill_switch.on_equity(950.0));
+        assert_eq!(kill_switch.filter(buy()), buy());
+
+        // 回撤 12%，触发
+        assert!(kill_switch.on_equity(880.0));
+        assert!(kill_switch.is_halted());
+        assert_eq!(kill_switch.filter(buy()), Signal::Hold);
+
+        // 卖出不受影响
+        let sell = Signal::sell("BTC-USDT".into(), 100.0, 1.0);
+        assert_eq!(kill_switch.filter(sell.clone()), sell);
+
+        // 回撤 8%，仍高于恢复阈值 5%
+        assert!(!kill_switch.on_equity(920.0));
+        assert_eq!(kill_switch.filter(buy()), Signal::Hold);
+
+        // 回撤 4%，恢复
+        assert!(!kill_switch.on_equity(960.0));
+        assert!(!kill_switch.is_halted());
+        assert_eq!(kill_switch.filter(buy()), buy());
+    }
+
+    #[test]
+    fn test_kill_switch_flatten() {
+        let mut kill_switch = DrawdownKillSwitch::new(RiskConfig {
+            flatten_on_breach: true,
+            ..RiskConfig::new(10.0)
+        });
+
+        kill_switch.on_equity(1000.0);
+        assert!(!kill_switch.should_flatten());
+
+        kill_switch.on_equity(800.0);
+        assert!(kill_switch.should_flatten());
+        approx::assert_abs_diff_eq!(kill_switch.drawdown_pct(), 20.0);
+    }
+
+    #[test]
+    fn test_position_limiter() {
+        let mut limiter = PositionLimiter::new(PortfolioConstraints {
+            max_concurrent_positions: Some(5),
+            max_positions_per_symbol: Some(2),
+            min_bars_between_entries: 3,
+        });
+        let buy = |symbol: &str| Signal::buy(symbol.into(), 100.0, 1.0);
+
+        for i in 0..5 {
+            let signal = buy(&format!("COIN{i}-USDT"));
+            assert_eq!(limiter.filter(signal.clone()), signal);
+            limiter.on_entry(&format!("COIN{i}-USDT").into());
+        }
+        assert_eq!(limiter.open_positions(), 5);
+
+        // 第 6 个交易对被拒绝
+        assert_eq!(
+            limiter.check(&buy("COIN5-USDT")),
+            Some(ConstraintViolation::MaxConcurrentPositions)
+        );
+        assert_eq!(limiter.filter(buy("COIN5-USDT")), Signal::Hold);
+
+        // 已持仓的交易对加仓不受数量上限影响，但要等够 K 线
+        let symbol: Symbol = "COIN0-USDT".into();
+        assert_eq!(
+            limiter.check(&buy("COIN0-USDT")),
+            Some(ConstraintViolation::MinBarsBetweenEntries)
+        );
+        for _ in 0..3 {
+            limiter.on_bar(&symbol);
+        }
+        assert_eq!(limiter.check(&buy("COIN0-USDT")), None);
+        limiter.on_entry(&symbol);
+
+        for _ in 0..3 {
+            limiter.on_bar(&symbol);
+        }
+        assert_eq!(
+            limiter.check(&buy("COIN0-USDT")),
+            Some(ConstraintViolation::MaxPositionsPerSymbol)
+        );
+
+        // 卖出不受影响；平仓后腾出名额
+        let sell = Signal::sell("COIN0-USDT".into(), 100.0, 2.0);
+        assert_eq!(limiter.filter(sell.clone()), sell);
+        limiter.on_flat(&symbol);
+        assert_eq!(limiter.open_positions(), 4);
+        assert_eq!(limiter.check(&buy("COIN5-USDT")), None);
+    }
+
+    #[test]
+    fn test_symbol_control() {
+        let control = SymbolControl::new();
+        let handle = control.clone();
+        let eth_buy = Signal::buy("ETH-USDT".into(), 100.0, 1.0);
+        let sell = Signal::sell("BTC-USDT".into(), 100.0, 1.0);
+
+        assert_eq!(control.filter(buy()), buy());
+
+        // 通过另一个句柄暂停，只影响该交易对，卖出信号同样被拦截
+        assert!(handle.disable("BTC-USDT"));
+        assert!(!handle.disable("BTC-USDT"));
+        assert!(!control.is_enabled("BTC-USDT"));
+        assert_eq!(control.filter(buy()), Signal::Hold);
+        assert_eq!(control.filter(sell.clone()), Signal::Hold);
+        assert_eq!(control.filter(eth_buy.clone()), eth_buy);
+        assert_eq!(control.filter(Signal::Hold), Signal::Hold);
+        assert_eq!(control.disabled(), vec![Symbol::from("BTC-USDT")]);
+
+        assert!(handle.enable("BTC-USDT"));
+        assert!(!handle.enable("BTC-USDT"));
+        assert_eq!(control.filter(sell.clone()), sell);
+
+        assert!(!control.toggle("ETH-USDT"));
+        assert_eq!(control.filter(eth_buy.clone()), Signal::Hold);
+        assert!(control.toggle("ETH-USDT"));
+        assert_eq!(control.filter(eth_buy.clone()), eth_buy);
+    }
+}
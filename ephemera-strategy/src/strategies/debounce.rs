@@ -0,0 +1,119 @@
+use ephemera_shared::{Side, Signal, Symbol};
+use futures::{Stream, StreamExt, future};
+use std::collections::HashMap;
+
+/// 信号去抖，防止噪声策略在相邻 K 线上反复开平仓。
+///
+/// 输入流中每个元素对应一根 K 线（包括 `Signal::Hold`）。若距离同一交易对上一次**被执行的同方向**
+/// 信号不足 `min_candles_between` 根 K 线，则新的 Buy/Sell 信号会被替换为 `Signal::Hold`，
+/// 以保持输出与输入一一对应。不同交易对、不同方向的信号互不影响。
+///
+/// K 线按交易对分别计数；`Signal::Hold` 不携带交易对，视为所有已出现交易对的一根 K 线。
+///
+/// `min_candles_between == 0` 时不做任何过滤。
+pub fn debounce_signals(
+    signal_stream: impl Stream<Item = Signal>,
+    min_candles_between: usize,
+) -> impl Stream<Item = Signal> {
+    // 每个交易对当前的 K 线序号
+    let candles: HashMap<Symbol, usize> = HashMap::new();
+    // 每个 (交易对, 方向) 上一次执行时的 K 线序号
+    let last_acted: HashMap<(Symbol, Side), usize> = HashMap::new();
+
+    signal_stream.scan(
+        (candles, last_acted),
+        move |(candles, last_acted), signal| {
+            let (symbol, side) = match &signal {
+                Signal::Buy { symbol, .. } => (symbol, Side::Buy),
+                Signal::Sell { symbol, .. } => (symbol, Side::Sell),
+                Signal::Hold => {
+                    candles.values_mut().for_each(|index| *index += 1);
+                    return future::ready(Some(signal));
+                }
+            };
+
+            let index = candles.entry(symbol.clone()).or_default();
+            let current = *index;
+            *index += 1;
+
+            let key = (symbol.clone(), side);
+            let suppressed = last_acted
+                .get(&key)
+                .is_some_and(|&last| current - last < min_candles_between);
+            if suppressed {
+                return future::ready(Some(Signal::Hold));
+            }
+
+            last_acted.insert(key, current);
+            future::ready(Some(signal))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    fn buy() -> Signal {
+        Signal::buy("BTC-USDT".into(), 100.0, 1.0)
+    }
+
+    fn sell() -> Signal {
+        Signal::sell("BTC-USDT".into(), 100.0, 1.0)
+    }
+
+    #[tokio::test]
+    async fn test_debounce_rapid_buys_collapse() {
+        let signals = vec![buy(), buy(), buy(), Signal::Hold, buy()];
+
+        let output: Vec<_> = debounce_signals(stream::iter(signals), 3).collect().await;
+
+        // 第 0 根执行，第 1、2 根被抑制，第 4 根距离上次已有 4 根 K 线
+        assert_eq!(
+            output,
+            vec![buy(), Signal::Hold, Signal::Hold, Signal::Hold, buy()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_debounce_sides_are_independent() {
+        let signals = vec![buy(), sell(), buy(), sell()];
+
+        let output: Vec<_> = debounce_signals(stream::iter(signals), 5).collect().await;
+
+        assert_eq!(output, vec![buy(), sell(), Signal::Hold, Signal::Hold]);
+    }
+
+    #[tokio::test]
+    async fn test_debounce_symbols_are_independent() {
+        let eth_buy = || Signal::buy("ETH-USDT".into(), 10.0, 1.0);
+        let signals = vec![buy(), eth_buy(), buy(), eth_buy(), buy(), eth_buy()];
+
+        let output: Vec<_> = debounce_signals(stream::iter(signals), 2).collect().await;
+
+        // 两个交易对交替出现，各自按自己的 K 线计数：第 1 根被抑制，第 2 根恢复执行
+        assert_eq!(
+            output,
+            vec![
+                buy(),
+                eth_buy(),
+                Signal::Hold,
+                Signal::Hold,
+                buy(),
+                eth_buy()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_debounce_disabled() {
+        let signals = vec![buy(), buy(), buy()];
+
+        let output: Vec<_> = debounce_signals(stream::iter(signals.clone()), 0)
+            .collect()
+            .await;
+
+        assert_eq!(output, signals);
+    }
+}
@@ -0,0 +1,207 @@
+use super::Strategy;
+use async_stream::stream;
+use ephemera_shared::{CandleData, ReconnectPolicy, Signal, Symbol, TimestampMs};
+use futures::{Stream, StreamExt};
+use std::{collections::HashMap, future::Future};
+
+/// K 线去重，按交易对记录已处理的最新开盘时间
+///
+/// 重连后交易所通常会重新推送最近的几根 K 线，开盘时间不晚于已处理 K 线的都视为重复。
+#[derive(Debug, Default, Clone)]
+pub struct CandleDedup {
+    last: HashMap<Symbol, TimestampMs>,
+}
+
+impl CandleDedup {
+    /// 是否为新的 K 线，是则记录它的开盘时间
+    pub fn is_new(&mut self, candle: &CandleData) -> bool {
+        match self.last.get_mut(&candle.symbol) {
+            Some(last) if candle.open_timestamp_ms <= *last => false,
+            Some(last) => {
+                *last = candle.open_timestamp_ms;
+                true
+            }
+            None => {
+                self.last
+                    .insert(candle.symbol.clone(), candle.open_timestamp_ms);
+                true
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LiveDriverError<SE, CE> {
+    #[error("Strategy error: {0:?}")]
+    Strategy(SE),
+
+    #[error("Failed to reconnect after {retries} retries: {source:?}")]
+    Reconnect { retries: usize, source: CE },
+}
+
+/// 断线重连时保留策略状态的实盘驱动
+///
+/// 通过 `connect` 建立 K 线流，流出错或结束时视为断线，等待 [`ReconnectPolicy::next_delay`] 后重新连接。
+/// 策略在整个过程中只有一个实例，指标状态不会因重连而重置，也不需要重新预热。
+/// 重连后重复推送的 K 线由 [`CandleDedup`] 过滤，每根 K 线只交给策略处理一次，
+/// 每处理一根输出一个信号（包括 `Signal::Hold`）。
+///
+/// 策略出错时输出错误并继续；连续重连失败超过 [`ReconnectPolicy::max_retries`] 次时输出错误并结束。
+/// 收到新的 K 线后失败次数清零（不使用 [`ReconnectPolicy::reset_after`]）。
+pub fn drive_strategy_with_reconnect<S, C, Fut, St, E>(
+    mut strategy: S,
+    mut connect: C,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Result<Signal, LiveDriverError<S::Error, E>>>
+where
+    S: Strategy<Input = CandleData>,
+    C: FnMut() -> Fut,
+    Fut: Future<Output = Result<St, E>>,
+    St: Stream<Item = Result<CandleData, E>>,
+    E: std::fmt::Debug,
+{
+    stream! {
+        let mut dedup = CandleDedup::default();
+        let mut failures = 0;
+
+        loop {
+            let candle_stream = match connect().await {
+                Ok(candle_stream) => candle_stream,
+                Err(e) => {
+                    if failures >= policy.max_retries {
+                        yield Err(LiveDriverError::Reconnect {
+                            retries: policy.max_retries,
+                            source: e,
+                        });
+                        return;
+                    }
+
+                    let delay = policy.next_delay(failures);
+                    failures += 1;
+                    tracing::warn!("K 线流连接失败（第 {} 次）: {:?}", failures, e);
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
+
+            futures::pin_mut!(candle_stream);
+            while let Some(candle) = candle_stream.next().await {
+                let candle = match candle {
+                    Ok(candle) => candle,
+                    Err(e) => {
+                        tracing::warn!("K 线流断开，准备重连: {:?}", e);
+                        break;
+                    }
+                };
+
+                if !dedup.is_new(&candle) {
+                    continue;
+                }
+                failures = 0;
+
+                yield strategy.process(candle).map_err(LiveDriverError::Strategy);
+            }
+
+            tokio::time::sleep(policy.next_delay(failures)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use std::time::Duration;
+
+    /// 记录收到的每根 K 线，并始终买入
+    struct Recorder {
+        seen: Vec<TimestampMs>,
+    }
+
+    impl Strategy for Recorder {
+        type Input = CandleData;
+        type Error = std::convert::Infallible;
+
+        fn process(&mut self, candle: CandleData) -> Result<Signal, Self::Error> {
+            self.seen.push(candle.open_timestamp_ms);
+            Ok(Signal::buy(
+                candle.symbol,
+                candle.close,
+                self.seen.len() as f64,
+            ))
+        }
+    }
+
+    fn candle(open_timestamp_ms: TimestampMs) -> Result<CandleData, &'static str> {
+        Ok(CandleData {
+            symbol: "BTC-USDT".into(),
+            interval_sc: 60,
+            open_timestamp_ms,
+            close: 100.0,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_candle_dedup() {
+        let mut dedup = CandleDedup::default();
+
+        assert!(dedup.is_new(&candle(60_000).unwrap()));
+        assert!(!dedup.is_new(&candle(60_000).unwrap()));
+        assert!(!dedup.is_new(&candle(0).unwrap()));
+        assert!(dedup.is_new(&candle(120_000).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_drive_strategy_across_reconnect() {
+        // 第一次连接推送 0..=2 后断开，第二次连接重新推送 1、2 并继续到 4，之后无法再连接
+        let mut connections = vec![
+            Err("refused"),
+            Ok(vec![
+                candle(60_000),
+                candle(120_000),
+                candle(180_000),
+                candle(240_000),
+            ]),
+            Ok(vec![
+                candle(0),
+                candle(60_000),
+                candle(120_000),
+                Err("disconnected"),
+            ]),
+        ];
+        let connect = move || {
+            let connection = connections.pop().unwrap();
+            async move { connection.map(stream::iter) }
+        };
+        let policy = ReconnectPolicy {
+            initial: Duration::ZERO,
+            max_retries: 0,
+            ..Default::default()
+        };
+
+        let outputs: Vec<_> =
+            drive_strategy_with_reconnect(Recorder { seen: Vec::new() }, connect, policy)
+                .collect()
+                .await;
+
+        // 每根 K 线只处理一次，策略的状态（已处理的数量）跨重连保留
+        let sizes: Vec<_> = outputs[..5]
+            .iter()
+            .map(|signal| match signal {
+                Ok(Signal::Buy { size, .. }) => *size,
+                other => panic!("unexpected output: {other:?}"),
+            })
+            .collect();
+        assert_eq!(sizes, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        assert_eq!(outputs.len(), 6);
+        assert!(matches!(
+            outputs[5],
+            Err(LiveDriverError::Reconnect {
+                source: "refused",
+                ..
+            })
+        ));
+    }
+}
@@ -1,6 +1,19 @@
+pub mod debounce;
+pub mod live;
+pub mod warmup;
+
+pub use debounce::*;
+pub use live::*;
+pub use warmup::*;
+
 pub trait Strategy {
     type Input;
     type Error;
 
     fn process(&mut self, input: Self::Input) -> Result<ephemera_shared::Signal, Self::Error>;
+
+    /// 是否已经完成预热，可以产生有效信号。默认没有预热期
+    fn is_ready(&self) -> bool {
+        true
+    }
 }
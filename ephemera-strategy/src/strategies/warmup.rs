@@ -0,0 +1,97 @@
+use super::Strategy;
+use futures::{Stream, StreamExt};
+
+/// 用历史数据预热策略，使其在第一根实盘 K 线到来时就已就绪
+///
+/// 按顺序把 `history` 的每个输入交给策略处理，产生的信号全部丢弃，历史行情不应触发下单。
+/// `history` 应当是紧接在实盘数据之前的最近一段数据，长度不少于策略的预热周期。
+///
+/// 返回处理的数据条数。历史数据不足以使策略就绪时只记录警告，由调用方决定是否继续。
+pub async fn warmup_from_history<S: Strategy>(
+    strategy: &mut S,
+    history: impl Stream<Item = S::Input>,
+) -> Result<usize, S::Error> {
+    futures::pin_mut!(history);
+
+    let mut count = 0;
+    while let Some(input) = history.next().await {
+        strategy.process(input)?;
+        count += 1;
+    }
+
+    if !strategy.is_ready() {
+        tracing::warn!("历史数据不足，处理 {} 条数据后策略仍未就绪", count);
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::{Indicator, MA};
+    use ephemera_shared::Signal;
+    use futures::stream;
+
+    /// 价格高于均线时买入，均线窗口填满之前未就绪
+    struct AboveMA {
+        ma: MA,
+        ready: bool,
+    }
+
+    impl Strategy for AboveMA {
+        type Input = f64;
+        type Error = std::convert::Infallible;
+
+        fn process(&mut self, price: f64) -> Result<Signal, Self::Error> {
+            let Some(ma) = self.ma.on_data(price) else {
+                return Ok(Signal::Hold);
+            };
+            self.ready = true;
+
+            if price > ma {
+                Ok(Signal::buy("BTC-USDT".into(), price, 1.0))
+            } else {
+                Ok(Signal::Hold)
+            }
+        }
+
+        fn is_ready(&self) -> bool {
+            self.ready
+        }
+    }
+
+    fn strategy() -> AboveMA {
+        AboveMA {
+            ma: MA::new(350),
+            ready: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ready_after_warmup() {
+        let mut strategy = strategy();
+        let history = (0..350).map(|i| 100.0 + i as f64);
+
+        let count = warmup_from_history(&mut strategy, stream::iter(history))
+            .await
+            .unwrap();
+
+        assert_eq!(count, 350);
+        assert!(strategy.is_ready());
+        // 第一根实盘数据就能产生信号
+        assert!(matches!(strategy.process(1000.0), Ok(Signal::Buy { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_insufficient_history() {
+        let mut strategy = strategy();
+
+        let count = warmup_from_history(&mut strategy, stream::iter(vec![100.0; 10]))
+            .await
+            .unwrap();
+
+        assert_eq!(count, 10);
+        assert!(!strategy.is_ready());
+    }
+}
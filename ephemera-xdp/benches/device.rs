@@ -0,0 +1,82 @@
+//! Benchmarks for the AF_XDP ring bookkeeping in `XdpReader`/`XdpWriter`.
+//!
+//! Like the crate tests, these need the veth pair `test_iface1`/`test_iface2`
+//! and the privileges to attach an XDP program.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use ephemera_xdp::{
+    device::{XdpDevice, XdpDeviceConfig},
+    reactor::XdpFlags,
+};
+use smoltcp::{
+    phy::{Device, RxToken, TxToken},
+    time::Instant,
+};
+use std::hint::black_box;
+
+const FRAME_COUNT: usize = 1024;
+const IF_NAME1: &str = "test_iface1";
+const IF_NAME2: &str = "test_iface2";
+
+fn create_device(if_name: &str) -> XdpDevice<FRAME_COUNT> {
+    XdpDeviceConfig::builder()
+        .if_name(if_name)
+        .queue_id(0)
+        .xdp_flags(XdpFlags::XDP_FLAGS_SKB_MODE)
+        .build()
+        .try_into()
+        .expect("Failed to create XDP device, are the test interfaces set up?")
+}
+
+fn bench_device(c: &mut Criterion) {
+    let mut device1 = create_device(IF_NAME1);
+    let mut device2 = create_device(IF_NAME2);
+
+    let mut group = c.benchmark_group("xdp_device");
+
+    for size in [64, 1500] {
+        let msg = vec![0xab; size];
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(format!("send_recv_{size}b"), |b| {
+            b.iter(|| {
+                let tx_token = device1.transmit(Instant::now()).unwrap();
+                tx_token.consume(msg.len(), |buf| buf.copy_from_slice(&msg));
+                device1.flush().unwrap();
+
+                loop {
+                    if let Some((rx_token, _)) = device2.receive(Instant::now()) {
+                        rx_token.consume(|buf| black_box(buf.len()));
+                        break;
+                    }
+                }
+            })
+        });
+    }
+
+    // Batched transmit: fill half the ring before a single flush
+    let msg = [0xcd; 64];
+    group.throughput(Throughput::Elements((FRAME_COUNT / 2) as u64));
+    group.bench_function("transmit_batch", |b| {
+        b.iter(|| {
+            for _ in 0..FRAME_COUNT / 2 {
+                let tx_token = device1.transmit(Instant::now()).unwrap();
+                tx_token.consume(msg.len(), |buf| buf.copy_from_slice(&msg));
+            }
+            device1.flush().unwrap();
+
+            let mut received = 0;
+            while received < FRAME_COUNT / 2 {
+                if let Some((rx_token, _)) = device2.receive(Instant::now()) {
+                    rx_token.consume(|buf| black_box(buf.len()));
+                    received += 1;
+                }
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_device);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+//! Compares round-trip latency to a TCP echo endpoint over AF_XDP and over the kernel stack.
+//!
+//! ```sh
+//! sudo -E cargo run -p ephemera-xdp --features ping --example xdp_ping -- 192.168.2.9:7 1000
+//! ```
+//!
+//! Needs the privileges to attach an XDP program to the default interface.
+
+use ephemera_xdp::ping::{RttStats, tcp_ping, xdp_ping};
+use std::io;
+
+fn report(name: &str, stats: &RttStats) {
+    println!(
+        "{name:>4}: n={} min={:?} median={:?} p99={:?} max={:?}",
+        stats.count, stats.min, stats.median, stats.p99, stats.max
+    );
+}
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: xdp_ping <addr> [count]",
+        )
+    })?;
+    let count = match args.next() {
+        Some(count) => count.parse().map_err(io::Error::other)?,
+        None => 1000,
+    };
+
+    report("tcp", &tcp_ping(addr.as_str(), count).await?);
+    report("xdp", &xdp_ping(addr.as_str(), count).await?);
+
+    Ok(())
+}
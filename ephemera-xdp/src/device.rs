@@ -11,7 +11,7 @@ use std::{
 use tracing::trace;
 use xsk_rs::{
     CompQueue, FillQueue, FrameDesc,
-    config::{BindFlags, LibxdpFlags, SocketConfig, UmemConfig, XdpFlags},
+    config::{BindFlags, LibxdpFlags, QueueSize, SocketConfig, UmemConfig, XdpFlags},
     socket::{RxQueue, Socket as XskSocket, TxQueue},
     umem::Umem,
 };
@@ -61,6 +61,20 @@ pub struct XdpDeviceConfig<const FC: usize = 1024> {
     /// Basic bind flags
     #[builder(default = BindFlags::XDP_USE_NEED_WAKEUP)]
     pub bind_flags: BindFlags,
+
+    /// Maximum transmission unit reported to smoltcp, in bytes.
+    /// Must fit in a UMEM frame after the XDP headroom (3840 bytes with the default frame size).
+    #[builder(default = 3000)]
+    pub mtu: usize,
+
+    /// Number of UMEM frames reserved for receiving. The UMEM holds `2 * FC` frames and the
+    /// remaining `2 * FC - rx_frames` are used for transmitting.
+    ///
+    /// Defaults to `FC`, an even 50/50 split. Receive-heavy workloads such as market data
+    /// ingest can shift the balance, e.g. `FC * 3 / 2` for a 75/25 split. The fill and
+    /// completion queues are sized to hold all rx and tx frames respectively.
+    #[builder(default = FC)]
+    pub rx_frames: usize,
 }
 
 impl<const FC: usize> TryFrom<XdpDeviceConfig<FC>> for XdpDevice<FC> {
@@ -73,8 +87,8 @@ impl<const FC: usize> TryFrom<XdpDeviceConfig<FC>> for XdpDevice<FC> {
 
 #[derive(Debug)]
 pub struct XdpDevice<const FC: usize = 1024> {
-    reader: XdpReader<FC>,
-    writer: XdpWriter<FC>,
+    reader: XdpReader,
+    writer: XdpWriter,
     umem: Umem,
     fd: RawFd,
     config: XdpDeviceConfig<FC>,
@@ -91,6 +105,8 @@ impl<const FC: usize> XdpDevice<FC> {
             libxdp_flags,
             xdp_flags,
             bind_flags,
+            mtu,
+            rx_frames,
         } = config.clone();
 
         // 1. Parse interface name (xsk_rs requires a specific Interface type)
@@ -101,23 +117,43 @@ impl<const FC: usize> XdpDevice<FC> {
             )
         })?;
 
-        // 2. Calculate total frame count (Rx + Tx)
+        // 2. Calculate total frame count (Rx + Tx) and validate the split
         let total_frame_count = NonZeroU32::new((FC * 2) as u32).ok_or(io::Error::new(
             io::ErrorKind::InvalidInput,
             "Frame count must be greater than zero",
         ))?;
 
+        let tx_frames = (FC * 2).saturating_sub(rx_frames);
+        if rx_frames == 0 || tx_frames == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "rx_frames must be in 1..{}, got {}",
+                    total_frame_count, rx_frames
+                ),
+            ));
+        }
+
         // 3. Create Umem (User space memory area)
-        let (umem, descs) = Umem::new(UmemConfig::default(), total_frame_count, use_huge_pages)
-            .map_err(io::Error::other)?;
+        let umem_config = umem_config(rx_frames, tx_frames)?;
+        // smoltcp hands tx tokens frames of up to `mtu` bytes, which must fit in one UMEM frame
+        if mtu == 0 || mtu > umem_config.mtu() as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "mtu must be in 1..={} for a {} byte UMEM frame, got {}",
+                    umem_config.mtu(),
+                    umem_config.frame_size().get(),
+                    mtu
+                ),
+            ));
+        }
+        let (umem, mut descs) =
+            Umem::new(umem_config, total_frame_count, use_huge_pages).map_err(io::Error::other)?;
 
-        // 4. Split frame descriptors (Rx first half, Tx second half)
-        let rx_fds: [FrameDesc; FC] = descs[..FC]
-            .try_into()
-            .expect("Failed to split frame descriptors for Rx");
-        let tx_fds: [FrameDesc; FC] = descs[FC..]
-            .try_into()
-            .expect("Failed to split frame descriptors for Tx");
+        // 4. Split frame descriptors (Rx first `rx_frames`, Tx the rest)
+        let tx_fds = descs.split_off(rx_frames).into_boxed_slice();
+        let rx_fds = descs.into_boxed_slice();
 
         // 5. Configure Socket
         let mut socket_config_builder = SocketConfig::builder();
@@ -164,9 +200,61 @@ impl<const FC: usize> XdpDevice<FC> {
     }
 
     /// Flush the transmit queue, submitting all pending data to the kernel
-    pub(crate) fn flush(&mut self) -> io::Result<usize> {
+    pub fn flush(&mut self) -> io::Result<usize> {
         self.writer.user_produce_and_wakeup()
     }
+
+    /// Flush and reclaim tx frames until the kernel has sent every pending frame.
+    ///
+    /// [`flush`](Self::flush) only hands frames to the kernel; call this on shutdown so
+    /// in-flight packets are not dropped with the socket.
+    ///
+    /// # Errors
+    /// Returns [`io::ErrorKind::TimedOut`] if frames are still pending after `timeout`.
+    pub fn drain(&mut self, timeout: std::time::Duration) -> io::Result<()> {
+        let deadline = std::time::Instant::now() + timeout;
+
+        loop {
+            self.writer.user_produce_and_wakeup()?;
+            self.writer.user_consume();
+
+            let (user_has_write, kernel_has_send) = (
+                self.writer.user_has_write_len(),
+                self.writer.kernel_has_send_len(),
+            );
+            if user_has_write == 0 && kernel_has_send == 0 {
+                return Ok(());
+            }
+
+            if std::time::Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "Drain timed out with {} unsubmitted and {} unreclaimed frames",
+                        user_has_write, kernel_has_send
+                    ),
+                ));
+            }
+
+            std::thread::yield_now();
+        }
+    }
+}
+
+/// Size the fill and completion queues so that every rx/tx frame fits, never going below
+/// the libxdp defaults.
+fn umem_config(rx_frames: usize, tx_frames: usize) -> io::Result<UmemConfig> {
+    let default = UmemConfig::default();
+    let queue_size = |frames: usize, default: QueueSize| {
+        let size = (frames as u32).next_power_of_two().max(default.get());
+        QueueSize::new(size).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    };
+
+    UmemConfig::builder()
+        .fill_queue_size(queue_size(rx_frames, default.fill_queue_size())?)
+        .comp_queue_size(queue_size(tx_frames, default.comp_queue_size())?)
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
 }
 
 impl<const FC: usize> AsRawFd for XdpDevice<FC> {
@@ -206,9 +294,8 @@ impl<const FC: usize> Device for XdpDevice<FC> {
     }
 
     fn capabilities(&self) -> DeviceCapabilities {
-        // TODO: Make configurable
         let mut caps = DeviceCapabilities::default();
-        caps.max_transmission_unit = 3000;
+        caps.max_transmission_unit = self.config.mtu;
         caps.medium = Medium::Ethernet;
         // caps.checksum.ipv4 = Checksum::Tx;
         // caps.checksum.tcp = Checksum::Tx;
@@ -225,13 +312,13 @@ impl<const FC: usize> Device for XdpDevice<FC> {
 /// ```
 ///
 /// # Invariants:
-/// - `user_has_recv_len + user_can_recv_len + kernel_can_write_len() == FC`
+/// - `user_has_recv_len + user_can_recv_len + kernel_can_write_len() == rx_fds.len()`
 /// - Regions do not overlap
-/// - All position indices < FC
+/// - All position indices < `rx_fds.len()`
 #[derive(Debug)]
-pub(crate) struct XdpReader<const FC: usize> {
+pub(crate) struct XdpReader {
     pub(crate) rx_q: RxQueue,
-    pub(crate) rx_fds: [FrameDesc; FC],
+    pub(crate) rx_fds: Box<[FrameDesc]>,
     pub(crate) fq: FillQueue,
 
     kernel_can_write_pos: usize,
@@ -245,10 +332,10 @@ pub(crate) struct XdpReader<const FC: usize> {
     rx_batch_threshold: usize,
 }
 
-impl<const FC: usize> XdpReader<FC> {
+impl XdpReader {
     pub(crate) fn new(
         rx_q: RxQueue,
-        rx_fds: [FrameDesc; FC],
+        rx_fds: Box<[FrameDesc]>,
         fq: FillQueue,
         rx_batch_threshold: usize,
     ) -> Self {
@@ -267,8 +354,8 @@ impl<const FC: usize> XdpReader<FC> {
 
     /// Length of "Kernel can Write" area (free space, waiting for kernel to write)
     #[inline]
-    pub(crate) const fn kernel_can_write_len(&self) -> usize {
-        FC - self.user_can_recv_len - self.user_has_recv_len
+    pub(crate) fn kernel_can_write_len(&self) -> usize {
+        self.rx_fds.len() - self.user_can_recv_len - self.user_has_recv_len
     }
 
     /// Length of "User can Read" area (has data, waiting for user to read)
@@ -290,6 +377,7 @@ impl<const FC: usize> XdpReader<FC> {
     /// # Return
     /// Number of frames successfully produced/returned
     pub(crate) fn user_produce(&mut self) -> usize {
+        let capacity = self.rx_fds.len();
         let mut n_produce = 0;
 
         // User has received area
@@ -304,7 +392,7 @@ impl<const FC: usize> XdpReader<FC> {
             // SAFETY: Frames in s1 have been read by the user, now returning to kernel
             let n = unsafe { self.fq.produce(s1) };
             self.user_has_recv_len -= n;
-            self.user_has_recv_pos = advance(self.user_has_recv_pos, n, FC);
+            self.user_has_recv_pos = advance(self.user_has_recv_pos, n, capacity);
             n_produce += n;
 
             if n != s1.len() {
@@ -316,7 +404,7 @@ impl<const FC: usize> XdpReader<FC> {
             // SAFETY: Frames in s2 have been read by the user, now returning to kernel
             let n = unsafe { self.fq.produce(s2) };
             self.user_has_recv_len -= n;
-            self.user_has_recv_pos = advance(self.user_has_recv_pos, n, FC);
+            self.user_has_recv_pos = advance(self.user_has_recv_pos, n, capacity);
             n_produce += n;
 
             if n != s2.len() {
@@ -339,6 +427,7 @@ impl<const FC: usize> XdpReader<FC> {
     /// # Return
     /// Number of frames successfully consumed/acquired
     pub(crate) fn user_consume(&mut self) -> usize {
+        let capacity = self.rx_fds.len();
         let mut n_consume = 0;
 
         let (s1, s2) = advance_get_mut(
@@ -352,7 +441,7 @@ impl<const FC: usize> XdpReader<FC> {
             // SAFETY: Frames in s1 currently belong to kernel, we try to acquire frames filled by kernel
             let n = unsafe { self.rx_q.consume(s1) };
             self.user_can_recv_len += n;
-            self.kernel_can_write_pos = advance(self.kernel_can_write_pos, n, FC);
+            self.kernel_can_write_pos = advance(self.kernel_can_write_pos, n, capacity);
             n_consume += n;
 
             if n != s1.len() {
@@ -364,7 +453,7 @@ impl<const FC: usize> XdpReader<FC> {
             // SAFETY: Frames in s2 currently belong to kernel, we try to acquire frames filled by kernel
             let n = unsafe { self.rx_q.consume(s2) };
             self.user_can_recv_len += n;
-            self.kernel_can_write_pos = advance(self.kernel_can_write_pos, n, FC);
+            self.kernel_can_write_pos = advance(self.kernel_can_write_pos, n, capacity);
             n_consume += n;
 
             if n != s2.len() {
@@ -388,6 +477,7 @@ impl<const FC: usize> XdpReader<FC> {
     /// # Return
     /// Reference to readable frame descriptor, or None if no frames available
     pub(crate) fn user_recv_one(&mut self) -> Option<&FrameDesc> {
+        let capacity = self.rx_fds.len();
         if self.user_can_recv_len == 0 {
             return None;
         }
@@ -396,7 +486,7 @@ impl<const FC: usize> XdpReader<FC> {
 
         self.user_can_recv_len -= 1;
         self.user_has_recv_len += 1;
-        self.user_can_recv_pos = advance(self.user_can_recv_pos, 1, FC);
+        self.user_can_recv_pos = advance(self.user_can_recv_pos, 1, capacity);
 
         Some(rx_fd)
     }
@@ -425,13 +515,13 @@ impl<const FC: usize> XdpReader<FC> {
 /// ```
 ///
 /// # Invariants:
-/// - `kernel_has_send_len + user_has_write_len + user_can_write_len() == FC`
+/// - `kernel_has_send_len + user_has_write_len + user_can_write_len() == tx_fds.len()`
 /// - Regions do not overlap
-/// - All position indices < FC
+/// - All position indices < `tx_fds.len()`
 #[derive(Debug)]
-pub(crate) struct XdpWriter<const FC: usize> {
+pub(crate) struct XdpWriter {
     pub(crate) tx_q: TxQueue,
-    pub(crate) tx_fds: [FrameDesc; FC],
+    pub(crate) tx_fds: Box<[FrameDesc]>,
     pub(crate) cq: CompQueue,
 
     user_can_write_pos: usize,
@@ -445,10 +535,10 @@ pub(crate) struct XdpWriter<const FC: usize> {
     tx_batch_threshold: usize,
 }
 
-impl<const FC: usize> XdpWriter<FC> {
+impl XdpWriter {
     pub(crate) fn new(
         tx_q: TxQueue,
-        tx_fds: [FrameDesc; FC],
+        tx_fds: Box<[FrameDesc]>,
         cq: CompQueue,
         tx_batch_threshold: usize,
     ) -> Self {
@@ -467,8 +557,8 @@ impl<const FC: usize> XdpWriter<FC> {
 
     /// Length of "User can Write" area (free space, waiting for user to write)
     #[inline]
-    pub(crate) const fn user_can_write_len(&self) -> usize {
-        FC - self.kernel_has_send_len - self.user_has_write_len
+    pub(crate) fn user_can_write_len(&self) -> usize {
+        self.tx_fds.len() - self.kernel_has_send_len - self.user_has_write_len
     }
 
     /// Length of "User has Write" area (data written, waiting to submit to kernel)
@@ -490,6 +580,7 @@ impl<const FC: usize> XdpWriter<FC> {
     /// # Return
     /// Mutable reference to writable frame descriptor, or None if no frames available
     pub(crate) fn user_write_one(&mut self) -> Option<&mut FrameDesc> {
+        let capacity = self.tx_fds.len();
         if self.user_can_write_len() == 0 {
             return None;
         }
@@ -497,7 +588,7 @@ impl<const FC: usize> XdpWriter<FC> {
         let tx_fd = &mut self.tx_fds[self.user_can_write_pos];
 
         self.user_has_write_len += 1;
-        self.user_can_write_pos = advance(self.user_can_write_pos, 1, FC);
+        self.user_can_write_pos = advance(self.user_can_write_pos, 1, capacity);
 
         Some(tx_fd)
     }
@@ -509,6 +600,7 @@ impl<const FC: usize> XdpWriter<FC> {
     /// # Return
     /// Number of frames successfully submitted
     pub(crate) fn user_produce_and_wakeup(&mut self) -> io::Result<usize> {
+        let capacity = self.tx_fds.len();
         let mut n_produce = 0;
 
         let (s1, s2) = advance_get_mut(
@@ -523,7 +615,7 @@ impl<const FC: usize> XdpWriter<FC> {
             let n = unsafe { self.tx_q.produce(s1) };
             self.user_has_write_len -= n;
             self.kernel_has_send_len += n;
-            self.user_has_write_pos = advance(self.user_has_write_pos, n, FC);
+            self.user_has_write_pos = advance(self.user_has_write_pos, n, capacity);
             n_produce += n;
 
             if n != s1.len() {
@@ -538,7 +630,7 @@ impl<const FC: usize> XdpWriter<FC> {
             let n = unsafe { self.tx_q.produce(s2) };
             self.user_has_write_len -= n;
             self.kernel_has_send_len += n;
-            self.user_has_write_pos = advance(self.user_has_write_pos, n, FC);
+            self.user_has_write_pos = advance(self.user_has_write_pos, n, capacity);
             n_produce += n;
 
             if n != s2.len() {
@@ -568,6 +660,7 @@ impl<const FC: usize> XdpWriter<FC> {
     /// # Return
     /// Number of frames successfully reclaimed
     pub(crate) fn user_consume(&mut self) -> usize {
+        let capacity = self.tx_fds.len();
         let mut n_consume = 0;
 
         let (s1, s2) = advance_get_mut(
@@ -581,7 +674,7 @@ impl<const FC: usize> XdpWriter<FC> {
             // SAFETY: Frames in s1 currently belong to kernel, we try to reclaim sent frames
             let n = unsafe { self.cq.consume(s1) };
             self.kernel_has_send_len -= n;
-            self.kernel_has_send_pos = advance(self.kernel_has_send_pos, n, FC);
+            self.kernel_has_send_pos = advance(self.kernel_has_send_pos, n, capacity);
 
             n_consume += n;
             if n != s1.len() {
@@ -593,7 +686,7 @@ impl<const FC: usize> XdpWriter<FC> {
             // SAFETY: Frames in s2 currently belong to kernel, we try to reclaim sent frames
             let n = unsafe { self.cq.consume(s2) };
             self.kernel_has_send_len -= n;
-            self.kernel_has_send_pos = advance(self.kernel_has_send_pos, n, FC);
+            self.kernel_has_send_pos = advance(self.kernel_has_send_pos, n, capacity);
 
             n_consume += n;
             if n != s2.len() {
@@ -870,6 +963,35 @@ mod tests {
         assert_eq!(writer.kernel_has_send_len(), 0);
     }
 
+    #[test]
+    fn test_drain() {
+        setup();
+
+        let mut device1 = create_device(INTERFACE_NAME1);
+        let msg = [0_u8; 64];
+
+        let n = FRAME_COUNT / 2;
+        for _ in 0..n {
+            let fd = device1.writer.user_write_one().unwrap();
+            let mut data_mut = unsafe { device1.umem.data_mut(fd) };
+            data_mut.cursor().write_all(msg.as_slice()).unwrap();
+        }
+        // Part of the frames are already with the kernel, the rest are not submitted yet.
+        device1.flush().unwrap();
+        for _ in 0..n {
+            let fd = device1.writer.user_write_one().unwrap();
+            let mut data_mut = unsafe { device1.umem.data_mut(fd) };
+            data_mut.cursor().write_all(msg.as_slice()).unwrap();
+        }
+
+        device1.drain(std::time::Duration::from_secs(1)).unwrap();
+
+        let writer = &device1.writer;
+        assert_eq!(writer.user_can_write_len(), FRAME_COUNT);
+        assert_eq!(writer.user_has_write_len(), 0);
+        assert_eq!(writer.kernel_has_send_len(), 0);
+    }
+
     #[test]
     fn test_xdp_reader() {
         setup();
@@ -953,4 +1075,47 @@ mod tests {
             rx_token.consume(|buf| check_recv_buf(buf, &msg))
         }
     }
+
+    #[test]
+    fn test_rx_tx_split() {
+        setup();
+
+        let config = |rx_frames| {
+            XdpDeviceConfig::<FRAME_COUNT>::builder()
+                .if_name(INTERFACE_NAME1)
+                .rx_frames(rx_frames)
+                .build()
+        };
+
+        // 75/25 split
+        let device = XdpDevice::new(config(FRAME_COUNT * 3 / 2)).unwrap();
+        assert_eq!(device.reader.rx_fds.len(), FRAME_COUNT * 3 / 2);
+        assert_eq!(device.writer.tx_fds.len(), FRAME_COUNT / 2);
+        assert_eq!(device.reader.kernel_can_write_len(), FRAME_COUNT * 3 / 2);
+        assert_eq!(device.writer.user_can_write_len(), FRAME_COUNT / 2);
+        drop(device);
+
+        // Both sides need at least one frame
+        for rx_frames in [0, FRAME_COUNT * 2, FRAME_COUNT * 3] {
+            let err = XdpDevice::new(config(rx_frames)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_mtu_exceeds_frame() {
+        let config = |mtu| {
+            XdpDeviceConfig::<FRAME_COUNT>::builder()
+                .if_name(INTERFACE_NAME1)
+                .mtu(mtu)
+                .build()
+        };
+
+        // Rejected before any socket is created, so no interface setup is needed
+        let frame_mtu = UmemConfig::default().mtu() as usize;
+        for mtu in [0, frame_mtu + 1, 9000] {
+            let err = XdpDevice::new(config(mtu)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
 }
@@ -1,11 +1,12 @@
 pub mod async_listener;
 pub mod async_stream;
 pub mod bpf;
+pub mod device;
+pub mod ping;
 pub mod reactor;
 
 pub use async_listener::XdpTcpListener;
 pub use async_stream::XdpTcpStream;
 
-mod device;
 #[cfg(test)]
 mod test_utils;
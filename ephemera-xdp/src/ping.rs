@@ -0,0 +1,197 @@
+//! Round-trip latency measurement against a TCP echo endpoint.
+//!
+//! [`ping_stream`] works on any connected stream, so the same measurement can be taken over
+//! [`XdpTcpStream`] and over the kernel TCP stack to compare the two. The helpers that open the
+//! connections themselves ([`xdp_ping`], [`tcp_ping`]) are behind the `ping` feature.
+
+use std::{io, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    time::Instant,
+};
+
+#[cfg(feature = "ping")]
+use crate::{async_stream::XdpTcpStream, reactor::XdpReactor};
+#[cfg(feature = "ping")]
+use std::net::ToSocketAddrs;
+
+/// Each probe is the sequence number followed by the send time in nanoseconds, both little endian.
+const PROBE_LEN: usize = 16;
+
+/// Summary of the measured round-trip times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RttStats {
+    pub count: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl RttStats {
+    /// Percentiles use the nearest-rank method. Returns `None` if `samples` is empty.
+    pub fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+
+        let rank = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+
+        Some(Self {
+            count: samples.len(),
+            min: samples[0],
+            median: rank(50),
+            p99: rank(99),
+            max: samples[samples.len() - 1],
+        })
+    }
+}
+
+/// Sends `count` timestamped probes over `stream`, one at a time, and measures how long each
+/// takes to be echoed back.
+///
+/// # Errors
+///
+/// Fails on I/O errors, or with [`io::ErrorKind::InvalidData`] if the echoed probe does not match
+/// the one sent.
+pub async fn ping_stream<S>(stream: &mut S, count: usize) -> io::Result<RttStats>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let start = Instant::now();
+    let mut samples = Vec::with_capacity(count);
+    let mut probe = [0_u8; PROBE_LEN];
+    let mut echo = [0_u8; PROBE_LEN];
+
+    for seq in 0..count as u64 {
+        let sent_at = Instant::now();
+        probe[..8].copy_from_slice(&seq.to_le_bytes());
+        probe[8..].copy_from_slice(&((sent_at - start).as_nanos() as u64).to_le_bytes());
+
+        stream.write_all(&probe).await?;
+        stream.flush().await?;
+        stream.read_exact(&mut echo).await?;
+        samples.push(sent_at.elapsed());
+
+        if echo != probe {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Probe {seq} was not echoed back unchanged"),
+            ));
+        }
+    }
+
+    RttStats::from_samples(samples)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "count must be positive"))
+}
+
+/// Measures round-trip times to the echo endpoint at `addr` over AF_XDP, using the global
+/// reactor.
+#[cfg(feature = "ping")]
+pub async fn xdp_ping(addr: impl ToSocketAddrs, count: usize) -> io::Result<RttStats> {
+    xdp_ping_with_reactor(addr, count, XdpReactor::global()).await
+}
+
+/// Measures round-trip times to the echo endpoint at `addr` over AF_XDP, using a specific
+/// reactor.
+#[cfg(feature = "ping")]
+pub async fn xdp_ping_with_reactor(
+    addr: impl ToSocketAddrs,
+    count: usize,
+    reactor: XdpReactor,
+) -> io::Result<RttStats> {
+    let mut stream = XdpTcpStream::connect_with_reactor(addr, reactor).await?;
+    ping_stream(&mut stream, count).await
+}
+
+/// Measures round-trip times to the echo endpoint at `addr` over the kernel TCP stack, as the
+/// baseline for [`xdp_ping`].
+#[cfg(feature = "ping")]
+pub async fn tcp_ping(addr: impl tokio::net::ToSocketAddrs, count: usize) -> io::Result<RttStats> {
+    let mut stream = tokio::net::TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+    ping_stream(&mut stream, count).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_stats() {
+        let samples = (1..=100).rev().map(Duration::from_micros).collect();
+        let stats = RttStats::from_samples(samples).unwrap();
+
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.min, Duration::from_micros(1));
+        assert_eq!(stats.median, Duration::from_micros(50));
+        assert_eq!(stats.p99, Duration::from_micros(99));
+        assert_eq!(stats.max, Duration::from_micros(100));
+
+        let single = RttStats::from_samples(vec![Duration::from_micros(7)]).unwrap();
+        assert_eq!(single.p99, Duration::from_micros(7));
+        assert!(RttStats::from_samples(Vec::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ping_stream() {
+        let (mut client, server) = tokio::io::duplex(64);
+        tokio::spawn(async move {
+            let (mut reader, mut writer) = tokio::io::split(server);
+            tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+        });
+
+        let stats = ping_stream(&mut client, 20).await.unwrap();
+        assert_eq!(stats.count, 20);
+        assert!(stats.min <= stats.median && stats.median <= stats.p99 && stats.p99 <= stats.max);
+
+        // An endpoint that does not echo the probe back unchanged
+        let (mut client, mut server) = tokio::io::duplex(64);
+        tokio::spawn(async move {
+            let mut probe = [0_u8; PROBE_LEN];
+            server.read_exact(&mut probe).await.unwrap();
+            server.write_all(&[0xff; PROBE_LEN]).await.unwrap();
+        });
+
+        let err = ping_stream(&mut client, 1).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "ping")]
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_xdp_ping() {
+        use crate::{async_listener::XdpTcpListener, test_utils::*};
+
+        setup();
+
+        let reactor1 = create_reactor1();
+        let reactor2 = create_reactor2();
+
+        let port = 12346;
+
+        let mut listener =
+            XdpTcpListener::bind_with_reactor(format!("{INTERFACE_IP1}:{port}"), reactor1.clone())
+                .unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut probe = [0_u8; PROBE_LEN];
+            for _ in 0..10 {
+                stream.read_exact(&mut probe).await.unwrap();
+                stream.write_all(&probe).await.unwrap();
+                stream.flush().await.unwrap();
+            }
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let stats = xdp_ping_with_reactor(format!("{INTERFACE_IP1}:{port}"), 10, reactor2.clone())
+            .await
+            .unwrap();
+        assert_eq!(stats.count, 10);
+
+        handle.await.unwrap();
+    }
+}
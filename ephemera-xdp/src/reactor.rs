@@ -13,7 +13,10 @@ use std::{
     net::IpAddr,
     ops::Deref,
     os::fd::AsRawFd,
-    sync::{Arc, Mutex, OnceLock},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 use tracing::debug;
 
@@ -22,21 +25,76 @@ pub use xsk_rs::config::{BindFlags, LibxdpFlags, XdpFlags};
 /// Global singleton instance of the XDP Reactor.
 pub(crate) static GLOBAL_XDP_REACTOR: OnceLock<XdpReactor> = OnceLock::new();
 
+/// How the background reactor thread behaves once the RX queue is drained.
+///
+/// This trades latency against CPU usage:
+///
+/// - [`BusySpin`](Self::BusySpin): never sleeps. Lowest and most stable latency, but the
+///   background thread pins one core at 100% even when idle. Best paired with an isolated core.
+///   The reactor lock is released and the thread yields between polls, so socket reads and
+///   writes from other threads are not starved.
+/// - [`Park`](Self::Park): sleeps in `phy::wait` until the socket is readable, smoltcp has a
+///   timer due, or the timeout expires. Near 0% CPU when idle, at the cost of a wakeup (a few
+///   microseconds) on the first packet after a quiet period.
+/// - [`Adaptive`](Self::Adaptive): spins while traffic is flowing and for `grace` after the
+///   last activity, then parks. CPU usage follows the traffic: a full core during bursts,
+///   near 0% when the market is quiet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStrategy {
+    BusySpin,
+    /// Park with the given maximum sleep time. Lower values bound how stale smoltcp timers can
+    /// get but wake the thread more often when idle.
+    Park(Duration),
+    Adaptive {
+        /// How long to keep spinning after the last socket state change.
+        grace: Duration,
+        /// Maximum sleep time once parked, as in [`WaitStrategy::Park`].
+        timeout: Duration,
+    },
+}
+
+impl Default for WaitStrategy {
+    fn default() -> Self {
+        WaitStrategy::Park(Duration::from_millis(10))
+    }
+}
+
+impl WaitStrategy {
+    /// Returns how long to park, or `None` to spin again.
+    fn park_timeout(&self, idle: Duration) -> Option<Duration> {
+        match *self {
+            WaitStrategy::BusySpin => None,
+            WaitStrategy::Park(timeout) => Some(timeout),
+            WaitStrategy::Adaptive { grace, timeout } => (idle >= grace).then_some(timeout),
+        }
+    }
+}
+
+/// Spin hints issued outside the reactor lock between two busy-spin polls.
+const SPIN_BACKOFF_ITERATIONS: usize = 64;
+
 /// Returns the global shared XDP reactor instance.
 /// Polls the reactor in a background thread.
 ///
 /// This drives the event loop, handling underlying XDP socket polling and interface flushing.
-/// It alternates between processing packets (busy-looping during bursts) and sleeping
-/// via `phy::wait` when idle to save CPU.
-pub(crate) fn run_reactor_background(reactor: XdpReactor, wait_timeout: Duration) {
+/// It busy-loops while processing bursts, and what it does when idle is controlled by
+/// `wait_strategy`.
+pub(crate) fn run_reactor_background(reactor: XdpReactor, wait_strategy: WaitStrategy) {
+    let metrics = reactor.metrics.clone();
+
     std::thread::spawn(move || {
+        let mut last_activity = Instant::now();
+
         loop {
             let (fd, delay) = {
                 let mut reactor_guard = reactor.lock().unwrap();
 
                 // Drain the RX queue and process events.
                 // Loop continues as long as state changes (handling bursts).
-                while reactor_guard.poll_and_flush().unwrap() == PollResult::SocketStateChanged {}
+                while reactor_guard.poll_and_flush().unwrap() == PollResult::SocketStateChanged {
+                    metrics.busy_iterations.fetch_add(1, Ordering::Relaxed);
+                    last_activity = Instant::now();
+                }
 
                 let XdpReactorInner {
                     device,
@@ -51,19 +109,135 @@ pub(crate) fn run_reactor_background(reactor: XdpReactor, wait_timeout: Duration
                 )
             };
 
+            let Some(timeout) = wait_strategy.park_timeout(Instant::now() - last_activity) else {
+                // The lock is released at this point. `std::sync::Mutex` is not fair, so
+                // re-locking right away lets this thread win every race against tasks waiting
+                // to read or write a socket. Spin briefly and yield before the next poll.
+                for _ in 0..SPIN_BACKOFF_ITERATIONS {
+                    std::hint::spin_loop();
+                }
+                std::thread::yield_now();
+                continue;
+            };
+
             // Sleep until I/O events occur or timeout expires.
-            smoltcp::phy::wait(fd, delay.or(Some(wait_timeout))).unwrap();
+            metrics.waits.fetch_add(1, Ordering::Relaxed);
+            smoltcp::phy::wait(fd, delay.or(Some(timeout))).unwrap();
         }
     });
 }
 
+/// Counters describing how hot the reactor runs.
+///
+/// Updated with relaxed atomics, so reading them never contends with the background loop.
+#[derive(Debug)]
+pub(crate) struct ReactorMetrics {
+    started_at: std::time::Instant,
+    polls: AtomicU64,
+    flushes: AtomicU64,
+    busy_iterations: AtomicU64,
+    waits: AtomicU64,
+}
+
+impl Default for ReactorMetrics {
+    fn default() -> Self {
+        Self {
+            started_at: std::time::Instant::now(),
+            polls: AtomicU64::new(0),
+            flushes: AtomicU64::new(0),
+            busy_iterations: AtomicU64::new(0),
+            waits: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ReactorMetrics {
+    fn snapshot(&self) -> ReactorMetricsSnapshot {
+        ReactorMetricsSnapshot {
+            uptime: self.started_at.elapsed(),
+            polls: self.polls.load(Ordering::Relaxed),
+            flushes: self.flushes.load(Ordering::Relaxed),
+            busy_iterations: self.busy_iterations.load(Ordering::Relaxed),
+            waits: self.waits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Point-in-time copy of the reactor counters, returned by [`XdpReactor::metrics`].
+///
+/// A high `busy_iterations` rate with few `waits` means the background loop is CPU-bound
+/// (packets keep arriving while it drains the RX queue). Mostly `waits` means it is
+/// I/O-bound, and the [`WaitStrategy`] park timeout dominates latency.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReactorMetricsSnapshot {
+    /// Time since the reactor was created.
+    pub uptime: std::time::Duration,
+    /// Interface polls, from the background loop and from sockets.
+    pub polls: u64,
+    /// Device flushes.
+    pub flushes: u64,
+    /// Background loop iterations that changed socket state and polled again immediately.
+    pub busy_iterations: u64,
+    /// Times the background loop went to sleep in `phy::wait`.
+    pub waits: u64,
+}
+
+impl ReactorMetricsSnapshot {
+    /// Counter deltas between `earlier` and `self`.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            uptime: self.uptime.saturating_sub(earlier.uptime),
+            polls: self.polls.saturating_sub(earlier.polls),
+            flushes: self.flushes.saturating_sub(earlier.flushes),
+            busy_iterations: self.busy_iterations.saturating_sub(earlier.busy_iterations),
+            waits: self.waits.saturating_sub(earlier.waits),
+        }
+    }
+
+    pub fn polls_per_sec(&self) -> f64 {
+        self.per_sec(self.polls)
+    }
+
+    pub fn flushes_per_sec(&self) -> f64 {
+        self.per_sec(self.flushes)
+    }
+
+    pub fn busy_iterations_per_sec(&self) -> f64 {
+        self.per_sec(self.busy_iterations)
+    }
+
+    fn per_sec(&self, count: u64) -> f64 {
+        let secs = self.uptime.as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            count as f64 / secs
+        }
+    }
+}
+
+/// Returns a fresh random seed for the smoltcp interface.
+fn default_random_seed() -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
+
 /// XDP Reactor - Manages the XDP device, network interface, and socket set.
 ///
 /// The `XdpReactor` is the primary entry point for the XDP network stack.
 /// It abstracts away background packet polling, allowing users to focus on
 /// socket creation and state management.
+///
+/// The smoltcp neighbor cache is sized at compile time. Enable one of the
+/// `neighbor-cache-*` features when a reactor talks to many next hops.
 #[derive(Clone)]
-pub struct XdpReactor(pub(crate) Arc<Mutex<XdpReactorInner>>);
+pub struct XdpReactor {
+    pub(crate) inner: Arc<Mutex<XdpReactorInner>>,
+    /// Shared with [`XdpReactorInner`], kept outside the mutex so [`XdpReactor::metrics`]
+    /// never waits on the background loop.
+    metrics: Arc<ReactorMetrics>,
+}
 
 #[bon::bon]
 impl XdpReactor {
@@ -71,10 +245,14 @@ impl XdpReactor {
     pub fn with_device(
         #[builder(start_fn)] mut device: XdpDevice,
 
-        /// Background thread wating timeout.
-        /// Lower values reduce latency but increase CPU usage when idle.
-        #[builder(default = Duration::from_millis(10))]
-        wait_timeout: Duration,
+        /// What the background thread does when idle, see [`WaitStrategy`].
+        #[builder(default)]
+        wait_strategy: WaitStrategy,
+
+        /// Seed for TCP initial sequence numbers and ephemeral ports.
+        /// Should differ between runs to avoid collisions with stale connections.
+        #[builder(default = default_random_seed())]
+        random_seed: u64,
     ) -> io::Result<Self> {
         let if_name = device.config().if_name.clone();
 
@@ -112,11 +290,9 @@ impl XdpReactor {
         .map_err(|e| io::Error::other(format!("Failed to load BPF program: {}", e)))?;
 
         // 4. Initialize smoltcp Interface
-        let mut iface = Interface::new(
-            smoltcp::iface::Config::new(xdp_mac.into()),
-            &mut device,
-            Instant::now(),
-        );
+        let mut iface_config = smoltcp::iface::Config::new(xdp_mac.into());
+        iface_config.random_seed = random_seed;
+        let mut iface = Interface::new(iface_config, &mut device, Instant::now());
 
         for ipv4 in &interface.ipv4 {
             iface.update_ip_addrs(|ip_addrs| {
@@ -164,10 +340,13 @@ impl XdpReactor {
             .map_err(|e| io::Error::other(format!("Failed to update xsks_map: {}", e)))?;
 
         let inner = XdpReactorInner::new(iface, device, bpf);
-        let reactor = XdpReactor(Arc::new(Mutex::new(inner)));
+        let reactor = XdpReactor {
+            metrics: inner.metrics.clone(),
+            inner: Arc::new(Mutex::new(inner)),
+        };
 
         {
-            let guard = reactor.0.lock().unwrap();
+            let guard = reactor.lock().unwrap();
             debug!(
                 if_name = xdp_if_name,
                 queue_id = guard.device.config().queue_id,
@@ -178,7 +357,7 @@ impl XdpReactor {
             );
         }
 
-        run_reactor_background(reactor.clone(), wait_timeout);
+        run_reactor_background(reactor.clone(), wait_strategy);
 
         Ok(reactor)
     }
@@ -191,20 +370,31 @@ impl XdpReactor {
         #[builder(into, default = netdev::get_default_interface().unwrap().name)]
         if_name: String,
 
-        /// Background thread wating timeout.
-        /// Lower values reduce latency but increase CPU usage when idle.
-        #[builder(default = Duration::from_millis(10))]
-        wait_timeout: Duration,
+        /// What the background thread does when idle, see [`WaitStrategy`].
+        #[builder(default)]
+        wait_strategy: WaitStrategy,
+
+        /// Maximum transmission unit of the device, in bytes.
+        #[builder(default = 3000)]
+        mtu: usize,
+
+        /// Seed for TCP initial sequence numbers and ephemeral ports.
+        #[builder(default = default_random_seed())]
+        random_seed: u64,
     ) -> io::Result<Self> {
         let device = XdpDeviceConfig::builder()
             .if_name(if_name)
+            .mtu(mtu)
             // Load custom xdp program
             .libxdp_flags(LibxdpFlags::XSK_LIBXDP_FLAGS_INHIBIT_PROG_LOAD)
             .build()
             .try_into()
             .map_err(|e| io::Error::other(format!("Failed to create XDP device: {}", e)))?;
 
-        Self::with_device(device).wait_timeout(wait_timeout).build()
+        Self::with_device(device)
+            .wait_strategy(wait_strategy)
+            .random_seed(random_seed)
+            .build()
     }
 
     /// Set global XDP reactor instance
@@ -222,6 +412,28 @@ impl XdpReactor {
             .clone()
     }
 
+    /// Returns the global reactor if it has been created, without initializing it.
+    pub fn try_global() -> Option<XdpReactor> {
+        GLOBAL_XDP_REACTOR.get().cloned()
+    }
+
+    /// Returns the poll/flush counters since the reactor was created.
+    ///
+    /// Use [`ReactorMetricsSnapshot::since`] on two snapshots to get rates over a window.
+    pub fn metrics(&self) -> ReactorMetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Pushes out pending socket data and waits for the device to transmit every queued frame.
+    ///
+    /// Call this before the process exits so in-flight packets (e.g. orders) are not dropped.
+    /// See [`XdpDevice::drain`].
+    pub fn drain(&self, timeout: std::time::Duration) -> io::Result<()> {
+        let mut reactor = self.lock().unwrap();
+        reactor.poll_and_flush()?;
+        reactor.device.drain(timeout)
+    }
+
     // ==================== BPF Filter Management ====================
 
     /// Sets the allowed protocol mask for a specific source IP.
@@ -370,7 +582,7 @@ impl Deref for XdpReactor {
     type Target = Arc<Mutex<XdpReactorInner>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
@@ -382,6 +594,7 @@ pub struct XdpReactorInner {
     pub(crate) device: XdpDevice,
     pub(crate) sockets: SocketSet<'static>,
     pub(crate) bpf: XdpFilter,
+    pub(crate) metrics: Arc<ReactorMetrics>,
 }
 
 impl XdpReactorInner {
@@ -391,12 +604,14 @@ impl XdpReactorInner {
             device,
             sockets: SocketSet::new(vec![]),
             bpf,
+            metrics: Arc::new(ReactorMetrics::default()),
         }
     }
 
     /// Polls the interface to advance socket states without flushing.
     pub(crate) fn poll(&mut self) -> PollResult {
         let now = Instant::now();
+        self.metrics.polls.fetch_add(1, Ordering::Relaxed);
         self.iface.poll(now, &mut self.device, &mut self.sockets)
     }
 
@@ -404,6 +619,7 @@ impl XdpReactorInner {
     pub(crate) fn poll_and_flush(&mut self) -> io::Result<PollResult> {
         let res = self.poll();
         self.device.flush()?;
+        self.metrics.flushes.fetch_add(1, Ordering::Relaxed);
         Ok(res)
     }
 
@@ -431,6 +647,46 @@ mod tests {
     };
     use std::net::Ipv4Addr;
 
+    #[test]
+    fn test_metrics_snapshot_rates() {
+        let earlier = ReactorMetricsSnapshot {
+            uptime: std::time::Duration::from_secs(10),
+            polls: 100,
+            flushes: 100,
+            busy_iterations: 40,
+            waits: 60,
+        };
+        let later = ReactorMetricsSnapshot {
+            uptime: std::time::Duration::from_secs(12),
+            polls: 300,
+            flushes: 300,
+            busy_iterations: 240,
+            waits: 60,
+        };
+
+        let window = later.since(&earlier);
+        assert_eq!(window.busy_iterations, 200);
+        assert_eq!(window.waits, 0);
+        assert_eq!(window.polls_per_sec(), 100.0);
+        assert_eq!(window.busy_iterations_per_sec(), 100.0);
+        assert_eq!(ReactorMetricsSnapshot::default().flushes_per_sec(), 0.0);
+    }
+
+    #[test]
+    fn test_wait_strategy_park_timeout() {
+        let ms = Duration::from_millis;
+
+        assert_eq!(WaitStrategy::BusySpin.park_timeout(ms(1000)), None);
+        assert_eq!(WaitStrategy::default().park_timeout(ms(0)), Some(ms(10)));
+
+        let adaptive = WaitStrategy::Adaptive {
+            grace: ms(5),
+            timeout: ms(20),
+        };
+        assert_eq!(adaptive.park_timeout(ms(1)), None);
+        assert_eq!(adaptive.park_timeout(ms(5)), Some(ms(20)));
+    }
+
     #[test]
     fn test_reactor_read_and_write() {
         setup();
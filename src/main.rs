@@ -1,15 +1,31 @@
-use ephemera_shared::{CandleData, Signal};
+use ephemera_backtest::engine::{
+    Allocation, BacktestConfig, BacktestProgress, FillTiming, MarginConfig, execute_backtest,
+};
+use ephemera_backtest::metrics::{CRYPTO_TRADING_DAYS, MetricRegistry};
+use ephemera_backtest::pipeline::{apply_strategy, extract_signals};
+use ephemera_backtest::portfolio::EquityResolution;
+use ephemera_backtest::report::{print_backtest_report, print_trades};
+use ephemera_shared::stream::on_closed_only;
+use ephemera_shared::{CandleData, OrderSide, OrderState};
 use ephemera_source::csv::csv_candle_data_stream;
+#[cfg(feature = "metrics")]
+use ephemera_source::metrics;
 use ephemera_source::okx::{
-    OkxAuth, OkxCandleInterval, OrderInfo, okx_execute_market_orders, okx_xdp_candle_data_stream,
+    OkxAuth, OkxCandleInterval, OkxEnvironment, OrderInfo, fetch::okx_candle_data_stream,
+    flatten_all, okx_execute_market_orders, okx_xdp_candle_data_stream,
 };
+use ephemera_source::utils::WsConfig;
+use ephemera_strategy::audit::{AuditOutcome, AuditRecord, Auditor, FileAuditSink, SignalKind};
+use ephemera_strategy::risk::{RiskConfig, SymbolControl};
 use ephemera_strategy::strategies::{
     CircuitBreakerConfig, LeverageConfig, MACrossStrategy, ScalpingStrategy, SlippageModel,
-    Strategy,
 };
+use ephemera_xdp::reactor::XdpReactor;
 use eyre::Result;
 use futures::{Stream, StreamExt};
+use std::path::Path;
 use std::pin::Pin;
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -22,6 +38,16 @@ async fn main() -> Result<()> {
 
     println!("🚀 Ephemera 交易系统\n");
 
+    // 设置了 METRICS_ADDR（如 0.0.0.0:9090）时暴露 Prometheus 指标
+    #[cfg(feature = "metrics")]
+    if let Ok(addr) = std::env::var("METRICS_ADDR") {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve_metrics(addr).await {
+                tracing::error!("metrics 服务退出: {:?}", e);
+            }
+        });
+    }
+
     // 从环境变量选择模式
     let mode = std::env::var("MODE").unwrap_or_else(|_| "backtest".to_string());
 
@@ -39,6 +65,19 @@ async fn main() -> Result<()> {
 
 /// 运行回测
 async fn run_backtest() -> Result<()> {
+    run_backtest_with_progress(|p| {
+        tracing::debug!(
+            "回测进度: {} 根K线, 权益 {:.2}, 耗时 {:?}",
+            p.candles_processed,
+            p.equity,
+            p.elapsed
+        );
+    })
+    .await
+}
+
+/// 运行回测，并每处理 [`PROGRESS_INTERVAL`](ephemera_backtest::engine::PROGRESS_INTERVAL) 根 K 线（以及结束时）回调一次 `progress`
+async fn run_backtest_with_progress(progress: impl FnMut(BacktestProgress) + Send) -> Result<()> {
     println!("📊 运行回测模式\n");
 
     // 配置参数
@@ -48,13 +87,34 @@ async fn run_backtest() -> Result<()> {
     let position_size = 0.01;
     let fast_period = 5;
     let slow_period = 20;
+    let trading_days_per_year = CRYPTO_TRADING_DAYS;
+    let config = BacktestConfig {
+        initial_balance,
+        margin: MarginConfig {
+            leverage: 20.0,
+            maintenance_margin_rate: 0.005,
+        },
+        risk: Some(RiskConfig::new(20.0)),
+        constraints: None,
+        equity_resolution: EquityResolution::Full,
+        allocation: Allocation::Shared,
+        fill_timing: FillTiming::NextOpen,
+        scaled_exit: None,
+        funding_rates: Vec::new(),
+        fx: None,
+        instruments: Vec::new(),
+        audit: auditor_from_env("scalping")?,
+    };
+    let margin = config.margin;
 
     println!("配置参数:");
     println!("  数据文件: {}", data_path);
     println!("  交易对: {}", symbol);
     println!("  初始资金: {} USDT", initial_balance);
     println!("  策略: 双均线交叉 (MA{}/MA{})", fast_period, slow_period);
-    println!("  仓位大小: {} BTC\n", position_size);
+    println!("  仓位大小: {} BTC", position_size);
+    println!("  杠杆: {}x", margin.leverage);
+    println!("  年交易日: {}\n", trading_days_per_year);
 
     // 创建数据流
     let candle_stream = csv_candle_data_stream(data_path).await?;
@@ -62,14 +122,14 @@ async fn run_backtest() -> Result<()> {
     // 创建策略
     let strategy = ScalpingStrategy::new(
         symbol.into(),
-        20,                        // 布林带周期
-        2.0,                       // 布林带标准差
-        5,                         // 快速 EMA
-        10,                        // 慢速 EMA
-        0.01,                      // 仓位大小
-        2.0,                       // 2% 止盈（杠杆放大后）
-        1.0,                       // 1% 止损（杠杆放大后）
-        LeverageConfig::new(20.0), // 20x 杠杆
+        20,   // 布林带周期
+        2.0,  // 布林带标准差
+        5,    // 快速 EMA
+        10,   // 慢速 EMA
+        0.01, // 仓位大小
+        2.0,  // 2% 止盈（杠杆放大后）
+        1.0,  // 1% 止损（杠杆放大后）
+        LeverageConfig::new(margin.leverage),
         SlippageModel::Dynamic {
             base_slippage: 0.1, // 基础 0.1% 滑点
             volume_factor: 0.5, // 成交量调整因子
@@ -84,15 +144,31 @@ async fn run_backtest() -> Result<()> {
     );
 
     // 组合 Stream：数据流 -> 策略流 -> 信号流
-    let signal_stream = apply_strategy(candle_stream, strategy);
+    let signal_stream = apply_strategy(
+        candle_stream,
+        strategy,
+        SymbolControl::default(),
+        config.audit.clone(),
+    );
 
     // 执行回测并收集结果
-    let report = execute_backtest(signal_stream, initial_balance).await?;
+    let report = execute_backtest(signal_stream, config, progress).await?;
 
     // 打印报告
-    print_backtest_report(&report);
+    print_backtest_report(&report, trading_days_per_year, &MetricRegistry::default());
     print_trades(&report.trades, Some(20));
 
+    // 设置了 BACKTEST_OUTPUT_DIR 时导出权益曲线与成交记录，便于用其他工具分析
+    if let Ok(dir) = std::env::var("BACKTEST_OUTPUT_DIR") {
+        let dir = Path::new(&dir);
+        tokio::fs::create_dir_all(dir).await?;
+        report
+            .write_equity_curve_csv(dir.join("equity_curve.csv"))
+            .await?;
+        report.write_trades_csv(dir.join("trades.csv")).await?;
+        println!("📁 回测结果已导出到: {}\n", dir.display());
+    }
+
     Ok(())
 }
 
@@ -120,251 +196,151 @@ async fn run_live_trading() -> Result<()> {
     println!("  策略: 双均线交叉 (MA{}/MA{})", fast_period, slow_period);
     println!("  仓位大小: {} BTC\n", position_size);
 
-    // 创建数据流 - 修复：明确指定类型为 ByteString
-    let candle_stream = okx_xdp_candle_data_stream(vec![symbol], OkxCandleInterval::Min1).await?;
+    // 创建数据流，传入 --xdp 时走 AF_XDP
+    let use_xdp = std::env::args().any(|arg| arg == "--xdp");
+    let candle_stream =
+        okx_candle_stream(auth.environment(), symbol, OkxCandleInterval::Min1, use_xdp).await?;
 
     println!("✅ 成功连接到 OKX 数据流\n");
 
     // 创建策略
     let strategy = MACrossStrategy::new(symbol.into(), fast_period, slow_period, position_size);
 
+    let audit = auditor_from_env("ma_cross")?;
+
+    // 数据源推送未收盘的K线时，策略只在K线收盘后决策，避免信号随K线更新来回翻转
+    let candle_stream = on_closed_only(candle_stream);
+
+    // 在终端输入交易对名称并回车即可暂停/恢复该交易对的交易
+    let control = SymbolControl::new();
+    tokio::spawn(toggle_symbols_from_stdin(control.clone()));
+
     // 组合 Stream：数据流 -> 策略流 -> 信号流 -> 订单执行流
-    let signal_stream = apply_strategy(candle_stream, strategy);
+    let signal_stream = apply_strategy(candle_stream, strategy, control, audit.clone());
 
     // 只提取 Signal，不包含 CandleData
     let signal_only_stream = extract_signals(signal_stream);
 
-    let order_stream = okx_execute_market_orders(auth, signal_only_stream);
+    let order_stream = okx_execute_market_orders(auth.clone(), signal_only_stream);
 
-    // 消费订单流
-    consume_order_stream(order_stream).await?;
+    // 消费订单流，Ctrl-C 时停止交易并平掉所有持仓，而不是留着持仓退出
+    tokio::select! {
+        result = consume_order_stream(order_stream, audit) => result?,
+        _ = tokio::signal::ctrl_c() => {
+            println!("\n⚠️ 收到 Ctrl-C，正在平掉所有持仓...");
+            let orders = flatten_all(&auth).await?;
+            println!("✅ 已平仓 {} 个持仓", orders.len());
+        }
+    }
 
     Ok(())
 }
 
-/// 将策略应用到数据流，生成信号流
-fn apply_strategy<S>(
-    candle_stream: impl Stream<Item = Result<CandleData>> + Send + 'static,
-    mut strategy: S,
-) -> Pin<Box<dyn Stream<Item = (Signal, CandleData)> + Send>>
-where
-    S: Strategy<Input = CandleData, Signal = Signal> + Send + 'static,
-    S::Error: std::fmt::Debug + Send, // 添加 Send 约束
-{
-    Box::pin(async_stream::stream! {
-        futures::pin_mut!(candle_stream);
-
-        let mut count = 0;
-
-        while let Some(result) = candle_stream.next().await {
-            match result {
-                Ok(candle) => {
-                    count += 1;
-
-                    if count % 100 == 0 {
-                        tracing::info!("已处理 {} 根K线...", count);
-                    }
-
-                    match strategy.on_data(candle.clone()).await {
-                        Ok(Some(signal)) => {
-                            yield (signal, candle);
-                        }
-                        Ok(None) => {
-                            // 策略还在预热，没有信号
-                        }
-                        Err(e) => {
-                            tracing::error!("策略处理错误: {:?}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("读取K线数据错误: {}", e);
-                    break;
-                }
+/// 连接 OKX K 线流
+///
+/// `use_xdp` 为 `true` 时先初始化全局 [`XdpReactor`] 并使用 AF_XDP 连接；初始化失败（没有 root 权限、
+/// 网卡不支持 XDP 等）时回退到普通 TCP。
+async fn okx_candle_stream(
+    env: OkxEnvironment,
+    symbol: &str,
+    interval: OkxCandleInterval,
+    use_xdp: bool,
+) -> Result<Pin<Box<dyn Stream<Item = Result<CandleData>> + Send>>> {
+    if use_xdp {
+        // 已经有全局 reactor 时直接沿用，不再创建新的（会重复绑定网卡队列）
+        let ready = match XdpReactor::try_global() {
+            Some(_) => Ok(()),
+            None => XdpReactor::builder().build().map(|reactor| {
+                // 并发初始化时以先设置的为准
+                let _ = XdpReactor::set_global(reactor);
+            }),
+        };
+
+        match ready {
+            Ok(()) => {
+                println!("⚡ 使用 AF_XDP 连接");
+
+                return Ok(okx_xdp_candle_data_stream(
+                    env,
+                    vec![ephemera_shared::Symbol::from(symbol)],
+                    interval,
+                    WsConfig::default(),
+                )
+                .await?
+                .0
+                .boxed());
             }
+            Err(e) => tracing::warn!("XDP 初始化失败，回退到普通 TCP: {}", e),
         }
+    }
 
-        tracing::info!("✅ 数据处理完成，共处理 {} 根K线", count);
-    })
+    Ok(okx_candle_data_stream(
+        env,
+        vec![ephemera_shared::Symbol::from(symbol)],
+        interval,
+        WsConfig::default(),
+    )
+    .await?
+    .0
+    .boxed())
 }
 
-/// 从信号流中只提取 Signal（用于实盘交易）
-fn extract_signals(
-    signal_stream: impl Stream<Item = (Signal, CandleData)> + Send + 'static,
-) -> Pin<Box<dyn Stream<Item = Signal> + Send>> {
-    Box::pin(async_stream::stream! {
-        futures::pin_mut!(signal_stream);
+/// 从环境变量 `AUDIT_LOG` 指定的文件创建审计记录器，未设置时不记录
+fn auditor_from_env(strategy: &str) -> Result<Option<Auditor>> {
+    let Ok(path) = std::env::var("AUDIT_LOG") else {
+        return Ok(None);
+    };
 
-        while let Some((signal, _candle)) = signal_stream.next().await {
-            yield signal;
-        }
-    })
-}
+    let sink = FileAuditSink::open(&path)?;
+    println!("📝 审计日志: {}\n", path);
 
-/// 执行回测，返回回测报告
-async fn execute_backtest(
-    signal_stream: impl Stream<Item = (Signal, CandleData)> + Send,
-    initial_balance: f64,
-) -> Result<BacktestReport> {
-    use std::collections::HashMap;
-
-    let mut available_balance = initial_balance;
-    let mut positions: HashMap<String, Position> = HashMap::new();
-    let mut trades = Vec::new();
-    let mut equity_curve = vec![initial_balance];
-    let mut max_equity = initial_balance;
-
-    futures::pin_mut!(signal_stream);
-
-    while let Some((signal, candle)) = signal_stream.next().await {
-        match signal {
-            Signal::Buy {
-                symbol,
-                price,
-                size,
-            } => {
-                let cost = price * size;
-                if available_balance >= cost {
-                    available_balance -= cost;
-
-                    let position = positions.entry(symbol.to_string()).or_insert(Position {
-                        size: 0.0,
-                        avg_price: 0.0,
-                    });
-
-                    if position.size == 0.0 {
-                        position.avg_price = price;
-                        position.size = size;
-                    } else {
-                        let total_cost = position.avg_price * position.size + price * size;
-                        position.size += size;
-                        position.avg_price = total_cost / position.size;
-                    }
-
-                    let equity = calculate_equity(available_balance, &positions, &candle);
-                    equity_curve.push(equity);
-                    max_equity = max_equity.max(equity);
-
-                    trades.push(Trade {
-                        timestamp: candle.open_timestamp_ms,
-                        symbol: symbol.to_string(),
-                        side: TradeSide::Buy,
-                        price,
-                        size,
-                        balance_after: equity,
-                    });
-
-                    tracing::info!(
-                        "📈 买入: {} @ {:.2}, 数量: {:.4}, 余额: {:.2}",
-                        symbol,
-                        price,
-                        size,
-                        available_balance
-                    );
-                }
-            }
-            Signal::Sell {
-                symbol,
-                price,
-                size,
-            } => {
-                // 修复：分两步操作，避免借用冲突
-                let symbol_string = symbol.to_string();
-
-                // 第一步：获取 actual_size（只读借用）
-                let actual_size = positions
-                    .get(&symbol_string)
-                    .map(|p| size.min(p.size))
-                    .unwrap_or(0.0);
-
-                // 第二步：如果需要卖出，再获取可变借用
-                if actual_size > 0.0 {
-                    let position = positions.get_mut(&symbol_string).unwrap();
-                    position.size -= actual_size;
-
-                    let revenue = price * actual_size;
-                    available_balance += revenue;
-
-                    // 注意：这里在使用 position 后就计算 equity
-                    let should_remove = position.size == 0.0;
-
-                    // 释放 position 的借用后再计算 equity
-                    drop(position);
-
-                    let equity = calculate_equity(available_balance, &positions, &candle);
-                    equity_curve.push(equity);
-                    max_equity = max_equity.max(equity);
-
-                    trades.push(Trade {
-                        timestamp: candle.open_timestamp_ms,
-                        symbol: symbol.to_string(),
-                        side: TradeSide::Sell,
-                        price,
-                        size: actual_size,
-                        balance_after: equity,
-                    });
-
-                    tracing::info!(
-                        "📉 卖出: {} @ {:.2}, 数量: {:.4}, 余额: {:.2}",
-                        symbol,
-                        price,
-                        actual_size,
-                        available_balance
-                    );
-
-                    if should_remove {
-                        positions.remove(&symbol_string);
-                    }
-                }
-            }
-            Signal::Hold => {}
-        }
-    }
+    Ok(Some(Auditor::new(strategy, Arc::new(sink))))
+}
 
-    // 计算最终余额
-    let final_balance = available_balance
-        + positions
-            .values()
-            .map(|p| p.size * p.avg_price)
-            .sum::<f64>();
+/// 从标准输入读取交易对名称，每读到一行就切换该交易对的暂停状态
+async fn toggle_symbols_from_stdin(control: SymbolControl) {
+    use tokio::io::AsyncBufReadExt;
 
-    Ok(BacktestReport {
-        initial_balance,
-        final_balance,
-        available_balance,
-        positions,
-        trades,
-        equity_curve,
-        max_equity,
-    })
-}
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let symbol = line.trim();
+        if symbol.is_empty() {
+            continue;
+        }
 
-/// 计算当前总权益
-fn calculate_equity(
-    available_balance: f64,
-    positions: &std::collections::HashMap<String, Position>,
-    candle: &CandleData,
-) -> f64 {
-    let mut equity = available_balance;
-    if let Some(position) = positions.get(&candle.symbol.to_string()) {
-        equity += position.size * candle.close;
+        if control.toggle(symbol) {
+            println!("▶️ 已恢复 {} 的交易", symbol);
+        } else {
+            println!("⏸️ 已暂停 {} 的交易", symbol);
+        }
     }
-    equity
 }
 
-/// 消费订单流
+/// 消费订单流，配置了 `audit` 时记录交易所返回的每个订单
 async fn consume_order_stream(
     order_stream: impl Stream<Item = Result<OrderInfo>> + Send,
+    audit: Option<Auditor>,
 ) -> Result<()> {
     futures::pin_mut!(order_stream);
 
     while let Some(result) = order_stream.next().await {
         match result {
             Ok(order_info) => {
+                if let Some(audit) = &audit {
+                    audit.record(order_audit_record(audit, &order_info));
+                }
+
                 println!("✅ 订单执行成功:");
                 println!("   订单ID: {}", order_info.ord_id);
                 println!("   交易对: {}", order_info.inst_id);
                 println!("   客户订单ID: {}", order_info.cl_ord_id);
+                println!(
+                    "   成交: {} / {} @ {}, 状态: {:?}",
+                    order_info.filled_size(),
+                    order_info.sz,
+                    order_info.avg_fill_price().unwrap_or_default(),
+                    order_info.state
+                );
                 println!("{:-<80}", "");
             }
             Err(e) => {
@@ -377,190 +353,38 @@ async fn consume_order_stream(
     Ok(())
 }
 
-// ============== 数据结构 ==============
-
-#[derive(Debug, Clone)]
-struct Position {
-    size: f64,
-    avg_price: f64,
-}
+/// 将交易所返回的订单转换为审计记录，价格和数量优先使用实际成交
+fn order_audit_record(audit: &Auditor, order: &OrderInfo) -> AuditRecord {
+    let parse = |s: &str| s.parse::<f64>().unwrap_or_default();
 
-#[derive(Debug, Clone)]
-struct Trade {
-    timestamp: u64,
-    symbol: String,
-    side: TradeSide,
-    price: f64,
-    size: f64,
-    balance_after: f64,
-}
-
-#[derive(Debug, Clone, PartialEq)]
-enum TradeSide {
-    Buy,
-    Sell,
-}
-
-#[derive(Debug)]
-struct BacktestReport {
-    initial_balance: f64,
-    final_balance: f64,
-    available_balance: f64,
-    positions: std::collections::HashMap<String, Position>,
-    trades: Vec<Trade>,
-    equity_curve: Vec<f64>,
-    max_equity: f64,
-}
-
-// ============== 报告生成函数 ==============
-
-fn print_backtest_report(report: &BacktestReport) {
-    let total_return = report.final_balance - report.initial_balance;
-    let total_return_pct = (total_return / report.initial_balance) * 100.0;
-    let max_drawdown = calculate_max_drawdown(&report.equity_curve);
-    let sharpe_ratio = calculate_sharpe_ratio(&report.equity_curve);
-    let (winning_trades, losing_trades) = calculate_win_loss(&report.trades);
-
-    println!("\n{:=<80}", "");
-    println!("📊 回测结果摘要");
-    println!("{:=<80}", "");
-    println!("初始资金: ${:.2}", report.initial_balance);
-    println!("最终资金: ${:.2}", report.final_balance);
-    println!("可用余额: ${:.2}", report.available_balance);
-    println!("总收益: ${:.2}", total_return);
-    println!("收益率: {:.2}%", total_return_pct);
-    println!("最大回撤: {:.2}%", max_drawdown);
-    println!("夏普比率: {:.2}", sharpe_ratio);
-    println!("总交易次数: {}", report.trades.len());
-    println!("盈利交易: {}", winning_trades);
-    println!("亏损交易: {}", losing_trades);
-
-    if winning_trades + losing_trades > 0 {
-        let win_rate = winning_trades as f64 / (winning_trades + losing_trades) as f64 * 100.0;
-        println!("胜率: {:.2}%", win_rate);
-    }
-
-    if !report.positions.is_empty() {
-        println!("\n持仓情况:");
-        for (symbol, position) in &report.positions {
-            if position.size > 0.0 {
-                println!(
-                    "  {}: {:.4} @ ${:.2}",
-                    symbol, position.size, position.avg_price
-                );
-            }
-        }
-    }
-
-    println!("{:=<80}\n", "");
-}
-
-fn print_trades(trades: &[Trade], limit: Option<usize>) {
-    println!("\n交易记录:");
-    println!("{:-<100}", "");
-    println!(
-        "{:<20} {:<15} {:<8} {:<12} {:<10} {:<15}",
-        "时间", "交易对", "方向", "价格", "数量", "账户余额"
-    );
-    println!("{:-<100}", "");
-
-    let trades_to_show = if let Some(n) = limit {
-        &trades[..n.min(trades.len())]
-    } else {
-        trades
+    let price = order.avg_fill_price().unwrap_or_else(|| parse(&order.px));
+    let size = match order.filled_size() {
+        0.0 => parse(&order.sz),
+        filled => filled,
+    };
+    let outcome = match order.state {
+        OrderState::Filled => AuditOutcome::Filled,
+        OrderState::Rejected | OrderState::Canceled => AuditOutcome::Rejected {
+            reason: format!("{:?}", order.state).to_lowercase(),
+        },
+        OrderState::Live | OrderState::PartiallyFilled => AuditOutcome::Submitted {
+            order_id: order.ord_id.to_string(),
+        },
     };
 
-    for trade in trades_to_show {
-        let datetime = chrono::DateTime::from_timestamp_millis(trade.timestamp as i64)
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-            .unwrap_or_else(|| "Invalid".to_string());
-
-        println!(
-            "{:<20} {:<15} {:<8} ${:<11.2} {:<10.4} ${:<14.2}",
-            datetime,
-            trade.symbol,
-            if trade.side == TradeSide::Buy {
-                "买入"
-            } else {
-                "卖出"
-            },
-            trade.price,
-            trade.size,
-            trade.balance_after
-        );
-    }
-    println!("{:-<100}\n", "");
-}
-
-fn calculate_max_drawdown(equity_curve: &[f64]) -> f64 {
-    let mut max_dd: f64 = 0.0;
-    let mut peak = equity_curve[0];
-
-    for &equity in equity_curve {
-        if equity > peak {
-            peak = equity;
-        }
-        let dd = (peak - equity) / peak * 100.0;
-        max_dd = max_dd.max(dd);
-    }
-
-    max_dd
-}
-
-fn calculate_sharpe_ratio(equity_curve: &[f64]) -> f64 {
-    if equity_curve.len() < 2 {
-        return 0.0;
-    }
-
-    let returns: Vec<f64> = equity_curve
-        .windows(2)
-        .map(|w| (w[1] - w[0]) / w[0])
-        .collect();
-
-    let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-    let variance = returns
-        .iter()
-        .map(|r| (r - mean_return).powi(2))
-        .sum::<f64>()
-        / returns.len() as f64;
-    let std_dev = variance.sqrt();
-
-    if std_dev == 0.0 {
-        0.0
-    } else {
-        mean_return / std_dev * (252.0_f64).sqrt()
-    }
-}
-
-fn calculate_win_loss(trades: &[Trade]) -> (usize, usize) {
-    use std::collections::HashMap;
-
-    let mut winning = 0;
-    let mut losing = 0;
-    let mut buy_prices: HashMap<String, Vec<f64>> = HashMap::new();
-
-    for trade in trades {
-        match trade.side {
-            TradeSide::Buy => {
-                buy_prices
-                    .entry(trade.symbol.clone())
-                    .or_default()
-                    .push(trade.price);
-            }
-            TradeSide::Sell => {
-                if let Some(prices) = buy_prices.get_mut(&trade.symbol) {
-                    if let Some(buy_price) = prices.pop() {
-                        if trade.price > buy_price {
-                            winning += 1;
-                        } else {
-                            losing += 1;
-                        }
-                    }
-                }
-            }
-        }
+    AuditRecord {
+        timestamp_ms: order
+            .c_time
+            .parse()
+            .unwrap_or_else(|_| chrono::Utc::now().timestamp_millis() as u64),
+        strategy: audit.strategy().to_string(),
+        symbol: order.inst_id.clone(),
+        signal: match order.side {
+            OrderSide::Buy => SignalKind::Buy,
+            OrderSide::Sell => SignalKind::Sell,
+        },
+        price,
+        size,
+        outcome,
     }
-
-    (winning, losing)
 }
-